@@ -56,7 +56,7 @@ fn main() {
         );
 
         let pipeline_layout = gpu.device.create_pipeline_layout(&[&set_layout]);
-        let entry_point = pso::EntryPoint { entry: "main", module: &shader };
+        let entry_point = pso::EntryPoint { entry: "main", module: &shader, specialization: &[] };
         let pipeline = gpu.device
             .create_compute_pipelines(&[(entry_point, &pipeline_layout)])
             .remove(0)
@@ -85,7 +85,7 @@ fn main() {
     {
         let mut writer = gpu.device.acquire_mapping_writer::<u32>(&staging_buffer, 0..stride * numbers.len() as u64).unwrap();
         writer.copy_from_slice(&numbers);
-        gpu.device.release_mapping_writer(writer);
+        gpu.device.release_mapping_writer(writer).unwrap();
     }
 
     let (device_memory, device_buffer) = create_buffer(
@@ -118,7 +118,8 @@ fn main() {
                         start: buffer::TRANSFER_WRITE, 
                         end: buffer::SHADER_READ | buffer::SHADER_WRITE 
                     },
-                    target: &device_buffer
+                    target: &device_buffer,
+                    families: None,
                 }]);
         command_buffer.bind_compute_pipeline(&pipeline);
         command_buffer.bind_compute_descriptor_sets(&pipeline_layout, 0, &[&desc_set]);
@@ -130,7 +131,8 @@ fn main() {
                         start: buffer::SHADER_READ | buffer::SHADER_WRITE,
                         end: buffer::TRANSFER_READ
                     },
-                    target: &device_buffer
+                    target: &device_buffer,
+                    families: None,
                 }]);
         command_buffer.copy_buffer(&device_buffer, &staging_buffer, &[command::BufferCopy { src: 0, dst: 0, size: stride * numbers.len() as u64}]);
         command_buffer.finish()
@@ -141,7 +143,7 @@ fn main() {
     {
         let reader = gpu.device.acquire_mapping_reader::<u32>(&staging_buffer, 0..stride * numbers.len() as u64).unwrap();
         println!("Times: {:?}", reader.into_iter().map(|n| *n).collect::<Vec<u32>>());
-        gpu.device.release_mapping_reader(reader);
+        gpu.device.release_mapping_reader(reader).unwrap();
     }
 
     gpu.device.destroy_descriptor_pool(desc_pool);
@@ -157,7 +159,7 @@ fn main() {
 }
 
 fn create_buffer<B: Backend>(gpu: &mut Gpu<B>, properties: memory::Properties, usage: buffer::Usage, stride: u64, len: u64) -> (B::Memory, B::Buffer) {
-    let buffer = gpu.device.create_buffer(stride * len, stride, usage).unwrap();
+    let buffer = gpu.device.create_buffer(stride * len, stride, usage, memory::SharingMode::Exclusive).unwrap();
     let requirements = gpu.device.get_buffer_requirements(&buffer);
 
     let ty = (&gpu.memory_types).into_iter().find(|memory_type| {
@@ -237,14 +237,14 @@ fn main() {
         // inidirect argument buffers in SPIRV-Cross
         #[cfg(any(feature = "vulkan", feature = "dx12", feature = "metal", feature = "gl"))]
         let (vs_entry, fs_entry) = (
-            pso::EntryPoint::<back::Backend> { entry: ENTRY_NAME, module: &vs_module },
-            pso::EntryPoint::<back::Backend> { entry: ENTRY_NAME, module: &fs_module },
+            pso::EntryPoint::<back::Backend> { entry: ENTRY_NAME, module: &vs_module, specialization: &[] },
+            pso::EntryPoint::<back::Backend> { entry: ENTRY_NAME, module: &fs_module, specialization: &[] },
         );
 
         #[cfg(all(feature = "metal", feature = "metal_argument_buffer"))]
         let (vs_entry, fs_entry) = (
-            pso::EntryPoint { entry: "vs_main", module: &shader_lib },
-            pso::EntryPoint { entry: "ps_main", module: &shader_lib },
+            pso::EntryPoint { entry: "vs_main", module: &shader_lib, specialization: &[] },
+            pso::EntryPoint { entry: "ps_main", module: &shader_lib, specialization: &[] },
         );
 
         let shader_entries = pso::GraphicsShaderSet {
@@ -285,7 +285,7 @@ fn main() {
             let pairs = images
                 .into_iter()
                 .map(|image| {
-                    let rtv = device.create_image_view(&image, ColorFormat::SELF, Swizzle::NO, COLOR_RANGE.clone()).unwrap();
+                    let rtv = device.create_image_view(&image, i::ViewKind::D2, ColorFormat::SELF, Swizzle::NO, COLOR_RANGE.clone()).unwrap();
                     (image, rtv)
                 })
                 .collect::<Vec<_>>();
@@ -308,7 +308,7 @@ fn main() {
     let buffer_stride = std::mem::size_of::<Vertex>() as u64;
     let buffer_len = QUAD.len() as u64 * buffer_stride;
 
-    let buffer_unbound = device.create_buffer(buffer_len, buffer_stride, buffer::VERTEX).unwrap();
+    let buffer_unbound = device.create_buffer(buffer_len, buffer_stride, buffer::VERTEX, m::SharingMode::Exclusive).unwrap();
     println!("{:?}", buffer_unbound);
     let buffer_req = device.get_buffer_requirements(&buffer_unbound);
 
@@ -327,7 +327,7 @@ fn main() {
             .acquire_mapping_writer::<Vertex>(&vertex_buffer, 0..buffer_len)
             .unwrap();
         vertices.copy_from_slice(&QUAD);
-        device.release_mapping_writer(vertices);
+        device.release_mapping_writer(vertices).unwrap();
     }
 
     // Image
@@ -344,7 +344,7 @@ fn main() {
 
     let image_upload_memory = device.allocate_memory(upload_type, upload_size).unwrap();
     let image_upload_buffer = {
-        let buffer = device.create_buffer(upload_size, image_stride as u64, buffer::TRANSFER_SRC).unwrap();
+        let buffer = device.create_buffer(upload_size, image_stride as u64, buffer::TRANSFER_SRC, m::SharingMode::Exclusive).unwrap();
         device.bind_buffer_memory(&image_upload_memory, 0, buffer).unwrap()
     };
 
@@ -358,10 +358,10 @@ fn main() {
             let dest_base = y * row_pitch as usize;
             data[dest_base .. dest_base + row.len()].copy_from_slice(row);
         }
-        device.release_mapping_writer(data);
+        device.release_mapping_writer(data).unwrap();
     }
 
-    let image_unbound = device.create_image(kind, 1, ColorFormat::SELF, i::TRANSFER_DST | i::SAMPLED).unwrap(); // TODO: usage
+    let image_unbound = device.create_image(kind, 1, ColorFormat::SELF, i::Tiling::Optimal, i::TRANSFER_DST | i::SAMPLED, m::SharingMode::Exclusive).unwrap(); // TODO: usage
     println!("{:?}", image_unbound);
     let image_req = device.get_image_requirements(&image_unbound);
 
@@ -375,14 +375,14 @@ fn main() {
     let image_memory = device.allocate_memory(device_type, image_req.size).unwrap();
 
     let image_logo = device.bind_image_memory(&image_memory, 0, image_unbound).unwrap();
-    let image_srv = device.create_image_view(&image_logo, ColorFormat::SELF, Swizzle::NO, COLOR_RANGE.clone()).unwrap();
+    let image_srv = device.create_image_view(&image_logo, i::ViewKind::D2, ColorFormat::SELF, Swizzle::NO, COLOR_RANGE.clone()).unwrap();
 
     let sampler = device.create_sampler(
         i::SamplerInfo::new(
             i::FilterMethod::Bilinear,
             i::WrapMode::Clamp,
         )
-    );
+    ).unwrap();
 
     device.update_descriptor_sets(&[
         pso::DescriptorSetWrite {
@@ -424,6 +424,7 @@ fn main() {
                         (i::TRANSFER_WRITE, i::ImageLayout::TransferDstOptimal),
                 target: &image_logo,
                 range: COLOR_RANGE.clone(),
+                families: None,
             };
             cmd_buffer.pipeline_barrier(pso::TOP_OF_PIPE .. pso::TRANSFER, &[image_barrier]);
 
@@ -449,6 +450,7 @@ fn main() {
                         (i::SHADER_READ, i::ImageLayout::ShaderReadOnlyOptimal),
                 target: &image_logo,
                 range: COLOR_RANGE.clone(),
+                families: None,
             };
             cmd_buffer.pipeline_barrier(pso::TRANSFER .. pso::BOTTOM_OF_PIPE, &[image_barrier]);
 
@@ -89,11 +89,11 @@ fn main() {
     };
     let pipeline = device.create_graphics_pipeline(
         pso::GraphicsShaderSet {
-            vertex: pso::EntryPoint { entry: "main", module: &vs_module },
+            vertex: pso::EntryPoint { entry: "main", module: &vs_module, specialization: &[] },
             hull: None,
             domain: None,
             geometry: None,
-            fragment: Some(pso::EntryPoint { entry: "main", module: &fs_module },),
+            fragment: Some(pso::EntryPoint { entry: "main", module: &fs_module, specialization: &[] },),
         },
         Primitive::TriangleList,
         pso::Rasterizer::new_fill(),
@@ -108,7 +108,7 @@ fn main() {
 
     // Framebuffer creation
     let frame_rtvs = backbuffers.iter().map(|backbuffer| {
-        device.create_image_view(&backbuffer.color, image_range.clone())
+        device.create_image_view(&backbuffer.color, i::ViewKind::D2, image_range.clone())
             .unwrap()
     }).collect::<Vec<_>>();
     let framebuffers = frame_rtvs.iter().map(|rtv| {
@@ -171,7 +171,7 @@ fn main() {
         1,
     ).unwrap();
 
-    let image_srv = device.create_image_view(&image, image_range)
+    let image_srv = device.create_image_view(&image, i::ViewKind::D2, image_range)
         .unwrap();
 
     let sampler = device.create_sampler(
@@ -179,7 +179,7 @@ fn main() {
             i::FilterMethod::Bilinear,
             i::WrapMode::Clamp,
         )
-    );
+    ).unwrap();
 
     device.update_descriptor_sets()
         .write(desc_data.sampled_image(&desc), 0, &[image_srv.as_ref()])
@@ -0,0 +1,33 @@
+Texture2DArray BlitSource : register(t0);
+SamplerState BlitSamplerNearest : register(s0);
+SamplerState BlitSamplerLinear : register(s1);
+
+cbuffer Region : register(b0) {
+    float2 offset;
+    float2 extent;
+    float layer;
+    float level;
+};
+
+struct VsOutput {
+    float4 pos: SV_POSITION;
+    float4 uv: TEXCOORD0;
+};
+
+// Create a screen filling triangle
+VsOutput vs_blit(uint id: SV_VertexID) {
+    float2 coord = float2((id << 1) & 2, id & 2);
+    VsOutput output = {
+        float4(float2(-1.0, 1.0) + coord * float2(2.0, -2.0), 0.0, 1.0),
+        float4(offset + coord * extent, layer, level)
+    };
+    return output;
+}
+
+float4 ps_blit_nearest(VsOutput input) : SV_Target {
+    return BlitSource.SampleLevel(BlitSamplerNearest, input.uv.xyz, input.uv.w);
+}
+
+float4 ps_blit_linear(VsOutput input) : SV_Target {
+    return BlitSource.SampleLevel(BlitSamplerLinear, input.uv.xyz, input.uv.w);
+}
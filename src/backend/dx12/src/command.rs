@@ -1,12 +1,13 @@
 use wio::com::ComPtr;
-use core::{command as com, image, memory, pass, pso, target};
+use core::{command as com, format, image, memory, pass, pso, target};
 use core::{IndexCount, IndexType, InstanceCount, VertexCount, VertexOffset, Viewport};
 use core::buffer::IndexBufferView;
 use winapi::{self, UINT64, UINT};
-use {conv, native as n, Backend};
+use {conv, internal, native as n, Backend};
 use smallvec::SmallVec;
-use std::{mem, ptr};
+use std::{cmp, mem, ptr};
 use std::ops::Range;
+use std::sync::Arc;
 
 fn get_rect(rect: &target::Rect) -> winapi::D3D12_RECT {
     winapi::D3D12_RECT {
@@ -32,6 +33,85 @@ pub struct RenderPassCache {
     attachment_clears: Vec<AttachmentClear>,
 }
 
+// Number of descriptors of newly created heaps in a `HeapRing`.
+const HEAP_RING_CAPACITY: u64 = 64;
+
+#[derive(Clone)]
+struct RingHeap {
+    raw: ComPtr<winapi::ID3D12DescriptorHeap>,
+    start: n::DualHandle,
+    handle_size: u64,
+    capacity: u64,
+}
+
+impl RingHeap {
+    fn at(&self, index: u64) -> n::DualHandle {
+        assert!(index < self.capacity);
+        n::DualHandle {
+            cpu: winapi::D3D12_CPU_DESCRIPTOR_HANDLE { ptr: self.start.cpu.ptr + self.handle_size * index },
+            gpu: winapi::D3D12_GPU_DESCRIPTOR_HANDLE { ptr: self.start.gpu.ptr + self.handle_size * index },
+        }
+    }
+}
+
+// Descriptor heaps used by internal commands, they are reused
+// after the command buffer is reset.
+#[derive(Clone)]
+struct HeapRing {
+    ty: winapi::D3D12_DESCRIPTOR_HEAP_TYPE,
+    shader_visible: bool,
+    heaps: Vec<RingHeap>,
+    current: usize,
+    offset: u64,
+}
+
+impl HeapRing {
+    fn new(ty: winapi::D3D12_DESCRIPTOR_HEAP_TYPE, shader_visible: bool) -> Self {
+        HeapRing {
+            ty,
+            shader_visible,
+            heaps: Vec::new(),
+            current: 0,
+            offset: 0,
+        }
+    }
+
+    // Allocate `count` consecutive descriptors from one of the heaps.
+    fn alloc(&mut self, service_pipes: &internal::ServicePipes, count: u64) -> RingHeap {
+        while self.current < self.heaps.len() &&
+            self.heaps[self.current].capacity - self.offset < count
+        {
+            self.current += 1;
+            self.offset = 0;
+        }
+        if self.current == self.heaps.len() {
+            let capacity = cmp::max(HEAP_RING_CAPACITY, count);
+            let heap = service_pipes.create_blit_heap(self.ty, self.shader_visible, capacity as _);
+            self.heaps.push(RingHeap {
+                raw: heap.raw,
+                start: heap.start,
+                handle_size: heap.handle_size,
+                capacity,
+            });
+        }
+
+        let heap = &self.heaps[self.current];
+        let slice = RingHeap {
+            raw: heap.raw.clone(),
+            start: heap.at(self.offset),
+            handle_size: heap.handle_size,
+            capacity: count,
+        };
+        self.offset += count;
+        slice
+    }
+
+    fn rewind(&mut self) {
+        self.current = 0;
+        self.offset = 0;
+    }
+}
+
 #[derive(Clone)]
 pub struct CommandBuffer {
     raw: ComPtr<winapi::ID3D12GraphicsCommandList>,
@@ -40,6 +120,26 @@ pub struct CommandBuffer {
     // Cache renderpasses for graphics operations
     pass_cache: Option<RenderPassCache>,
     cur_subpass: usize,
+
+    // Internal pipelines for emulated commands
+    service_pipes: Arc<internal::ServicePipes>,
+    // Descriptor heaps used by recorded internal commands.
+    srv_ring: HeapRing,
+    rtv_ring: HeapRing,
+    // Bound state, restored after internal commands overrode it.
+    pipeline: Option<*mut winapi::ID3D12PipelineState>,
+    topology: winapi::D3D12_PRIMITIVE_TOPOLOGY,
+    gr_root_signature: Option<*mut winapi::ID3D12RootSignature>,
+    gr_root_tables: Vec<Option<winapi::D3D12_GPU_DESCRIPTOR_HANDLE>>,
+    cp_root_signature: Option<*mut winapi::ID3D12RootSignature>,
+    cp_root_tables: Vec<Option<winapi::D3D12_GPU_DESCRIPTOR_HANDLE>>,
+    heaps: Option<[*mut winapi::ID3D12DescriptorHeap; 2]>,
+    viewports: Vec<winapi::D3D12_VIEWPORT>,
+    scissors: Vec<winapi::D3D12_RECT>,
+    // States baked into the bound graphics pipeline, which can't be overridden.
+    baked_states: Option<pso::BakedStates>,
+    // Stream output targets, only bound while transform feedback is active.
+    so_targets: Vec<winapi::D3D12_STREAM_OUTPUT_BUFFER_VIEW>,
 }
 
 unsafe impl Send for CommandBuffer { }
@@ -48,12 +148,27 @@ impl CommandBuffer {
     pub(crate) fn new(
         raw: ComPtr<winapi::ID3D12GraphicsCommandList>,
         allocator: ComPtr<winapi::ID3D12CommandAllocator>,
+        service_pipes: Arc<internal::ServicePipes>,
     ) -> Self {
         CommandBuffer {
             raw,
             allocator,
             pass_cache: None,
             cur_subpass: !0,
+            service_pipes,
+            srv_ring: HeapRing::new(winapi::D3D12_DESCRIPTOR_HEAP_TYPE_CBV_SRV_UAV, true),
+            rtv_ring: HeapRing::new(winapi::D3D12_DESCRIPTOR_HEAP_TYPE_RTV, false),
+            pipeline: None,
+            topology: winapi::D3D_PRIMITIVE_TOPOLOGY_UNDEFINED,
+            gr_root_signature: None,
+            gr_root_tables: Vec::new(),
+            cp_root_signature: None,
+            cp_root_tables: Vec::new(),
+            heaps: None,
+            viewports: Vec::new(),
+            scissors: Vec::new(),
+            baked_states: None,
+            so_targets: Vec::new(),
         }
     }
 
@@ -61,6 +176,135 @@ impl CommandBuffer {
         self.raw.as_mut() as *mut _ as *mut _
     }
 
+    // Check if the state is baked into the bound pipeline, setting it would be ignored.
+    fn is_baked<F>(&self, name: &str, is_dynamic: F) -> bool
+    where
+        F: Fn(&pso::BakedStates) -> bool,
+    {
+        let baked = self.baked_states.as_ref().map_or(false, |states| !is_dynamic(states));
+        if baked {
+            warn!("Ignoring {}, baked into the bound pipeline", name);
+        }
+        baked
+    }
+
+    fn apply_viewports(&mut self, viewports: &[Viewport]) {
+        let viewports: SmallVec<[winapi::D3D12_VIEWPORT; 16]> = viewports
+            .iter()
+            .map(|viewport| {
+                winapi::D3D12_VIEWPORT {
+                    TopLeftX: viewport.x as _,
+                    TopLeftY: viewport.y as _,
+                    Width: viewport.w as _,
+                    Height: viewport.h as _,
+                    MinDepth: viewport.near,
+                    MaxDepth: viewport.far,
+                }
+            })
+            .collect();
+
+        unsafe {
+            self.raw.RSSetViewports(
+                viewports.len() as _,
+                viewports.as_ptr(),
+            );
+        }
+        self.viewports.clear();
+        self.viewports.extend(viewports);
+    }
+
+    fn apply_scissors(&mut self, scissors: &[target::Rect]) {
+        let rects: SmallVec<[winapi::D3D12_RECT; 16]> = scissors.iter().map(get_rect).collect();
+        unsafe {
+            self.raw
+                .RSSetScissorRects(rects.len() as _, rects.as_ptr())
+        };
+        self.scissors.clear();
+        self.scissors.extend(rects);
+    }
+
+    fn set_graphics_root_table(&mut self, table_id: UINT, gpu: winapi::D3D12_GPU_DESCRIPTOR_HANDLE) {
+        unsafe {
+            self.raw.SetGraphicsRootDescriptorTable(table_id, gpu);
+        }
+        let index = table_id as usize;
+        if self.gr_root_tables.len() <= index {
+            self.gr_root_tables.resize(index + 1, None);
+        }
+        self.gr_root_tables[index] = Some(gpu);
+    }
+
+    fn set_compute_root_table(&mut self, table_id: UINT, gpu: winapi::D3D12_GPU_DESCRIPTOR_HANDLE) {
+        unsafe {
+            self.raw.SetComputeRootDescriptorTable(table_id, gpu);
+        }
+        let index = table_id as usize;
+        if self.cp_root_tables.len() <= index {
+            self.cp_root_tables.resize(index + 1, None);
+        }
+        self.cp_root_tables[index] = Some(gpu);
+    }
+
+    // Re-apply the bound state after an internal command replaced it.
+    // Changing the descriptor heaps requires rebinding the tables of both root signatures.
+    fn restore_state(&mut self) {
+        unsafe {
+            if let Some(pipeline) = self.pipeline {
+                self.raw.SetPipelineState(pipeline);
+            }
+            self.raw.IASetPrimitiveTopology(self.topology);
+            if let Some(mut heaps) = self.heaps {
+                self.raw.SetDescriptorHeaps(2, heaps.as_mut_ptr());
+            }
+            if let Some(signature) = self.gr_root_signature {
+                self.raw.SetGraphicsRootSignature(signature);
+                for (table_id, table) in self.gr_root_tables.iter().enumerate() {
+                    if let Some(gpu) = *table {
+                        self.raw.SetGraphicsRootDescriptorTable(table_id as _, gpu);
+                    }
+                }
+            }
+            if let Some(signature) = self.cp_root_signature {
+                self.raw.SetComputeRootSignature(signature);
+                for (table_id, table) in self.cp_root_tables.iter().enumerate() {
+                    if let Some(gpu) = *table {
+                        self.raw.SetComputeRootDescriptorTable(table_id as _, gpu);
+                    }
+                }
+            }
+            self.raw.RSSetViewports(self.viewports.len() as _, self.viewports.as_ptr());
+            self.raw.RSSetScissorRects(self.scissors.len() as _, self.scissors.as_ptr());
+        }
+    }
+
+    fn reset_state(&mut self) {
+        self.srv_ring.rewind();
+        self.rtv_ring.rewind();
+        self.pipeline = None;
+        self.topology = winapi::D3D_PRIMITIVE_TOPOLOGY_UNDEFINED;
+        self.gr_root_signature = None;
+        self.gr_root_tables.clear();
+        self.cp_root_signature = None;
+        self.cp_root_tables.clear();
+        self.heaps = None;
+        self.viewports.clear();
+        self.scissors.clear();
+        self.baked_states = None;
+        self.so_targets.clear();
+    }
+
+    fn apply_stencil_reference(&mut self, front: target::Stencil, back: target::Stencil) {
+        if front != back {
+            error!(
+                "Unable to set different stencil ref values for front ({}) and back ({})",
+                front,
+                back,
+            );
+        }
+
+        unsafe { self.raw.OMSetStencilRef(front as _); }
+    }
+
     fn insert_subpass_barriers(&self) {
         let state = self.pass_cache.as_ref().unwrap();
         let proto_barriers = match state.render_pass.subpasses.get(self.cur_subpass) {
@@ -138,6 +382,34 @@ impl CommandBuffer {
         }
     }
 
+    fn transition_subresources<I>(
+        &self,
+        resource: *mut winapi::ID3D12Resource,
+        subresources: I,
+        states: Range<winapi::D3D12_RESOURCE_STATES>,
+    ) where
+        I: Iterator<Item = UINT>,
+    {
+        let barriers = subresources
+            .map(|subresource| winapi::D3D12_RESOURCE_BARRIER {
+                Type: winapi::D3D12_RESOURCE_BARRIER_TYPE_TRANSITION,
+                Flags: winapi::D3D12_RESOURCE_BARRIER_FLAG_NONE,
+                u: winapi::D3D12_RESOURCE_TRANSITION_BARRIER {
+                    pResource: resource,
+                    Subresource: subresource,
+                    StateBefore: states.start,
+                    StateAfter: states.end,
+                },
+            })
+            .collect::<SmallVec<[_; 16]>>();
+
+        if !barriers.is_empty() {
+            unsafe {
+                self.raw.clone().ResourceBarrier(barriers.len() as _, barriers.as_ptr());
+            }
+        }
+    }
+
     fn clear_render_target_view(
         &self,
         rtv: winapi::D3D12_CPU_DESCRIPTOR_HANDLE,
@@ -200,6 +472,7 @@ impl CommandBuffer {
 impl com::RawCommandBuffer<Backend> for CommandBuffer {
     fn begin(&mut self) {
         unsafe { self.raw.Reset(self.allocator.as_mut(), ptr::null_mut()); }
+        self.reset_state();
     }
 
     fn finish(&mut self) {
@@ -208,6 +481,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
 
     fn reset(&mut self, _release_resources: bool) {
         unsafe { self.raw.Reset(self.allocator.as_mut(), ptr::null_mut()); }
+        self.reset_state();
     }
 
     fn begin_renderpass(
@@ -285,7 +559,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         // transition barriers
         for barrier in barriers {
             match *barrier {
-                memory::Barrier::Buffer { ref states, target } => {
+                memory::Barrier::Buffer { ref states, target, .. } => {
                     let state_src = conv::map_buffer_resource_state(states.start);
                     let state_dst = conv::map_buffer_resource_state(states.end);
 
@@ -306,7 +580,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                         }
                     );
                 }
-                memory::Barrier::Image { ref states, target, ref range } => {
+                memory::Barrier::Image { ref states, target, ref range, .. } => {
                     let _ = range; //TODO: use subresource range
                     let state_src = conv::map_image_resource_state(states.start.0, states.start.1);
                     let state_dst = conv::map_image_resource_state(states.end.0, states.end.1);
@@ -339,6 +613,23 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                         }
                     }
                 }
+                memory::Barrier::Aliasing { ref before, ref after } => {
+                    let map_resource = |resource: &Option<memory::Resource<Backend>>| match *resource {
+                        Some(memory::Resource::Buffer(buffer)) => buffer.resource,
+                        Some(memory::Resource::Image(image)) => image.resource,
+                        None => ptr::null_mut(),
+                    };
+                    let mut barrier = winapi::D3D12_RESOURCE_BARRIER {
+                        Type: winapi::D3D12_RESOURCE_BARRIER_TYPE_ALIASING,
+                        Flags: winapi::D3D12_RESOURCE_BARRIER_FLAG_NONE,
+                        u: unsafe { mem::zeroed() },
+                    };
+                    *unsafe { barrier.Aliasing_mut() } = winapi::D3D12_RESOURCE_ALIASING_BARRIER {
+                        pResourceBefore: map_resource(before),
+                        pResourceAfter: map_resource(after),
+                    };
+                    raw_barriers.push(barrier);
+                }
             }
         }
 
@@ -359,22 +650,6 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             raw_barriers.push(barrier);
         }
 
-        // Alias barriers
-        //
-        // TODO: Optimize, don't always add an alias barrier
-        {
-            let mut barrier = winapi::D3D12_RESOURCE_BARRIER {
-                Type: winapi::D3D12_RESOURCE_BARRIER_TYPE_ALIASING,
-                Flags: winapi::D3D12_RESOURCE_BARRIER_FLAG_NONE,
-                u: unsafe { mem::zeroed() },
-            };
-            *unsafe { barrier.Aliasing_mut() } = winapi::D3D12_RESOURCE_ALIASING_BARRIER {
-                pResourceBefore: ptr::null_mut(),
-                        pResourceAfter: ptr::null_mut(),
-            };
-            raw_barriers.push(barrier);
-        }
-
         unsafe {
             self.raw.ResourceBarrier(
                 raw_barriers.len() as _,
@@ -506,6 +781,202 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         }
     }
 
+    fn blit_image(
+        &mut self,
+        src: &n::Image,
+        src_layout: image::ImageLayout,
+        dst: &n::Image,
+        dst_layout: image::ImageLayout,
+        filter: image::Filter,
+        regions: &[com::ImageBlit],
+    ) {
+        // D3D12 has no blit command, the regions are drawn with an internal
+        // fullscreen triangle pipeline instead. The bound state is restored afterwards.
+        // Render pass encoders borrow the command buffer, so no pass can be active here.
+        debug_assert!(self.pass_cache.is_none());
+        // Depth and integer formats don't report the blit features,
+        // 1D and 3D images are excluded by the `blit_1d_3d` feature.
+        let is_blittable = |image: &n::Image| {
+            let single_sampled = image.kind.get_dimensions().3.get_num_fragments() == 1;
+            match image.kind {
+                // Cube images are 2D arrays of faces.
+                image::Kind::D2(..) | image::Kind::D2Array(..) |
+                image::Kind::Cube(..) | image::Kind::CubeArray(..) => single_sampled &&
+                    image.format.0.aspects() == image::ASPECT_COLOR &&
+                    image.format.1 != format::ChannelType::Int &&
+                    image.format.1 != format::ChannelType::Uint,
+                _ => false,
+            }
+        };
+        assert!(is_blittable(src) && is_blittable(dst),
+            "Blits are only supported between single-sampled 2D or cube color images with float channels");
+
+        let num_rtvs = regions
+            .iter()
+            .map(|region| (region.dst_subresource.layers.end - region.dst_subresource.layers.start) as usize)
+            .sum::<usize>();
+        if num_rtvs == 0 {
+            return;
+        }
+
+        let src_state = conv::map_image_resource_state(image::TRANSFER_READ, src_layout);
+        let dst_state = conv::map_image_resource_state(image::TRANSFER_WRITE, dst_layout);
+
+        let mut device = self.service_pipes.device();
+        let srv_heap = self.srv_ring.alloc(&self.service_pipes, 1);
+        let rtv_heap = self.rtv_ring.alloc(&self.service_pipes, num_rtvs as _);
+
+        // View the whole source image, regions select the level and layer in the shader.
+        let mut srv_desc = winapi::D3D12_SHADER_RESOURCE_VIEW_DESC {
+            Format: src.dxgi_format,
+            ViewDimension: winapi::D3D12_SRV_DIMENSION_TEXTURE2DARRAY,
+            Shader4ComponentMapping: 0x1688, // identity
+            u: unsafe { mem::zeroed() },
+        };
+        *unsafe { srv_desc.Texture2DArray_mut() } = winapi::D3D12_TEX2D_ARRAY_SRV {
+            MostDetailedMip: 0,
+            MipLevels: src.num_levels as _,
+            FirstArraySlice: 0,
+            ArraySize: src.num_layers as _,
+            PlaneSlice: 0,
+            ResourceMinLODClamp: 0.0,
+        };
+
+        let pipe = self.service_pipes.get_blit_pipe(dst.dxgi_format, filter);
+        unsafe {
+            device.CreateShaderResourceView(src.resource, &srv_desc, srv_heap.start.cpu);
+
+            self.raw.SetGraphicsRootSignature(self.service_pipes.blit_signature.as_mut());
+            self.raw.SetPipelineState(pipe.as_mut());
+            self.raw.IASetPrimitiveTopology(winapi::D3D_PRIMITIVE_TOPOLOGY_TRIANGLELIST);
+            let mut heaps = [srv_heap.raw.as_mut() as *mut _];
+            self.raw.SetDescriptorHeaps(1, heaps.as_mut_ptr());
+            self.raw.SetGraphicsRootDescriptorTable(internal::BLIT_ROOT_SOURCE, srv_heap.start.gpu);
+        }
+
+        let mut rtv_id = 0;
+        for region in regions {
+            let src_level = region.src_subresource.level;
+            let dst_level = region.dst_subresource.level;
+            let src_layers = region.src_subresource.layers.clone();
+            let dst_layers = region.dst_subresource.layers.clone();
+            assert_eq!(src_layers.end - src_layers.start, dst_layers.end - dst_layers.start);
+
+            let src_subresources = || src_layers
+                .clone()
+                .map(move |layer| src.calc_subresource(src_level as _, layer as _, 0));
+            let dst_subresources = || dst_layers
+                .clone()
+                .map(move |layer| dst.calc_subresource(dst_level as _, layer as _, 0));
+            self.transition_subresources(
+                src.resource,
+                src_subresources(),
+                src_state .. winapi::D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE,
+            );
+            self.transition_subresources(
+                dst.resource,
+                dst_subresources(),
+                dst_state .. winapi::D3D12_RESOURCE_STATE_RENDER_TARGET,
+            );
+
+            // Mirrored destination bounds are handled by mirroring the source instead.
+            let (mut src_x, mut dst_x) = (
+                (region.src_bounds.start.x, region.src_bounds.end.x),
+                (region.dst_bounds.start.x, region.dst_bounds.end.x),
+            );
+            if dst_x.0 > dst_x.1 {
+                src_x = (src_x.1, src_x.0);
+                dst_x = (dst_x.1, dst_x.0);
+            }
+            let (mut src_y, mut dst_y) = (
+                (region.src_bounds.start.y, region.src_bounds.end.y),
+                (region.dst_bounds.start.y, region.dst_bounds.end.y),
+            );
+            if dst_y.0 > dst_y.1 {
+                src_y = (src_y.1, src_y.0);
+                dst_y = (dst_y.1, dst_y.0);
+            }
+
+            let viewport = winapi::D3D12_VIEWPORT {
+                TopLeftX: dst_x.0 as _,
+                TopLeftY: dst_y.0 as _,
+                Width: (dst_x.1 - dst_x.0) as _,
+                Height: (dst_y.1 - dst_y.0) as _,
+                MinDepth: 0.0,
+                MaxDepth: 1.0,
+            };
+            let scissor = winapi::D3D12_RECT {
+                left: dst_x.0,
+                top: dst_y.0,
+                right: dst_x.1,
+                bottom: dst_y.1,
+            };
+            unsafe {
+                self.raw.RSSetViewports(1, &viewport);
+                self.raw.RSSetScissorRects(1, &scissor);
+            }
+
+            let (width, height, _, _) = src.kind.get_dimensions();
+            let level_width = cmp::max(1, width >> src_level) as f32;
+            let level_height = cmp::max(1, height >> src_level) as f32;
+
+            for (src_layer, dst_layer) in src_layers.clone().zip(dst_layers.clone()) {
+                let rtv = rtv_heap.at(rtv_id).cpu;
+                rtv_id += 1;
+
+                let mut rtv_desc = winapi::D3D12_RENDER_TARGET_VIEW_DESC {
+                    Format: dst.dxgi_format,
+                    ViewDimension: winapi::D3D12_RTV_DIMENSION_TEXTURE2DARRAY,
+                    .. unsafe { mem::zeroed() }
+                };
+                *unsafe { rtv_desc.Texture2DArray_mut() } = winapi::D3D12_TEX2D_ARRAY_RTV {
+                    MipSlice: dst_level as _,
+                    FirstArraySlice: dst_layer as _,
+                    ArraySize: 1,
+                    PlaneSlice: 0,
+                };
+
+                let constants = internal::BlitRegion {
+                    offset: [
+                        src_x.0 as f32 / level_width,
+                        src_y.0 as f32 / level_height,
+                    ],
+                    extent: [
+                        (src_x.1 - src_x.0) as f32 / level_width,
+                        (src_y.1 - src_y.0) as f32 / level_height,
+                    ],
+                    layer: src_layer as f32,
+                    level: src_level as f32,
+                };
+
+                unsafe {
+                    device.CreateRenderTargetView(dst.resource, &rtv_desc, rtv);
+                    self.raw.OMSetRenderTargets(1, &rtv, winapi::FALSE, ptr::null());
+                    self.raw.SetGraphicsRoot32BitConstants(
+                        internal::BLIT_ROOT_REGION,
+                        (mem::size_of::<internal::BlitRegion>() / 4) as _,
+                        &constants as *const _ as *const _,
+                        0,
+                    );
+                    self.raw.DrawInstanced(3, 1, 0, 0);
+                }
+            }
+
+            self.transition_subresources(
+                src.resource,
+                src_subresources(),
+                winapi::D3D12_RESOURCE_STATE_PIXEL_SHADER_RESOURCE .. src_state,
+            );
+            self.transition_subresources(
+                dst.resource,
+                dst_subresources(),
+                winapi::D3D12_RESOURCE_STATE_RENDER_TARGET .. dst_state,
+            );
+        }
+
+        self.restore_state();
+    }
+
     fn bind_index_buffer(&mut self, ibv: IndexBufferView<Backend>) {
         let format = match ibv.index_type {
             IndexType::U16 => winapi::DXGI_FORMAT_R16_UINT,
@@ -543,51 +1014,67 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         }
     }
 
-    fn set_viewports(&mut self, viewports: &[Viewport]) {
-        let viewports: SmallVec<[winapi::D3D12_VIEWPORT; 16]> = viewports
-            .iter()
-            .map(|viewport| {
-                winapi::D3D12_VIEWPORT {
-                    TopLeftX: viewport.x as _,
-                    TopLeftY: viewport.y as _,
-                    Width: viewport.w as _,
-                    Height: viewport.h as _,
-                    MinDepth: viewport.near,
-                    MaxDepth: viewport.far,
-                }
-            })
-            .collect();
+    fn bind_transform_feedback_buffers(&mut self, first_binding: u32, buffers: &[com::TransformFeedbackBuffer<Backend>]) {
+        let end = first_binding as usize + buffers.len();
+        if self.so_targets.len() < end {
+            self.so_targets.resize(end, unsafe { mem::zeroed() });
+        }
+        for (target, tf) in self.so_targets[first_binding as usize ..].iter_mut().zip(buffers) {
+            let base = unsafe { (*tf.buffer.resource).GetGPUVirtualAddress() };
+            let (counter_buffer, counter_offset) = tf.counter;
+            let counter = unsafe { (*counter_buffer.resource).GetGPUVirtualAddress() } + counter_offset;
+            *target = winapi::D3D12_STREAM_OUTPUT_BUFFER_VIEW {
+                BufferLocation: base + tf.range.start,
+                SizeInBytes: tf.range.end - tf.range.start,
+                BufferFilledSizeLocation: counter,
+            };
+        }
+    }
 
+    fn begin_transform_feedback(&mut self) {
         unsafe {
-            self.raw.RSSetViewports(
-                viewports.len() as _,
-                viewports.as_ptr(),
-            );
+            self.raw.SOSetTargets(0, self.so_targets.len() as _, self.so_targets.as_ptr());
         }
     }
 
-    fn set_scissors(&mut self, scissors: &[target::Rect]) {
-        let rects: SmallVec<[winapi::D3D12_RECT; 16]> = scissors.iter().map(get_rect).collect();
+    fn end_transform_feedback(&mut self) {
         unsafe {
-            self.raw
-                .RSSetScissorRects(rects.len() as _, rects.as_ptr())
-        };
+            self.raw.SOSetTargets(0, self.so_targets.len() as _, ptr::null());
+        }
+    }
+
+    fn set_viewports(&mut self, viewports: &[Viewport]) {
+        if !self.is_baked("viewports", |states| states.viewports.is_dynamic()) {
+            self.apply_viewports(viewports);
+        }
+    }
+
+    fn set_scissors(&mut self, scissors: &[target::Rect]) {
+        if !self.is_baked("scissors", |states| states.scissors.is_dynamic()) {
+            self.apply_scissors(scissors);
+        }
     }
 
     fn set_blend_constants(&mut self, color: target::ColorValue) {
-        unsafe { self.raw.OMSetBlendFactor(&color); }
+        if !self.is_baked("blend constants", |states| states.blend_constants.is_dynamic()) {
+            unsafe { self.raw.OMSetBlendFactor(&color); }
+        }
     }
 
     fn set_stencil_reference(&mut self, front: target::Stencil, back: target::Stencil) {
-        if front != back {
-            error!(
-                "Unable to set different stencil ref values for front ({}) and back ({})",
-                front,
-                back,
-            );
+        if !self.is_baked("stencil reference", |states| states.stencil_reference.is_dynamic()) {
+            self.apply_stencil_reference(front, back);
         }
+    }
 
-        unsafe { self.raw.OMSetStencilRef(front as _); }
+    fn set_depth_bounds(&mut self, _bounds: Range<f32>) {
+        error!("Depth bounds test is not supported");
+    }
+
+    fn set_line_width(&mut self, width: f32) {
+        if width != 1.0 {
+            error!("Unable to set line width {}, only 1.0 is supported", width);
+        }
     }
 
     fn bind_graphics_pipeline(&mut self, pipeline: &n::GraphicsPipeline) {
@@ -595,6 +1082,23 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             self.raw.SetPipelineState(pipeline.raw);
             self.raw.IASetPrimitiveTopology(pipeline.topology);
         };
+        self.pipeline = Some(pipeline.raw);
+        self.topology = pipeline.topology;
+
+        let states = &pipeline.baked_states;
+        if let pso::State::Static(ref viewports) = states.viewports {
+            self.apply_viewports(viewports);
+        }
+        if let pso::State::Static(ref scissors) = states.scissors {
+            self.apply_scissors(scissors);
+        }
+        if let pso::State::Static(ref color) = states.blend_constants {
+            unsafe { self.raw.OMSetBlendFactor(color); }
+        }
+        if let pso::State::Static((front, back)) = states.stencil_reference {
+            self.apply_stencil_reference(front, back);
+        }
+        self.baked_states = Some(states.clone());
     }
 
     fn bind_graphics_descriptor_sets(
@@ -603,6 +1107,11 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         first_set: usize,
         sets: &[&n::DescriptorSet],
     ) {
+        // Changing the root signature invalidates the bound tables.
+        if self.gr_root_signature != Some(layout.raw) {
+            self.gr_root_signature = Some(layout.raw);
+            self.gr_root_tables.clear();
+        }
         unsafe {
             self.raw.SetGraphicsRootSignature(layout.raw);
 
@@ -613,7 +1122,8 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                 sets[0].heap_srv_cbv_uav.as_mut() as *mut _,
                 sets[0].heap_samplers.as_mut() as *mut _
             ];
-            self.raw.SetDescriptorHeaps(2, heaps.as_mut_ptr())
+            self.raw.SetDescriptorHeaps(2, heaps.as_mut_ptr());
+            self.heaps = Some(heaps);
         }
 
         let mut table_id = 0;
@@ -626,16 +1136,16 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             }
         }
         for (set, table) in sets.iter().zip(layout.tables[first_set..].iter()) {
-            set.first_gpu_view.map(|gpu| unsafe {
+            if let Some(gpu) = set.first_gpu_view {
                 assert!(table.contains(n::SRV_CBV_UAV));
-                self.raw.SetGraphicsRootDescriptorTable(table_id, gpu);
+                self.set_graphics_root_table(table_id, gpu);
                 table_id += 1;
-            });
-            set.first_gpu_sampler.map(|gpu| unsafe {
+            }
+            if let Some(gpu) = set.first_gpu_sampler {
                 assert!(table.contains(n::SAMPLERS));
-                self.raw.SetGraphicsRootDescriptorTable(table_id, gpu);
+                self.set_graphics_root_table(table_id, gpu);
                 table_id += 1;
-            });
+            }
         }
     }
 
@@ -643,6 +1153,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         unsafe {
             self.raw.SetPipelineState(pipeline.raw);
         }
+        self.pipeline = Some(pipeline.raw);
     }
 
     fn bind_compute_descriptor_sets(
@@ -651,6 +1162,10 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         first_set: usize,
         sets: &[&n::DescriptorSet],
     ) {
+        if self.cp_root_signature != Some(layout.raw) {
+            self.cp_root_signature = Some(layout.raw);
+            self.cp_root_tables.clear();
+        }
         unsafe {
             self.raw.SetComputeRootSignature(layout.raw);
 
@@ -661,7 +1176,8 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                 sets[0].heap_srv_cbv_uav.as_mut() as *mut _,
                 sets[0].heap_samplers.as_mut() as *mut _
             ];
-            self.raw.SetDescriptorHeaps(2, heaps.as_mut_ptr())
+            self.raw.SetDescriptorHeaps(2, heaps.as_mut_ptr());
+            self.heaps = Some(heaps);
         }
 
         let mut table_id = 0;
@@ -674,16 +1190,16 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             }
         }
         for (set, table) in sets.iter().zip(layout.tables[first_set..].iter()) {
-            set.first_gpu_view.map(|gpu| unsafe {
+            if let Some(gpu) = set.first_gpu_view {
                 assert!(table.contains(n::SRV_CBV_UAV));
-                self.raw.SetComputeRootDescriptorTable(table_id, gpu);
+                self.set_compute_root_table(table_id, gpu);
                 table_id += 1;
-            });
-            set.first_gpu_sampler.map(|gpu| unsafe {
+            }
+            if let Some(gpu) = set.first_gpu_sampler {
                 assert!(table.contains(n::SAMPLERS));
-                self.raw.SetComputeRootDescriptorTable(table_id, gpu);
+                self.set_compute_root_table(table_id, gpu);
                 table_id += 1;
-            });
+            }
         }
     }
 
@@ -697,6 +1213,23 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         unimplemented!()
     }
 
+    fn set_event(&mut self, _event: &n::Event, _stage: pso::PipelineStage) {
+        error!("Events are not supported");
+    }
+
+    fn reset_event(&mut self, _event: &n::Event, _stage: pso::PipelineStage) {
+        error!("Events are not supported");
+    }
+
+    fn wait_events(
+        &mut self,
+        _events: &[&n::Event],
+        _stages: Range<pso::PipelineStage>,
+        _barriers: &[memory::Barrier<Backend>],
+    ) {
+        error!("Events are not supported");
+    }
+
     fn fill_buffer(
         &mut self,
         _buffer: &n::Buffer,
@@ -798,21 +1331,24 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             Type: winapi::D3D12_TEXTURE_COPY_TYPE_SUBRESOURCE_INDEX,
             u: unsafe { mem::zeroed() },
         };
-        let (width, height, depth, _) = image.kind.get_dimensions();
         for region in regions {
+            // Footprints of compressed formats cover whole blocks
+            let (width, height, depth, _) = image.kind
+                .get_level_dimensions_aligned(region.image_layers.level, image.block);
             // Copy each layer in the region
             let layers = region.image_layers.layers.clone();
-            for layer in layers {
+            for layer in layers.clone() {
                 assert_eq!(region.buffer_offset % winapi::D3D12_TEXTURE_DATA_PLACEMENT_ALIGNMENT as u64, 0);
                 assert_eq!(region.buffer_row_pitch % winapi::D3D12_TEXTURE_DATA_PITCH_ALIGNMENT as u32, 0);
-                assert!(region.buffer_row_pitch >= width as u32 * image.bits_per_texel as u32 / 8);
+                assert!(region.buffer_row_pitch >= image.block.row_pitch(width as u32));
 
                 let height = height as _;
                 let depth = depth as _;
+                let layer_offset = (layer - layers.start) as u32 * region.buffer_slice_pitch * depth;
 
                 // Advance buffer offset with each layer
                 *unsafe { src.PlacedFootprint_mut() } = winapi::D3D12_PLACED_SUBRESOURCE_FOOTPRINT {
-                    Offset: region.buffer_offset as UINT64 + (layer as u32 * region.buffer_row_pitch * height * depth) as UINT64,
+                    Offset: region.buffer_offset as UINT64 + layer_offset as UINT64,
                     Footprint: winapi::D3D12_SUBRESOURCE_FOOTPRINT {
                         Format: image.dxgi_format,
                         Width: width as _,
@@ -823,11 +1359,13 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                 };
                 *unsafe { dst.SubresourceIndex_mut() } =
                     image.calc_subresource(region.image_layers.level as _, layer as _, 0);
+                // Boxes of compressed formats need to be block aligned
+                let (blocks_x, blocks_y) = image.block.count(region.image_extent.width, region.image_extent.height);
                 let src_box = winapi::D3D12_BOX {
                     left: 0,
                     top: 0,
-                    right: region.image_extent.width as _,
-                    bottom: region.image_extent.height as _,
+                    right: blocks_x * image.block.width as u32,
+                    bottom: blocks_y * image.block.height as u32,
                     front: 0,
                     back: region.image_extent.depth as _,
                 };
@@ -862,23 +1400,26 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             Type: winapi::D3D12_TEXTURE_COPY_TYPE_PLACED_FOOTPRINT,
             u: unsafe { mem::zeroed() },
         };
-        let (width, height, depth, _) = image.kind.get_dimensions();
         for region in regions {
+            // Footprints of compressed formats cover whole blocks
+            let (width, height, depth, _) = image.kind
+                .get_level_dimensions_aligned(region.image_layers.level, image.block);
             // Copy each layer in the region
             let layers = region.image_layers.layers.clone();
-            for layer in layers {
+            for layer in layers.clone() {
                 assert_eq!(region.buffer_offset % winapi::D3D12_TEXTURE_DATA_PLACEMENT_ALIGNMENT as u64, 0);
                 assert_eq!(region.buffer_row_pitch % winapi::D3D12_TEXTURE_DATA_PITCH_ALIGNMENT as u32, 0);
-                assert!(region.buffer_row_pitch >= width as u32 * image.bits_per_texel as u32 / 8);
+                assert!(region.buffer_row_pitch >= image.block.row_pitch(width as u32));
 
                 let height = height as _;
                 let depth = depth as _;
+                let layer_offset = (layer - layers.start) as u32 * region.buffer_slice_pitch * depth;
 
                 // Advance buffer offset with each layer
                 *unsafe { src.SubresourceIndex_mut() } =
                     image.calc_subresource(region.image_layers.level as _, layer as _, 0);
                 *unsafe { dst.PlacedFootprint_mut() } = winapi::D3D12_PLACED_SUBRESOURCE_FOOTPRINT {
-                    Offset: region.buffer_offset as UINT64 + (layer as u32 * region.buffer_row_pitch * height * depth) as UINT64,
+                    Offset: region.buffer_offset as UINT64 + layer_offset as UINT64,
                     Footprint: winapi::D3D12_SUBRESOURCE_FOOTPRINT {
                         Format: image.dxgi_format,
                         Width: width as _,
@@ -887,11 +1428,13 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                         RowPitch: region.buffer_row_pitch,
                     },
                 };
+                // Boxes of compressed formats need to be block aligned
+                let (blocks_x, blocks_y) = image.block.count(region.image_extent.width, region.image_extent.height);
                 let src_box = winapi::D3D12_BOX {
                     left: 0,
                     top: 0,
-                    right: region.image_extent.width as _,
-                    bottom: region.image_extent.height as _,
+                    right: blocks_x * image.block.width as u32,
+                    bottom: blocks_y * image.block.height as u32,
                     front: 0,
                     back: region.image_extent.depth as _,
                 };
@@ -956,6 +1499,10 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
     ) {
         unimplemented!()
     }
+
+    fn draw_auto(&mut self) {
+        error!("D3D12 doesn't support drawing the stream output without a vertex count");
+    }
 }
 
 pub struct SubpassCommandBuffer {}
@@ -1,6 +1,6 @@
 use core::format::{Format, SurfaceType};
-use core::{buffer, state, pso, Primitive};
-use core::image::{self, FilterMethod, WrapMode};
+use core::{buffer, format, state, pso, Primitive};
+use core::image::{self, WrapMode};
 use core::pso::DescriptorSetLayoutBinding;
 use core::state::Comparison;
 use std::fmt;
@@ -114,10 +114,57 @@ pub fn map_format(format: Format) -> Option<DXGI_FORMAT> {
             _ => return None,
         },
         D32_S8 => match format.1 {
+            Unorm |
             Float => DXGI_FORMAT_R32_FLOAT_X8X24_TYPELESS,
             Uint  => DXGI_FORMAT_X32_TYPELESS_G8X24_UINT,
             _ => return None,
         },
+        // There is no stencil-only format, the depth of D24_S8 is left unused.
+        S8 => match format.1 {
+            Uint  => DXGI_FORMAT_X24_TYPELESS_G8_UINT,
+            _ => return None,
+        },
+        // BC1 always carries the 1-bit alpha mode.
+        BC1_R8_G8_B8 | BC1_R8_G8_B8_A8 => match format.1 {
+            Unorm => DXGI_FORMAT_BC1_UNORM,
+            Srgb  => DXGI_FORMAT_BC1_UNORM_SRGB,
+            _ => return None,
+        },
+        BC2_R8_G8_B8_A8 => match format.1 {
+            Unorm => DXGI_FORMAT_BC2_UNORM,
+            Srgb  => DXGI_FORMAT_BC2_UNORM_SRGB,
+            _ => return None,
+        },
+        BC3_R8_G8_B8_A8 => match format.1 {
+            Unorm => DXGI_FORMAT_BC3_UNORM,
+            Srgb  => DXGI_FORMAT_BC3_UNORM_SRGB,
+            _ => return None,
+        },
+        BC4_R8 => match format.1 {
+            Inorm => DXGI_FORMAT_BC4_SNORM,
+            Unorm => DXGI_FORMAT_BC4_UNORM,
+            _ => return None,
+        },
+        BC5_R8_G8 => match format.1 {
+            Inorm => DXGI_FORMAT_BC5_SNORM,
+            Unorm => DXGI_FORMAT_BC5_UNORM,
+            _ => return None,
+        },
+        BC6_R16_G16_B16 => match format.1 {
+            Float  => DXGI_FORMAT_BC6H_SF16,
+            Ufloat => DXGI_FORMAT_BC6H_UF16,
+            _ => return None,
+        },
+        BC7_R8_G8_B8_A8 => match format.1 {
+            Unorm => DXGI_FORMAT_BC7_UNORM,
+            Srgb  => DXGI_FORMAT_BC7_UNORM_SRGB,
+            _ => return None,
+        },
+        ETC2_R8_G8_B8 | ETC2_R8_G8_B8_A1 | ETC2_R8_G8_B8_A8 |
+        EAC_R11 | EAC_R11_G11 => return None,
+        ASTC_4x4 | ASTC_5x4 | ASTC_5x5 | ASTC_6x5 | ASTC_6x6 |
+        ASTC_8x5 | ASTC_8x6 | ASTC_8x8 | ASTC_10x5 | ASTC_10x6 |
+        ASTC_10x8 | ASTC_10x10 | ASTC_12x10 | ASTC_12x12 => return None,
     })
 }
 
@@ -128,6 +175,43 @@ pub fn map_format_dsv(surface: SurfaceType) -> Option<DXGI_FORMAT> {
         SurfaceType::D24_S8 => DXGI_FORMAT_D24_UNORM_S8_UINT,
         SurfaceType::D32    => DXGI_FORMAT_D32_FLOAT,
         SurfaceType::D32_S8 => DXGI_FORMAT_D32_FLOAT_S8X24_UINT,
+        SurfaceType::S8     => DXGI_FORMAT_D24_UNORM_S8_UINT,
+        _ => return None,
+    })
+}
+
+/// Typeless resource format of a surface. Depth and stencil surfaces can then be
+/// viewed both as depth-stencil targets and shader resources, color surfaces
+/// with any channel type of the family.
+pub fn map_format_typeless(surface: SurfaceType) -> Option<DXGI_FORMAT> {
+    use core::format::SurfaceType::*;
+    Some(match surface {
+        R8              => DXGI_FORMAT_R8_TYPELESS,
+        R8_G8           => DXGI_FORMAT_R8G8_TYPELESS,
+        R8_G8_B8_A8     => DXGI_FORMAT_R8G8B8A8_TYPELESS,
+        R10_G10_B10_A2  => DXGI_FORMAT_R10G10B10A2_TYPELESS,
+        R16             => DXGI_FORMAT_R16_TYPELESS,
+        R16_G16         => DXGI_FORMAT_R16G16_TYPELESS,
+        R16_G16_B16_A16 => DXGI_FORMAT_R16G16B16A16_TYPELESS,
+        R32             => DXGI_FORMAT_R32_TYPELESS,
+        R32_G32         => DXGI_FORMAT_R32G32_TYPELESS,
+        R32_G32_B32     => DXGI_FORMAT_R32G32B32_TYPELESS,
+        R32_G32_B32_A32 => DXGI_FORMAT_R32G32B32A32_TYPELESS,
+        B8_G8_R8_A8     => DXGI_FORMAT_B8G8R8A8_TYPELESS,
+        BC1_R8_G8_B8 |
+        BC1_R8_G8_B8_A8 => DXGI_FORMAT_BC1_TYPELESS,
+        BC2_R8_G8_B8_A8 => DXGI_FORMAT_BC2_TYPELESS,
+        BC3_R8_G8_B8_A8 => DXGI_FORMAT_BC3_TYPELESS,
+        BC4_R8          => DXGI_FORMAT_BC4_TYPELESS,
+        BC5_R8_G8       => DXGI_FORMAT_BC5_TYPELESS,
+        BC6_R16_G16_B16 => DXGI_FORMAT_BC6H_TYPELESS,
+        BC7_R8_G8_B8_A8 => DXGI_FORMAT_BC7_TYPELESS,
+        D16             => DXGI_FORMAT_R16_TYPELESS,
+        D24 |
+        D24_S8 |
+        S8              => DXGI_FORMAT_R24G8_TYPELESS,
+        D32             => DXGI_FORMAT_R32_TYPELESS,
+        D32_S8          => DXGI_FORMAT_R32G8X24_TYPELESS,
         _ => return None,
     })
 }
@@ -238,8 +322,31 @@ fn map_blend_op(equation: state::Equation) -> D3D12_BLEND_OP {
 }
 
 
+fn map_logic_op(op: pso::LogicOp) -> D3D12_LOGIC_OP {
+    use core::pso::LogicOp::*;
+    match op {
+        Clear => D3D12_LOGIC_OP_CLEAR,
+        And => D3D12_LOGIC_OP_AND,
+        AndReverse => D3D12_LOGIC_OP_AND_REVERSE,
+        AndInverted => D3D12_LOGIC_OP_AND_INVERTED,
+        Copy => D3D12_LOGIC_OP_COPY,
+        CopyInverted => D3D12_LOGIC_OP_COPY_INVERTED,
+        NoOp => D3D12_LOGIC_OP_NOOP,
+        Xor => D3D12_LOGIC_OP_XOR,
+        Nor => D3D12_LOGIC_OP_NOR,
+        Or => D3D12_LOGIC_OP_OR,
+        OrReverse => D3D12_LOGIC_OP_OR_REVERSE,
+        OrInverted => D3D12_LOGIC_OP_OR_INVERTED,
+        Equivalent => D3D12_LOGIC_OP_EQUIV,
+        Invert => D3D12_LOGIC_OP_INVERT,
+        Nand => D3D12_LOGIC_OP_NAND,
+        Set => D3D12_LOGIC_OP_SET,
+    }
+}
+
 pub fn map_render_targets(
     color_targets: &[pso::ColorInfo],
+    logic_op: Option<pso::LogicOp>,
 ) -> [D3D12_RENDER_TARGET_BLEND_DESC; 8] {
     let dummy_target = D3D12_RENDER_TARGET_BLEND_DESC {
         BlendEnable: FALSE,
@@ -258,6 +365,13 @@ pub fn map_render_targets(
     for (target, desc) in targets.iter_mut().zip(color_targets.iter()) {
         target.RenderTargetWriteMask = desc.mask.bits() as UINT8;
 
+        // Logic operations replace blending, D3D12 doesn't allow enabling both.
+        if let Some(op) = logic_op {
+            target.LogicOpEnable = TRUE;
+            target.LogicOp = map_logic_op(op);
+            continue;
+        }
+
         if let Some(ref b) = desc.color {
             target.BlendEnable = TRUE;
             target.SrcBlend = map_blend_factor(b.source, false);
@@ -372,30 +486,42 @@ pub fn map_wrap(wrap: WrapMode) -> D3D12_TEXTURE_ADDRESS_MODE {
 pub enum FilterOp {
     Product,
     Comparison,
-    //Maximum, TODO
-    //Minimum, TODO
+    Minimum,
+    Maximum,
 }
 
-pub fn map_filter(filter: FilterMethod, op: FilterOp) -> D3D12_FILTER {
-    use core::image::FilterMethod::*;
-    match op {
-        FilterOp::Product => match filter {
-            Scale          => D3D12_FILTER_MIN_MAG_MIP_POINT,
-            Mipmap         => D3D12_FILTER_MIN_MAG_POINT_MIP_LINEAR,
-            Bilinear       => D3D12_FILTER_MIN_MAG_LINEAR_MIP_POINT,
-            Trilinear      => D3D12_FILTER_MIN_MAG_MIP_LINEAR,
-            Anisotropic(_) => D3D12_FILTER_ANISOTROPIC,
-        },
-        FilterOp::Comparison => match filter {
-            Scale          => D3D12_FILTER_COMPARISON_MIN_MAG_MIP_POINT,
-            Mipmap         => D3D12_FILTER_COMPARISON_MIN_MAG_POINT_MIP_LINEAR,
-            Bilinear       => D3D12_FILTER_COMPARISON_MIN_MAG_LINEAR_MIP_POINT,
-            Trilinear      => D3D12_FILTER_COMPARISON_MIN_MAG_MIP_LINEAR,
-            Anisotropic(_) => D3D12_FILTER_COMPARISON_ANISOTROPIC,
-        },
+fn map_filter_type(filter: image::Filter) -> u32 {
+    match filter {
+        image::Filter::Nearest => 0, // D3D12_FILTER_TYPE_POINT
+        image::Filter::Linear => 1, // D3D12_FILTER_TYPE_LINEAR
     }
 }
 
+// Equivalent of `D3D12_ENCODE_BASIC_FILTER` and `D3D12_ENCODE_ANISOTROPIC_FILTER`.
+pub fn map_filter(
+    mag_filter: image::Filter,
+    min_filter: image::Filter,
+    mip_filter: image::Filter,
+    anisotropic: bool,
+    op: FilterOp,
+) -> D3D12_FILTER {
+    let reduction = match op {
+        FilterOp::Product => 0, // D3D12_FILTER_REDUCTION_TYPE_STANDARD
+        FilterOp::Comparison => 1, // D3D12_FILTER_REDUCTION_TYPE_COMPARISON
+        FilterOp::Minimum => 2, // D3D12_FILTER_REDUCTION_TYPE_MINIMUM
+        FilterOp::Maximum => 3, // D3D12_FILTER_REDUCTION_TYPE_MAXIMUM
+    };
+    let bits = if anisotropic {
+        0x40 | 0x15 // D3D12_ANISOTROPIC_FILTERING_BIT | MIN_MAG_MIP_LINEAR
+    } else {
+        map_filter_type(min_filter) << 4 |
+        map_filter_type(mag_filter) << 2 |
+        map_filter_type(mip_filter)
+    };
+
+    D3D12_FILTER(bits | reduction << 7)
+}
+
 pub fn map_function(fun: Comparison) -> D3D12_COMPARISON_FUNC {
     match fun {
         Comparison::Never => D3D12_COMPARISON_FUNC_NEVER,
@@ -535,3 +661,67 @@ pub fn map_image_flags(usage: image::Usage) -> D3D12_RESOURCE_FLAGS {
 
     flags
 }
+
+pub fn map_format_support(
+    format: Format,
+    support1: D3D12_FORMAT_SUPPORT1,
+    support2: D3D12_FORMAT_SUPPORT2,
+    heterogeneous_resource_heaps: bool,
+) -> format::Properties {
+    let has1 = |flag: D3D12_FORMAT_SUPPORT1| (support1 & flag).0 != 0;
+    let has2 = |flag: D3D12_FORMAT_SUPPORT2| (support2 & flag).0 != 0;
+    // Blits are emulated by rendering into the destination, sampling the source with a float view.
+    // Blit destinations are render targets, which can't share heaps with other textures on tier 1.
+    let blittable = format.0.aspects() == image::ASPECT_COLOR &&
+        format.1 != format::ChannelType::Int &&
+        format.1 != format::ChannelType::Uint;
+
+    let mut image = format::ImageFeature::empty();
+    if has1(D3D12_FORMAT_SUPPORT1_SHADER_SAMPLE) {
+        image |= format::SAMPLED | format::SAMPLED_LINEAR;
+        if blittable {
+            image |= format::BLIT_SRC;
+        }
+    }
+    if has1(D3D12_FORMAT_SUPPORT1_TYPED_UNORDERED_ACCESS_VIEW) {
+        image |= format::STORAGE;
+        if has2(D3D12_FORMAT_SUPPORT2_UAV_ATOMIC_ADD) {
+            image |= format::STORAGE_ATOMIC;
+        }
+    }
+    if has1(D3D12_FORMAT_SUPPORT1_RENDER_TARGET) {
+        image |= format::COLOR_ATTACHMENT;
+        if blittable && heterogeneous_resource_heaps {
+            image |= format::BLIT_DST;
+        }
+        if has1(D3D12_FORMAT_SUPPORT1_BLENDABLE) {
+            image |= format::COLOR_ATTACHMENT_BLEND;
+        }
+    }
+    if has1(D3D12_FORMAT_SUPPORT1_DEPTH_STENCIL) {
+        image |= format::DEPTH_STENCIL_ATTACHMENT;
+    }
+
+    let mut buffer = format::BufferFeature::empty();
+    if has1(D3D12_FORMAT_SUPPORT1_BUFFER) {
+        if has1(D3D12_FORMAT_SUPPORT1_SHADER_LOAD) {
+            buffer |= format::UNIFORM_TEXEL;
+        }
+        if has1(D3D12_FORMAT_SUPPORT1_TYPED_UNORDERED_ACCESS_VIEW) {
+            buffer |= format::STORAGE_TEXEL;
+            if has2(D3D12_FORMAT_SUPPORT2_UAV_ATOMIC_ADD) {
+                buffer |= format::STORAGE_TEXEL_ATOMIC;
+            }
+        }
+    }
+    if has1(D3D12_FORMAT_SUPPORT1_IA_VERTEX_BUFFER) {
+        buffer |= format::VERTEX;
+    }
+
+    format::Properties {
+        // Row-major textures are too restricted to be exposed.
+        linear_tiling: format::ImageFeature::empty(),
+        optimal_tiling: image,
+        buffer_features: buffer,
+    }
+}
@@ -9,6 +9,7 @@ use kernel32;
 use spirv_cross::{hlsl, spirv, ErrorCode as SpirvErrorCode};
 use std::collections::BTreeMap;
 use std::ops::Range;
+use std::sync::Mutex;
 use std::{ffi, mem, ptr, slice};
 use {free_list, native as n, shade, Backend as B, Device};
 use winapi;
@@ -43,19 +44,22 @@ pub struct UnboundBuffer {
 #[derive(Debug)]
 pub struct UnboundImage {
     desc: winapi::D3D12_RESOURCE_DESC,
+    format: format::Format,
+    // Fully typed format, the resource itself may be typeless.
+    view_format: winapi::DXGI_FORMAT,
     dsv_format: winapi::DXGI_FORMAT,
     requirements: memory::Requirements,
     kind: image::Kind,
     usage: image::Usage,
     aspects: image::AspectFlags,
-    bits_per_texel: u8,
+    block: format::FormatBlock,
     num_levels: image::Level,
     num_layers: image::Layer,
 }
 
 impl Device {
     /// Compile a single shader entry point from a HLSL text shader
-    fn compile_shader(
+    pub(crate) fn compile_shader(
         stage: pso::Stage,
         shader_model: hlsl::ShaderModel,
         entry: &str,
@@ -64,9 +68,11 @@ impl Device {
         let stage_to_str = |stage, shader_model| {
             let stage = match stage {
                 pso::Stage::Vertex => "vs",
+                pso::Stage::Hull => "hs",
+                pso::Stage::Domain => "ds",
+                pso::Stage::Geometry => "gs",
                 pso::Stage::Fragment => "ps",
                 pso::Stage::Compute => "cs",
-                _ => unimplemented!(),
             };
 
             let model = match shader_model {
@@ -122,7 +128,90 @@ impl Device {
         let mut shader_map = BTreeMap::new();
         let blob = Self::compile_shader(stage, hlsl::ShaderModel::V5_1, hlsl_entry, code)?;
         shader_map.insert(entry_point.into(), blob);
-        Ok(n::ShaderModule { shaders: shader_map })
+        Ok(n::ShaderModule { shaders: shader_map, spirv: None })
+    }
+
+    /// Translate a SPIR-V module to HLSL, applying the specialization constants.
+    fn translate_spirv(
+        raw_data: &[u8],
+        shader_model: hlsl::ShaderModel,
+        specialization: &[pso::Specialization],
+    ) -> Result<(spirv::Ast<hlsl::Target>, String), d::ShaderError> {
+        // spec requires "codeSize must be a multiple of 4"
+        assert_eq!(raw_data.len() & 3, 0);
+
+        let module = spirv::Module::from_words(unsafe {
+            slice::from_raw_parts(
+                raw_data.as_ptr() as *const u32,
+                raw_data.len() / mem::size_of::<u32>(),
+            )
+        });
+
+        let mut ast = spirv::Ast::<hlsl::Target>::parse(&module)
+            .map_err(|err| {
+                let msg =  match err {
+                    SpirvErrorCode::CompilationError(msg) => msg,
+                    SpirvErrorCode::Unhandled => "Unknown parsing error".into(),
+                };
+                d::ShaderError::CompilationFailed(msg)
+            })?;
+
+        // Patch descriptor sets due to the splitting of descriptor heaps into
+        // SrvCbvUav and sampler heap. Each set will have a new location to match
+        // the layout of the root signatures.
+        let shader_resources = ast.get_shader_resources().map_err(gen_query_error)?;
+        for image in &shader_resources.separate_images {
+            let set = ast.get_decoration(image.id, spirv::Decoration::DescriptorSet).map_err(gen_query_error)?;
+            ast.set_decoration(image.id, spirv::Decoration::DescriptorSet, 2*set)
+               .map_err(gen_unexpected_error)?;
+        }
+
+        for sampler in &shader_resources.separate_samplers {
+            let set = ast.get_decoration(sampler.id, spirv::Decoration::DescriptorSet).map_err(gen_query_error)?;
+            ast.set_decoration(sampler.id, spirv::Decoration::DescriptorSet, 2*set+1)
+               .map_err(gen_unexpected_error)?;
+        }
+
+        for constant in ast.get_specialization_constants().map_err(gen_query_error)? {
+            if let Some(spec) = specialization.iter().find(|spec| spec.id == constant.constant_id) {
+                ast.set_scalar_constant(constant.id, spec.value.to_bits())
+                   .map_err(gen_unexpected_error)?;
+            }
+        }
+
+        let mut compile_options = hlsl::CompilerOptions::default();
+        compile_options.shader_model = shader_model;
+        compile_options.vertex.invert_y = true;
+
+        ast.set_compiler_options(&compile_options)
+           .map_err(gen_unexpected_error)?;
+        let shader_code = ast.compile()
+            .map_err(|err| {
+                let msg =  match err {
+                    SpirvErrorCode::CompilationError(msg) => msg,
+                    SpirvErrorCode::Unhandled => "Unknown compile error".into(),
+                };
+                d::ShaderError::CompilationFailed(msg)
+            })?;
+
+        debug!("SPIRV-Cross generated shader:\n{}", shader_code);
+        Ok((ast, shader_code))
+    }
+
+    /// Compile an entry point of a SPIR-V module with its specialization constants applied.
+    fn compile_specialized(
+        stage: pso::Stage,
+        source: &pso::EntryPoint<B>,
+    ) -> Result<ComPtr<winapi::ID3DBlob>, d::ShaderError> {
+        let raw_data = match source.module.spirv {
+            Some(ref data) => data,
+            None => return Err(d::ShaderError::CompilationFailed(
+                "Specialization constants require a SPIR-V shader module".into())),
+        };
+        let shader_model = hlsl::ShaderModel::V5_1;
+        let (_, shader_code) = Self::translate_spirv(raw_data, shader_model, source.specialization)?;
+        let blob = Self::compile_shader(stage, shader_model, source.entry, shader_code.as_bytes())?;
+        Ok(unsafe { ComPtr::new(blob) })
     }
 
     pub(crate) fn create_descriptor_heap_impl(
@@ -221,32 +310,75 @@ impl Device {
     fn view_image_as_render_target(
         &mut self,
         resource: *mut winapi::ID3D12Resource,
-        kind: image::Kind,
+        kind: image::ViewKind,
+        msaa: bool,
         format: winapi::DXGI_FORMAT,
         range: &image::SubresourceRange,
     ) -> Result<winapi::D3D12_CPU_DESCRIPTOR_HANDLE, image::ViewError> {
-        //TODO: use subresource range
         let handle = self.rtv_pool.lock().unwrap().alloc_handles(1).cpu;
 
-        if kind.get_dimensions().3 != image::AaMode::Single {
-            error!("No MSAA supported yet!");
-        }
-
         let mut desc = winapi::D3D12_RENDER_TARGET_VIEW_DESC {
             Format: format,
             .. unsafe { mem::zeroed() }
         };
 
+        let mip_slice = range.levels.start as _;
+        let first_layer = range.layers.start as _;
+        let num_layers = (range.layers.end - range.layers.start) as _;
         match kind {
-            image::Kind::D2(..) => {
-                assert_eq!(range.levels.start + 1, range.levels.end);
+            image::ViewKind::D1 => {
+                desc.ViewDimension = winapi::D3D12_RTV_DIMENSION_TEXTURE1D;
+                *unsafe { desc.Texture1D_mut() } = winapi::D3D12_TEX1D_RTV {
+                    MipSlice: mip_slice,
+                };
+            }
+            image::ViewKind::D1Array => {
+                desc.ViewDimension = winapi::D3D12_RTV_DIMENSION_TEXTURE1DARRAY;
+                *unsafe { desc.Texture1DArray_mut() } = winapi::D3D12_TEX1D_ARRAY_RTV {
+                    MipSlice: mip_slice,
+                    FirstArraySlice: first_layer,
+                    ArraySize: num_layers,
+                };
+            }
+            image::ViewKind::D2 if msaa => {
+                desc.ViewDimension = winapi::D3D12_RTV_DIMENSION_TEXTURE2DMS;
+                *unsafe { desc.Texture2DMS_mut() } = winapi::D3D12_TEX2DMS_RTV {
+                    UnusedField_NothingToDefine: 0,
+                };
+            }
+            image::ViewKind::D2Array if msaa => {
+                desc.ViewDimension = winapi::D3D12_RTV_DIMENSION_TEXTURE2DMSARRAY;
+                *unsafe { desc.Texture2DMSArray_mut() } = winapi::D3D12_TEX2DMS_ARRAY_RTV {
+                    FirstArraySlice: first_layer,
+                    ArraySize: num_layers,
+                };
+            }
+            image::ViewKind::D2 => {
                 desc.ViewDimension = winapi::D3D12_RTV_DIMENSION_TEXTURE2D;
                 *unsafe { desc.Texture2D_mut() } = winapi::D3D12_TEX2D_RTV {
-                    MipSlice: range.levels.start as _,
+                    MipSlice: mip_slice,
                     PlaneSlice: 0, //TODO
                 };
-            },
-            _ => unimplemented!()
+            }
+            image::ViewKind::D2Array |
+            image::ViewKind::Cube |
+            image::ViewKind::CubeArray => {
+                desc.ViewDimension = winapi::D3D12_RTV_DIMENSION_TEXTURE2DARRAY;
+                *unsafe { desc.Texture2DArray_mut() } = winapi::D3D12_TEX2D_ARRAY_RTV {
+                    MipSlice: mip_slice,
+                    FirstArraySlice: first_layer,
+                    ArraySize: num_layers,
+                    PlaneSlice: 0, //TODO
+                };
+            }
+            image::ViewKind::D3 => {
+                desc.ViewDimension = winapi::D3D12_RTV_DIMENSION_TEXTURE3D;
+                *unsafe { desc.Texture3D_mut() } = winapi::D3D12_TEX3D_RTV {
+                    MipSlice: mip_slice,
+                    FirstWSlice: 0,
+                    WSize: !0, // all depth slices
+                };
+            }
         };
 
         unsafe {
@@ -259,31 +391,68 @@ impl Device {
     fn view_image_as_depth_stencil(
         &mut self,
         resource: *mut winapi::ID3D12Resource,
-        kind: image::Kind,
+        kind: image::ViewKind,
+        msaa: bool,
         format: winapi::DXGI_FORMAT,
         range: &image::SubresourceRange,
     ) -> Result<winapi::D3D12_CPU_DESCRIPTOR_HANDLE, image::ViewError> {
-        //TODO: use subresource range
         let handle = self.dsv_pool.lock().unwrap().alloc_handles(1).cpu;
 
-        if kind.get_dimensions().3 != image::AaMode::Single {
-            error!("No MSAA supported yet!");
-        }
-
         let mut desc = winapi::D3D12_DEPTH_STENCIL_VIEW_DESC {
             Format: format,
             .. unsafe { mem::zeroed() }
         };
 
+        let mip_slice = range.levels.start as _;
+        let first_layer = range.layers.start as _;
+        let num_layers = (range.layers.end - range.layers.start) as _;
         match kind {
-            image::Kind::D2(..) => {
-                assert_eq!(range.levels.start + 1, range.levels.end);
+            image::ViewKind::D1 => {
+                desc.ViewDimension = winapi::D3D12_DSV_DIMENSION_TEXTURE1D;
+                *unsafe { desc.Texture1D_mut() } = winapi::D3D12_TEX1D_DSV {
+                    MipSlice: mip_slice,
+                };
+            }
+            image::ViewKind::D1Array => {
+                desc.ViewDimension = winapi::D3D12_DSV_DIMENSION_TEXTURE1DARRAY;
+                *unsafe { desc.Texture1DArray_mut() } = winapi::D3D12_TEX1D_ARRAY_DSV {
+                    MipSlice: mip_slice,
+                    FirstArraySlice: first_layer,
+                    ArraySize: num_layers,
+                };
+            }
+            image::ViewKind::D2 if msaa => {
+                desc.ViewDimension = winapi::D3D12_DSV_DIMENSION_TEXTURE2DMS;
+                *unsafe { desc.Texture2DMS_mut() } = winapi::D3D12_TEX2DMS_DSV {
+                    UnusedField_NothingToDefine: 0,
+                };
+            }
+            image::ViewKind::D2Array if msaa => {
+                desc.ViewDimension = winapi::D3D12_DSV_DIMENSION_TEXTURE2DMSARRAY;
+                *unsafe { desc.Texture2DMSArray_mut() } = winapi::D3D12_TEX2DMS_ARRAY_DSV {
+                    FirstArraySlice: first_layer,
+                    ArraySize: num_layers,
+                };
+            }
+            image::ViewKind::D2 => {
                 desc.ViewDimension = winapi::D3D12_DSV_DIMENSION_TEXTURE2D;
                 *unsafe { desc.Texture2D_mut() } = winapi::D3D12_TEX2D_DSV {
-                    MipSlice: range.levels.start as _,
+                    MipSlice: mip_slice,
                 };
-            },
-            _ => unimplemented!()
+            }
+            image::ViewKind::D2Array |
+            image::ViewKind::Cube |
+            image::ViewKind::CubeArray => {
+                desc.ViewDimension = winapi::D3D12_DSV_DIMENSION_TEXTURE2DARRAY;
+                *unsafe { desc.Texture2DArray_mut() } = winapi::D3D12_TEX2D_ARRAY_DSV {
+                    MipSlice: mip_slice,
+                    FirstArraySlice: first_layer,
+                    ArraySize: num_layers,
+                };
+            }
+            image::ViewKind::D3 => {
+                return Err(image::ViewError::BadKind(kind));
+            }
         };
 
         unsafe {
@@ -296,40 +465,102 @@ impl Device {
     fn view_image_as_shader_resource(
         &mut self,
         resource: *mut winapi::ID3D12Resource,
-        kind: image::Kind,
+        kind: image::ViewKind,
+        msaa: bool,
         format: winapi::DXGI_FORMAT,
         range: &image::SubresourceRange,
     ) -> Result<winapi::D3D12_CPU_DESCRIPTOR_HANDLE, image::ViewError> {
         let handle = self.srv_pool.lock().unwrap().alloc_handles(1).cpu;
 
-        let dimension = match kind {
-            image::Kind::D1(..) |
-            image::Kind::D1Array(..) => winapi::D3D12_SRV_DIMENSION_TEXTURE1D,
-            image::Kind::D2(..) |
-            image::Kind::D2Array(..) => winapi::D3D12_SRV_DIMENSION_TEXTURE2D,
-            image::Kind::D3(..) |
-            image::Kind::Cube(..) |
-            image::Kind::CubeArray(..) => winapi::D3D12_SRV_DIMENSION_TEXTURE3D,
-        };
-
         let mut desc = winapi::D3D12_SHADER_RESOURCE_VIEW_DESC {
             Format: format,
-            ViewDimension: dimension,
+            ViewDimension: winapi::D3D12_SRV_DIMENSION_UNKNOWN,
             Shader4ComponentMapping: 0x1688, // TODO: map swizzle
             u: unsafe { mem::zeroed() },
         };
 
+        let most_detailed_mip = range.levels.start as _;
+        let mip_levels = (range.levels.end - range.levels.start) as _;
+        let first_layer = range.layers.start as _;
+        let num_layers = (range.layers.end - range.layers.start) as _;
         match kind {
-            image::Kind::D2(_, _, image::AaMode::Single) => {
-                assert_eq!(range.levels.start, 0);
-                *unsafe{ desc.Texture2D_mut() } = winapi::D3D12_TEX2D_SRV {
-                    MostDetailedMip: 0,
-                    MipLevels: range.levels.end as _,
+            image::ViewKind::D1 => {
+                desc.ViewDimension = winapi::D3D12_SRV_DIMENSION_TEXTURE1D;
+                *unsafe { desc.Texture1D_mut() } = winapi::D3D12_TEX1D_SRV {
+                    MostDetailedMip: most_detailed_mip,
+                    MipLevels: mip_levels,
+                    ResourceMinLODClamp: 0.0,
+                }
+            }
+            image::ViewKind::D1Array => {
+                desc.ViewDimension = winapi::D3D12_SRV_DIMENSION_TEXTURE1DARRAY;
+                *unsafe { desc.Texture1DArray_mut() } = winapi::D3D12_TEX1D_ARRAY_SRV {
+                    MostDetailedMip: most_detailed_mip,
+                    MipLevels: mip_levels,
+                    FirstArraySlice: first_layer,
+                    ArraySize: num_layers,
+                    ResourceMinLODClamp: 0.0,
+                }
+            }
+            image::ViewKind::D2 if msaa => {
+                desc.ViewDimension = winapi::D3D12_SRV_DIMENSION_TEXTURE2DMS;
+                *unsafe { desc.Texture2DMS_mut() } = winapi::D3D12_TEX2DMS_SRV {
+                    UnusedField_NothingToDefine: 0,
+                }
+            }
+            image::ViewKind::D2Array if msaa => {
+                desc.ViewDimension = winapi::D3D12_SRV_DIMENSION_TEXTURE2DMSARRAY;
+                *unsafe { desc.Texture2DMSArray_mut() } = winapi::D3D12_TEX2DMS_ARRAY_SRV {
+                    FirstArraySlice: first_layer,
+                    ArraySize: num_layers,
+                }
+            }
+            image::ViewKind::D2 => {
+                desc.ViewDimension = winapi::D3D12_SRV_DIMENSION_TEXTURE2D;
+                *unsafe { desc.Texture2D_mut() } = winapi::D3D12_TEX2D_SRV {
+                    MostDetailedMip: most_detailed_mip,
+                    MipLevels: mip_levels,
+                    PlaneSlice: 0, //TODO
+                    ResourceMinLODClamp: 0.0,
+                }
+            }
+            image::ViewKind::D2Array => {
+                desc.ViewDimension = winapi::D3D12_SRV_DIMENSION_TEXTURE2DARRAY;
+                *unsafe { desc.Texture2DArray_mut() } = winapi::D3D12_TEX2D_ARRAY_SRV {
+                    MostDetailedMip: most_detailed_mip,
+                    MipLevels: mip_levels,
+                    FirstArraySlice: first_layer,
+                    ArraySize: num_layers,
                     PlaneSlice: 0, //TODO
                     ResourceMinLODClamp: 0.0,
                 }
             }
-            _ => unimplemented!()
+            image::ViewKind::D3 => {
+                desc.ViewDimension = winapi::D3D12_SRV_DIMENSION_TEXTURE3D;
+                *unsafe { desc.Texture3D_mut() } = winapi::D3D12_TEX3D_SRV {
+                    MostDetailedMip: most_detailed_mip,
+                    MipLevels: mip_levels,
+                    ResourceMinLODClamp: 0.0,
+                }
+            }
+            image::ViewKind::Cube => {
+                desc.ViewDimension = winapi::D3D12_SRV_DIMENSION_TEXTURECUBE;
+                *unsafe { desc.TextureCube_mut() } = winapi::D3D12_TEXCUBE_SRV {
+                    MostDetailedMip: most_detailed_mip,
+                    MipLevels: mip_levels,
+                    ResourceMinLODClamp: 0.0,
+                }
+            }
+            image::ViewKind::CubeArray => {
+                desc.ViewDimension = winapi::D3D12_SRV_DIMENSION_TEXTURECUBEARRAY;
+                *unsafe { desc.TextureCubeArray_mut() } = winapi::D3D12_TEXCUBE_ARRAY_SRV {
+                    MostDetailedMip: most_detailed_mip,
+                    MipLevels: mip_levels,
+                    First2DArrayFace: first_layer,
+                    NumCubes: num_layers / 6,
+                    ResourceMinLODClamp: 0.0,
+                }
+            }
         }
 
         unsafe {
@@ -342,36 +573,62 @@ impl Device {
     fn view_image_as_storage(
         &mut self,
         resource: *mut winapi::ID3D12Resource,
-        kind: image::Kind,
+        kind: image::ViewKind,
         format: winapi::DXGI_FORMAT,
         range: &image::SubresourceRange,
     ) -> Result<winapi::D3D12_CPU_DESCRIPTOR_HANDLE, image::ViewError> {
         let handle = self.uav_pool.lock().unwrap().alloc_handles(1).cpu;
 
-        let dimension = match kind {
-            image::Kind::D1(..) |
-            image::Kind::D1Array(..) => winapi::D3D12_UAV_DIMENSION_TEXTURE1D,
-            image::Kind::D2(..) |
-            image::Kind::D2Array(..) => winapi::D3D12_UAV_DIMENSION_TEXTURE2D,
-            image::Kind::D3(..) |
-            image::Kind::Cube(..) |
-            image::Kind::CubeArray(..) => winapi::D3D12_UAV_DIMENSION_TEXTURE3D,
-        };
-
         let mut desc = winapi::D3D12_UNORDERED_ACCESS_VIEW_DESC {
             Format: format,
-            ViewDimension: dimension,
+            ViewDimension: winapi::D3D12_UAV_DIMENSION_UNKNOWN,
             u: unsafe { mem::zeroed() },
         };
 
+        let mip_slice = range.levels.start as _;
+        let first_layer = range.layers.start as _;
+        let num_layers = (range.layers.end - range.layers.start) as _;
         match kind {
-            image::Kind::D2(_, _, _) => {
-                *unsafe{ desc.Texture2D_mut() } = winapi::D3D12_TEX2D_UAV {
-                    MipSlice: range.levels.start as _,
-                    PlaneSlice: 0,
+            image::ViewKind::D1 => {
+                desc.ViewDimension = winapi::D3D12_UAV_DIMENSION_TEXTURE1D;
+                *unsafe { desc.Texture1D_mut() } = winapi::D3D12_TEX1D_UAV {
+                    MipSlice: mip_slice,
+                }
+            }
+            image::ViewKind::D1Array => {
+                desc.ViewDimension = winapi::D3D12_UAV_DIMENSION_TEXTURE1DARRAY;
+                *unsafe { desc.Texture1DArray_mut() } = winapi::D3D12_TEX1D_ARRAY_UAV {
+                    MipSlice: mip_slice,
+                    FirstArraySlice: first_layer,
+                    ArraySize: num_layers,
+                }
+            }
+            image::ViewKind::D2 => {
+                desc.ViewDimension = winapi::D3D12_UAV_DIMENSION_TEXTURE2D;
+                *unsafe { desc.Texture2D_mut() } = winapi::D3D12_TEX2D_UAV {
+                    MipSlice: mip_slice,
+                    PlaneSlice: 0, //TODO
+                }
+            }
+            image::ViewKind::D2Array |
+            image::ViewKind::Cube |
+            image::ViewKind::CubeArray => {
+                desc.ViewDimension = winapi::D3D12_UAV_DIMENSION_TEXTURE2DARRAY;
+                *unsafe { desc.Texture2DArray_mut() } = winapi::D3D12_TEX2D_ARRAY_UAV {
+                    MipSlice: mip_slice,
+                    FirstArraySlice: first_layer,
+                    ArraySize: num_layers,
+                    PlaneSlice: 0, //TODO
+                }
+            }
+            image::ViewKind::D3 => {
+                desc.ViewDimension = winapi::D3D12_UAV_DIMENSION_TEXTURE3D;
+                *unsafe { desc.Texture3D_mut() } = winapi::D3D12_TEX3D_UAV {
+                    MipSlice: mip_slice,
+                    FirstWSlice: 0,
+                    WSize: !0, // all depth slices
                 }
             }
-            _ => unimplemented!()
         }
 
         unsafe {
@@ -380,12 +637,166 @@ impl Device {
 
         Ok(handle)
     }
+
+    /// Combined support of the view and depth-stencil formats of a format.
+    fn format_support(
+        &self,
+        format: format::Format,
+    ) -> Option<(winapi::D3D12_FORMAT_SUPPORT1, winapi::D3D12_FORMAT_SUPPORT2)> {
+        let view = conv::map_format(format);
+        let dsv = conv::map_format_dsv(format.0);
+        if view.is_none() && dsv.is_none() {
+            return None;
+        }
+
+        let mut support = (winapi::D3D12_FORMAT_SUPPORT1_NONE, winapi::D3D12_FORMAT_SUPPORT2_NONE);
+        for dxgi_format in view.into_iter().chain(dsv) {
+            let (support1, support2) = self.query_format_support(dxgi_format);
+            support = (support.0 | support1, support.1 | support2);
+        }
+        Some(support)
+    }
+
+    fn query_format_support(
+        &self,
+        format: winapi::DXGI_FORMAT,
+    ) -> (winapi::D3D12_FORMAT_SUPPORT1, winapi::D3D12_FORMAT_SUPPORT2) {
+        let mut data = winapi::D3D12_FEATURE_DATA_FORMAT_SUPPORT {
+            Format: format,
+            Support1: winapi::D3D12_FORMAT_SUPPORT1_NONE,
+            Support2: winapi::D3D12_FORMAT_SUPPORT2_NONE,
+        };
+        let hr = unsafe {
+            self.raw.clone().CheckFeatureSupport(
+                winapi::D3D12_FEATURE_FORMAT_SUPPORT,
+                &mut data as *mut _ as *mut _,
+                mem::size_of::<winapi::D3D12_FEATURE_DATA_FORMAT_SUPPORT>() as _,
+            )
+        };
+        if winapi::SUCCEEDED(hr) {
+            (data.Support1, data.Support2)
+        } else {
+            (winapi::D3D12_FORMAT_SUPPORT1_NONE, winapi::D3D12_FORMAT_SUPPORT2_NONE)
+        }
+    }
+
+    fn sample_count_mask(&self, format: winapi::DXGI_FORMAT) -> image::NumSamples {
+        let mut mask = 0;
+        for i in 0 .. 7 {
+            let mut data = winapi::D3D12_FEATURE_DATA_MULTISAMPLE_QUALITY_LEVELS {
+                Format: format,
+                SampleCount: 1 << i,
+                Flags: winapi::D3D12_MULTISAMPLE_QUALITY_LEVELS_FLAG_NONE,
+                NumQualityLevels: 0,
+            };
+            let hr = unsafe {
+                self.raw.clone().CheckFeatureSupport(
+                    winapi::D3D12_FEATURE_MULTISAMPLE_QUALITY_LEVELS,
+                    &mut data as *mut _ as *mut _,
+                    mem::size_of::<winapi::D3D12_FEATURE_DATA_MULTISAMPLE_QUALITY_LEVELS>() as _,
+                )
+            };
+            if winapi::SUCCEEDED(hr) && data.NumQualityLevels != 0 {
+                mask |= 1 << i;
+            }
+        }
+        mask
+    }
 }
 
+
 impl d::Device<B> for Device {
     fn get_features(&self) -> &Features { &self.features }
     fn get_limits(&self) -> &Limits { &self.limits }
 
+    fn format_properties(&self, format: format::Format) -> format::Properties {
+        match self.format_support(format) {
+            Some((support1, support2)) => conv::map_format_support(
+                format, support1, support2, self.private_caps.heterogeneous_resource_heaps),
+            None => format::Properties::empty(),
+        }
+    }
+
+    fn image_format_properties(
+        &self, kind: image::Kind, format: format::Format, tiling: image::Tiling, usage: image::Usage,
+    ) -> Option<image::FormatProperties> {
+        // Row-major textures are limited to cross-adapter sharing.
+        if tiling == image::Tiling::Linear {
+            return None;
+        }
+        let (support1, _) = self.format_support(format)?;
+        let has = |flag: winapi::D3D12_FORMAT_SUPPORT1| (support1 & flag).0 != 0;
+
+        let (dimension, max_extent, max_layers) = match kind {
+            image::Kind::D1(..) |
+            image::Kind::D1Array(..) => (
+                winapi::D3D12_FORMAT_SUPPORT1_TEXTURE1D,
+                d::Extent { width: winapi::D3D12_REQ_TEXTURE1D_U_DIMENSION, height: 1, depth: 1 },
+                winapi::D3D12_REQ_TEXTURE1D_ARRAY_AXIS_DIMENSION,
+            ),
+            image::Kind::D2(..) |
+            image::Kind::D2Array(..) => (
+                winapi::D3D12_FORMAT_SUPPORT1_TEXTURE2D,
+                d::Extent {
+                    width: winapi::D3D12_REQ_TEXTURE2D_U_OR_V_DIMENSION,
+                    height: winapi::D3D12_REQ_TEXTURE2D_U_OR_V_DIMENSION,
+                    depth: 1,
+                },
+                winapi::D3D12_REQ_TEXTURE2D_ARRAY_AXIS_DIMENSION,
+            ),
+            image::Kind::D3(..) => (
+                winapi::D3D12_FORMAT_SUPPORT1_TEXTURE3D,
+                d::Extent {
+                    width: winapi::D3D12_REQ_TEXTURE3D_U_V_OR_W_DIMENSION,
+                    height: winapi::D3D12_REQ_TEXTURE3D_U_V_OR_W_DIMENSION,
+                    depth: winapi::D3D12_REQ_TEXTURE3D_U_V_OR_W_DIMENSION,
+                },
+                1,
+            ),
+            image::Kind::Cube(..) |
+            image::Kind::CubeArray(..) => (
+                winapi::D3D12_FORMAT_SUPPORT1_TEXTURECUBE,
+                d::Extent {
+                    width: winapi::D3D12_REQ_TEXTURECUBE_DIMENSION,
+                    height: winapi::D3D12_REQ_TEXTURECUBE_DIMENSION,
+                    depth: 1,
+                },
+                winapi::D3D12_REQ_TEXTURE2D_ARRAY_AXIS_DIMENSION,
+            ),
+        };
+
+        if !has(dimension) ||
+            (usage.contains(image::SAMPLED) && !has(winapi::D3D12_FORMAT_SUPPORT1_SHADER_SAMPLE)) ||
+            (usage.contains(image::COLOR_ATTACHMENT) && !has(winapi::D3D12_FORMAT_SUPPORT1_RENDER_TARGET)) ||
+            (usage.contains(image::DEPTH_STENCIL_ATTACHMENT) && !has(winapi::D3D12_FORMAT_SUPPORT1_DEPTH_STENCIL)) ||
+            (usage.contains(image::STORAGE) && !has(winapi::D3D12_FORMAT_SUPPORT1_TYPED_UNORDERED_ACCESS_VIEW))
+        {
+            return None;
+        }
+
+        let multisampled = match kind {
+            image::Kind::D2(..) | image::Kind::D2Array(..) => {
+                usage.intersects(image::COLOR_ATTACHMENT | image::DEPTH_STENCIL_ATTACHMENT)
+            }
+            _ => false,
+        };
+        let sample_count_mask = if multisampled {
+            let dxgi_format = conv::map_format_dsv(format.0).or(conv::map_format(format));
+            self.sample_count_mask(dxgi_format.unwrap())
+        } else {
+            1
+        };
+
+        Some(image::FormatProperties {
+            max_extent,
+            max_levels: winapi::D3D12_REQ_MIP_LEVELS as _,
+            max_layers: max_layers as _,
+            sample_count_mask,
+            // Upper bound of the spec, the actual limit depends on the dedicated memory.
+            max_resource_size: (winapi::D3D12_REQ_RESOURCE_SIZE_IN_MEGABYTES_EXPRESSION_C_TERM as u64) << 20,
+        })
+    }
+
     fn allocate_memory(
         &mut self,
         mem_type: &MemoryType,
@@ -421,11 +832,52 @@ impl d::Device<B> for Device {
             return Err(d::OutOfMemory);
         }
         assert_eq!(winapi::S_OK, hr);
+        let heap: ComPtr<winapi::ID3D12Heap> = unsafe { ComPtr::new(heap as _) };
+
+        // D3D12 doesn't expose heap mapping, only resource mapping.
+        // Place a buffer over the whole heap to be able to map the memory.
+        let can_map = mem_type.properties.contains(memory::CPU_VISIBLE) &&
+            mem_type.id >> 2 <= 1; // heap allows buffers
+        let resource = if can_map {
+            let mut resource = ptr::null_mut();
+            let desc = winapi::D3D12_RESOURCE_DESC {
+                Dimension: winapi::D3D12_RESOURCE_DIMENSION_BUFFER,
+                Alignment: 0,
+                Width: size,
+                Height: 1,
+                DepthOrArraySize: 1,
+                MipLevels: 1,
+                Format: winapi::DXGI_FORMAT_UNKNOWN,
+                SampleDesc: winapi::DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                Layout: winapi::D3D12_TEXTURE_LAYOUT_ROW_MAJOR,
+                Flags: winapi::D3D12_RESOURCE_FLAG_NONE,
+            };
+
+            assert_eq!(winapi::S_OK, unsafe {
+                self.raw.CreatePlacedResource(
+                    heap.as_mut(),
+                    0,
+                    &desc,
+                    winapi::D3D12_RESOURCE_STATE_COMMON,
+                    ptr::null(),
+                    &dxguid::IID_ID3D12Resource,
+                    &mut resource,
+                )
+            });
+            Some(resource as *mut _)
+        } else {
+            None
+        };
 
         Ok(n::Memory {
-            heap: unsafe { ComPtr::new(heap as _) },
+            heap,
             ty: mem_type.clone(),
             size,
+            resource,
+            mapped: Mutex::new(false),
         })
     }
 
@@ -452,7 +904,7 @@ impl d::Device<B> for Device {
             .iter()
             .map(|att| AttachmentInfo {
                 sub_states: vec![SubState::Undefined; subpasses.len()],
-                target_state: if att.format.0.is_depth() {
+                target_state: if att.format.0.is_depth() || att.format.0.is_stencil() {
                     winapi::D3D12_RESOURCE_STATE_DEPTH_WRITE //TODO?
                 } else {
                     winapi::D3D12_RESOURCE_STATE_RENDER_TARGET
@@ -671,6 +1123,7 @@ impl d::Device<B> for Device {
         n::PipelineLayout {
             raw: signature,
             tables: set_tables,
+            set_bindings: sets.iter().map(|set| set.bindings.clone()).collect(),
         }
     }
 
@@ -679,30 +1132,59 @@ impl d::Device<B> for Device {
         descs: &[(pso::GraphicsShaderSet<'a, B>, &n::PipelineLayout, pass::Subpass<'a, B>, &pso::GraphicsPipelineDesc)],
     ) -> Vec<Result<n::GraphicsPipeline, pso::CreationError>> {
         descs.iter().map(|&(shaders, ref signature, ref subpass, ref desc)| {
-            let build_shader = |source: Option<pso::EntryPoint<'a, B>>| {
-                // TODO: better handle case where looking up shader fails
-                let shader = source.and_then(|src| src.module.shaders.get(src.entry));
-                match shader {
-                    Some(shader) => {
-                        winapi::D3D12_SHADER_BYTECODE {
-                            pShaderBytecode: unsafe { (**shader).GetBufferPointer() as *const _ },
-                            BytecodeLength: unsafe { (**shader).GetBufferSize() as u64 },
+            desc.validate_vertex_input(&self.features, &self.limits)?;
+            desc.validate_baked_states(&self.features, &self.limits)?;
+            desc.validate_blender(&self.features)?;
+            desc.validate_tessellation(&shaders, &self.limits)?;
+            desc.validate_stream_output(&self.features)?;
+            let color_formats = subpass.main_pass.subpasses
+                .get(subpass.index)
+                .ok_or(pso::CreationError::InvalidSubpass(subpass.index))?
+                .color_attachments
+                .iter()
+                .map(|&(id, _)| subpass.main_pass.attachments[id].format)
+                .collect::<Vec<_>>();
+            desc.validate_shader_interface(self, &shaders, &signature.set_bindings, &color_formats)?;
+
+            // Specialized shaders are compiled for this pipeline only.
+            let mut specialized = Vec::new();
+            let (vs, fs, gs, ds, hs) = {
+                let mut build_shader = |stage, source: Option<pso::EntryPoint<'a, B>>| {
+                    // TODO: better handle case where looking up shader fails
+                    let shader = match source {
+                        Some(ref src) if !src.specialization.is_empty() => {
+                            let mut blob = Self::compile_specialized(stage, src)?;
+                            let raw = blob.as_mut() as *mut winapi::ID3DBlob;
+                            specialized.push(blob);
+                            Some(raw)
                         }
-                    }
-                    None => {
-                        winapi::D3D12_SHADER_BYTECODE {
-                            pShaderBytecode: ptr::null(),
-                            BytecodeLength: 0,
+                        Some(ref src) => src.module.shaders.get(src.entry).cloned(),
+                        None => None,
+                    };
+                    Ok::<_, d::ShaderError>(match shader {
+                        Some(shader) => {
+                            winapi::D3D12_SHADER_BYTECODE {
+                                pShaderBytecode: unsafe { (*shader).GetBufferPointer() as *const _ },
+                                BytecodeLength: unsafe { (*shader).GetBufferSize() as u64 },
+                            }
                         }
-                    }
-                }
-            };
+                        None => {
+                            winapi::D3D12_SHADER_BYTECODE {
+                                pShaderBytecode: ptr::null(),
+                                BytecodeLength: 0,
+                            }
+                        }
+                    })
+                };
 
-            let vs = build_shader(Some(shaders.vertex));
-            let fs = build_shader(shaders.fragment);
-            let gs = build_shader(shaders.geometry);
-            let ds = build_shader(shaders.domain);
-            let hs = build_shader(shaders.hull);
+                (
+                    build_shader(pso::Stage::Vertex, Some(shaders.vertex))?,
+                    build_shader(pso::Stage::Fragment, shaders.fragment)?,
+                    build_shader(pso::Stage::Geometry, shaders.geometry)?,
+                    build_shader(pso::Stage::Domain, shaders.domain)?,
+                    build_shader(pso::Stage::Hull, shaders.hull)?,
+                )
+            };
 
             // Define input element descriptions
             let mut vs_reflect = shade::reflect_shader(&vs);
@@ -711,12 +1193,8 @@ impl d::Device<B> for Device {
                 desc.attributes
                     .iter()
                     .map(|attrib| {
-                        let buffer_desc = if let Some(buffer_desc) = desc.vertex_buffers.get(attrib.binding as usize) {
-                                buffer_desc
-                            } else {
-                                error!("Couldn't find associated vertex buffer description {:?}", attrib.binding);
-                                return Err(pso::CreationError::Other);
-                            };
+                        // Buffer indices are checked by the validation.
+                        let buffer_desc = &desc.vertex_buffers[attrib.binding as usize];
 
                         let input_elem =
                             if let Some(input_elem) = input_descs.iter().find(|elem| elem.semantic_index == attrib.location) {
@@ -726,6 +1204,8 @@ impl d::Device<B> for Device {
                                 return Err(pso::CreationError::Other);
                             };
 
+                        // Per-vertex data requires a step rate of zero,
+                        // which matches our rate of per-vertex buffers.
                         let slot_class = match buffer_desc.rate {
                             0 => winapi::D3D12_INPUT_CLASSIFICATION_PER_VERTEX_DATA,
                             _ => winapi::D3D12_INPUT_CLASSIFICATION_PER_INSTANCE_DATA,
@@ -739,7 +1219,7 @@ impl d::Device<B> for Device {
                                 Some(fm) => fm,
                                 None => {
                                     error!("Unable to find DXGI format for {:?}", format);
-                                    return Err(pso::CreationError::Other);
+                                    return Err(pso::InputError::UnsupportedFormat(attrib.location, format).into());
                                 }
                             },
                             InputSlot: attrib.binding as _,
@@ -772,21 +1252,63 @@ impl d::Device<B> for Device {
                 (rtvs, num_rtvs)
             };
 
+            // Captured outputs are looked up in the output signature of the last
+            // vertex processing stage, the location matching the semantic index.
+            // The reflection is kept alive as it owns the semantic names.
+            let (so_entries, so_strides, so_rasterized, _so_reflect) = match desc.stream_output {
+                Some(ref so) => {
+                    let last_stage = if !gs.pShaderBytecode.is_null() {
+                        &gs
+                    } else if !ds.pShaderBytecode.is_null() {
+                        &ds
+                    } else {
+                        &vs
+                    };
+                    let mut reflection = shade::reflect_shader(last_stage);
+                    let outputs = shade::reflect_output_elements(&mut reflection);
+                    let entries = so.elements
+                        .iter()
+                        .map(|elem| {
+                            let output = outputs
+                                .iter()
+                                .find(|output| {
+                                    !output.system_value &&
+                                    output.semantic_index == elem.location &&
+                                    output.stream == elem.stream
+                                })
+                                .ok_or(pso::StreamOutputError::MissingOutput(elem.location))?;
+                            Ok(winapi::D3D12_SO_DECLARATION_ENTRY {
+                                Stream: elem.stream,
+                                SemanticName: output.semantic_name,
+                                SemanticIndex: output.semantic_index,
+                                StartComponent: elem.start_component,
+                                ComponentCount: elem.component_count,
+                                OutputSlot: elem.buffer,
+                            })
+                        })
+                        .collect::<Result<Vec<_>, pso::StreamOutputError>>()?;
+                    // D3D12_SO_NO_RASTERIZED_STREAM
+                    (entries, so.strides.clone(), so.rasterized_stream.unwrap_or(!0), Some(reflection))
+                }
+                None => (Vec::new(), Vec::new(), 0, None),
+            };
+
             // Setup pipeline description
             let pso_desc = winapi::D3D12_GRAPHICS_PIPELINE_STATE_DESC {
                 pRootSignature: signature.raw,
                 VS: vs, PS: fs, GS: gs, DS: ds, HS: hs,
                 StreamOutput: winapi::D3D12_STREAM_OUTPUT_DESC {
-                    pSODeclaration: ptr::null(),
-                    NumEntries: 0,
-                    pBufferStrides: ptr::null(),
-                    NumStrides: 0,
-                    RasterizedStream: 0,
+                    pSODeclaration: if so_entries.is_empty() { ptr::null() } else { so_entries.as_ptr() },
+                    NumEntries: so_entries.len() as _,
+                    pBufferStrides: if so_strides.is_empty() { ptr::null() } else { so_strides.as_ptr() },
+                    NumStrides: so_strides.len() as _,
+                    RasterizedStream: so_rasterized,
                 },
                 BlendState: winapi::D3D12_BLEND_DESC {
                     AlphaToCoverageEnable: if desc.blender.alpha_coverage { winapi::TRUE } else { winapi::FALSE },
-                    IndependentBlendEnable: winapi::TRUE,
-                    RenderTarget: conv::map_render_targets(&desc.blender.targets),
+                    // Logic operations are only allowed without independent blending.
+                    IndependentBlendEnable: if desc.blender.logic_op.is_some() { winapi::FALSE } else { winapi::TRUE },
+                    RenderTarget: conv::map_render_targets(&desc.blender.targets, desc.blender.logic_op),
                 },
                 SampleMask: winapi::UINT::max_value(),
                 RasterizerState: conv::map_rasterizer(&desc.rasterizer),
@@ -797,6 +1319,7 @@ impl d::Device<B> for Device {
                             depth: None,
                             front: None,
                             back: None,
+                            depth_bounds: false,
                         }
                     }),
                 InputLayout: winapi::D3D12_INPUT_LAYOUT_DESC {
@@ -834,7 +1357,11 @@ impl d::Device<B> for Device {
             };
 
             if winapi::SUCCEEDED(hr) {
-                Ok(n::GraphicsPipeline { raw: pipeline, topology })
+                Ok(n::GraphicsPipeline {
+                    raw: pipeline,
+                    topology,
+                    baked_states: desc.baked_states.clone(),
+                })
             } else {
                 Err(pso::CreationError::Other)
             }
@@ -846,13 +1373,23 @@ impl d::Device<B> for Device {
         descs: &[(pso::EntryPoint<'a, B>, &n::PipelineLayout)],
     ) -> Vec<Result<n::ComputePipeline, pso::CreationError>> {
         descs.iter().map(|&(shader, ref signature)| {
+            // Keeps a specialized shader alive until the pipeline is created.
+            let mut specialized = if shader.specialization.is_empty() {
+                None
+            } else {
+                Some(Self::compile_specialized(pso::Stage::Compute, &shader)?)
+            };
             let cs = {
                 // TODO: better handle case where looking up shader fails
-                match shader.module.shaders.get(shader.entry) {
+                let blob = match specialized {
+                    Some(ref mut blob) => Some(blob.as_mut() as *mut winapi::ID3DBlob),
+                    None => shader.module.shaders.get(shader.entry).cloned(),
+                };
+                match blob {
                     Some(shader) => {
                         winapi::D3D12_SHADER_BYTECODE {
-                            pShaderBytecode: unsafe { (**shader).GetBufferPointer() as *const _ },
-                            BytecodeLength: unsafe { (**shader).GetBufferSize() as u64 },
+                            pShaderBytecode: unsafe { (*shader).GetBufferPointer() as *const _ },
+                            BytecodeLength: unsafe { (*shader).GetBufferSize() as u64 },
                         }
                     }
                     None => {
@@ -904,58 +1441,8 @@ impl d::Device<B> for Device {
     }
 
     fn create_shader_module(&mut self, raw_data: &[u8]) -> Result<n::ShaderModule, d::ShaderError> {
-        // spec requires "codeSize must be a multiple of 4"
-        assert_eq!(raw_data.len() & 3, 0);
-
-        let module = spirv::Module::from_words(unsafe {
-            slice::from_raw_parts(
-                raw_data.as_ptr() as *const u32,
-                raw_data.len() / mem::size_of::<u32>(),
-            )
-        });
-
-        let mut ast = spirv::Ast::<hlsl::Target>::parse(&module)
-            .map_err(|err| {
-                let msg =  match err {
-                    SpirvErrorCode::CompilationError(msg) => msg,
-                    SpirvErrorCode::Unhandled => "Unknown parsing error".into(),
-                };
-                d::ShaderError::CompilationFailed(msg)
-            })?;
-
-        // Patch descriptor sets due to the splitting of descriptor heaps into
-        // SrvCbvUav and sampler heap. Each set will have a new location to match
-        // the layout of the root signatures.
-        let shader_resources = ast.get_shader_resources().map_err(gen_query_error)?;
-        for image in &shader_resources.separate_images {
-            let set = ast.get_decoration(image.id, spirv::Decoration::DescriptorSet).map_err(gen_query_error)?;
-            ast.set_decoration(image.id, spirv::Decoration::DescriptorSet, 2*set)
-               .map_err(gen_unexpected_error)?;
-        }
-
-        for sampler in &shader_resources.separate_samplers {
-            let set = ast.get_decoration(sampler.id, spirv::Decoration::DescriptorSet).map_err(gen_query_error)?;
-            ast.set_decoration(sampler.id, spirv::Decoration::DescriptorSet, 2*set+1)
-               .map_err(gen_unexpected_error)?;
-        }
-
         let shader_model = hlsl::ShaderModel::V5_1;
-        let mut compile_options = hlsl::CompilerOptions::default();
-        compile_options.shader_model = shader_model;
-        compile_options.vertex.invert_y = true;
-
-        ast.set_compiler_options(&compile_options)
-           .map_err(gen_unexpected_error)?;
-        let shader_code = ast.compile()
-            .map_err(|err| {
-                let msg =  match err {
-                    SpirvErrorCode::CompilationError(msg) => msg,
-                    SpirvErrorCode::Unhandled => "Unknown compile error".into(),
-                };
-                d::ShaderError::CompilationFailed(msg)
-            })?;
-
-        debug!("SPIRV-Cross generated shader:\n{}", shader_code);
+        let (ast, shader_code) = Self::translate_spirv(raw_data, shader_model, &[])?;
 
         let mut shader_map = BTreeMap::new();
         let entry_points = ast.get_entry_points().map_err(gen_query_error)?;
@@ -975,7 +1462,14 @@ impl d::Device<B> for Device {
 
             shader_map.insert(entry_point.name, shader_blob);
         }
-        Ok(n::ShaderModule { shaders: shader_map })
+        Ok(n::ShaderModule { shaders: shader_map, spirv: Some(raw_data.to_vec()) })
+    }
+
+    fn reflect_shader_module(&self, module: &n::ShaderModule) -> Result<pso::ShaderReflection, pso::ReflectError> {
+        match module.spirv {
+            Some(ref spirv) => pso::ShaderReflection::from_spirv(spirv),
+            None => Err(pso::ReflectError::NotSpirv),
+        }
     }
 
     fn create_buffer(
@@ -983,6 +1477,7 @@ impl d::Device<B> for Device {
         mut size: u64,
         stride: u64,
         usage: buffer::Usage,
+        _sharing: memory::SharingMode,
     ) -> Result<UnboundBuffer, buffer::CreationError> {
         if usage.contains(buffer::UNIFORM) {
             // Constant buffer view sizes need to be aligned.
@@ -1072,20 +1567,36 @@ impl d::Device<B> for Device {
         kind: image::Kind,
         mip_levels: image::Level,
         format: format::Format,
+        tiling: image::Tiling,
         usage: image::Usage,
+        _sharing: memory::SharingMode,
     ) -> Result<UnboundImage, image::CreationError> {
-        let mut aspects = image::AspectFlags::empty();
-        let bits = format.0.describe_bits();
-        if bits.color + bits.alpha != 0 {
-            aspects |= image::ASPECT_COLOR;
-        }
-        if bits.depth != 0 {
-            aspects |= image::ASPECT_DEPTH;
-        }
-        if bits.stencil != 0 {
-            aspects |= image::ASPECT_STENCIL;
+        // Row-major textures are limited to cross-adapter sharing.
+        if tiling == image::Tiling::Linear {
+            return Err(image::CreationError::Tiling(tiling));
         }
 
+        let aspects = format.0.aspects();
+        let block = format.0.describe_block();
+        let mut flags = conv::map_image_flags(usage);
+        // Blits into color images are implemented by rendering a fullscreen triangle.
+        if usage.contains(image::TRANSFER_DST) &&
+            self.format_properties(format).optimal_tiling.contains(format::BLIT_DST)
+        {
+            flags = flags | winapi::D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET;
+        }
+        let can_target = (flags & (winapi::D3D12_RESOURCE_FLAG_ALLOW_RENDER_TARGET | winapi::D3D12_RESOURCE_FLAG_ALLOW_DEPTH_STENCIL)).0 != 0;
+        let view_format = match conv::map_format(format) {
+            Some(format) => format,
+            None => return Err(image::CreationError::Format(format.0, Some(format.1))),
+        };
+        // Depth formats are typeless to be viewed as depth-stencil targets and
+        // shader resources, color formats only if views may change the channel type.
+        let typeless = if aspects != image::ASPECT_COLOR || usage.contains(image::MUTABLE_FORMAT) {
+            conv::map_format_typeless(format.0)
+        } else {
+            None
+        };
         let (width, height, depth, aa) = kind.get_dimensions();
         let dimension = match kind {
             image::Kind::D1(..) |
@@ -1103,16 +1614,13 @@ impl d::Device<B> for Device {
             Height: height as u32,
             DepthOrArraySize: depth,
             MipLevels: mip_levels as u16,
-            Format: match conv::map_format(format) {
-                Some(format) => format,
-                None => return Err(image::CreationError::Format(format.0, Some(format.1))),
-            },
+            Format: typeless.unwrap_or(view_format),
             SampleDesc: winapi::DXGI_SAMPLE_DESC {
                 Count: aa.get_num_fragments() as u32,
                 Quality: 0,
             },
             Layout: winapi::D3D12_TEXTURE_LAYOUT_UNKNOWN,
-            Flags: conv::map_image_flags(usage),
+            Flags: flags,
         };
 
         let mut alloc_info = unsafe { mem::zeroed() };
@@ -1123,16 +1631,18 @@ impl d::Device<B> for Device {
         Ok(UnboundImage {
             dsv_format: conv::map_format_dsv(format.0).unwrap_or(desc.Format),
             desc,
+            format,
+            view_format,
             requirements: memory::Requirements {
                 size: alloc_info.SizeInBytes,
                 alignment: alloc_info.Alignment,
                 type_mask: if self.private_caps.heterogeneous_resource_heaps { 0x7 }
-                    else if usage.can_target() { 0x7<<12 } else { 0x7<<8 },
+                    else if can_target { 0x7<<12 } else { 0x7<<8 },
             },
             kind,
             usage,
             aspects,
-            bits_per_texel: bits.total,
+            block,
             num_levels: mip_levels,
             num_layers: kind.get_num_layers(),
         })
@@ -1142,6 +1652,12 @@ impl d::Device<B> for Device {
         image.requirements
     }
 
+    fn get_image_subresource_footprint(
+        &mut self, _image: &n::Image, _aspect: image::AspectFlags, _subresource: image::Subresource,
+    ) -> image::SubresourceFootprint {
+        unreachable!("Linear images are rejected by `create_image`")
+    }
+
     fn bind_image_memory(
         &mut self,
         memory: &n::Memory,
@@ -1175,12 +1691,29 @@ impl d::Device<B> for Device {
         // for this, we need to check the format and force the `RENDER_TARGET` flag behind the user's back
         // if the format supports being rendered into, allowing us to create clear_Xv
 
+        // Clear views cover all the layers of the image.
+        let view_kind = match image.kind {
+            image::Kind::D1(..) => image::ViewKind::D1,
+            image::Kind::D1Array(..) => image::ViewKind::D1Array,
+            image::Kind::D2(..) => image::ViewKind::D2,
+            image::Kind::D2Array(..) |
+            image::Kind::Cube(..) |
+            image::Kind::CubeArray(..) => image::ViewKind::D2Array,
+            image::Kind::D3(..) => image::ViewKind::D3,
+        };
+        let msaa = image.kind.get_dimensions().3 != image::AaMode::Single;
+
         Ok(n::Image {
             resource: resource as *mut _,
             kind: image.kind,
+            format: image.format,
             usage: image.usage,
-            dxgi_format: image.desc.Format,
-            bits_per_texel: image.bits_per_texel,
+            dxgi_format: if image.aspects == image::ASPECT_COLOR {
+                image.view_format
+            } else {
+                image.desc.Format
+            },
+            block: image.block,
             num_levels: image.num_levels,
             num_layers: image.num_layers,
             clear_cv: if image.aspects.contains(image::ASPECT_COLOR) && image.usage.contains(image::COLOR_ATTACHMENT) {
@@ -1189,7 +1722,7 @@ impl d::Device<B> for Device {
                     levels: 0 .. 1, //TODO?
                     layers: 0 .. image.num_layers,
                 };
-                Some(self.view_image_as_render_target(resource as *mut _, image.kind, image.desc.Format, &range).unwrap())
+                Some(self.view_image_as_render_target(resource as *mut _, view_kind, msaa, image.view_format, &range).unwrap())
             } else {
                 None
             },
@@ -1199,7 +1732,7 @@ impl d::Device<B> for Device {
                     levels: 0 .. 1, //TODO?
                     layers: 0 .. image.num_layers,
                 };
-                Some(self.view_image_as_depth_stencil(resource as *mut _, image.kind, image.dsv_format, &range).unwrap())
+                Some(self.view_image_as_depth_stencil(resource as *mut _, view_kind, msaa, image.dsv_format, &range).unwrap())
             } else {
                 None
             },
@@ -1209,76 +1742,131 @@ impl d::Device<B> for Device {
                     levels: 0 .. 1, //TODO?
                     layers: 0 .. image.num_layers,
                 };
-                Some(self.view_image_as_depth_stencil(resource as *mut _, image.kind, image.dsv_format, &range).unwrap())
+                Some(self.view_image_as_depth_stencil(resource as *mut _, view_kind, msaa, image.dsv_format, &range).unwrap())
             } else {
                 None
             },
         })
     }
 
+    fn create_sparse_buffer(
+        &mut self, _size: u64, _stride: u64, _usage: buffer::Usage, _sharing: memory::SharingMode,
+    ) -> Result<n::Buffer, buffer::CreationError> {
+        // `Features::sparse_binding` isn't supported.
+        Err(buffer::CreationError::Sparse)
+    }
+
+    fn get_sparse_buffer_requirements(&mut self, _buffer: &n::Buffer) -> Requirements {
+        unreachable!("Sparse buffers are rejected by `create_sparse_buffer`")
+    }
+
+    fn create_sparse_image(
+        &mut self, _kind: image::Kind, _mip_levels: image::Level, _format: format::Format, _usage: image::Usage,
+        _sharing: memory::SharingMode,
+    ) -> Result<n::Image, image::CreationError> {
+        // `Features::sparse_binding` isn't supported.
+        Err(image::CreationError::Sparse)
+    }
+
+    fn get_sparse_image_requirements(&mut self, _image: &n::Image) -> (Requirements, d::Extent) {
+        unreachable!("Sparse images are rejected by `create_sparse_image`")
+    }
+
     fn create_image_view(
         &mut self,
         image: &n::Image,
+        kind: image::ViewKind,
         format: format::Format,
         _swizzle: format::Swizzle,
         range: image::SubresourceRange,
     ) -> Result<n::ImageView, image::ViewError> {
+        image.kind.check_view(image.format, kind, format, &range)?;
+        let msaa = image.kind.get_dimensions().3 != image::AaMode::Single;
+
         let format_raw = conv::map_format(format).ok_or(image::ViewError::BadFormat);
+        // Only typeless resources can be viewed with a different channel type,
+        // color images are typeless if created with a mutable format.
+        if format != image.format && image.format.0.aspects() == image::ASPECT_COLOR &&
+            !image.usage.contains(image::MUTABLE_FORMAT)
+        {
+            return Err(image::ViewError::BadFormat);
+        }
 
         Ok(n::ImageView {
             resource: image.resource,
             handle_srv: if image.usage.contains(image::SAMPLED) {
-                Some(self.view_image_as_shader_resource(image.resource, image.kind, format_raw.clone()?, &range)?)
+                Some(self.view_image_as_shader_resource(image.resource, kind, msaa, format_raw.clone()?, &range)?)
             } else {
                 None
             },
             handle_rtv: if image.usage.contains(image::COLOR_ATTACHMENT) {
-                Some(self.view_image_as_render_target(image.resource, image.kind, format_raw.clone()?, &range)?)
+                Some(self.view_image_as_render_target(image.resource, kind, msaa, format_raw.clone()?, &range)?)
             } else {
                 None
             },
             handle_dsv: if image.usage.contains(image::DEPTH_STENCIL_ATTACHMENT) {
                 let fmt = conv::map_format_dsv(format.0).ok_or(image::ViewError::BadFormat);
-                Some(self.view_image_as_depth_stencil(image.resource, image.kind, fmt?, &range)?)
+                Some(self.view_image_as_depth_stencil(image.resource, kind, msaa, fmt?, &range)?)
             } else {
                 None
             },
             handle_uav: if image.usage.contains(image::STORAGE) {
-                Some(self.view_image_as_storage(image.resource, image.kind, format_raw?, &range)?)
+                // Multisampled images can't be bound as unordered access views.
+                if msaa {
+                    return Err(image::ViewError::Unsupported);
+                }
+                Some(self.view_image_as_storage(image.resource, kind, format_raw?, &range)?)
             } else {
                 None
             },
         })
     }
 
-    fn create_sampler(&mut self, info: image::SamplerInfo) -> n::Sampler {
+    fn create_sampler(&mut self, info: image::SamplerInfo) -> Result<n::Sampler, image::SamplerError> {
+        info.validate(&self.features)?;
+
         let handle = self.sampler_pool.lock().unwrap().alloc_handles(1).cpu;
 
-        let op = match info.comparison {
-            Some(_) => conv::FilterOp::Comparison,
-            None => conv::FilterOp::Product,
+        let op = match (info.comparison, info.reduction) {
+            (Some(_), _) => conv::FilterOp::Comparison,
+            (None, image::ReductionMode::WeightedAverage) => conv::FilterOp::Product,
+            (None, image::ReductionMode::Minimum) => conv::FilterOp::Minimum,
+            (None, image::ReductionMode::Maximum) => conv::FilterOp::Maximum,
+        };
+        let max_anisotropy = match info.anisotropic {
+            image::Anisotropic::On(max) if max > 1 => max as _,
+            _ => 0,
+        };
+        let min_lod: f32 = info.lod_range.start.into();
+        let max_lod: f32 = match info.mip_filter {
+            Some(_) => info.lod_range.end.into(),
+            None => min_lod,
         };
+
         let desc = winapi::D3D12_SAMPLER_DESC {
-            Filter: conv::map_filter(info.filter, op),
+            Filter: conv::map_filter(
+                info.mag_filter,
+                info.min_filter,
+                info.mip_filter.unwrap_or(image::Filter::Nearest),
+                max_anisotropy != 0,
+                op,
+            ),
             AddressU: conv::map_wrap(info.wrap_mode.0),
             AddressV: conv::map_wrap(info.wrap_mode.1),
             AddressW: conv::map_wrap(info.wrap_mode.2),
             MipLODBias: info.lod_bias.into(),
-            MaxAnisotropy: match info.filter {
-                image::FilterMethod::Anisotropic(max) => max as _, // TODO: check support here?
-                _ => 0,
-            },
+            MaxAnisotropy: max_anisotropy,
             ComparisonFunc: conv::map_function(info.comparison.unwrap_or(state::Comparison::Always)),
             BorderColor: info.border.into(),
-            MinLOD: info.lod_range.start.into(),
-            MaxLOD: info.lod_range.end.into(),
+            MinLOD: min_lod,
+            MaxLOD: max_lod,
         };
 
         unsafe {
             self.raw.CreateSampler(&desc, handle);
         }
 
-        n::Sampler { handle }
+        Ok(n::Sampler { handle })
     }
 
     fn create_descriptor_pool(
@@ -1464,6 +2052,61 @@ impl d::Device<B> for Device {
             });
     }
 
+    fn map_memory(&mut self, memory: &n::Memory, range: Range<u64>)
+        -> Result<*mut u8, mapping::Error>
+    {
+        let resource = match memory.resource {
+            Some(resource) => resource,
+            None => return Err(mapping::Error::InvalidAccess),
+        };
+        if range.start > range.end || range.end > memory.size {
+            return Err(mapping::Error::OutOfBounds);
+        }
+        let mut mapped = memory.mapped.lock().unwrap();
+        if *mapped {
+            return Err(mapping::Error::InvalidAccess);
+        }
+
+        let mut ptr = ptr::null_mut();
+        let hr = unsafe {
+            // Null read range: the whole heap may be read
+            (*resource).Map(0, ptr::null(), &mut ptr)
+        };
+        match hr {
+            winapi::S_OK => (),
+            winapi::E_OUTOFMEMORY => return Err(mapping::Error::OutOfMemory),
+            _ => return Err(mapping::Error::InvalidAccess),
+        }
+        *mapped = true;
+
+        Ok(unsafe { (ptr as *mut u8).offset(range.start as _) })
+    }
+
+    fn flush_mapped_memory_ranges(
+        &mut self, _ranges: &[(&n::Memory, Range<u64>)]
+    ) -> Result<(), d::OutOfMemory> {
+        // CPU visible memory is always coherent
+        Ok(())
+    }
+
+    fn invalidate_mapped_memory_ranges(
+        &mut self, _ranges: &[(&n::Memory, Range<u64>)]
+    ) -> Result<(), d::OutOfMemory> {
+        // CPU visible memory is always coherent
+        Ok(())
+    }
+
+    fn unmap_memory(&mut self, memory: &n::Memory) {
+        let mut mapped = memory.mapped.lock().unwrap();
+        match memory.resource {
+            Some(resource) if *mapped => {
+                unsafe { (*resource).Unmap(0, ptr::null()) };
+                *mapped = false;
+            }
+            _ => error!("Memory wasn't mapped"),
+        }
+    }
+
     fn acquire_mapping_raw(&mut self, buf: &n::Buffer, read: Option<Range<u64>>)
         -> Result<*mut u8, mapping::Error>
     {
@@ -1479,14 +2122,19 @@ impl d::Device<B> for Device {
         };
 
         let mut ptr = ptr::null_mut();
-        assert_eq!(winapi::S_OK, unsafe {
+        let hr = unsafe {
             (*buf.resource).Map(0, &read_range, &mut ptr)
-        });
-
-        Ok(ptr as *mut _)
+        };
+        match hr {
+            winapi::S_OK => Ok(ptr as *mut _),
+            winapi::E_OUTOFMEMORY => Err(mapping::Error::OutOfMemory),
+            _ => Err(mapping::Error::InvalidAccess),
+        }
     }
 
-    fn release_mapping_raw(&mut self, buf: &n::Buffer, wrote: Option<Range<u64>>) {
+    fn release_mapping_raw(&mut self, buf: &n::Buffer, wrote: Option<Range<u64>>)
+        -> Result<(), mapping::Error>
+    {
         let written_range = match wrote {
             Some(w) => winapi::D3D12_RANGE {
                 Begin: w.start,
@@ -1499,6 +2147,7 @@ impl d::Device<B> for Device {
         };
 
         unsafe { (*buf.resource).Unmap(0, &written_range) };
+        Ok(())
     }
 
     fn create_semaphore(&mut self) -> n::Semaphore {
@@ -1568,8 +2217,28 @@ impl d::Device<B> for Device {
         }
     }
 
-    fn free_memory(&mut self, _memory: n::Memory) {
-        // Just drop
+    fn create_event(&mut self) -> n::Event {
+        error!("Events are not supported");
+        n::Event
+    }
+
+    fn set_event(&mut self, _event: &n::Event) {
+        error!("Events are not supported");
+    }
+
+    fn reset_event(&mut self, _event: &n::Event) {
+        error!("Events are not supported");
+    }
+
+    fn get_event_status(&mut self, _event: &n::Event) -> bool {
+        error!("Events are not supported");
+        false
+    }
+
+    fn free_memory(&mut self, memory: n::Memory) {
+        if let Some(resource) = memory.resource {
+            unsafe { (*resource).Release(); }
+        }
     }
 
     fn destroy_shader_module(&mut self, shader_lib: n::ShaderModule) {
@@ -1636,4 +2305,8 @@ impl d::Device<B> for Device {
     fn destroy_semaphore(&mut self, _semaphore: n::Semaphore) {
         // Just drop, ComPtr backed
     }
+
+    fn destroy_event(&mut self, _event: n::Event) {
+        // Just drop
+    }
 }
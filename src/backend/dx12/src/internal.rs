@@ -0,0 +1,260 @@
+//! Internal pipelines used to emulate commands not natively supported by D3D12.
+
+use core::{image, pso, state};
+use d3d12;
+use dxguid;
+use spirv_cross::hlsl;
+use winapi;
+use wio::com::ComPtr;
+
+use std::{f32, mem, ptr};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use {conv, native as n, Device};
+
+/// Root parameter of the blit source SRV table.
+pub const BLIT_ROOT_SOURCE: u32 = 0;
+/// Root parameter of the blit region constants.
+pub const BLIT_ROOT_REGION: u32 = 1;
+
+/// Region constants of a single blit draw, see `shaders/blit.hlsl`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct BlitRegion {
+    pub offset: [f32; 2],
+    pub extent: [f32; 2],
+    pub layer: f32,
+    pub level: f32,
+}
+
+/// Pipelines and shaders shared by all command buffers of a device.
+pub struct ServicePipes {
+    device: ComPtr<winapi::ID3D12Device>,
+    pub(crate) blit_signature: ComPtr<winapi::ID3D12RootSignature>,
+    blit_vs: ComPtr<winapi::ID3DBlob>,
+    blit_ps_nearest: ComPtr<winapi::ID3DBlob>,
+    blit_ps_linear: ComPtr<winapi::ID3DBlob>,
+    // Blit pipelines, created on demand for each target format and filter.
+    blit_pipes: Mutex<HashMap<(u32, image::Filter), ComPtr<winapi::ID3D12PipelineState>>>,
+}
+unsafe impl Send for ServicePipes {}
+unsafe impl Sync for ServicePipes {}
+
+impl ServicePipes {
+    pub(crate) fn new(mut device: ComPtr<winapi::ID3D12Device>) -> Self {
+        let code = include_bytes!("../shaders/blit.hlsl");
+        let compile = |stage, entry| {
+            let blob = Device::compile_shader(stage, hlsl::ShaderModel::V5_0, entry, code)
+                .expect("Failed to compile internal blit shader");
+            unsafe { ComPtr::new(blob) }
+        };
+        let blit_vs = compile(pso::Stage::Vertex, "vs_blit");
+        let blit_ps_nearest = compile(pso::Stage::Fragment, "ps_blit_nearest");
+        let blit_ps_linear = compile(pso::Stage::Fragment, "ps_blit_linear");
+
+        let range = winapi::D3D12_DESCRIPTOR_RANGE {
+            RangeType: winapi::D3D12_DESCRIPTOR_RANGE_TYPE_SRV,
+            NumDescriptors: 1,
+            BaseShaderRegister: 0,
+            RegisterSpace: 0,
+            OffsetInDescriptorsFromTableStart: 0,
+        };
+
+        let mut source = winapi::D3D12_ROOT_PARAMETER {
+            ParameterType: winapi::D3D12_ROOT_PARAMETER_TYPE_DESCRIPTOR_TABLE,
+            ShaderVisibility: winapi::D3D12_SHADER_VISIBILITY_PIXEL,
+            .. unsafe { mem::zeroed() }
+        };
+        *unsafe { source.DescriptorTable_mut() } = winapi::D3D12_ROOT_DESCRIPTOR_TABLE {
+            NumDescriptorRanges: 1,
+            pDescriptorRanges: &range,
+        };
+
+        let mut region = winapi::D3D12_ROOT_PARAMETER {
+            ParameterType: winapi::D3D12_ROOT_PARAMETER_TYPE_32BIT_CONSTANTS,
+            ShaderVisibility: winapi::D3D12_SHADER_VISIBILITY_VERTEX,
+            .. unsafe { mem::zeroed() }
+        };
+        *unsafe { region.Constants_mut() } = winapi::D3D12_ROOT_CONSTANTS {
+            ShaderRegister: 0,
+            RegisterSpace: 0,
+            Num32BitValues: (mem::size_of::<BlitRegion>() / 4) as _,
+        };
+
+        let parameters = [source, region];
+
+        let static_sampler = |filter, register| winapi::D3D12_STATIC_SAMPLER_DESC {
+            Filter: filter,
+            AddressU: winapi::D3D12_TEXTURE_ADDRESS_MODE_CLAMP,
+            AddressV: winapi::D3D12_TEXTURE_ADDRESS_MODE_CLAMP,
+            AddressW: winapi::D3D12_TEXTURE_ADDRESS_MODE_CLAMP,
+            MipLODBias: 0.0,
+            MaxAnisotropy: 0,
+            ComparisonFunc: winapi::D3D12_COMPARISON_FUNC_ALWAYS,
+            BorderColor: winapi::D3D12_STATIC_BORDER_COLOR_TRANSPARENT_BLACK,
+            MinLOD: 0.0,
+            MaxLOD: f32::MAX,
+            ShaderRegister: register,
+            RegisterSpace: 0,
+            ShaderVisibility: winapi::D3D12_SHADER_VISIBILITY_PIXEL,
+        };
+        let static_samplers = [
+            static_sampler(winapi::D3D12_FILTER_MIN_MAG_MIP_POINT, 0),
+            static_sampler(winapi::D3D12_FILTER_MIN_MAG_LINEAR_MIP_POINT, 1),
+        ];
+
+        let desc = winapi::D3D12_ROOT_SIGNATURE_DESC {
+            NumParameters: parameters.len() as _,
+            pParameters: parameters.as_ptr(),
+            NumStaticSamplers: static_samplers.len() as _,
+            pStaticSamplers: static_samplers.as_ptr(),
+            Flags: winapi::D3D12_ROOT_SIGNATURE_FLAG_NONE,
+        };
+
+        let mut signature = ptr::null_mut();
+        let mut signature_raw = ptr::null_mut();
+        let mut error = ptr::null_mut();
+
+        unsafe {
+            let hr = d3d12::D3D12SerializeRootSignature(
+                &desc,
+                winapi::D3D_ROOT_SIGNATURE_VERSION_1,
+                &mut signature_raw,
+                &mut error,
+            );
+            if !error.is_null() {
+                (*error).Release();
+            }
+            assert!(winapi::SUCCEEDED(hr), "Failed to serialize blit root signature: {:x}", hr);
+
+            device.CreateRootSignature(
+                0,
+                (*signature_raw).GetBufferPointer(),
+                (*signature_raw).GetBufferSize(),
+                &dxguid::IID_ID3D12RootSignature,
+                &mut signature as *mut *mut _ as *mut *mut _,
+            );
+            (*signature_raw).Release();
+        }
+
+        ServicePipes {
+            device,
+            blit_signature: unsafe { ComPtr::new(signature) },
+            blit_vs,
+            blit_ps_nearest,
+            blit_ps_linear,
+            blit_pipes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get the pipeline for blitting into a target of the given format.
+    pub(crate) fn get_blit_pipe(
+        &self,
+        format: winapi::DXGI_FORMAT,
+        filter: image::Filter,
+    ) -> ComPtr<winapi::ID3D12PipelineState> {
+        let mut pipes = self.blit_pipes.lock().unwrap();
+        if let Some(pipe) = pipes.get(&(format.0, filter)) {
+            return pipe.clone();
+        }
+
+        let bytecode = |blob: &ComPtr<winapi::ID3DBlob>| unsafe {
+            let mut blob = blob.clone();
+            winapi::D3D12_SHADER_BYTECODE {
+                pShaderBytecode: blob.GetBufferPointer() as *const _,
+                BytecodeLength: blob.GetBufferSize() as u64,
+            }
+        };
+        let empty_shader = winapi::D3D12_SHADER_BYTECODE {
+            pShaderBytecode: ptr::null(),
+            BytecodeLength: 0,
+        };
+
+        let mut rtvs = [winapi::DXGI_FORMAT_UNKNOWN; 8];
+        rtvs[0] = format;
+
+        let pso_desc = winapi::D3D12_GRAPHICS_PIPELINE_STATE_DESC {
+            pRootSignature: self.blit_signature.as_mut() as *mut _,
+            VS: bytecode(&self.blit_vs),
+            PS: match filter {
+                image::Filter::Nearest => bytecode(&self.blit_ps_nearest),
+                image::Filter::Linear => bytecode(&self.blit_ps_linear),
+            },
+            GS: empty_shader,
+            DS: empty_shader,
+            HS: empty_shader,
+            StreamOutput: winapi::D3D12_STREAM_OUTPUT_DESC {
+                pSODeclaration: ptr::null(),
+                NumEntries: 0,
+                pBufferStrides: ptr::null(),
+                NumStrides: 0,
+                RasterizedStream: 0,
+            },
+            BlendState: winapi::D3D12_BLEND_DESC {
+                AlphaToCoverageEnable: winapi::FALSE,
+                IndependentBlendEnable: winapi::FALSE,
+                RenderTarget: conv::map_render_targets(&[pso::ColorInfo::from(state::MASK_ALL)], None),
+            },
+            SampleMask: winapi::UINT::max_value(),
+            RasterizerState: conv::map_rasterizer(&pso::Rasterizer::new_fill()),
+            DepthStencilState: conv::map_depth_stencil(&pso::DepthStencilInfo {
+                depth: None,
+                front: None,
+                back: None,
+                depth_bounds: false,
+            }),
+            InputLayout: winapi::D3D12_INPUT_LAYOUT_DESC {
+                pInputElementDescs: ptr::null(),
+                NumElements: 0,
+            },
+            IBStripCutValue: winapi::D3D12_INDEX_BUFFER_STRIP_CUT_VALUE_DISABLED,
+            PrimitiveTopologyType: winapi::D3D12_PRIMITIVE_TOPOLOGY_TYPE_TRIANGLE,
+            NumRenderTargets: 1,
+            RTVFormats: rtvs,
+            DSVFormat: winapi::DXGI_FORMAT_UNKNOWN,
+            SampleDesc: winapi::DXGI_SAMPLE_DESC {
+                Count: 1,
+                Quality: 0,
+            },
+            NodeMask: 0,
+            CachedPSO: winapi::D3D12_CACHED_PIPELINE_STATE {
+                pCachedBlob: ptr::null(),
+                CachedBlobSizeInBytes: 0,
+            },
+            Flags: winapi::D3D12_PIPELINE_STATE_FLAG_NONE,
+        };
+
+        let mut pipeline = ptr::null_mut();
+        let hr = unsafe {
+            self.device.clone().CreateGraphicsPipelineState(
+                &pso_desc,
+                &dxguid::IID_ID3D12PipelineState,
+                &mut pipeline as *mut *mut _ as *mut *mut _)
+        };
+        assert!(winapi::SUCCEEDED(hr), "Failed to create blit pipeline: {:x}", hr);
+
+        let pipe = unsafe { ComPtr::new(pipeline) };
+        pipes.insert((format.0, filter), pipe.clone());
+        pipe
+    }
+
+    /// Create a transient descriptor heap for the blit views.
+    pub(crate) fn create_blit_heap(
+        &self,
+        heap_type: winapi::D3D12_DESCRIPTOR_HEAP_TYPE,
+        shader_visible: bool,
+        capacity: usize,
+    ) -> n::DescriptorHeap {
+        Device::create_descriptor_heap_impl(
+            &mut self.device.clone(),
+            heap_type,
+            shader_visible,
+            capacity,
+        )
+    }
+
+    pub(crate) fn device(&self) -> ComPtr<winapi::ID3D12Device> {
+        self.device.clone()
+    }
+}
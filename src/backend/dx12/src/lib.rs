@@ -20,6 +20,7 @@ mod command;
 mod conv;
 mod device;
 mod free_list;
+mod internal;
 mod native;
 mod pool;
 mod shade;
@@ -100,6 +101,7 @@ pub struct QueueFamily;
 
 impl core::QueueFamily for QueueFamily {
     fn num_queues(&self) -> u32 { 1 } // TODO: infinite software queues actually
+    fn id(&self) -> core::QueueFamilyId { core::QueueFamilyId(0) }
 }
 
 /// Create associated command queues for a specific queue type
@@ -145,6 +147,7 @@ fn collect_queues<C>(
                             raw: ComPtr::new(queue),
                             device: device_raw,
                             list_type: qtype,
+                            service_pipes: device.service_pipes.clone(),
                         }
                     )
                 }
@@ -322,6 +325,7 @@ pub struct CommandQueue {
     pub(crate) raw: ComPtr<winapi::ID3D12CommandQueue>,
     device: ComPtr<winapi::ID3D12Device>,
     list_type: winapi::D3D12_COMMAND_LIST_TYPE,
+    service_pipes: Arc<internal::ServicePipes>,
 }
 unsafe impl Send for CommandQueue {} //blocked by ComPtr
 
@@ -345,6 +349,15 @@ impl core::RawCommandQueue<Backend> for CommandQueue {
             );
         }
     }
+
+    unsafe fn bind_sparse(
+        &mut self,
+        _submission: core::queue::RawSparseSubmission<Backend>,
+        _fence: Option<&native::Fence>,
+    ) -> core::SubmissionResult<()> {
+        // TODO: UpdateTileMappings
+        unreachable!("Sparse resources are rejected by `create_sparse_*`")
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -377,6 +390,8 @@ pub struct Device {
     heap_srv_cbv_uav: Arc<Mutex<native::DescriptorHeap>>,
     heap_sampler: Arc<Mutex<native::DescriptorHeap>>,
     events: Vec<winapi::HANDLE>,
+    // Internal pipelines for emulated commands.
+    service_pipes: Arc<internal::ServicePipes>,
 }
 unsafe impl Send for Device {} //blocked by ComPtr
 
@@ -485,6 +500,8 @@ impl Device {
             (false, _)            => (MemoryArchitecture::NUMA, HEAPS_NUMA),
         };
 
+        let service_pipes = Arc::new(internal::ServicePipes::new(device.clone()));
+
         Device {
             raw: device,
             features: Features { // TODO
@@ -495,22 +512,40 @@ impl Device {
                 draw_indexed_instanced: true,
                 draw_indexed_instanced_base_vertex: true,
                 draw_indexed_instanced_base: true,
-                instance_rate: false,
+                instance_rate: true,
                 vertex_base: false,
                 srgb_color: false,
                 constant_buffer: false,
                 unordered_access_view: false,
-                separate_blending_slots: false,
+                separate_blending_slots: true,
                 copy_buffer: false,
-                sampler_anisotropy: false,
+                sampler_anisotropy: true,
                 sampler_border_color: false,
+                // Minimum and maximum filtering come with tiled resources tier 2.
+                sampler_reduction: features.TiledResourcesTier != winapi::D3D12_TILED_RESOURCES_TIER_NOT_SUPPORTED &&
+                    features.TiledResourcesTier != winapi::D3D12_TILED_RESOURCES_TIER_1,
+                sampler_unnormalized: false,
+                persistent_mapping: true,
+                sparse_binding: false, // TODO: reserved resources
+                sparse_residency_buffer: false,
+                sparse_residency_image: false,
+                format_bc: true,
+                format_etc2: false,
+                format_astc_ldr: false,
+                depth_bounds: false, // TODO: ID3D12GraphicsCommandList1::OMSetDepthBounds
+                wide_lines: false,
+                logic_op: features.OutputMergerLogicOp != 0,
+                stream_output: true,
+                draw_auto: false, // No DrawAuto in D3D12
+                events: false,
+                blit_1d_3d: false, // Blits render into 2D views
                 sampler_lod_bias: false,
                 sampler_objects: false,
             },
             limits: Limits { // TODO
                 max_texture_size: 0,
-                max_patch_size: 0,
-                max_viewports: 0,
+                max_patch_size: 32, // D3D12_IA_PATCH_MAX_CONTROL_POINT_COUNT
+                max_viewports: 16, // D3D12_VIEWPORT_AND_SCISSORRECT_OBJECT_COUNT_PER_PIPELINE
                 max_compute_group_count: [
                     winapi::D3D12_CS_THREAD_GROUP_MAX_X  as _,
                     winapi::D3D12_CS_THREAD_GROUP_MAX_Y  as _,
@@ -521,9 +556,14 @@ impl Device {
                     1, //TODO
                     1, //TODO
                 ],
+                max_vertex_input_attributes: 32, // D3D12_IA_VERTEX_INPUT_STRUCTURE_ELEMENT_COUNT
+                max_vertex_input_bindings: 32, // D3D12_IA_VERTEX_INPUT_RESOURCE_SLOT_COUNT
+                max_vertex_input_attribute_offset: 2047,
+                max_vertex_input_binding_stride: 2048, // D3D12_REQ_MULTI_ELEMENT_STRUCTURE_SIZE_IN_BYTES
                 min_buffer_copy_offset_alignment: winapi::D3D12_TEXTURE_DATA_PLACEMENT_ALIGNMENT as _,
                 min_buffer_copy_pitch_alignment: winapi::D3D12_TEXTURE_DATA_PITCH_ALIGNMENT as _,
                 min_uniform_buffer_offset_alignment: 256, // Required alignment for CBVs
+                non_coherent_atom_size: 1, // CPU visible heaps are always coherent
             },
             private_caps: Capabilities {
                 heterogeneous_resource_heaps: features.ResourceHeapTier != winapi::D3D12_RESOURCE_HEAP_TIER_1,
@@ -538,6 +578,7 @@ impl Device {
             heap_srv_cbv_uav: Arc::new(Mutex::new(heap_srv_cbv_uav)),
             heap_sampler: Arc::new(Mutex::new(heap_sampler)),
             events: Vec::new(),
+            service_pipes,
         }
     }
 }
@@ -691,4 +732,5 @@ impl core::Backend for Backend {
 
     type Fence = native::Fence;
     type Semaphore = native::Semaphore;
+    type Event = native::Event;
 }
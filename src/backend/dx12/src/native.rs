@@ -1,4 +1,4 @@
-use core::{self, image, pass, pso, MemoryType};
+use core::{self, format, image, pass, pso, MemoryType};
 use free_list;
 use winapi::{self, UINT};
 use wio::com::ComPtr;
@@ -6,11 +6,14 @@ use Backend;
 
 use std::collections::BTreeMap;
 use std::ops::Range;
+use std::sync::Mutex;
 
 
 #[derive(Debug, Hash)]
 pub struct ShaderModule {
     pub(crate) shaders: BTreeMap<String, *mut winapi::ID3DBlob>,
+    /// Source SPIR-V, kept around for specialized recompilation.
+    pub(crate) spirv: Option<Vec<u8>>,
 }
 unsafe impl Send for ShaderModule { }
 unsafe impl Sync for ShaderModule { }
@@ -73,10 +76,11 @@ pub struct RenderPass {
     pub(crate) post_barriers: Vec<BarrierDesc>,
 }
 
-#[derive(Debug, Hash)]
+#[derive(Debug)]
 pub struct GraphicsPipeline {
     pub(crate) raw: *mut winapi::ID3D12PipelineState,
     pub(crate) topology: winapi::D3D12_PRIMITIVE_TOPOLOGY,
+    pub(crate) baked_states: pso::BakedStates,
 }
 unsafe impl Send for GraphicsPipeline { }
 unsafe impl Sync for GraphicsPipeline { }
@@ -105,6 +109,8 @@ pub struct PipelineLayout {
     // Storing for each associated descriptor set layout, which tables we created
     // in the root signature. This is required for binding descriptor sets.
     pub(crate) tables: Vec<SetTableTypes>,
+    // Bindings of each descriptor set layout, for validating shader interfaces.
+    pub(crate) set_bindings: Vec<Vec<pso::DescriptorSetLayoutBinding>>,
 }
 unsafe impl Send for PipelineLayout { }
 unsafe impl Sync for PipelineLayout { }
@@ -130,9 +136,10 @@ pub struct BufferView;
 pub struct Image {
     pub(crate) resource: *mut winapi::ID3D12Resource,
     pub(crate) kind: image::Kind,
+    pub(crate) format: format::Format,
     pub(crate) usage: image::Usage,
     pub(crate) dxgi_format: winapi::DXGI_FORMAT,
-    pub(crate) bits_per_texel: u8,
+    pub(crate) block: format::FormatBlock,
     pub(crate) num_levels: image::Level,
     pub(crate) num_layers: image::Layer,
     pub(crate) clear_cv: Option<winapi::D3D12_CPU_DESCRIPTOR_HANDLE>,
@@ -192,12 +199,21 @@ pub struct Semaphore {
 unsafe impl Send for Semaphore {}
 unsafe impl Sync for Semaphore {}
 
+#[derive(Debug)]
+pub struct Event;
+
 #[derive(Debug)]
 pub struct Memory {
     pub(crate) heap: ComPtr<winapi::ID3D12Heap>,
     pub(crate) ty: MemoryType,
     pub(crate) size: u64,
+    // Buffer spanning the whole heap, used for mapping CPU visible memory.
+    pub(crate) resource: Option<*mut winapi::ID3D12Resource>,
+    // Whether the resource is currently mapped, `Map` calls are reference counted.
+    pub(crate) mapped: Mutex<bool>,
 }
+unsafe impl Send for Memory { }
+unsafe impl Sync for Memory { }
 
 #[derive(Debug)]
 pub struct DescriptorRange {
@@ -2,16 +2,19 @@ use wio::com::ComPtr;
 use dxguid;
 use std::ptr;
 use std::os::raw::c_void;
+use std::sync::Arc;
 use winapi;
 
 use core::pool;
 use command::CommandBuffer;
+use internal::ServicePipes;
 use {Backend, CommandQueue};
 
 pub struct RawCommandPool {
     inner: ComPtr<winapi::ID3D12CommandAllocator>,
     device: ComPtr<winapi::ID3D12Device>,
     list_type: winapi::D3D12_COMMAND_LIST_TYPE,
+    service_pipes: Arc<ServicePipes>,
 }
 
 impl RawCommandPool {
@@ -55,7 +58,7 @@ impl pool::RawCommandPool<Backend> for RawCommandPool {
 
     fn allocate(&mut self, num: usize) -> Vec<CommandBuffer> {
         (0..num)
-            .map(|_| CommandBuffer::new(self.create_command_list(), self.inner.clone()))
+            .map(|_| CommandBuffer::new(self.create_command_list(), self.inner.clone(), self.service_pipes.clone()))
             .collect()
     }
 
@@ -82,6 +85,7 @@ impl pool::RawCommandPool<Backend> for RawCommandPool {
             inner: ComPtr::new(command_allocator),
             device: queue.device.clone(),
             list_type: queue.list_type,
+            service_pipes: queue.service_pipes.clone(),
         }
     }
 }
@@ -51,3 +51,36 @@ pub fn reflect_input_elements(
         }
     }).collect()
 }
+
+#[derive(Debug)]
+pub struct OutputElemDesc {
+    pub semantic_name: winapi::LPCSTR,
+    pub semantic_index: winapi::UINT,
+    pub stream: winapi::UINT,
+    pub system_value: bool,
+}
+
+pub fn reflect_output_elements(
+    reflection: &mut ComPtr<winapi::ID3D12ShaderReflection>
+) -> Vec<OutputElemDesc> {
+    let shader_desc = unsafe {
+        let mut desc = mem::zeroed();
+        reflection.GetDesc(&mut desc);
+        desc
+    };
+
+    (0 .. shader_desc.OutputParameters).map(|i| {
+        let output_desc = unsafe {
+            let mut desc = mem::zeroed();
+            reflection.GetOutputParameterDesc(i, &mut desc);
+            desc
+        };
+
+        OutputElemDesc {
+            semantic_name: output_desc.SemanticName,
+            semantic_index: output_desc.SemanticIndex,
+            stream: output_desc.Stream,
+            system_value: output_desc.SystemValueType != winapi::D3D_NAME_UNDEFINED,
+        }
+    }).collect()
+}
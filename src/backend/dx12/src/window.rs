@@ -151,9 +151,10 @@ impl core::Surface<Backend> for Surface {
             n::Image {
                 resource,
                 kind,
+                format: config.color_format,
                 usage: image::COLOR_ATTACHMENT,
                 dxgi_format: format,
-                bits_per_texel: config.color_format.0.describe_bits().total,
+                block: config.color_format.0.describe_block(),
                 num_levels: 1,
                 num_layers: 1,
                 clear_cv: Some(rtv_handle),
@@ -46,6 +46,7 @@ impl core::Backend for Backend {
 
     type Fence = ();
     type Semaphore = ();
+    type Event = ();
 }
 
 /// Dummy adapter.
@@ -70,6 +71,12 @@ impl core::RawCommandQueue<Backend> for CommandQueue {
     unsafe fn submit_raw(&mut self, _: core::RawSubmission<Backend>, _: Option<&()>) {
         unimplemented!()
     }
+
+    unsafe fn bind_sparse(
+        &mut self, _: core::queue::RawSparseSubmission<Backend>, _: Option<&()>,
+    ) -> core::SubmissionResult<()> {
+        unimplemented!()
+    }
 }
 
 /// Dummy device doing nothing.
@@ -84,6 +91,16 @@ impl core::Device<Backend> for Device {
         unimplemented!()
     }
 
+    fn format_properties(&self, _: format::Format) -> format::Properties {
+        unimplemented!()
+    }
+
+    fn image_format_properties(
+        &self, _: image::Kind, _: format::Format, _: image::Tiling, _: image::Usage,
+    ) -> Option<image::FormatProperties> {
+        unimplemented!()
+    }
+
     fn allocate_memory(&mut self, _: &core::MemoryType, _: u64) -> Result<(), device::OutOfMemory> {
         unimplemented!()
     }
@@ -122,10 +139,14 @@ impl core::Device<Backend> for Device {
         unimplemented!()
     }
 
-    fn create_sampler(&mut self, _: image::SamplerInfo) -> () {
+    fn reflect_shader_module(&self, _: &()) -> Result<pso::ShaderReflection, pso::ReflectError> {
+        unimplemented!()
+    }
+
+    fn create_sampler(&mut self, _: image::SamplerInfo) -> Result<(), image::SamplerError> {
         unimplemented!()
     }
-    fn create_buffer(&mut self, _: u64, _: u64, _: buffer::Usage) -> Result<(), buffer::CreationError> {
+    fn create_buffer(&mut self, _: u64, _: u64, _: buffer::Usage, _: memory::SharingMode) -> Result<(), buffer::CreationError> {
         unimplemented!()
     }
 
@@ -141,7 +162,7 @@ impl core::Device<Backend> for Device {
         unimplemented!()
     }
 
-    fn create_image(&mut self, _: image::Kind, _: image::Level, _: format::Format, _: image::Usage)
+    fn create_image(&mut self, _: image::Kind, _: image::Level, _: format::Format, _: image::Tiling, _: image::Usage, _: memory::SharingMode)
          -> Result<(), image::CreationError>
     {
         unimplemented!()
@@ -151,13 +172,38 @@ impl core::Device<Backend> for Device {
         unimplemented!()
     }
 
+    fn get_image_subresource_footprint(
+        &mut self, _: &(), _: image::AspectFlags, _: image::Subresource,
+    ) -> image::SubresourceFootprint {
+        unimplemented!()
+    }
+
     fn bind_image_memory(&mut self, _: &(), _: u64, _: ()) -> Result<(), device::BindError> {
         unimplemented!()
     }
 
+    fn create_sparse_buffer(&mut self, _: u64, _: u64, _: buffer::Usage, _: memory::SharingMode) -> Result<(), buffer::CreationError> {
+        unimplemented!()
+    }
+
+    fn get_sparse_buffer_requirements(&mut self, _: &()) -> memory::Requirements {
+        unimplemented!()
+    }
+
+    fn create_sparse_image(
+        &mut self, _: image::Kind, _: image::Level, _: format::Format, _: image::Usage, _: memory::SharingMode,
+    ) -> Result<(), image::CreationError> {
+        unimplemented!()
+    }
+
+    fn get_sparse_image_requirements(&mut self, _: &()) -> (memory::Requirements, device::Extent) {
+        unimplemented!()
+    }
+
     fn create_image_view(&
         mut self,
         _: &(),
+        _: image::ViewKind,
         _: format::Format,
         _: format::Swizzle,
         _: image::SubresourceRange,
@@ -176,13 +222,29 @@ impl core::Device<Backend> for Device {
         unimplemented!()
     }
 
+    fn map_memory(&mut self, _: &(), _: Range<u64>) -> Result<*mut u8, mapping::Error> {
+        unimplemented!()
+    }
+
+    fn flush_mapped_memory_ranges(&mut self, _: &[(&(), Range<u64>)]) -> Result<(), device::OutOfMemory> {
+        unimplemented!()
+    }
+
+    fn invalidate_mapped_memory_ranges(&mut self, _: &[(&(), Range<u64>)]) -> Result<(), device::OutOfMemory> {
+        unimplemented!()
+    }
+
+    fn unmap_memory(&mut self, _: &()) {
+        unimplemented!()
+    }
+
     fn acquire_mapping_raw(&mut self, _: &(), _: Option<Range<u64>>)
         -> Result<*mut u8, mapping::Error>
     {
         unimplemented!()
     }
 
-    fn release_mapping_raw(&mut self, _: &(), _: Option<Range<u64>>) {
+    fn release_mapping_raw(&mut self, _: &(), _: Option<Range<u64>>) -> Result<(), mapping::Error> {
         unimplemented!()
     }
 
@@ -201,6 +263,22 @@ impl core::Device<Backend> for Device {
         unimplemented!()
     }
 
+    fn create_event(&mut self) -> () {
+        unimplemented!()
+    }
+
+    fn set_event(&mut self, _: &()) {
+        unimplemented!()
+    }
+
+    fn reset_event(&mut self, _: &()) {
+        unimplemented!()
+    }
+
+    fn get_event_status(&mut self, _: &()) -> bool {
+        unimplemented!()
+    }
+
     fn free_memory(&mut self, _: ()) {
         unimplemented!()
     }
@@ -257,6 +335,10 @@ impl core::Device<Backend> for Device {
     fn destroy_semaphore(&mut self, _: ()) {
         unimplemented!()
     }
+
+    fn destroy_event(&mut self, _: ()) {
+        unimplemented!()
+    }
 }
 
 /// Dummy queue family;
@@ -265,6 +347,9 @@ impl core::QueueFamily for QueueFamily {
     fn num_queues(&self) -> u32 {
         unimplemented!()
     }
+    fn id(&self) -> core::QueueFamilyId {
+        unimplemented!()
+    }
 }
 
 /// Dummy subpass command buffer.
@@ -320,6 +405,23 @@ impl core::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
+    fn set_event(&mut self, _: &(), _: pso::PipelineStage) {
+        unimplemented!()
+    }
+
+    fn reset_event(&mut self, _: &(), _: pso::PipelineStage) {
+        unimplemented!()
+    }
+
+    fn wait_events(
+        &mut self,
+        _: &[&()],
+        _: Range<pso::PipelineStage>,
+        _: &[memory::Barrier<Backend>],
+    ) {
+        unimplemented!()
+    }
+
     fn fill_buffer(&mut self, _: &(), _: Range<u64>, _: u32) {
         unimplemented!()
     }
@@ -363,6 +465,18 @@ impl core::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
+    fn blit_image(
+        &mut self,
+        _: &(),
+        _: image::ImageLayout,
+        _: &(),
+        _: image::ImageLayout,
+        _: image::Filter,
+        _: &[command::ImageBlit],
+    ) {
+        unimplemented!()
+    }
+
     fn bind_index_buffer(&mut self, _: buffer::IndexBufferView<Backend>) {
         unimplemented!()
     }
@@ -371,6 +485,18 @@ impl core::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
+    fn bind_transform_feedback_buffers(&mut self, _: u32, _: &[command::TransformFeedbackBuffer<Backend>]) {
+        unimplemented!()
+    }
+
+    fn begin_transform_feedback(&mut self) {
+        unimplemented!()
+    }
+
+    fn end_transform_feedback(&mut self) {
+        unimplemented!()
+    }
+
     fn set_viewports(&mut self, _: &[core::Viewport]) {
 
     }
@@ -389,6 +515,14 @@ impl core::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
+    fn set_depth_bounds(&mut self, _: Range<f32>) {
+        unimplemented!()
+    }
+
+    fn set_line_width(&mut self, _: f32) {
+        unimplemented!()
+    }
+
 
     fn begin_renderpass(
         &mut self,
@@ -507,6 +641,10 @@ impl core::RawCommandBuffer<Backend> for RawCommandBuffer {
     ) {
         unimplemented!()
     }
+
+    fn draw_auto(&mut self) {
+        unimplemented!()
+    }
 }
 
 // Dummy descriptor pool.
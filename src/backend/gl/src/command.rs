@@ -1,7 +1,7 @@
 #![allow(missing_docs)]
 
 use gl;
-use hal::{self as c, command, image, memory, target, Viewport};
+use hal::{self as c, command, image, memory, pso, target, Viewport};
 use hal::buffer::IndexBufferView;
 use hal::target::{ColorValue, Stencil};
 use {native as n, Backend};
@@ -67,18 +67,38 @@ pub enum Command {
         base_vertex: c::VertexOffset,
         instances: Range<c::InstanceCount>,
     },
+    BindProgram(n::Program),
     BindIndexBuffer(gl::types::GLuint),
     BindVertexBuffers(BufferSlice),
+    BindTransformFeedbackBuffer(gl::types::GLuint, n::RawBuffer, Range<u64>),
+    BeginTransformFeedback(gl::types::GLenum),
+    EndTransformFeedback,
+    DrawAuto(gl::types::GLenum),
+    SetRasterizerDiscard(bool),
     SetViewports {
         viewport_ptr: BufferSlice,
         depth_range_ptr: BufferSlice,
     },
     SetScissors(BufferSlice),
     SetBlendColor(ColorValue),
+    SetBlendTarget(c::ColorSlot, pso::ColorInfo),
+    SetLogicOp(Option<pso::LogicOp>),
+    SetAlphaToCoverage(bool),
+    SetLineWidth(f32),
+    SetPatchSize(c::PatchSize),
     ClearColor(n::ImageView, command::ClearColor),
     BindFrameBuffer(FrameBufferTarget, n::FrameBuffer),
     BindTargetView(FrameBufferTarget, AttachmentPoint, n::ImageView),
     SetDrawColorBuffers(usize),
+    /// Blit between two color views, attached to the given framebuffer.
+    BlitFramebuffer {
+        fbo: n::FrameBuffer,
+        src: n::ImageView,
+        dst: n::ImageView,
+        src_rect: [gl::types::GLint; 4],
+        dst_rect: [gl::types::GLint; 4],
+        filter: gl::types::GLenum,
+    },
 }
 
 pub type FrameBufferTarget = gl::types::GLenum;
@@ -89,12 +109,16 @@ pub type AttachmentPoint = gl::types::GLenum;
 struct Cache {
     // Active primitive topology, set by the current pipeline.
     primitive: Option<gl::types::GLenum>,
+    // Primitive captured by transform feedback, set by the current pipeline.
+    feedback_primitive: Option<gl::types::GLenum>,
     // Active index type, set by the current index buffer.
     index_type: Option<c::IndexType>,
     // Stencil reference values (front, back).
     stencil_ref: Option<(Stencil, Stencil)>,
     // Blend color.
     blend_color: Option<ColorValue>,
+    // States baked into the bound graphics pipeline, which can't be overridden.
+    baked_states: Option<pso::BakedStates>,
     ///
     framebuffer: Option<(FrameBufferTarget, n::FrameBuffer)>,
     ///
@@ -106,9 +130,11 @@ impl Cache {
     pub fn new() -> Cache {
         Cache {
             primitive: None,
+            feedback_primitive: None,
             index_type: None,
             stencil_ref: None,
             blend_color: None,
+            baked_states: None,
             framebuffer: None,
             error_state: false,
         }
@@ -195,6 +221,78 @@ impl RawCommandBuffer {
         }
     }
 
+    // Check if the state is baked into the bound pipeline, setting it would be ignored.
+    fn is_baked<F>(&self, name: &str, is_dynamic: F) -> bool
+    where
+        F: Fn(&pso::BakedStates) -> bool,
+    {
+        let baked = self.cache.baked_states.as_ref().map_or(false, |states| !is_dynamic(states));
+        if baked {
+            warn!("Ignoring {}, baked into the bound pipeline", name);
+        }
+        baked
+    }
+
+    fn apply_viewports(&mut self, viewports: &[Viewport]) {
+        match viewports.len() {
+            0 => {
+                error!("Number of viewports can not be zero.");
+                self.cache.error_state = true;
+            }
+            n if n <= self.limits.max_viewports => {
+                // OpenGL has two functions for setting the viewports.
+                // Configuring the rectangle area and setting the depth bounds are separated.
+                //
+                // We try to store everything into a contiguous block of memory,
+                // which allows us to avoid memory allocations when executing the commands.
+                let mut viewport_ptr = BufferSlice { offset: 0, size: 0 };
+                let mut depth_range_ptr = BufferSlice { offset: 0, size: 0 };
+
+                for viewport in viewports {
+                    let viewport = &[viewport.x as f32, viewport.y as f32, viewport.w as f32, viewport.h as f32];
+                    viewport_ptr.append(self.add::<f32>(viewport));
+                }
+                for viewport in viewports {
+                    let depth_range = &[viewport.near as f64, viewport.far as f64];
+                    depth_range_ptr.append(self.add::<f64>(depth_range));
+                }
+                self.push_cmd(Command::SetViewports { viewport_ptr, depth_range_ptr });
+            }
+            _ => {
+                error!("Number of viewports exceeds the number of maximum viewports");
+                self.cache.error_state = true;
+            }
+        }
+    }
+
+    fn apply_scissors(&mut self, scissors: &[target::Rect]) {
+        match scissors.len() {
+            0 => {
+                error!("Number of scissors can not be zero.");
+                self.cache.error_state = true;
+            }
+            n if n <= self.limits.max_viewports => {
+                let mut scissors_ptr = BufferSlice { offset: 0, size: 0 };
+                for scissor in scissors {
+                    let scissor = &[scissor.x as i32, scissor.y as i32, scissor.w as i32, scissor.h as i32];
+                    scissors_ptr.append(self.add::<i32>(scissor));
+                }
+                self.push_cmd(Command::SetScissors(scissors_ptr));
+            }
+            _ => {
+                error!("Number of scissors exceeds the number of maximum viewports");
+                self.cache.error_state = true;
+            }
+        }
+    }
+
+    fn apply_blend_constants(&mut self, cv: target::ColorValue) {
+        if self.cache.blend_color != Some(cv) {
+            self.cache.blend_color = Some(cv);
+            self.push_cmd(Command::SetBlendColor(cv));
+        }
+    }
+
     // Soft reset only the buffers, but doesn't free any memory or clears memory
     // of the owning pool.
     pub(crate) fn soft_reset(&mut self) {
@@ -298,6 +396,23 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
+    fn set_event(&mut self, _event: &n::Event, _stage: c::pso::PipelineStage) {
+        error!("Events are not supported");
+    }
+
+    fn reset_event(&mut self, _event: &n::Event, _stage: c::pso::PipelineStage) {
+        error!("Events are not supported");
+    }
+
+    fn wait_events(
+        &mut self,
+        _events: &[&n::Event],
+        _stages: Range<c::pso::PipelineStage>,
+        _barriers: &[memory::Barrier<Backend>],
+    ) {
+        error!("Events are not supported");
+    }
+
     fn fill_buffer(&mut self, _buffer: &n::Buffer, _range: Range<u64>, _data: u32) {
         unimplemented!()
     }
@@ -333,9 +448,9 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         value: command::ClearColor,
     ) {
         let fbo = self.fbo;
-        let view = match *image {
-            n::Image::Surface(id) => n::ImageView::Surface(id),
-            n::Image::Texture(id) => n::ImageView::Texture(id, 0), //TODO
+        let view = match image.raw {
+            n::ImageKind::Surface(id) => n::ImageView::Surface(id),
+            n::ImageKind::Texture(id) => n::ImageView::Texture(id, 0), //TODO
         };
         self.push_cmd(Command::BindFrameBuffer(gl::DRAW_FRAMEBUFFER, fbo));
         self.push_cmd(Command::BindTargetView(gl::DRAW_FRAMEBUFFER, gl::COLOR_ATTACHMENT0, view));
@@ -368,6 +483,52 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
+    fn blit_image(
+        &mut self,
+        src: &n::Image,
+        _src_layout: image::ImageLayout,
+        dst: &n::Image,
+        _dst_layout: image::ImageLayout,
+        filter: image::Filter,
+        regions: &[command::ImageBlit],
+    ) {
+        let filter = match filter {
+            image::Filter::Nearest => gl::NEAREST,
+            image::Filter::Linear => gl::LINEAR,
+        };
+        let view = |image: &n::Image, level, layer, single_layer| match image.raw {
+            n::ImageKind::Surface(id) => n::ImageView::Surface(id),
+            n::ImageKind::Texture(id) if single_layer => n::ImageView::Texture(id, level),
+            n::ImageKind::Texture(id) => n::ImageView::TextureLayer(id, level, layer),
+        };
+
+        for region in regions {
+            if region.src_subresource.aspects != image::ASPECT_COLOR ||
+                region.dst_subresource.aspects != image::ASPECT_COLOR
+            {
+                error!("Only color blits are supported");
+                self.cache.error_state = true;
+                continue;
+            }
+
+            let (src_layers, dst_layers) = (&region.src_subresource.layers, &region.dst_subresource.layers);
+            let single_layer = src_layers.end - src_layers.start == 1 && src_layers.start == 0 && dst_layers.start == 0;
+            let (s, d) = (&region.src_bounds, &region.dst_bounds);
+
+            for (src_layer, dst_layer) in src_layers.clone().zip(dst_layers.clone()) {
+                let cmd = Command::BlitFramebuffer {
+                    fbo: self.fbo,
+                    src: view(src, region.src_subresource.level, src_layer, single_layer),
+                    dst: view(dst, region.dst_subresource.level, dst_layer, single_layer),
+                    src_rect: [s.start.x, s.start.y, s.end.x, s.end.y],
+                    dst_rect: [d.start.x, d.start.y, d.end.x, d.end.y],
+                    filter,
+                };
+                self.push_cmd(cmd);
+            }
+        }
+    }
+
     fn bind_index_buffer(&mut self, ibv: IndexBufferView<Backend>) {
         // TODO: how can we incoporate the buffer offset?
         if ibv.offset > 0 {
@@ -382,75 +543,101 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
         unimplemented!()
     }
 
-    fn set_viewports(&mut self, viewports: &[Viewport]) {
-        match viewports.len() {
-            0 => {
-                error!("Number of viewports can not be zero.");
-                self.cache.error_state = true;
-            }
-            n if n <= self.limits.max_viewports => {
-                // OpenGL has two functions for setting the viewports.
-                // Configuring the rectangle area and setting the depth bounds are separated.
-                //
-                // We try to store everything into a contiguous block of memory,
-                // which allows us to avoid memory allocations when executing the commands.
-                let mut viewport_ptr = BufferSlice { offset: 0, size: 0 };
-                let mut depth_range_ptr = BufferSlice { offset: 0, size: 0 };
+    fn bind_transform_feedback_buffers(&mut self, first_binding: u32, buffers: &[command::TransformFeedbackBuffer<Backend>]) {
+        // The written vertex count is tracked by the transform feedback object,
+        // counter buffers aren't needed.
+        for (i, tf) in buffers.iter().enumerate() {
+            self.push_cmd(Command::BindTransformFeedbackBuffer(
+                first_binding + i as u32,
+                tf.buffer.raw,
+                tf.range.clone(),
+            ));
+        }
+    }
 
-                for viewport in viewports {
-                    let viewport = &[viewport.x as f32, viewport.y as f32, viewport.w as f32, viewport.h as f32];
-                    viewport_ptr.append(self.add::<f32>(viewport));
-                }
-                for viewport in viewports {
-                    let depth_range = &[viewport.near as f64, viewport.far as f64];
-                    depth_range_ptr.append(self.add::<f64>(depth_range));
-                }
-                self.push_cmd(Command::SetViewports { viewport_ptr, depth_range_ptr });
-            }
-            _ => {
-                error!("Number of viewports exceeds the number of maximum viewports");
+    fn begin_transform_feedback(&mut self) {
+        match self.cache.feedback_primitive {
+            Some(primitive) => self.push_cmd(Command::BeginTransformFeedback(primitive)),
+            None => {
+                warn!("No stream output bound. A pipeline with stream output needs to be bound before calling `begin_transform_feedback`.");
                 self.cache.error_state = true;
             }
         }
     }
 
+    fn end_transform_feedback(&mut self) {
+        self.push_cmd(Command::EndTransformFeedback);
+    }
+
+    fn set_viewports(&mut self, viewports: &[Viewport]) {
+        if !self.is_baked("viewports", |states| states.viewports.is_dynamic()) {
+            self.apply_viewports(viewports);
+        }
+    }
+
     fn set_scissors(&mut self, scissors: &[target::Rect]) {
-        match scissors.len() {
-            0 => {
-                error!("Number of scissors can not be zero.");
-                self.cache.error_state = true;
-            }
-            n if n <= self.limits.max_viewports => {
-                let mut scissors_ptr = BufferSlice { offset: 0, size: 0 };
-                for scissor in scissors {
-                    let scissor = &[scissor.x as i32, scissor.y as i32, scissor.w as i32, scissor.h as i32];
-                    scissors_ptr.append(self.add::<i32>(scissor));
-                }
-                self.push_cmd(Command::SetScissors(scissors_ptr));
-            }
-            _ => {
-                error!("Number of scissors exceeds the number of maximum viewports");
-                self.cache.error_state = true;
-            }
+        if !self.is_baked("scissors", |states| states.scissors.is_dynamic()) {
+            self.apply_scissors(scissors);
         }
     }
 
     fn set_stencil_reference(&mut self, front: target::Stencil, back: target::Stencil) {
-        // Only cache the stencil references values until
-        // we assembled all the pieces to set the stencil state
-        // from the pipeline.
-        self.cache.stencil_ref = Some((front, back));
+        if !self.is_baked("stencil reference", |states| states.stencil_reference.is_dynamic()) {
+            // Only cache the stencil references values until
+            // we assembled all the pieces to set the stencil state
+            // from the pipeline.
+            self.cache.stencil_ref = Some((front, back));
+        }
     }
 
     fn set_blend_constants(&mut self, cv: target::ColorValue) {
-        if self.cache.blend_color != Some(cv) {
-            self.cache.blend_color = Some(cv);
-            self.push_cmd(Command::SetBlendColor(cv));
+        if !self.is_baked("blend constants", |states| states.blend_constants.is_dynamic()) {
+            self.apply_blend_constants(cv);
         }
     }
 
-    fn bind_graphics_pipeline(&mut self, _pipeline: &n::GraphicsPipeline) {
-        unimplemented!()
+    fn set_depth_bounds(&mut self, _bounds: Range<f32>) {
+        error!("Depth bounds test is not supported");
+        self.cache.error_state = true;
+    }
+
+    fn set_line_width(&mut self, width: f32) {
+        self.push_cmd(Command::SetLineWidth(width));
+    }
+
+    fn bind_graphics_pipeline(&mut self, pipeline: &n::GraphicsPipeline) {
+        self.cache.primitive = Some(pipeline.primitive);
+        self.cache.feedback_primitive = pipeline.feedback_primitive;
+        self.push_cmd(Command::BindProgram(pipeline.program));
+        self.push_cmd(Command::SetRasterizerDiscard(pipeline.rasterizer_discard));
+        if let Some(size) = pipeline.patch_size {
+            self.push_cmd(Command::SetPatchSize(size));
+        }
+
+        let blender = &pipeline.blender;
+        self.push_cmd(Command::SetAlphaToCoverage(blender.alpha_coverage));
+        self.push_cmd(Command::SetLogicOp(blender.logic_op));
+        for (slot, info) in blender.targets.iter().enumerate() {
+            self.push_cmd(Command::SetBlendTarget(slot as _, *info));
+        }
+
+        let states = &pipeline.baked_states;
+        if let pso::State::Static(ref viewports) = states.viewports {
+            self.apply_viewports(viewports);
+        }
+        if let pso::State::Static(ref scissors) = states.scissors {
+            self.apply_scissors(scissors);
+        }
+        if let pso::State::Static(color) = states.blend_constants {
+            self.apply_blend_constants(color);
+        }
+        if let pso::State::Static(stencil_ref) = states.stencil_reference {
+            self.cache.stencil_ref = Some(stencil_ref);
+        }
+        if let pso::State::Static(width) = pipeline.line_width {
+            self.push_cmd(Command::SetLineWidth(width));
+        }
+        self.cache.baked_states = Some(states.clone());
     }
 
     fn bind_graphics_descriptor_sets(
@@ -594,6 +781,16 @@ impl command::RawCommandBuffer<Backend> for RawCommandBuffer {
     ) {
         unimplemented!()
     }
+
+    fn draw_auto(&mut self) {
+        match self.cache.primitive {
+            Some(primitive) => self.push_cmd(Command::DrawAuto(primitive)),
+            None => {
+                warn!("No primitive bound. An active pipeline needs to be bound before calling `draw_auto`.");
+                self.cache.error_state = true;
+            }
+        }
+    }
 }
 
 /// A subpass command buffer abstraction for OpenGL
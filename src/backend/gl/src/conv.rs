@@ -1,5 +1,38 @@
 use gl::{self, types as t};
-use hal::{buffer, image as i};
+use hal::{buffer, image as i, pso, Primitive};
+use hal::format::Format;
+use native::BindingRegister;
+
+// S3TC (GL_EXT_texture_compression_s3tc, GL_EXT_texture_sRGB) and
+// ASTC (GL_KHR_texture_compression_astc_ldr) are not part of the core registry.
+const COMPRESSED_RGB_S3TC_DXT1_EXT: t::GLenum = 0x83F0;
+const COMPRESSED_RGBA_S3TC_DXT1_EXT: t::GLenum = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT3_EXT: t::GLenum = 0x83F2;
+const COMPRESSED_RGBA_S3TC_DXT5_EXT: t::GLenum = 0x83F3;
+const COMPRESSED_SRGB_S3TC_DXT1_EXT: t::GLenum = 0x8C4C;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT: t::GLenum = 0x8C4D;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT: t::GLenum = 0x8C4E;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT: t::GLenum = 0x8C4F;
+const COMPRESSED_RGBA_ASTC_4X4_KHR: t::GLenum = 0x93B0;
+const COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR: t::GLenum = 0x93D0;
+// Neither is GL_ARB_texture_filter_minmax.
+pub const TEXTURE_REDUCTION_MODE_ARB: t::GLenum = 0x9366;
+const WEIGHTED_AVERAGE_ARB: t::GLenum = 0x9367;
+
+pub fn primitive_to_gl_primitive(primitive: Primitive) -> t::GLenum {
+    match primitive {
+        Primitive::PointList => gl::POINTS,
+        Primitive::LineList => gl::LINES,
+        Primitive::LineStrip => gl::LINE_STRIP,
+        Primitive::TriangleList => gl::TRIANGLES,
+        Primitive::TriangleStrip => gl::TRIANGLE_STRIP,
+        Primitive::LineListAdjacency => gl::LINES_ADJACENCY,
+        Primitive::LineStripAdjacency => gl::LINE_STRIP_ADJACENCY,
+        Primitive::TriangleListAdjacency => gl::TRIANGLES_ADJACENCY,
+        Primitive::TriangleStripAdjacency => gl::TRIANGLE_STRIP_ADJACENCY,
+        Primitive::PatchList(_) => gl::PATCHES,
+    }
+}
 
 pub fn image_kind_to_gl(kind: i::Kind) -> t::GLenum {
     match kind {
@@ -15,14 +48,24 @@ pub fn image_kind_to_gl(kind: i::Kind) -> t::GLenum {
     }
 }
 
-pub fn filter_to_gl(f: i::FilterMethod) -> (t::GLenum, t::GLenum) {
-    match f {
-        i::FilterMethod::Scale => (gl::NEAREST, gl::NEAREST),
-        i::FilterMethod::Mipmap => (gl::NEAREST_MIPMAP_NEAREST, gl::NEAREST),
-        i::FilterMethod::Bilinear => (gl::LINEAR, gl::LINEAR),
-        i::FilterMethod::Trilinear => (gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR),
-        i::FilterMethod::Anisotropic(..) => (gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR),
-    }
+pub fn filter_to_gl(mag: i::Filter, min: i::Filter, mip: Option<i::Filter>) -> (t::GLenum, t::GLenum) {
+    use hal::image::Filter::*;
+
+    let mag_filter = match mag {
+        Nearest => gl::NEAREST,
+        Linear => gl::LINEAR,
+    };
+
+    let min_filter = match (min, mip) {
+        (Nearest, None) => gl::NEAREST,
+        (Nearest, Some(Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+        (Nearest, Some(Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+        (Linear, None) => gl::LINEAR,
+        (Linear, Some(Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+        (Linear, Some(Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+    };
+
+    (min_filter, mag_filter)
 }
 
 pub fn wrap_to_gl(w: i::WrapMode) -> t::GLenum {
@@ -34,6 +77,14 @@ pub fn wrap_to_gl(w: i::WrapMode) -> t::GLenum {
     }
 }
 
+pub fn reduction_to_gl(mode: i::ReductionMode) -> t::GLenum {
+    match mode {
+        i::ReductionMode::WeightedAverage => WEIGHTED_AVERAGE_ARB,
+        i::ReductionMode::Minimum => gl::MIN,
+        i::ReductionMode::Maximum => gl::MAX,
+    }
+}
+
 pub fn buffer_usage_to_gl_target(usage: buffer::Usage) -> Option<t::GLenum> {
     match usage & (buffer::UNIFORM | buffer::INDEX | buffer::VERTEX | buffer::INDIRECT) {
         buffer::UNIFORM => Some(gl::UNIFORM_BUFFER),
@@ -43,3 +94,203 @@ pub fn buffer_usage_to_gl_target(usage: buffer::Usage) -> Option<t::GLenum> {
         _ => None
     }
 }
+
+pub fn descriptor_to_gl_register(ty: pso::DescriptorType) -> BindingRegister {
+    use hal::pso::DescriptorType as D;
+    match ty {
+        // Samplers are bound to texture units together with their images.
+        D::Sampler |
+        D::SampledImage |
+        D::UniformTexelBuffer |
+        D::InputAttachment => BindingRegister::Textures,
+        D::StorageImage |
+        D::StorageTexelBuffer => BindingRegister::Images,
+        D::UniformBuffer => BindingRegister::UniformBuffers,
+        D::StorageBuffer => BindingRegister::StorageBuffers,
+    }
+}
+
+/// Conversion of the vertex attribute data into shader inputs.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VertexAttribFunction {
+    /// Floating point inputs through `glVertexAttribPointer`, with the normalization flag.
+    Float(t::GLboolean),
+    /// Integer inputs through `glVertexAttribIPointer`.
+    Integer,
+}
+
+/// Component count, component type and conversion of a vertex attribute format.
+pub fn format_to_gl_vertex(format: Format) -> Option<(t::GLint, t::GLenum, VertexAttribFunction)> {
+    use hal::format::SurfaceType::*;
+    use hal::format::ChannelType::*;
+
+    let (count, bits) = match format.0 {
+        R8 => (1, 8),
+        R8_G8 => (2, 8),
+        R8_G8_B8_A8 => (4, 8),
+        R10_G10_B10_A2 => (4, 10),
+        R16 => (1, 16),
+        R16_G16 => (2, 16),
+        R16_G16_B16 => (3, 16),
+        R16_G16_B16_A16 => (4, 16),
+        R32 => (1, 32),
+        R32_G32 => (2, 32),
+        R32_G32_B32 => (3, 32),
+        R32_G32_B32_A32 => (4, 32),
+        _ => return None,
+    };
+    let function = match format.1 {
+        Int | Uint => VertexAttribFunction::Integer,
+        Inorm | Unorm => VertexAttribFunction::Float(gl::TRUE),
+        Iscaled | Uscaled | Float => VertexAttribFunction::Float(gl::FALSE),
+        _ => return None,
+    };
+    let gl_type = match (bits, format.1) {
+        // Packed types can't be read as integers.
+        (10, Int) | (10, Uint) => return None,
+        (10, Inorm) | (10, Iscaled) => gl::INT_2_10_10_10_REV,
+        (10, _) => gl::UNSIGNED_INT_2_10_10_10_REV,
+        (16, Float) => gl::HALF_FLOAT,
+        (32, Float) => gl::FLOAT,
+        (_, Float) => return None,
+        (8, Int) | (8, Inorm) | (8, Iscaled) => gl::BYTE,
+        (8, _) => gl::UNSIGNED_BYTE,
+        (16, Int) | (16, Inorm) | (16, Iscaled) => gl::SHORT,
+        (16, _) => gl::UNSIGNED_SHORT,
+        (_, Int) | (_, Inorm) | (_, Iscaled) => gl::INT,
+        _ => gl::UNSIGNED_INT,
+    };
+    Some((count, gl_type, function))
+}
+
+/// Sized internal format of an image format.
+pub fn format_to_gl_internal(format: Format) -> Option<t::GLenum> {
+    use hal::format::SurfaceType::*;
+    use hal::format::ChannelType::*;
+
+    Some(match format {
+        Format(R4_G4_B4_A4, Unorm) => gl::RGBA4,
+        Format(R5_G5_B5_A1, Unorm) => gl::RGB5_A1,
+        Format(R5_G6_B5, Unorm) => gl::RGB565,
+        Format(R8, Int) => gl::R8I,
+        Format(R8, Uint) => gl::R8UI,
+        Format(R8, Inorm) => gl::R8_SNORM,
+        Format(R8, Unorm) => gl::R8,
+        Format(R8_G8, Int) => gl::RG8I,
+        Format(R8_G8, Uint) => gl::RG8UI,
+        Format(R8_G8, Inorm) => gl::RG8_SNORM,
+        Format(R8_G8, Unorm) => gl::RG8,
+        Format(R8_G8_B8_A8, Int) => gl::RGBA8I,
+        Format(R8_G8_B8_A8, Uint) => gl::RGBA8UI,
+        Format(R8_G8_B8_A8, Inorm) => gl::RGBA8_SNORM,
+        Format(R8_G8_B8_A8, Unorm) => gl::RGBA8,
+        Format(R8_G8_B8_A8, Srgb) => gl::SRGB8_ALPHA8,
+        Format(R10_G10_B10_A2, Uint) => gl::RGB10_A2UI,
+        Format(R10_G10_B10_A2, Unorm) => gl::RGB10_A2,
+        Format(R11_G11_B10, Float) => gl::R11F_G11F_B10F,
+        Format(R16, Int) => gl::R16I,
+        Format(R16, Uint) => gl::R16UI,
+        Format(R16, Inorm) => gl::R16_SNORM,
+        Format(R16, Unorm) => gl::R16,
+        Format(R16, Float) => gl::R16F,
+        Format(R16_G16, Int) => gl::RG16I,
+        Format(R16_G16, Uint) => gl::RG16UI,
+        Format(R16_G16, Inorm) => gl::RG16_SNORM,
+        Format(R16_G16, Unorm) => gl::RG16,
+        Format(R16_G16, Float) => gl::RG16F,
+        Format(R16_G16_B16, Int) => gl::RGB16I,
+        Format(R16_G16_B16, Uint) => gl::RGB16UI,
+        Format(R16_G16_B16, Inorm) => gl::RGB16_SNORM,
+        Format(R16_G16_B16, Unorm) => gl::RGB16,
+        Format(R16_G16_B16, Float) => gl::RGB16F,
+        Format(R16_G16_B16_A16, Int) => gl::RGBA16I,
+        Format(R16_G16_B16_A16, Uint) => gl::RGBA16UI,
+        Format(R16_G16_B16_A16, Inorm) => gl::RGBA16_SNORM,
+        Format(R16_G16_B16_A16, Unorm) => gl::RGBA16,
+        Format(R16_G16_B16_A16, Float) => gl::RGBA16F,
+        Format(R32, Int) => gl::R32I,
+        Format(R32, Uint) => gl::R32UI,
+        Format(R32, Float) => gl::R32F,
+        Format(R32_G32, Int) => gl::RG32I,
+        Format(R32_G32, Uint) => gl::RG32UI,
+        Format(R32_G32, Float) => gl::RG32F,
+        Format(R32_G32_B32, Int) => gl::RGB32I,
+        Format(R32_G32_B32, Uint) => gl::RGB32UI,
+        Format(R32_G32_B32, Float) => gl::RGB32F,
+        Format(R32_G32_B32_A32, Int) => gl::RGBA32I,
+        Format(R32_G32_B32_A32, Uint) => gl::RGBA32UI,
+        Format(R32_G32_B32_A32, Float) => gl::RGBA32F,
+        Format(D16, Unorm) => gl::DEPTH_COMPONENT16,
+        Format(D24, Unorm) => gl::DEPTH_COMPONENT24,
+        Format(D24_S8, Unorm) => gl::DEPTH24_STENCIL8,
+        Format(D32, Float) => gl::DEPTH_COMPONENT32F,
+        Format(D32_S8, Float) => gl::DEPTH32F_STENCIL8,
+        Format(S8, Uint) => gl::STENCIL_INDEX8,
+        _ => return compressed_format_to_gl(format),
+    })
+}
+
+/// Internal format of a compressed image format.
+pub fn compressed_format_to_gl(format: Format) -> Option<t::GLenum> {
+    use hal::format::SurfaceType::*;
+    use hal::format::ChannelType::*;
+
+    Some(match format {
+        Format(BC1_R8_G8_B8, Unorm) => COMPRESSED_RGB_S3TC_DXT1_EXT,
+        Format(BC1_R8_G8_B8, Srgb) => COMPRESSED_SRGB_S3TC_DXT1_EXT,
+        Format(BC1_R8_G8_B8_A8, Unorm) => COMPRESSED_RGBA_S3TC_DXT1_EXT,
+        Format(BC1_R8_G8_B8_A8, Srgb) => COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT,
+        Format(BC2_R8_G8_B8_A8, Unorm) => COMPRESSED_RGBA_S3TC_DXT3_EXT,
+        Format(BC2_R8_G8_B8_A8, Srgb) => COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT,
+        Format(BC3_R8_G8_B8_A8, Unorm) => COMPRESSED_RGBA_S3TC_DXT5_EXT,
+        Format(BC3_R8_G8_B8_A8, Srgb) => COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT,
+        Format(BC4_R8, Unorm) => gl::COMPRESSED_RED_RGTC1,
+        Format(BC4_R8, Inorm) => gl::COMPRESSED_SIGNED_RED_RGTC1,
+        Format(BC5_R8_G8, Unorm) => gl::COMPRESSED_RG_RGTC2,
+        Format(BC5_R8_G8, Inorm) => gl::COMPRESSED_SIGNED_RG_RGTC2,
+        Format(BC6_R16_G16_B16, Float) => gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT,
+        Format(BC6_R16_G16_B16, Ufloat) => gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+        Format(BC7_R8_G8_B8_A8, Unorm) => gl::COMPRESSED_RGBA_BPTC_UNORM,
+        Format(BC7_R8_G8_B8_A8, Srgb) => gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+        Format(ETC2_R8_G8_B8, Unorm) => gl::COMPRESSED_RGB8_ETC2,
+        Format(ETC2_R8_G8_B8, Srgb) => gl::COMPRESSED_SRGB8_ETC2,
+        Format(ETC2_R8_G8_B8_A1, Unorm) => gl::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+        Format(ETC2_R8_G8_B8_A1, Srgb) => gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+        Format(ETC2_R8_G8_B8_A8, Unorm) => gl::COMPRESSED_RGBA8_ETC2_EAC,
+        Format(ETC2_R8_G8_B8_A8, Srgb) => gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+        Format(EAC_R11, Unorm) => gl::COMPRESSED_R11_EAC,
+        Format(EAC_R11, Inorm) => gl::COMPRESSED_SIGNED_R11_EAC,
+        Format(EAC_R11_G11, Unorm) => gl::COMPRESSED_RG11_EAC,
+        Format(EAC_R11_G11, Inorm) => gl::COMPRESSED_SIGNED_RG11_EAC,
+        // ASTC formats are enumerated in the order of `SurfaceType`
+        Format(ASTC_4x4, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 0,
+        Format(ASTC_4x4, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 0,
+        Format(ASTC_5x4, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 1,
+        Format(ASTC_5x4, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 1,
+        Format(ASTC_5x5, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 2,
+        Format(ASTC_5x5, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 2,
+        Format(ASTC_6x5, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 3,
+        Format(ASTC_6x5, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 3,
+        Format(ASTC_6x6, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 4,
+        Format(ASTC_6x6, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 4,
+        Format(ASTC_8x5, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 5,
+        Format(ASTC_8x5, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 5,
+        Format(ASTC_8x6, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 6,
+        Format(ASTC_8x6, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 6,
+        Format(ASTC_8x8, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 7,
+        Format(ASTC_8x8, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 7,
+        Format(ASTC_10x5, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 8,
+        Format(ASTC_10x5, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 8,
+        Format(ASTC_10x6, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 9,
+        Format(ASTC_10x6, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 9,
+        Format(ASTC_10x8, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 10,
+        Format(ASTC_10x8, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 10,
+        Format(ASTC_10x10, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 11,
+        Format(ASTC_10x10, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 11,
+        Format(ASTC_12x10, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 12,
+        Format(ASTC_12x10, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 12,
+        Format(ASTC_12x12, Unorm) => COMPRESSED_RGBA_ASTC_4X4_KHR + 13,
+        Format(ASTC_12x12, Srgb) => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4_KHR + 13,
+        _ => return None,
+    })
+}
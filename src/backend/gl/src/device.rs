@@ -1,6 +1,9 @@
-use std::ptr;
+use std::{cmp, mem, ptr, slice};
+use std::collections::HashMap;
+use std::ffi::CString;
 use std::ops::Range;
 use std::rc::Rc;
+use std::sync::Mutex;
 
 use gl;
 use gl::types::{GLint, GLenum, GLfloat};
@@ -8,8 +11,47 @@ use hal::{self as c, device as d, image as i, memory, pass, pso, buffer, mapping
 use hal::format::{Format, Swizzle};
 use std::iter::repeat;
 
+use spirv_cross::{glsl, spirv, ErrorCode as SpirvErrorCode};
+
 use {Backend as B, Share};
 use {conv, native as n, state};
+use info::Version;
+
+
+/// Emit error during SPIR-V translation.
+fn gen_spirv_error(err: SpirvErrorCode) -> d::ShaderError {
+    let msg = match err {
+        SpirvErrorCode::CompilationError(msg) => msg,
+        SpirvErrorCode::Unhandled => "Unexpected SPIR-V translation error".into(),
+    };
+    d::ShaderError::CompilationFailed(msg)
+}
+
+/// GLSL version to translate SPIR-V into, and whether it supports
+/// explicit binding slots in the layout qualifiers.
+fn glsl_version(version: &Version) -> (glsl::Version, bool) {
+    if version.is_embedded {
+        return match (version.major, version.minor) {
+            (3, _) => (glsl::Version::V3_00Es, false),
+            _ => (glsl::Version::V1_00Es, false),
+        };
+    }
+    match (version.major, version.minor) {
+        (4, 5) | (4, 6) => (glsl::Version::V4_50, true),
+        (4, 4) => (glsl::Version::V4_40, true),
+        (4, 3) => (glsl::Version::V4_30, true),
+        (4, 2) => (glsl::Version::V4_20, true),
+        (4, 1) => (glsl::Version::V4_10, false),
+        (4, 0) => (glsl::Version::V4_00, false),
+        (3, 3) => (glsl::Version::V3_30, false),
+        (3, 2) => (glsl::Version::V1_50, false),
+        (3, 1) => (glsl::Version::V1_40, false),
+        (3, 0) => (glsl::Version::V1_30, false),
+        (2, 1) => (glsl::Version::V1_20, false),
+        (major, _) if major > 4 => (glsl::Version::V4_50, true),
+        _ => (glsl::Version::V1_10, false),
+    }
+}
 
 
 fn get_shader_iv(gl: &gl::Gl, name: n::Shader, query: GLenum) -> gl::types::GLint {
@@ -24,6 +66,135 @@ fn get_program_iv(gl: &gl::Gl, name: n::Program, query: GLenum) -> gl::types::GL
     iv
 }
 
+// GLSL shader translated from SPIR-V.
+struct TranslatedShader {
+    source: String,
+    // Resources which need their slots assigned after linking.
+    late_bindings: Vec<(String, n::BindingRegister, gl::types::GLuint)>,
+    // Captured outputs, by location.
+    outputs: Vec<(pso::Location, String, u32)>,
+}
+
+// Remove the entry points other than `entry` of `execution_model` from the SPIR-V
+// module, along with their execution modes. Entry points precede the modes.
+fn select_entry_point(words: &[u32], entry: &str, execution_model: u32) -> Vec<u32> {
+    const HEADER_SIZE: usize = 5;
+    const OP_ENTRY_POINT: u32 = 15;
+    const OP_EXECUTION_MODE: u32 = 16;
+    const OP_EXECUTION_MODE_ID: u32 = 331;
+
+    let mut selected = words[.. cmp::min(HEADER_SIZE, words.len())].to_vec();
+    let mut removed = Vec::new();
+    let mut offset = HEADER_SIZE;
+    while offset < words.len() {
+        let count = (words[offset] >> 16) as usize;
+        if count == 0 || offset + count > words.len() {
+            // Malformed, left for the parser to report.
+            selected.extend_from_slice(&words[offset ..]);
+            break;
+        }
+        let instruction = &words[offset .. offset + count];
+        offset += count;
+        match instruction[0] & 0xFFFF {
+            OP_ENTRY_POINT if count >= 4 => {
+                let name = instruction[3 ..]
+                    .iter()
+                    .flat_map(|&word| (0 .. 4).map(move |i| (word >> (i * 8)) as u8))
+                    .take_while(|&byte| byte != 0)
+                    .collect::<Vec<_>>();
+                if instruction[1] != execution_model || name != entry.as_bytes() {
+                    removed.push(instruction[2]);
+                    continue;
+                }
+            }
+            OP_EXECUTION_MODE | OP_EXECUTION_MODE_ID if count >= 2 => {
+                if removed.contains(&instruction[1]) {
+                    continue;
+                }
+            }
+            _ => (),
+        }
+        selected.extend_from_slice(instruction);
+    }
+    selected
+}
+
+// Names of the captured outputs, in the interleaved layout of the output buffers.
+// Outputs are captured as a whole, the remaining stride is skipped.
+fn stream_output_varyings(
+    desc: &pso::StreamOutputDesc, outputs: &[(pso::Location, String, u32)]
+) -> Result<Vec<CString>, pso::StreamOutputError> {
+    // Vertex streams are selected by the geometry shader, only the first one is supported.
+    let streams = desc.elements.iter().map(|element| element.stream).chain(desc.rasterized_stream);
+    if let Some(stream) = streams.filter(|&stream| stream != 0).next() {
+        return Err(pso::StreamOutputError::Stream(stream));
+    }
+
+    let mut varyings = Vec::new();
+    for (buffer, &stride) in desc.strides.iter().enumerate() {
+        if buffer != 0 {
+            varyings.push(CString::new("gl_NextBuffer").unwrap());
+        }
+        let mut size = 0;
+        for element in desc.elements.iter().filter(|element| element.buffer as usize == buffer) {
+            let (name, components) = match outputs.iter().find(|&&(location, _, _)| location == element.location) {
+                Some(&(_, ref name, components)) => (name, components),
+                None => return Err(pso::StreamOutputError::MissingOutput(element.location)),
+            };
+            if element.start_component != 0 || element.component_count as u32 != components {
+                return Err(pso::StreamOutputError::PartialOutput(element.location));
+            }
+            varyings.push(CString::new(name.as_str()).unwrap());
+            size += components * 4;
+        }
+        if stride < size || (stride - size) % 4 != 0 {
+            return Err(pso::StreamOutputError::Stride(buffer as u8));
+        }
+        let mut skipped = (stride - size) / 4;
+        while skipped != 0 {
+            let count = cmp::min(skipped, 4);
+            varyings.push(CString::new(format!("gl_SkipComponents{}", count)).unwrap());
+            skipped -= count;
+        }
+    }
+    Ok(varyings)
+}
+
+// Primitive type passed to `BeginTransformFeedback`, matching the output
+// of the last vertex processing stage.
+fn transform_feedback_primitive(
+    gl: &gl::Gl,
+    program: n::Program,
+    has_geometry: bool,
+    has_domain: bool,
+    primitive: c::Primitive,
+) -> GLenum {
+    if has_geometry {
+        match get_program_iv(gl, program, gl::GEOMETRY_OUTPUT_TYPE) as GLenum {
+            gl::POINTS => gl::POINTS,
+            gl::LINE_STRIP => gl::LINES,
+            _ => gl::TRIANGLES,
+        }
+    } else if has_domain {
+        if get_program_iv(gl, program, gl::TESS_GEN_POINT_MODE) != 0 {
+            gl::POINTS
+        } else if get_program_iv(gl, program, gl::TESS_GEN_MODE) as GLenum == gl::ISOLINES {
+            gl::LINES
+        } else {
+            gl::TRIANGLES
+        }
+    } else {
+        match primitive {
+            c::Primitive::PointList => gl::POINTS,
+            c::Primitive::LineList |
+            c::Primitive::LineStrip |
+            c::Primitive::LineListAdjacency |
+            c::Primitive::LineStripAdjacency => gl::LINES,
+            _ => gl::TRIANGLES,
+        }
+    }
+}
+
 fn get_shader_log(gl: &gl::Gl, name: n::Shader) -> String {
     let mut length = get_shader_iv(gl, name, gl::INFO_LOG_LENGTH);
     if length > 0 {
@@ -92,6 +263,10 @@ impl Device {
         data: &[u8],
         stage: pso::Stage,
     ) -> Result<n::ShaderModule, d::ShaderError> {
+        self.compile_shader(data, stage).map(n::ShaderModule::Raw)
+    }
+
+    fn compile_shader(&self, data: &[u8], stage: pso::Stage) -> Result<n::Shader, d::ShaderError> {
         let gl = &self.share.context;
 
         let target = match stage {
@@ -121,12 +296,179 @@ impl Device {
             if !log.is_empty() {
                 warn!("\tLog: {}", log);
             }
-            Ok(n::ShaderModule { raw: name })
+            Ok(name)
         } else {
-            Err(d::ShaderError::CompilationFailed(String::new())) // TODO
+            unsafe { gl.DeleteShader(name) };
+            Err(d::ShaderError::CompilationFailed(log))
         }
     }
 
+    /// Translate the SPIR-V `data` into GLSL for the context version.
+    ///
+    /// Descriptors are remapped to the binding slots of the pipeline layout,
+    /// separate samplers are combined with the texture units of their images.
+    /// Resources need their slots assigned after linking if the GLSL version
+    /// lacks explicit bindings.
+    fn translate_spirv(
+        &self,
+        data: &[u8],
+        stage: pso::Stage,
+        entry: &str,
+        specialization: &[pso::Specialization],
+        layout: &n::PipelineLayout,
+    ) -> Result<TranslatedShader, d::ShaderError> {
+        // spec requires "codeSize must be a multiple of 4"
+        assert_eq!(data.len() & 3, 0);
+
+        // GLSL has a single `main` function per shader, which is generated
+        // from the only entry point left in the module.
+        let execution_model = match stage {
+            pso::Stage::Vertex => 0,
+            pso::Stage::Hull => 1,
+            pso::Stage::Domain => 2,
+            pso::Stage::Geometry => 3,
+            pso::Stage::Fragment => 4,
+            pso::Stage::Compute => 5,
+        };
+        let words = select_entry_point(
+            unsafe {
+                slice::from_raw_parts(
+                    data.as_ptr() as *const u32,
+                    data.len() / mem::size_of::<u32>(),
+                )
+            },
+            entry,
+            execution_model,
+        );
+        let module = spirv::Module::from_words(&words);
+        let mut ast = spirv::Ast::<glsl::Target>::parse(&module)
+            .map_err(gen_spirv_error)?;
+
+        if ast.get_entry_points().map_err(gen_spirv_error)?.is_empty() {
+            return Err(d::ShaderError::CompilationFailed(
+                format!("Entry point {} not found for {:?}", entry, stage)));
+        }
+
+        for constant in ast.get_specialization_constants().map_err(gen_spirv_error)? {
+            if let Some(spec) = specialization.iter().find(|spec| spec.id == constant.constant_id) {
+                ast.set_scalar_constant(constant.id, spec.value.to_bits())
+                    .map_err(gen_spirv_error)?;
+            }
+        }
+
+        let (version, explicit_binding) = glsl_version(&self.share.info.version);
+        let resources = ast.get_shader_resources().map_err(gen_spirv_error)?;
+        let mut late_bindings = Vec::new();
+        let mut slots = HashMap::new();
+        for resource in resources.uniform_buffers.iter()
+            .chain(&resources.storage_buffers)
+            .chain(&resources.sampled_images)
+            .chain(&resources.separate_images)
+            .chain(&resources.separate_samplers)
+            .chain(&resources.storage_images)
+            .chain(&resources.subpass_inputs)
+        {
+            let set = ast.get_decoration(resource.id, spirv::Decoration::DescriptorSet)
+                .map_err(gen_spirv_error)?;
+            let binding = ast.get_decoration(resource.id, spirv::Decoration::Binding)
+                .map_err(gen_spirv_error)?;
+            let (register, slot) = match layout.slots
+                .get(set as usize)
+                .and_then(|bindings| bindings.get(&(binding as usize)))
+            {
+                Some(&slot) => slot,
+                None => return Err(d::ShaderError::CompilationFailed(
+                    format!("Descriptor (set {}, binding {}) of {} is missing in the pipeline layout",
+                        set, binding, resource.name))),
+            };
+            ast.set_decoration(resource.id, spirv::Decoration::DescriptorSet, 0)
+                .map_err(gen_spirv_error)?;
+            ast.set_decoration(resource.id, spirv::Decoration::Binding, slot)
+                .map_err(gen_spirv_error)?;
+            slots.insert(resource.id, (resource.name.clone(), slot));
+            if !explicit_binding {
+                late_bindings.push((resource.name.clone(), register, slot));
+            }
+        }
+
+        // GL samples textures through the units of combined samplers, so each
+        // separate image and sampler pair is bound to the unit of the image.
+        ast.build_combined_image_samplers().map_err(gen_spirv_error)?;
+        for combined in ast.get_combined_image_samplers().map_err(gen_spirv_error)? {
+            let (ref image_name, unit) = slots[&combined.image_id];
+            let (ref sampler_name, _) = slots[&combined.sampler_id];
+            let name = format!("{}_{}", image_name, sampler_name);
+            ast.set_name(combined.combined_id, &name)
+                .map_err(gen_spirv_error)?;
+            ast.set_decoration(combined.combined_id, spirv::Decoration::DescriptorSet, 0)
+                .map_err(gen_spirv_error)?;
+            ast.set_decoration(combined.combined_id, spirv::Decoration::Binding, unit)
+                .map_err(gen_spirv_error)?;
+            if !explicit_binding {
+                late_bindings.push((name, n::BindingRegister::Textures, unit));
+            }
+        }
+
+        // Output variables by location, used to select the captured stream output.
+        let reflection = pso::ShaderReflection::from_spirv(data)
+            .map_err(|err| d::ShaderError::CompilationFailed(err.to_string()))?;
+        let reflected_outputs = reflection.entry_point(entry)
+            .map_or(&[][..], |point| &point.outputs[..]);
+        let mut outputs = Vec::new();
+        for resource in &resources.stage_outputs {
+            let location = ast.get_decoration(resource.id, spirv::Decoration::Location)
+                .map_err(gen_spirv_error)?;
+            if let Some(output) = reflected_outputs.iter().find(|output| output.location == location) {
+                outputs.push((location, resource.name.clone(), output.components));
+            }
+        }
+
+        let mut compile_options = glsl::CompilerOptions::default();
+        compile_options.version = version;
+        // Map the depth range of the clip space from [0, 1] to [-1, 1].
+        compile_options.vertex.transform_clip_space = true;
+        ast.set_compiler_options(&compile_options)
+            .map_err(gen_spirv_error)?;
+
+        let source = ast.compile().map_err(gen_spirv_error)?;
+        debug!("SPIRV-Cross generated shader:\n{}", source);
+        Ok(TranslatedShader {
+            source,
+            late_bindings,
+            outputs,
+        })
+    }
+
+    /// Assign binding slots to the resources of a linked program,
+    /// for GLSL versions without explicit bindings.
+    fn bind_slots(&self, program: n::Program, bindings: &[(String, n::BindingRegister, gl::types::GLuint)]) {
+        let gl = &self.share.context;
+        unsafe { gl.UseProgram(program) };
+        for &(ref name, register, slot) in bindings {
+            let name = CString::new(name.as_str()).unwrap();
+            match register {
+                n::BindingRegister::UniformBuffers => unsafe {
+                    let index = gl.GetUniformBlockIndex(program, name.as_ptr());
+                    if index != gl::INVALID_INDEX {
+                        gl.UniformBlockBinding(program, index, slot);
+                    }
+                },
+                n::BindingRegister::Textures => unsafe {
+                    let location = gl.GetUniformLocation(program, name.as_ptr());
+                    if location >= 0 {
+                        gl.Uniform1i(location, slot as GLint);
+                    }
+                },
+                // Storage buffers and images require versions with explicit bindings.
+                n::BindingRegister::StorageBuffers |
+                n::BindingRegister::Images => {
+                    error!("Unable to bind {:?} without explicit bindings", name);
+                }
+            }
+        }
+        unsafe { gl.UseProgram(0) };
+    }
+
     fn bind_target(gl: &gl::Gl, point: GLenum, attachment: GLenum, view: &n::ImageView) {
         match *view {
             n::ImageView::Surface(surface) => unsafe {
@@ -151,11 +493,153 @@ impl d::Device<B> for Device {
         &self.share.limits
     }
 
+    fn format_properties(&self, format: Format) -> c::format::Properties {
+        use hal::format as f;
+        use hal::format::ChannelType;
+        use hal::format::SurfaceType::*;
+
+        let mut properties = f::Properties::empty();
+        if conv::format_to_gl_vertex(format).is_some() {
+            properties.buffer_features = f::VERTEX;
+        }
+        if conv::format_to_gl_internal(format).is_none() {
+            return properties;
+        }
+
+        let features = &self.share.features;
+        if format.0.is_compressed() {
+            let supported = match format.0 {
+                BC1_R8_G8_B8 | BC1_R8_G8_B8_A8 | BC2_R8_G8_B8_A8 | BC3_R8_G8_B8_A8 |
+                BC4_R8 | BC5_R8_G8 | BC6_R16_G16_B16 | BC7_R8_G8_B8_A8 => features.format_bc,
+                ETC2_R8_G8_B8 | ETC2_R8_G8_B8_A1 | ETC2_R8_G8_B8_A8 |
+                EAC_R11 | EAC_R11_G11 => features.format_etc2,
+                _ => features.format_astc_ldr,
+            };
+            if supported {
+                properties.optimal_tiling = f::SAMPLED | f::SAMPLED_LINEAR | f::BLIT_SRC;
+            }
+            return properties;
+        }
+
+        let is_integer = match format.1 {
+            ChannelType::Int | ChannelType::Uint => true,
+            _ => false,
+        };
+        let is_depth_stencil = format.0.is_depth() || format.0.is_stencil();
+        let mut image = f::SAMPLED;
+        if !is_integer {
+            image |= f::SAMPLED_LINEAR;
+        }
+        if self.share.private_caps.framebuffer {
+            image |= f::BLIT_SRC | f::BLIT_DST;
+            if is_depth_stencil {
+                image |= f::DEPTH_STENCIL_ATTACHMENT;
+            } else {
+                image |= f::COLOR_ATTACHMENT;
+                if !is_integer {
+                    image |= f::COLOR_ATTACHMENT_BLEND;
+                }
+            }
+        }
+        // Image load/store doesn't cover three component formats.
+        let three_components = match format.0 {
+            R16_G16_B16 | R32_G32_B32 => true,
+            _ => false,
+        };
+        if features.unordered_access_view && !is_depth_stencil && !three_components {
+            image |= f::STORAGE;
+        }
+        properties.optimal_tiling = image;
+
+        properties
+    }
+
+    fn image_format_properties(
+        &self, kind: i::Kind, format: Format, tiling: i::Tiling, usage: i::Usage,
+    ) -> Option<i::FormatProperties> {
+        use hal::format as f;
+
+        if tiling == i::Tiling::Linear {
+            return None;
+        }
+
+        let features = self.format_properties(format).optimal_tiling;
+        let required = [
+            (i::SAMPLED, f::SAMPLED),
+            (i::STORAGE, f::STORAGE),
+            (i::COLOR_ATTACHMENT, f::COLOR_ATTACHMENT),
+            (i::DEPTH_STENCIL_ATTACHMENT, f::DEPTH_STENCIL_ATTACHMENT),
+        ];
+        if features.is_empty() || required.iter().any(|&(u, f)| usage.contains(u) && !features.contains(f)) {
+            return None;
+        }
+
+        let gl = &self.share.context;
+        let get = |name| {
+            let mut value = 0 as GLint;
+            unsafe { gl.GetIntegerv(name, &mut value) };
+            value as u32
+        };
+        let max_size = self.share.limits.max_texture_size as u32;
+        let (max_extent, max_layers) = match kind {
+            i::Kind::D1(..) | i::Kind::D1Array(..) =>
+                (d::Extent { width: max_size, height: 1, depth: 1 }, get(gl::MAX_ARRAY_TEXTURE_LAYERS)),
+            i::Kind::D2(..) | i::Kind::D2Array(..) =>
+                (d::Extent { width: max_size, height: max_size, depth: 1 }, get(gl::MAX_ARRAY_TEXTURE_LAYERS)),
+            i::Kind::D3(..) => {
+                let size = get(gl::MAX_3D_TEXTURE_SIZE);
+                (d::Extent { width: size, height: size, depth: size }, 1)
+            }
+            i::Kind::Cube(..) | i::Kind::CubeArray(..) => {
+                let size = get(gl::MAX_CUBE_MAP_TEXTURE_SIZE);
+                (d::Extent { width: size, height: size, depth: 1 }, get(gl::MAX_ARRAY_TEXTURE_LAYERS))
+            }
+        };
+        let max_levels = 32 - max_extent.width.max(max_extent.depth).leading_zeros();
+
+        let multisampled = match kind {
+            i::Kind::D2(..) | i::Kind::D2Array(..) => {
+                usage.intersects(i::COLOR_ATTACHMENT | i::DEPTH_STENCIL_ATTACHMENT)
+            }
+            _ => false,
+        };
+        let sample_count_mask = if !multisampled {
+            1
+        } else if self.share.private_caps.internalformat_query {
+            let internal_format = conv::format_to_gl_internal(format).unwrap();
+            let mut count = 0;
+            unsafe {
+                gl.GetInternalformativ(gl::RENDERBUFFER, internal_format, gl::NUM_SAMPLE_COUNTS, 1, &mut count);
+            }
+            let mut samples = vec![0 as GLint; count as usize];
+            unsafe {
+                gl.GetInternalformativ(gl::RENDERBUFFER, internal_format, gl::SAMPLES, count, samples.as_mut_ptr());
+            }
+            samples
+                .into_iter()
+                .fold(1, |mask, count| mask | count as i::NumSamples)
+        } else {
+            let max_samples = get(gl::MAX_SAMPLES);
+            (0 .. 8)
+                .filter(|bit| (1 << bit) <= max_samples)
+                .fold(0, |mask, bit| mask | 1 << bit)
+        };
+
+        Some(i::FormatProperties {
+            max_extent,
+            max_levels: max_levels as _,
+            max_layers: max_layers.min(i::Layer::max_value() as u32) as _,
+            sample_count_mask,
+            max_resource_size: !0,
+        })
+    }
+
     fn allocate_memory(
         &mut self, mem_type: &c::MemoryType, _size: u64,
     ) -> Result<n::Memory, d::OutOfMemory> {
         Ok(n::Memory {
             properties: mem_type.properties,
+            mapping: Mutex::new(n::MemoryMapping::default()),
         })
     }
 
@@ -188,8 +672,32 @@ impl d::Device<B> for Device {
         }
     }
 
-    fn create_pipeline_layout(&mut self, _: &[&n::DescriptorSetLayout]) -> n::PipelineLayout {
-        n::PipelineLayout
+    fn create_pipeline_layout(&mut self, layouts: &[&n::DescriptorSetLayout]) -> n::PipelineLayout {
+        // Each kind of resource has its own range of binding slots,
+        // which are assigned in order of the sets and their bindings.
+        let mut next_slots = HashMap::new();
+        let slots = layouts
+            .iter()
+            .map(|layout| {
+                let mut bindings = layout.bindings.clone();
+                bindings.sort_by_key(|binding| binding.binding);
+                bindings
+                    .iter()
+                    .map(|binding| {
+                        let register = conv::descriptor_to_gl_register(binding.ty);
+                        let next = next_slots.entry(register).or_insert(0);
+                        let slot = *next;
+                        *next += binding.count as gl::types::GLuint;
+                        (binding.binding, (register, slot))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        n::PipelineLayout {
+            slots,
+            set_bindings: layouts.iter().map(|layout| layout.bindings.clone()).collect(),
+        }
     }
 
     fn create_graphics_pipelines<'a>(
@@ -200,28 +708,111 @@ impl d::Device<B> for Device {
         let priv_caps = &self.share.private_caps;
         let share = &self.share;
         descs.iter()
-             .map(|&(shaders, _layout, subpass, _desc)| {
-                let subpass = match subpass.main_pass.subpasses.get(subpass.index) {
+             .map(|&(shaders, layout, subpass, desc)| {
+                desc.validate_vertex_input(&share.features, &share.limits)?;
+                desc.validate_baked_states(&share.features, &share.limits)?;
+                desc.validate_blender(&share.features)?;
+                desc.validate_tessellation(&shaders, &share.limits)?;
+                desc.validate_stream_output(&share.features)?;
+                for attribute in &desc.attributes {
+                    if conv::format_to_gl_vertex(attribute.element.format).is_none() {
+                        return Err(pso::InputError::UnsupportedFormat(
+                            attribute.location, attribute.element.format).into());
+                    }
+                }
+
+                let main_pass = subpass.main_pass;
+                let subpass = match main_pass.subpasses.get(subpass.index) {
                     Some(sp) => sp,
                     None => return Err(pso::CreationError::InvalidSubpass(subpass.index)),
                 };
+                let color_formats = subpass.color_attachments
+                    .iter()
+                    .map(|&id| main_pass.attachments[id].format)
+                    .collect::<Vec<_>>();
+                desc.validate_shader_interface(self, &shaders, &layout.set_bindings, &color_formats)?;
 
                 let program = {
                     let name = unsafe { gl.CreateProgram() };
 
-                    let attach_shader = |point_maybe: Option<pso::EntryPoint<B>>| {
-                        if let Some(point) = point_maybe {
-                            assert_eq!(point.entry, "main");
-                            unsafe { gl.AttachShader(name, point.module.raw); }
+                    // Shaders translated from SPIR-V, owned by the program.
+                    let mut translated = Vec::new();
+                    let mut late_bindings = Vec::new();
+                    // Outputs of the last vertex processing stage.
+                    let mut vertex_outputs = Vec::new();
+                    let attached = {
+                        let mut attach_shader = |stage: pso::Stage, point_maybe: Option<pso::EntryPoint<B>>| -> Result<(), d::ShaderError> {
+                            let point = match point_maybe {
+                                Some(point) => point,
+                                None => return Ok(()),
+                            };
+                            let shader = match *point.module {
+                                n::ShaderModule::Raw(raw) => {
+                                    assert_eq!(point.entry, "main");
+                                    if !point.specialization.is_empty() {
+                                        return Err(d::ShaderError::CompilationFailed(
+                                            "Specialization constants require a SPIR-V shader module".into()));
+                                    }
+                                    if stage != pso::Stage::Hull && stage != pso::Stage::Fragment {
+                                        vertex_outputs.clear();
+                                    }
+                                    raw
+                                }
+                                n::ShaderModule::Spirv(ref data) => {
+                                    let translation = self.translate_spirv(
+                                        data, stage, point.entry, point.specialization, layout)?;
+                                    let shader = self.compile_shader(translation.source.as_bytes(), stage)?;
+                                    translated.push(shader);
+                                    late_bindings.extend(translation.late_bindings);
+                                    if stage != pso::Stage::Hull && stage != pso::Stage::Fragment {
+                                        vertex_outputs = translation.outputs;
+                                    }
+                                    shader
+                                }
+                            };
+                            unsafe { gl.AttachShader(name, shader); }
+                            Ok(())
+                        };
+
+                        // Attach shaders to program
+                        [
+                            (pso::Stage::Vertex, Some(shaders.vertex)),
+                            (pso::Stage::Hull, shaders.hull),
+                            (pso::Stage::Domain, shaders.domain),
+                            (pso::Stage::Geometry, shaders.geometry),
+                            (pso::Stage::Fragment, shaders.fragment),
+                        ]
+                        .iter()
+                        .map(|&(stage, point)| attach_shader(stage, point))
+                        .collect::<Result<Vec<_>, _>>()
+                    };
+                    let varyings = attached
+                        .map_err(pso::CreationError::Shader)
+                        .and_then(|_| match desc.stream_output {
+                            Some(ref so) => stream_output_varyings(so, &vertex_outputs)
+                                .map(Some)
+                                .map_err(pso::CreationError::StreamOutput),
+                            None => Ok(None),
+                        });
+                    let varyings = match varyings {
+                        Ok(varyings) => varyings,
+                        Err(err) => {
+                            unsafe {
+                                for &shader in &translated {
+                                    gl.DeleteShader(shader);
+                                }
+                                gl.DeleteProgram(name);
+                            }
+                            return Err(err);
                         }
                     };
 
-                    // Attach shaders to program
-                    attach_shader(Some(shaders.vertex));
-                    attach_shader(shaders.hull);
-                    attach_shader(shaders.domain);
-                    attach_shader(shaders.geometry);
-                    attach_shader(shaders.fragment);
+                    if let Some(ref varyings) = varyings {
+                        let names = varyings.iter().map(|name| name.as_ptr()).collect::<Vec<_>>();
+                        unsafe {
+                            gl.TransformFeedbackVaryings(name, names.len() as _, names.as_ptr(), gl::INTERLEAVED_ATTRIBS);
+                        }
+                    }
 
                     if !priv_caps.program_interface && priv_caps.frag_data_location {
                         for i in 0..subpass.color_attachments.len() {
@@ -238,6 +829,11 @@ impl d::Device<B> for Device {
                         panic!("Error linking program: {:?}", err);
                     }
 
+                    // The program keeps the translated shaders alive until it's deleted.
+                    for &shader in &translated {
+                        unsafe { gl.DeleteShader(shader) };
+                    }
+
                     let status = get_program_iv(gl, name, gl::LINK_STATUS);
                     let log = get_program_log(gl, name);
                     if status != 0 {
@@ -245,14 +841,41 @@ impl d::Device<B> for Device {
                             warn!("\tLog: {}", log);
                         }
                     } else {
-                        return Err(pso::CreationError::Other);
+                        unsafe { gl.DeleteProgram(name) };
+                        return Err(d::ShaderError::InterfaceMismatch(log).into());
+                    }
+
+                    if !late_bindings.is_empty() {
+                        self.bind_slots(name, &late_bindings);
                     }
 
                     name
                 };
 
+                let feedback_primitive = desc.stream_output.as_ref().map(|_| {
+                    transform_feedback_primitive(
+                        gl,
+                        program,
+                        shaders.geometry.is_some(),
+                        shaders.domain.is_some(),
+                        desc.input_assembler.primitive,
+                    )
+                });
+
                 Ok(n::GraphicsPipeline {
                     program,
+                    primitive: conv::primitive_to_gl_primitive(desc.input_assembler.primitive),
+                    patch_size: match desc.input_assembler.primitive {
+                        c::Primitive::PatchList(size) => Some(size),
+                        _ => None,
+                    },
+                    blender: desc.blender.clone(),
+                    feedback_primitive,
+                    rasterizer_discard: desc.stream_output
+                        .as_ref()
+                        .map_or(false, |so| so.rasterized_stream.is_none()),
+                    line_width: desc.rasterizer.line_width.clone(),
+                    baked_states: desc.baked_states.clone(),
                 })
              })
              .collect()
@@ -304,27 +927,35 @@ impl d::Device<B> for Device {
 
     fn create_shader_module(
         &mut self,
-        _data: &[u8],
+        data: &[u8],
     ) -> Result<n::ShaderModule, d::ShaderError> {
-        //TODO: SPIRV loading or conversion to GLSL
-        unimplemented!()
+        // Translation depends on the pipeline layout, so it's deferred to pipeline creation.
+        Ok(n::ShaderModule::Spirv(data.to_vec()))
+    }
+
+    fn reflect_shader_module(&self, module: &n::ShaderModule) -> Result<pso::ShaderReflection, pso::ReflectError> {
+        match *module {
+            n::ShaderModule::Raw(_) => Err(pso::ReflectError::NotSpirv),
+            n::ShaderModule::Spirv(ref spirv) => pso::ShaderReflection::from_spirv(spirv),
+        }
     }
 
-    fn create_sampler(&mut self, info: i::SamplerInfo) -> n::FatSampler {
+    fn create_sampler(&mut self, info: i::SamplerInfo) -> Result<n::FatSampler, i::SamplerError> {
+        info.validate(&self.share.features)?;
         if !self.share.features.sampler_objects {
-            return n::FatSampler::Info(info);
+            return Ok(n::FatSampler::Info(info));
         }
 
         let gl = &self.share.context;
         let mut name = 0 as n::Sampler;
 
-        let (min, mag) = conv::filter_to_gl(info.filter);
+        let (min, mag) = conv::filter_to_gl(info.mag_filter, info.min_filter, info.mip_filter);
 
         unsafe {
             gl.GenSamplers(1, &mut name);
 
-            match info.filter{
-                i::FilterMethod::Anisotropic(fac) if fac > 1 => {
+            match info.anisotropic {
+                i::Anisotropic::On(fac) if fac > 1 => {
                     if self.share.private_caps.sampler_anisotropy_ext {
                         gl.SamplerParameterf(name, gl::TEXTURE_MAX_ANISOTROPY_EXT, fac as GLfloat);
                     } else if self.share.features.sampler_anisotropy {
@@ -351,6 +982,10 @@ impl d::Device<B> for Device {
                 gl.SamplerParameterfv(name, gl::TEXTURE_BORDER_COLOR, &border[0]);
             }
 
+            if info.reduction != i::ReductionMode::WeightedAverage {
+                gl.SamplerParameteri(name, conv::TEXTURE_REDUCTION_MODE_ARB, conv::reduction_to_gl(info.reduction) as GLint);
+            }
+
             gl.SamplerParameterf(name, gl::TEXTURE_MIN_LOD, info.lod_range.start.into());
             gl.SamplerParameterf(name, gl::TEXTURE_MAX_LOD, info.lod_range.end.into());
 
@@ -367,11 +1002,11 @@ impl d::Device<B> for Device {
             panic!("Error {:?} creating sampler: {:?}", err, info)
         }
 
-        n::FatSampler::Sampler(name)
+        Ok(n::FatSampler::Sampler(name))
     }
 
     fn create_buffer(
-        &mut self, size: u64, stride: u64, usage: buffer::Usage,
+        &mut self, size: u64, stride: u64, usage: buffer::Usage, _: memory::SharingMode,
     ) -> Result<UnboundBuffer, buffer::CreationError> {
         if !self.share.features.constant_buffer && usage.contains(buffer::UNIFORM) {
             error!("Constant buffers are not supported by this GL version");
@@ -409,7 +1044,7 @@ impl d::Device<B> for Device {
     }
 
     fn bind_buffer_memory(
-        &mut self, memory: &n::Memory, _offset: u64, unbound: UnboundBuffer,
+        &mut self, memory: &n::Memory, offset: u64, unbound: UnboundBuffer,
     ) -> Result<n::Buffer, d::BindError> {
         let gl = &self.share.context;
         let target = unbound.target;
@@ -418,7 +1053,6 @@ impl d::Device<B> for Device {
         let cpu_can_write = memory.can_upload();
 
         if self.share.private_caps.buffer_storage {
-            //TODO: gl::DYNAMIC_STORAGE_BIT | gl::MAP_PERSISTENT_BIT
             let mut flags = 0;
             if cpu_can_read {
                 flags |= gl::MAP_READ_BIT;
@@ -426,6 +1060,10 @@ impl d::Device<B> for Device {
             if cpu_can_write {
                 flags |= gl::MAP_WRITE_BIT;
             }
+            if cpu_can_read || cpu_can_write {
+                // All the CPU visible memory types are coherent
+                flags |= gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+            }
             //TODO: use *Named calls to avoid binding
             unsafe {
                 gl.BindBuffer(target, unbound.name);
@@ -463,6 +1101,14 @@ impl d::Device<B> for Device {
                 err, unbound, memory.properties);
         }
 
+        if cpu_can_read || cpu_can_write {
+            let mut state = memory.mapping.lock().unwrap();
+            if state.buffer.is_none() {
+                let range = offset .. offset + unbound.requirements.size;
+                state.buffer = Some((unbound.name, target, range));
+            }
+        }
+
         Ok(n::Buffer {
             raw: unbound.name,
             target,
@@ -477,7 +1123,7 @@ impl d::Device<B> for Device {
         unimplemented!()
     }
 
-    fn create_image(&mut self, _: i::Kind, _: i::Level, _: Format, _: i::Usage)
+    fn create_image(&mut self, _: i::Kind, _: i::Level, _: Format, _: i::Tiling, _: i::Usage, _: memory::SharingMode)
          -> Result<UnboundImage, i::CreationError>
     {
         unimplemented!()
@@ -487,22 +1133,50 @@ impl d::Device<B> for Device {
         unimplemented!()
     }
 
+    fn get_image_subresource_footprint(
+        &mut self, _: &n::Image, _: i::AspectFlags, _: i::Subresource,
+    ) -> i::SubresourceFootprint {
+        unreachable!("Linear images are not supported")
+    }
+
     fn bind_image_memory(&mut self, _: &n::Memory, _: u64, _: UnboundImage) -> Result<n::Image, d::BindError> {
         unimplemented!()
     }
 
+    fn create_sparse_buffer(
+        &mut self, _: u64, _: u64, _: buffer::Usage, _: memory::SharingMode,
+    ) -> Result<n::Buffer, buffer::CreationError> {
+        // `Features::sparse_binding` isn't supported.
+        Err(buffer::CreationError::Sparse)
+    }
+
+    fn get_sparse_buffer_requirements(&mut self, _: &n::Buffer) -> memory::Requirements {
+        unreachable!("Sparse buffers are rejected by `create_sparse_buffer`")
+    }
+
+    fn create_sparse_image(
+        &mut self, _: i::Kind, _: i::Level, _: Format, _: i::Usage, _: memory::SharingMode,
+    ) -> Result<n::Image, i::CreationError> {
+        // `Features::sparse_binding` isn't supported.
+        Err(i::CreationError::Sparse)
+    }
+
+    fn get_sparse_image_requirements(&mut self, _: &n::Image) -> (memory::Requirements, d::Extent) {
+        unreachable!("Sparse images are rejected by `create_sparse_image`")
+    }
+
     fn create_image_view(&mut self,
-        image: &n::Image, format: Format, swizzle: Swizzle, range: i::SubresourceRange,
+        image: &n::Image, kind: i::ViewKind, format: Format, swizzle: Swizzle, range: i::SubresourceRange,
     ) -> Result<n::ImageView, i::ViewError> {
-        //TODO: check if `layers.end` covers all the layers
+        image.kind.check_view(image.format, kind, format, &range)?;
         let level = range.levels.start;
         assert_eq!(level + 1, range.levels.end);
-        //assert_eq!(format, image.format);
         assert_eq!(swizzle, Swizzle::NO);
-        //TODO: check format
-        match *image {
-            n::Image::Surface(surface) => {
-                if range.levels.start == 0 && range.layers.start == 0 {
+        match image.raw {
+            n::ImageKind::Surface(surface) => {
+                if kind != i::ViewKind::D2 {
+                    Err(i::ViewError::BadKind(kind))
+                } else if range.levels.start == 0 && range.layers.start == 0 {
                     Ok(n::ImageView::Surface(surface))
                 } else if level != 0 {
                     Err(i::ViewError::Level(level)) //TODO
@@ -510,14 +1184,26 @@ impl d::Device<B> for Device {
                     Err(i::ViewError::Layer(i::LayerError::OutOfBounds(range.layers)))
                 }
             }
-            n::Image::Texture(texture) => {
+            n::ImageKind::Texture(texture) => {
                 //TODO: check that `level` exists
-                if range.layers.start == 0 {
-                    Ok(n::ImageView::Texture(texture, level))
-                } else if range.layers.start + 1 == range.layers.end {
-                    Ok(n::ImageView::TextureLayer(texture, level, range.layers.start))
-                } else {
-                    Err(i::ViewError::Layer(i::LayerError::OutOfBounds(range.layers)))
+                // Views of a different kind require `glTextureView`, which is not used yet.
+                match kind {
+                    i::ViewKind::D1 | i::ViewKind::D2 | i::ViewKind::D3
+                        if range.layers.start + 1 == range.layers.end =>
+                    {
+                        if range.layers.start == 0 {
+                            Ok(n::ImageView::Texture(texture, level))
+                        } else {
+                            Ok(n::ImageView::TextureLayer(texture, level, range.layers.start))
+                        }
+                    }
+                    i::ViewKind::D1Array | i::ViewKind::D2Array |
+                    i::ViewKind::Cube | i::ViewKind::CubeArray
+                        if range.layers.start == 0 =>
+                    {
+                        Ok(n::ImageView::Texture(texture, level))
+                    }
+                    _ => Err(i::ViewError::Layer(i::LayerError::OutOfBounds(range.layers))),
                 }
             }
         }
@@ -527,14 +1213,94 @@ impl d::Device<B> for Device {
         n::DescriptorPool { }
     }
 
-    fn create_descriptor_set_layout(&mut self, _: &[pso::DescriptorSetLayoutBinding]) -> n::DescriptorSetLayout {
-        n::DescriptorSetLayout
+    fn create_descriptor_set_layout(&mut self, bindings: &[pso::DescriptorSetLayoutBinding]) -> n::DescriptorSetLayout {
+        n::DescriptorSetLayout {
+            bindings: bindings.to_vec(),
+        }
     }
 
     fn update_descriptor_sets(&mut self, _: &[pso::DescriptorSetWrite<B>]) {
         unimplemented!()
     }
 
+    fn map_memory(&mut self, memory: &n::Memory, range: Range<u64>)
+        -> Result<*mut u8, mapping::Error>
+    {
+        let mut state = memory.mapping.lock().unwrap();
+        let (raw, target, offset) = match state.buffer {
+            Some((raw, target, ref bound)) => {
+                // Ranges outside of the mapped buffer belong to other buffers.
+                if range.start < bound.start || range.start > range.end || range.end > bound.end {
+                    return Err(mapping::Error::OutOfBounds);
+                }
+                (raw, target, bound.start)
+            }
+            None => return Err(mapping::Error::InvalidAccess),
+        };
+        if state.mapped {
+            return Err(mapping::Error::InvalidAccess);
+        }
+
+        let mut access = 0;
+        if memory.can_download() {
+            access |= gl::MAP_READ_BIT;
+        }
+        if memory.can_upload() {
+            access |= gl::MAP_WRITE_BIT;
+        }
+        if self.share.private_caps.buffer_storage {
+            access |= gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+        }
+        let gl = &self.share.context;
+
+        let data = unsafe {
+            gl.BindBuffer(target, raw);
+            let ptr = gl.MapBufferRange(
+                target,
+                (range.start - offset) as _,
+                (range.end - range.start) as _,
+                access,
+            );
+            gl.BindBuffer(target, 0);
+            ptr
+        };
+
+        if let Err(err) = self.share.check() {
+            panic!("Error mapping memory: {:?}, range = {:?}", err, range);
+        }
+        debug_assert_ne!(data, ptr::null_mut());
+        state.mapped = true;
+        Ok(data as _)
+    }
+
+    fn flush_mapped_memory_ranges(&mut self, _: &[(&n::Memory, Range<u64>)]) -> Result<(), d::OutOfMemory> {
+        // All the exposed memory types are coherent
+        Ok(())
+    }
+
+    fn invalidate_mapped_memory_ranges(&mut self, _: &[(&n::Memory, Range<u64>)]) -> Result<(), d::OutOfMemory> {
+        // All the exposed memory types are coherent
+        Ok(())
+    }
+
+    fn unmap_memory(&mut self, memory: &n::Memory) {
+        let mut state = memory.mapping.lock().unwrap();
+        let (raw, target) = match state.buffer {
+            Some((raw, target, _)) if state.mapped => (raw, target),
+            _ => {
+                error!("Memory wasn't mapped");
+                return;
+            }
+        };
+        state.mapped = false;
+        let gl = &self.share.context;
+        unsafe {
+            gl.BindBuffer(target, raw);
+            gl.UnmapBuffer(target);
+            gl.BindBuffer(target, 0);
+        }
+    }
+
     fn acquire_mapping_raw(&mut self, buffer: &n::Buffer, read: Option<Range<u64>>)
         -> Result<*mut u8, mapping::Error>
     {
@@ -560,7 +1326,9 @@ impl d::Device<B> for Device {
         Ok(data as _)
     }
 
-    fn release_mapping_raw(&mut self, buffer: &n::Buffer, wrote: Option<Range<u64>>) {
+    fn release_mapping_raw(&mut self, buffer: &n::Buffer, wrote: Option<Range<u64>>)
+        -> Result<(), mapping::Error>
+    {
         assert!(wrote.is_none() || buffer.cpu_can_write);
         let gl = &self.share.context;
         unsafe {
@@ -568,6 +1336,7 @@ impl d::Device<B> for Device {
             gl.UnmapBuffer(buffer.target);
             gl.BindBuffer(buffer.target, 0);
         }
+        Ok(())
     }
 
     fn create_semaphore(&mut self) -> n::Semaphore {
@@ -601,6 +1370,24 @@ impl d::Device<B> for Device {
         }
     }
 
+    fn create_event(&mut self) -> n::Event {
+        error!("Events are not supported");
+        n::Event
+    }
+
+    fn set_event(&mut self, _event: &n::Event) {
+        error!("Events are not supported");
+    }
+
+    fn reset_event(&mut self, _event: &n::Event) {
+        error!("Events are not supported");
+    }
+
+    fn get_event_status(&mut self, _event: &n::Event) -> bool {
+        error!("Events are not supported");
+        false
+    }
+
     fn wait_for_fences(&mut self, fences: &[&n::Fence], wait: d::WaitFor, timeout_ms: u32) -> bool {
         if !self.share.private_caps.sync {
             return true;
@@ -653,8 +1440,10 @@ impl d::Device<B> for Device {
         unimplemented!()
     }
 
-    fn destroy_shader_module(&mut self, _: n::ShaderModule) {
-        unimplemented!()
+    fn destroy_shader_module(&mut self, module: n::ShaderModule) {
+        if let n::ShaderModule::Raw(raw) = module {
+            unsafe { self.share.context.DeleteShader(raw) };
+        }
     }
 
     fn destroy_renderpass(&mut self, _: n::RenderPass) {
@@ -705,6 +1494,10 @@ impl d::Device<B> for Device {
     fn destroy_semaphore(&mut self, _: n::Semaphore) {
         unimplemented!()
     }
+
+    fn destroy_event(&mut self, _: n::Event) {
+        // Just drop
+    }
 }
 
 pub fn wait_fence(fence: &n::Fence, gl: &gl::Gl, timeout_ms: u32) -> GLenum {
@@ -165,6 +165,8 @@ pub struct PrivateCaps {
     pub map: bool,
     /// Indicates if we only have support via the EXT.
     pub sampler_anisotropy_ext: bool,
+    /// Can query supported sample counts of internal formats
+    pub internalformat_query: bool,
 }
 
 /// OpenGL implementation information
@@ -250,8 +252,19 @@ pub fn query_all(gl: &gl::Gl) -> (Info, Features, Limits, PrivateCaps) {
     let tessellation_supported =           info.is_supported(&[Core(4,0),
                                                                Ext("GL_ARB_tessellation_shader")]);
     let multi_viewports_supported =        info.is_supported(&[Core(4,1)]); // TODO: extension
+    let vertex_attrib_binding_supported =  info.is_supported(&[Core(4,3),
+                                                               Es(3,1),
+                                                               Ext("GL_ARB_vertex_attrib_binding")]);
     let compute_supported =                info.is_supported(&[Core(4,3),
                                                                Ext("GL_ARB_compute_shader")]);
+    // BC1-3 are provided by S3TC, BC4-5 by RGTC and BC6-7 by BPTC.
+    let format_bc_supported =              info.is_supported(&[Ext("GL_EXT_texture_compression_s3tc")]) &&
+                                           info.is_supported(&[Core(3,0),
+                                                               Ext("GL_ARB_texture_compression_rgtc"),
+                                                               Ext("GL_EXT_texture_compression_rgtc")]) &&
+                                           info.is_supported(&[Core(4,2),
+                                                               Ext("GL_ARB_texture_compression_bptc"),
+                                                               Ext("GL_EXT_texture_compression_bptc")]);
     let mut max_compute_group_count = [0usize; 3];
     let mut max_compute_group_size = [0usize; 3];
     if compute_supported {
@@ -276,12 +289,24 @@ pub fn query_all(gl: &gl::Gl) -> (Info, Features, Limits, PrivateCaps) {
         max_viewports: if multi_viewports_supported { get_usize(gl, gl::MAX_VIEWPORTS) } else {1},
         max_compute_group_count,
         max_compute_group_size,
+        max_vertex_input_attributes: get_usize(gl, gl::MAX_VERTEX_ATTRIBS),
+        // Without separate bindings each attribute is sourced from its own buffer.
+        max_vertex_input_bindings: if vertex_attrib_binding_supported {
+            get_usize(gl, gl::MAX_VERTEX_ATTRIB_BINDINGS)
+        } else {
+            get_usize(gl, gl::MAX_VERTEX_ATTRIBS)
+        },
+        max_vertex_input_attribute_offset: 2047, // minimum of MAX_VERTEX_ATTRIB_RELATIVE_OFFSET
+        max_vertex_input_binding_stride: 2048, // minimum of MAX_VERTEX_ATTRIB_STRIDE
 
         min_buffer_copy_offset_alignment: 1,
         min_buffer_copy_pitch_alignment: 1,
         min_uniform_buffer_offset_alignment: 1, // TODO
+        non_coherent_atom_size: 1,
 
     };
+    let mut line_width_range = [1.0 as gl::types::GLfloat; 2];
+    unsafe { gl.GetFloatv(gl::ALIASED_LINE_WIDTH_RANGE, line_width_range.as_mut_ptr()) };
     let features = Features {
         indirect_execution:                 info.is_supported(&[Core(4,3),
                                                                 Es  (3,1)]), // TODO: extension
@@ -322,6 +347,28 @@ pub fn query_all(gl: &gl::Gl) -> (Info, Features, Limits, PrivateCaps) {
                                                                 Ext ("GL_ARB_texture_filter_anisotropic"),
                                                                 Ext ("GL_EXT_texture_filter_anisotropic")]),
         sampler_border_color:               info.is_supported(&[Core(3,3)]), // TODO: extensions
+        sampler_reduction:                  info.is_supported(&[Ext ("GL_ARB_texture_filter_minmax")]),
+        sampler_unnormalized:               false,
+        persistent_mapping:                 info.is_supported(&[Core(4,4),
+                                                                Ext ("GL_ARB_buffer_storage")]),
+        sparse_binding:                     false, // TODO: GL_ARB_sparse_buffer
+        sparse_residency_buffer:            false,
+        sparse_residency_image:             false,
+        format_bc:                          format_bc_supported,
+        format_etc2:                        info.is_supported(&[Core(4,3),
+                                                                Es  (3,0),
+                                                                Ext ("GL_ARB_ES3_compatibility")]),
+        format_astc_ldr:                    info.is_supported(&[Es  (3,2),
+                                                                Ext ("GL_KHR_texture_compression_astc_ldr")]),
+        depth_bounds:                       false, // TODO: GL_EXT_depth_bounds_test
+        wide_lines:                         line_width_range[1] > 1.0,
+        logic_op:                           !info.version.is_embedded,
+        stream_output:                      info.is_supported(&[Core(4,0),
+                                                                Ext ("GL_ARB_transform_feedback3")]),
+        draw_auto:                          info.is_supported(&[Core(4,0),
+                                                                Ext ("GL_ARB_transform_feedback2")]),
+        events:                             false,
+        blit_1d_3d:                         false, // Framebuffer blits are limited to 2D layers
     };
     let private = PrivateCaps {
         vertex_array:                       info.is_supported(&[Core(3,0),
@@ -347,6 +394,9 @@ pub fn query_all(gl: &gl::Gl) -> (Info, Features, Limits, PrivateCaps) {
         sampler_anisotropy_ext:             !info.is_supported(&[Core(4,6),
                                                                 Ext ("GL_ARB_texture_filter_anisotropic")]) &&
                                             info.is_supported(&[Ext ("GL_EXT_texture_filter_anisotropic")]),
+        internalformat_query:               info.is_supported(&[Core(4,2),
+                                                                Es  (3,0),
+                                                                Ext ("GL_ARB_internalformat_query")]),
     };
 
     (info, features, limits, private)
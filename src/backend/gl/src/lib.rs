@@ -8,6 +8,7 @@ extern crate log;
 extern crate gfx_gl as gl;
 extern crate gfx_hal as hal;
 extern crate smallvec;
+extern crate spirv_cross;
 #[cfg(feature = "glutin")]
 pub extern crate glutin;
 
@@ -69,6 +70,7 @@ impl c::Backend for Backend {
 
     type Fence = native::Fence;
     type Semaphore = native::Semaphore;
+    type Event = native::Event;
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
@@ -286,4 +288,5 @@ pub struct QueueFamily;
 
 impl c::QueueFamily for QueueFamily {
     fn num_queues(&self) -> u32 { 1 }
+    fn id(&self) -> c::QueueFamilyId { c::QueueFamilyId(0) }
 }
@@ -1,8 +1,11 @@
-use hal::{self, image as i, memory as mem, pass};
+use hal::{self, image as i, memory as mem, pass, pso};
 use hal::target::{Layer, Level};
 use gl;
 use Backend;
 use std::cell::Cell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Mutex;
 
 
 pub type RawBuffer   = gl::types::GLuint;
@@ -35,9 +38,17 @@ impl Fence {
     }
 }
 
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug)]
 pub struct GraphicsPipeline {
     pub(crate) program: Program,
+    pub(crate) primitive: gl::types::GLenum,
+    pub(crate) patch_size: Option<hal::PatchSize>,
+    pub(crate) blender: pso::BlendDesc,
+    // Primitive captured by transform feedback, if the pipeline has a stream output.
+    pub(crate) feedback_primitive: Option<gl::types::GLenum>,
+    pub(crate) rasterizer_discard: bool,
+    pub(crate) line_width: pso::State<f32>,
+    pub(crate) baked_states: pso::BakedStates,
 }
 
 #[derive(Clone, Debug, Copy)]
@@ -46,11 +57,19 @@ pub struct ComputePipeline {
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub enum Image {
+pub enum ImageKind {
     Surface(Surface),
     Texture(Texture),
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Image {
+    pub(crate) raw: ImageKind,
+    // Kind and format of the image, views are validated against them.
+    pub(crate) kind: i::Kind,
+    pub(crate) format: hal::format::Format,
+}
+
 #[derive(Clone, PartialEq, Eq, Hash, Debug)]
 /// Additionally storing the `SamplerInfo` for older OpenGL versions, which
 /// don't support separate sampler objects.
@@ -66,8 +85,10 @@ pub enum ImageView {
     TextureLayer(Texture, Level, Layer),
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
-pub struct DescriptorSetLayout;
+#[derive(Clone, Debug)]
+pub struct DescriptorSetLayout {
+    pub(crate) bindings: Vec<pso::DescriptorSetLayoutBinding>,
+}
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub struct DescriptorSet;
@@ -85,14 +106,26 @@ impl hal::DescriptorPool<Backend> for DescriptorPool {
     }
 }
 
-#[derive(Clone, Copy, Debug, Hash)]
-pub struct ShaderModule {
-    pub(crate) raw: Shader,
+#[derive(Clone, Debug, Hash)]
+pub enum ShaderModule {
+    /// Compiled GLSL shader, only usable with the `main` entry point.
+    Raw(Shader),
+    /// SPIR-V module, translated to GLSL on pipeline creation.
+    Spirv(Vec<u8>),
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct MemoryMapping {
+    /// GL can only map buffers, so the first CPU accessible buffer bound
+    /// to the memory is used for mapping: `(buffer, target, bound range)`.
+    pub(crate) buffer: Option<(RawBuffer, gl::types::GLenum, Range<u64>)>,
+    pub(crate) mapped: bool,
 }
 
 #[derive(Debug)]
 pub struct Memory {
     pub(crate) properties: mem::Properties,
+    pub(crate) mapping: Mutex<MemoryMapping>,
 }
 
 impl Memory {
@@ -115,9 +148,26 @@ pub struct SubpassDesc {
     pub(crate) color_attachments: Vec<usize>,
 }
 
+/// Kinds of resources, each with a separate range of GL binding slots.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BindingRegister {
+    UniformBuffers,
+    StorageBuffers,
+    Textures,
+    Images,
+}
+
 #[derive(Debug)]
-pub struct PipelineLayout;
+pub struct PipelineLayout {
+    /// GL binding slots of the descriptors, per set and binding.
+    pub(crate) slots: Vec<HashMap<usize, (BindingRegister, gl::types::GLuint)>>,
+    /// Bindings of each descriptor set layout.
+    pub(crate) set_bindings: Vec<Vec<pso::DescriptorSetLayoutBinding>>,
+}
 
 #[derive(Debug)]
 // No inter-queue synchronization required for GL.
 pub struct Semaphore;
+
+#[derive(Debug)]
+pub struct Event;
@@ -58,6 +58,8 @@ pub struct CommandQueue {
     pub(crate) share: Rc<Share>,
     vao: ArrayBuffer,
     state: State,
+    // Transform feedback object, created on first use.
+    transform_feedback: Option<gl::types::GLuint>,
 }
 
 impl CommandQueue {
@@ -67,6 +69,7 @@ impl CommandQueue {
             share: share.clone(),
             vao,
             state: State::new(),
+            transform_feedback: None,
         }
     }
 
@@ -166,11 +169,27 @@ impl CommandQueue {
         }
     }
 
-    fn _unbind_target(&mut self, point: gl::types::GLenum, attachment: gl::types::GLenum) {
+    fn unbind_target(&mut self, point: gl::types::GLenum, attachment: gl::types::GLenum) {
         let gl = &self.share.context;
         unsafe { gl.FramebufferTexture(point, attachment, 0, 0) };
     }
 
+    // Bind the transform feedback object of the queue, creating it if needed.
+    fn bind_transform_feedback(&mut self) -> gl::types::GLuint {
+        let gl = &self.share.context;
+        let name = match self.transform_feedback {
+            Some(name) => name,
+            None => {
+                let mut name = 0;
+                unsafe { gl.GenTransformFeedbacks(1, &mut name) };
+                self.transform_feedback = Some(name);
+                name
+            }
+        };
+        unsafe { gl.BindTransformFeedback(gl::TRANSFORM_FEEDBACK, name) };
+        name
+    }
+
     /// Return a reference to a stored data object.
     fn get<T>(data: &[u8], ptr: com::BufferSlice) -> &[T] {
         assert_eq!(ptr.size % mem::size_of::<T>() as u32, 0);
@@ -244,6 +263,41 @@ impl CommandQueue {
 
     fn process(&mut self, cmd: &com::Command, data_buf: &[u8]) {
         match *cmd {
+            com::Command::BindTransformFeedbackBuffer(index, buffer, ref range) => {
+                self.bind_transform_feedback();
+                let gl = &self.share.context;
+                unsafe {
+                    gl.BindBufferRange(
+                        gl::TRANSFORM_FEEDBACK_BUFFER,
+                        index,
+                        buffer,
+                        range.start as _,
+                        (range.end - range.start) as _,
+                    );
+                }
+            }
+            com::Command::BeginTransformFeedback(primitive) => {
+                self.bind_transform_feedback();
+                unsafe { self.share.context.BeginTransformFeedback(primitive) };
+            }
+            com::Command::EndTransformFeedback => unsafe {
+                self.share.context.EndTransformFeedback();
+            }
+            com::Command::DrawAuto(primitive) => {
+                let name = self.bind_transform_feedback();
+                unsafe { self.share.context.DrawTransformFeedback(primitive, name) };
+            }
+            com::Command::SetRasterizerDiscard(discard) => unsafe {
+                let gl = &self.share.context;
+                if discard {
+                    gl.Enable(gl::RASTERIZER_DISCARD);
+                } else {
+                    gl.Disable(gl::RASTERIZER_DISCARD);
+                }
+            }
+            com::Command::BindProgram(program) => unsafe {
+                self.share.context.UseProgram(program);
+            }
             com::Command::BindIndexBuffer(buffer) => {
                 let gl = &self.share.context;
                 self.state.index_buffer = Some(buffer);
@@ -418,6 +472,27 @@ impl CommandQueue {
             com::Command::SetBlendColor(color) => {
                 state::set_blend_color(&self.share.context, color);
             }
+            com::Command::SetBlendTarget(slot, ref info) => {
+                let gl = &self.share.context;
+                if self.share.features.separate_blending_slots {
+                    state::bind_blend_target(gl, Some(slot), info);
+                } else if slot == 0 {
+                    // Pipeline creation ensures all targets share the same blend state.
+                    state::bind_blend_target(gl, None, info);
+                }
+            }
+            com::Command::SetLogicOp(op) => {
+                state::bind_logic_op(&self.share.context, op);
+            }
+            com::Command::SetAlphaToCoverage(enabled) => {
+                state::bind_alpha_coverage(&self.share.context, enabled);
+            }
+            com::Command::SetPatchSize(size) => unsafe {
+                self.share.context.PatchParameteri(gl::PATCH_VERTICES, size as _);
+            }
+            com::Command::SetLineWidth(width) => {
+                unsafe { self.share.context.LineWidth(width) };
+            }
             com::Command::ClearColor(_texture, c) => {
                 //TODO: check texture?
                 let gl = &self.share.context;
@@ -465,6 +540,28 @@ impl CommandQueue {
             com::Command::SetDrawColorBuffers(num) => {
                 state::bind_draw_color_buffers(&self.share.context, num);
             }
+            com::Command::BlitFramebuffer { fbo, ref src, ref dst, src_rect, dst_rect, filter } => {
+                if !self.share.private_caps.framebuffer {
+                    error!("Blitting requires FBO support!");
+                    return;
+                }
+                // Source and destination are attached to different slots of the same framebuffer.
+                unsafe { self.share.context.BindFramebuffer(gl::FRAMEBUFFER, fbo) };
+                self.bind_target(gl::READ_FRAMEBUFFER, gl::COLOR_ATTACHMENT0, src);
+                self.bind_target(gl::DRAW_FRAMEBUFFER, gl::COLOR_ATTACHMENT1, dst);
+                let gl = &self.share.context;
+                unsafe {
+                    gl.ReadBuffer(gl::COLOR_ATTACHMENT0);
+                    gl.DrawBuffers(1, &gl::COLOR_ATTACHMENT1);
+                    gl.BlitFramebuffer(
+                        src_rect[0], src_rect[1], src_rect[2], src_rect[3],
+                        dst_rect[0], dst_rect[1], dst_rect[2], dst_rect[3],
+                        gl::COLOR_BUFFER_BIT,
+                        filter,
+                    );
+                }
+                self.unbind_target(gl::DRAW_FRAMEBUFFER, gl::COLOR_ATTACHMENT1);
+            }
             /*
             com::Command::BindProgram(program) => unsafe {
                 self.share.context.UseProgram(program);
@@ -637,4 +734,12 @@ impl c::RawCommandQueue<Backend> for CommandQueue {
         }
         fence.map(|fence| self.signal_fence(fence));
     }
+
+    unsafe fn bind_sparse(
+        &mut self,
+        _: c::queue::RawSparseSubmission<Backend>,
+        _: Option<&native::Fence>,
+    ) -> c::SubmissionResult<()> {
+        unreachable!("Sparse resources are rejected by `create_sparse_*`")
+    }
 }
@@ -1,4 +1,4 @@
-use hal::{ColorSlot};
+use hal::{pso, ColorSlot};
 use hal::state as s;
 use hal::state::{
     BlendValue, Comparison, CullFace, Equation,
@@ -213,6 +213,90 @@ pub fn bind_blend_slot(gl: &gl::Gl, slot: ColorSlot, color: s::Color) {
     )};
 }
 
+fn map_blend_channel(channel: Option<s::BlendChannel>) -> (gl::types::GLenum, gl::types::GLenum, gl::types::GLenum) {
+    match channel {
+        Some(b) => (map_equation(b.equation), map_factor(b.source), map_factor(b.destination)),
+        None => (gl::FUNC_ADD, gl::ONE, gl::ZERO),
+    }
+}
+
+fn map_logic_op(op: pso::LogicOp) -> gl::types::GLenum {
+    use hal::pso::LogicOp::*;
+    match op {
+        Clear        => gl::CLEAR,
+        And          => gl::AND,
+        AndReverse   => gl::AND_REVERSE,
+        AndInverted  => gl::AND_INVERTED,
+        Copy         => gl::COPY,
+        CopyInverted => gl::COPY_INVERTED,
+        NoOp         => gl::NOOP,
+        Xor          => gl::XOR,
+        Nor          => gl::NOR,
+        Or           => gl::OR,
+        OrReverse    => gl::OR_REVERSE,
+        OrInverted   => gl::OR_INVERTED,
+        Equivalent   => gl::EQUIV,
+        Invert       => gl::INVERT,
+        Nand         => gl::NAND,
+        Set          => gl::SET,
+    }
+}
+
+/// Bind the blend state of a color target, either for all draw buffers
+/// or only for the given slot.
+pub fn bind_blend_target(gl: &gl::Gl, slot: Option<ColorSlot>, info: &pso::ColorInfo) {
+    let (color_eq, color_src, color_dst) = map_blend_channel(info.color);
+    let (alpha_eq, alpha_src, alpha_dst) = map_blend_channel(info.alpha);
+    let enabled = info.color.is_some() || info.alpha.is_some();
+    let mask = |channel: s::ColorMask| if (info.mask & channel).is_empty() { gl::FALSE } else { gl::TRUE };
+
+    match slot {
+        Some(slot) => unsafe {
+            let buf = slot as gl::types::GLuint;
+            if enabled {
+                gl.Enablei(gl::BLEND, buf);
+                gl.BlendEquationSeparateiARB(buf, color_eq, alpha_eq);
+                gl.BlendFuncSeparateiARB(buf, color_src, color_dst, alpha_src, alpha_dst);
+            } else {
+                gl.Disablei(gl::BLEND, buf);
+            }
+            gl.ColorMaski(buf, mask(s::RED), mask(s::GREEN), mask(s::BLUE), mask(s::ALPHA));
+        },
+        None => unsafe {
+            if enabled {
+                gl.Enable(gl::BLEND);
+                gl.BlendEquationSeparate(color_eq, alpha_eq);
+                gl.BlendFuncSeparate(color_src, color_dst, alpha_src, alpha_dst);
+            } else {
+                gl.Disable(gl::BLEND);
+            }
+            gl.ColorMask(mask(s::RED), mask(s::GREEN), mask(s::BLUE), mask(s::ALPHA));
+        },
+    }
+}
+
+pub fn bind_logic_op(gl: &gl::Gl, op: Option<pso::LogicOp>) {
+    match op {
+        Some(op) => unsafe {
+            gl.Enable(gl::COLOR_LOGIC_OP);
+            gl.LogicOp(map_logic_op(op));
+        },
+        None => unsafe {
+            gl.Disable(gl::COLOR_LOGIC_OP);
+        },
+    }
+}
+
+pub fn bind_alpha_coverage(gl: &gl::Gl, enabled: bool) {
+    unsafe {
+        if enabled {
+            gl.Enable(gl::SAMPLE_ALPHA_TO_COVERAGE);
+        } else {
+            gl.Disable(gl::SAMPLE_ALPHA_TO_COVERAGE);
+        }
+    }
+}
+
 pub fn unlock_color_mask(gl: &gl::Gl) {
     unsafe { gl.ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE) };
 }
@@ -1,6 +1,7 @@
 use {Backend};
 use native;
 
+use std::cmp;
 use std::ops::{Deref, Range};
 use std::sync::{Arc};
 use std::cell::UnsafeCell;
@@ -9,16 +10,52 @@ use core::{self, memory, target, pool, pso};
 use core::{VertexCount, VertexOffset, InstanceCount, IndexCount, Viewport};
 use core::{RawSubmission};
 use core::buffer::{IndexBufferView};
-use core::image::{ImageLayout, SubresourceRange};
+use core::image::{Filter, ImageLayout, SubresourceRange};
 use core::command::{AttachmentClear, ClearColor, ClearDepthStencil, ClearValue, BufferImageCopy, BufferCopy};
-use core::command::{ImageCopy, SubpassContents};
-use core::command::{ImageResolve};
+use core::command::{ImageBlit, ImageCopy, SubpassContents};
+use core::command::{ImageResolve, TransformFeedbackBuffer};
 
 use metal::*;
 use cocoa::foundation::NSUInteger;
 use block::{ConcreteBlock};
 
 
+fn map_viewport(viewport: &Viewport) -> MTLViewport {
+    MTLViewport {
+        originX: viewport.x as f64,
+        originY: viewport.y as f64,
+        width: viewport.w as f64,
+        height: viewport.h as f64,
+        znear: viewport.near as f64,
+        zfar: viewport.far as f64,
+    }
+}
+
+fn map_scissor(rect: &target::Rect) -> MTLScissorRect {
+    MTLScissorRect {
+        x: rect.x as NSUInteger,
+        y: rect.y as NSUInteger,
+        width: rect.w as NSUInteger,
+        height: rect.h as NSUInteger,
+    }
+}
+
+/// Size of a buffer-image copy region, clamped to the mip level.
+/// Regions of compressed formats cover whole blocks, which Metal expects
+/// to be clamped at the edges of the texture.
+fn map_copy_size(texture: MTLTexture, region: &BufferImageCopy) -> MTLSize {
+    let level = region.image_layers.level;
+    let clamp = |size: NSUInteger, offset: i32, extent: u32| {
+        let level_size = cmp::max(1, size >> level) - offset as NSUInteger;
+        cmp::min(extent as NSUInteger, level_size)
+    };
+    MTLSize {
+        width: clamp(texture.width(), region.image_offset.x, region.image_extent.width),
+        height: clamp(texture.height(), region.image_offset.y, region.image_extent.height),
+        depth: clamp(texture.depth(), region.image_offset.z, region.image_extent.depth),
+    }
+}
+
 pub struct CommandQueue(Arc<QueueInner>);
 
 struct QueueInner {
@@ -107,7 +144,11 @@ struct CommandBufferInner {
     encoder_state: EncoderState,
     viewport: Option<MTLViewport>,
     scissors: Option<MTLScissorRect>,
+    blend_color: Option<target::ColorValue>,
+    stencil_reference: Option<(target::Stencil, target::Stencil)>,
     pipeline_state: Option<MTLRenderPipelineState>, // Unretained
+    // States baked into the bound pipeline, which can't be overridden.
+    baked_states: Option<pso::BakedStates>,
     primitive_type: MTLPrimitiveType,
     resources_vs: StageResources,
     resources_fs: StageResources,
@@ -124,6 +165,66 @@ impl CommandBufferInner {
         self.resources_fs.clear();
     }
 
+    // Check if the state is baked into the bound pipeline, setting it would be ignored.
+    fn is_baked<F>(&self, name: &str, is_dynamic: F) -> bool
+    where
+        F: Fn(&pso::BakedStates) -> bool,
+    {
+        let baked = self.baked_states.as_ref().map_or(false, |states| !is_dynamic(states));
+        if baked {
+            warn!("Ignoring {}, baked into the bound pipeline", name);
+        }
+        baked
+    }
+
+    fn set_viewport(&mut self, viewport: MTLViewport) {
+        self.viewport = Some(viewport);
+        if let EncoderState::Render(ref encoder) = self.encoder_state {
+            encoder.set_viewport(viewport);
+        }
+    }
+
+    fn set_scissor(&mut self, scissor: MTLScissorRect) {
+        self.scissors = Some(scissor);
+        if let EncoderState::Render(ref encoder) = self.encoder_state {
+            encoder.set_scissor_rect(scissor);
+        }
+    }
+
+    fn set_blend_color(&mut self, color: target::ColorValue) {
+        self.blend_color = Some(color);
+        if let EncoderState::Render(encoder) = self.encoder_state {
+            Self::apply_blend_color(encoder, color);
+        }
+    }
+
+    fn set_stencil_reference(&mut self, front: target::Stencil, back: target::Stencil) {
+        self.stencil_reference = Some((front, back));
+        if let EncoderState::Render(encoder) = self.encoder_state {
+            Self::apply_stencil_reference(encoder, front, back);
+        }
+    }
+
+    fn apply_blend_color(encoder: MTLRenderCommandEncoder, color: target::ColorValue) {
+        unsafe {
+            msg_send![encoder.0,
+                setBlendColorRed: color[0]
+                green: color[1]
+                blue: color[2]
+                alpha: color[3]
+            ];
+        }
+    }
+
+    fn apply_stencil_reference(encoder: MTLRenderCommandEncoder, front: target::Stencil, back: target::Stencil) {
+        unsafe {
+            msg_send![encoder.0,
+                setStencilFrontReferenceValue: front as u32
+                backReferenceValue: back as u32
+            ];
+        }
+    }
+
     fn begin_renderpass(&mut self, encoder: MTLRenderCommandEncoder) {
         self.encoder_state = EncoderState::Render(encoder);
         // Apply previously bound values for this command buffer
@@ -133,6 +234,12 @@ impl CommandBufferInner {
         if let Some(scissors) = self.scissors {
             encoder.set_scissor_rect(scissors);
         }
+        if let Some(color) = self.blend_color {
+            Self::apply_blend_color(encoder, color);
+        }
+        if let Some((front, back)) = self.stencil_reference {
+            Self::apply_stencil_reference(encoder, front, back);
+        }
         if let Some(pipeline_state) = self.pipeline_state {
             encoder.set_render_pipeline_state(pipeline_state);
         }
@@ -242,6 +349,12 @@ impl core::RawCommandQueue<Backend> for CommandQueue {
             command_buffer.commit();
         }
     }
+
+    unsafe fn bind_sparse(
+        &mut self, _submit: core::queue::RawSparseSubmission<Backend>, _fence: Option<&native::Fence>,
+    ) -> core::SubmissionResult<()> {
+        unreachable!("Sparse resources are rejected by `create_sparse_*`")
+    }
 }
 
 impl core::RawCommandPool<Backend> for CommandPool {
@@ -276,7 +389,10 @@ impl core::RawCommandPool<Backend> for CommandPool {
                     encoder_state: EncoderState::None,
                     viewport: None,
                     scissors: None,
+                    blend_color: None,
+                    stencil_reference: None,
                     pipeline_state: None,
+                    baked_states: None,
                     primitive_type: MTLPrimitiveType::Point,
                     resources_vs: StageResources::new(),
                     resources_fs: StageResources::new(),
@@ -384,6 +500,23 @@ impl core::RawCommandBuffer<Backend> for CommandBuffer {
         // TODO: MTLRenderCommandEncoder.textureBarrier on macOS?
     }
 
+    fn set_event(&mut self, _event: &native::Event, _stage: pso::PipelineStage) {
+        error!("Events are not supported");
+    }
+
+    fn reset_event(&mut self, _event: &native::Event, _stage: pso::PipelineStage) {
+        error!("Events are not supported");
+    }
+
+    fn wait_events(
+        &mut self,
+        _events: &[&native::Event],
+        _stages: Range<pso::PipelineStage>,
+        _barriers: &[memory::Barrier<Backend>],
+    ) {
+        error!("Events are not supported");
+    }
+
     fn fill_buffer(
         &mut self,
         buffer: &native::Buffer,
@@ -441,6 +574,53 @@ impl core::RawCommandBuffer<Backend> for CommandBuffer {
         unimplemented!()
     }
 
+    fn blit_image(
+        &mut self,
+        src: &native::Image,
+        _src_layout: ImageLayout,
+        dst: &native::Image,
+        _dst_layout: ImageLayout,
+        _filter: Filter,
+        regions: &[ImageBlit],
+    ) {
+        let pixel_format = |image: &native::Image| -> NSUInteger {
+            unsafe { msg_send![(image.0).0, pixelFormat] }
+        };
+        let same_format = pixel_format(src) == pixel_format(dst);
+        let encoder = self.encode_blit();
+        // FIXME: layout
+
+        for region in regions {
+            let (s, d) = (&region.src_bounds, &region.dst_bounds);
+            let (width, height, depth) = (d.end.x - d.start.x, d.end.y - d.start.y, d.end.z - d.start.z);
+            if s.end.x - s.start.x != width || s.end.y - s.start.y != height || s.end.z - s.start.z != depth
+                || width < 0 || height < 0 || depth < 0 || !same_format
+            {
+                //TODO: scaling, mirroring and format conversion require a render pipeline,
+                // until then formats don't report the `BLIT_SRC` and `BLIT_DST` features.
+                error!("Only unscaled blits between images of the same format are supported");
+                continue;
+            }
+
+            let layers = region.src_subresource.layers.clone().zip(region.dst_subresource.layers.clone());
+            for (src_layer, dst_layer) in layers {
+                unsafe {
+                    msg_send![encoder.0,
+                        copyFromTexture: (src.0).0
+                        sourceSlice: src_layer as NSUInteger
+                        sourceLevel: region.src_subresource.level as NSUInteger
+                        sourceOrigin: MTLOrigin { x: s.start.x as _, y: s.start.y as _, z: s.start.z as _ }
+                        sourceSize: MTLSize { width: width as _, height: height as _, depth: depth as _ }
+                        toTexture: (dst.0).0
+                        destinationSlice: dst_layer as NSUInteger
+                        destinationLevel: region.dst_subresource.level as NSUInteger
+                        destinationOrigin: MTLOrigin { x: d.start.x as _, y: d.start.y as _, z: d.start.z as _ }
+                    ]
+                }
+            }
+        }
+    }
+
     fn bind_index_buffer(&mut self, view: IndexBufferView<Backend>) {
         unimplemented!()
     }
@@ -452,32 +632,34 @@ impl core::RawCommandBuffer<Backend> for CommandBuffer {
             buffers.push(None)
         }
         for (out, &(buffer, offset)) in buffers.iter_mut().zip(buffer_set.0.iter()) {
-            *out = Some((buffer.0, offset));
+            *out = Some((buffer.raw, buffer.offset + offset));
         }
         if let EncoderState::Render(ref encoder) = inner.encoder_state {
             for (i, &(buffer, offset)) in buffer_set.0.iter().enumerate() {
-                encoder.set_vertex_buffer(i as _, offset as _, buffer.0);
+                encoder.set_vertex_buffer(i as _, (buffer.offset + offset) as _, buffer.raw);
             }
         }
     }
 
+    fn bind_transform_feedback_buffers(&mut self, _: u32, _: &[TransformFeedbackBuffer<Backend>]) {
+        error!("Transform feedback is not supported");
+    }
+
+    fn begin_transform_feedback(&mut self) {
+        error!("Transform feedback is not supported");
+    }
+
+    fn end_transform_feedback(&mut self) {
+        error!("Transform feedback is not supported");
+    }
+
     fn set_viewports(&mut self, rects: &[Viewport]) {
         let inner = self.inner();
         if rects.len() != 1 {
             panic!("Metal supports only one viewport");
         }
-        let rect = &rects[0];
-        let vp = MTLViewport {
-            originX: rect.x as f64,
-            originY: rect.y as f64,
-            width: rect.w as f64,
-            height: rect.h as f64,
-            znear: rect.near as f64,
-            zfar: rect.far as f64,
-        };
-        inner.viewport = Some(vp);
-        if let EncoderState::Render(ref encoder) = inner.encoder_state {
-            encoder.set_viewport(vp);
+        if !inner.is_baked("viewports", |states| states.viewports.is_dynamic()) {
+            inner.set_viewport(map_viewport(&rects[0]));
         }
     }
 
@@ -486,25 +668,33 @@ impl core::RawCommandBuffer<Backend> for CommandBuffer {
         if rects.len() != 1 {
             panic!("Metal supports only one scissor");
         }
-        let rect = &rects[0];
-        let scissor = MTLScissorRect {
-            x: rect.x as NSUInteger,
-            y: rect.y as NSUInteger,
-            width: rect.w as NSUInteger,
-            height: rect.h as NSUInteger,
-        };
-        inner.scissors = Some(scissor);
-        if let EncoderState::Render(ref encoder) = inner.encoder_state {
-            encoder.set_scissor_rect(scissor);
+        if !inner.is_baked("scissors", |states| states.scissors.is_dynamic()) {
+            inner.set_scissor(map_scissor(&rects[0]));
         }
     }
 
     fn set_stencil_reference(&mut self, front: target::Stencil, back: target::Stencil) {
-        unimplemented!()
+        let inner = self.inner();
+        if !inner.is_baked("stencil reference", |states| states.stencil_reference.is_dynamic()) {
+            inner.set_stencil_reference(front, back);
+        }
     }
 
     fn set_blend_constants(&mut self, color: target::ColorValue) {
-        unimplemented!()
+        let inner = self.inner();
+        if !inner.is_baked("blend constants", |states| states.blend_constants.is_dynamic()) {
+            inner.set_blend_color(color);
+        }
+    }
+
+    fn set_depth_bounds(&mut self, _bounds: Range<f32>) {
+        error!("Depth bounds test is not supported");
+    }
+
+    fn set_line_width(&mut self, width: f32) {
+        if width != 1.0 {
+            error!("Unable to set line width {}, only 1.0 is supported", width);
+        }
     }
 
     fn begin_renderpass(
@@ -578,6 +768,22 @@ impl core::RawCommandBuffer<Backend> for CommandBuffer {
         if let EncoderState::Render(encoder) = inner.encoder_state {
             encoder.set_render_pipeline_state(pipeline.raw);
         }
+
+        // Metal has no pipeline states for these, set them on the encoder.
+        let states = &pipeline.baked_states;
+        if let pso::State::Static(ref viewports) = states.viewports {
+            inner.set_viewport(map_viewport(&viewports[0]));
+        }
+        if let pso::State::Static(ref scissors) = states.scissors {
+            inner.set_scissor(map_scissor(&scissors[0]));
+        }
+        if let pso::State::Static(color) = states.blend_constants {
+            inner.set_blend_color(color);
+        }
+        if let pso::State::Static((front, back)) = states.stencil_reference {
+            inner.set_stencil_reference(front, back);
+        }
+        inner.baked_states = Some(states.clone());
     }
 
     fn bind_graphics_descriptor_sets(
@@ -730,22 +936,18 @@ impl core::RawCommandBuffer<Backend> for CommandBuffer {
         regions: &[BufferImageCopy],
     ) {
         let encoder = self.encode_blit();
-        let extent = MTLSize {
-            width: dst.0.width(),
-            height: dst.0.height(),
-            depth: dst.0.depth(),
-        };
         // FIXME: layout
 
         for region in regions {
             let image_offset = &region.image_offset;
             let r = &region.image_layers;
+            let extent = map_copy_size(dst.0, region);
 
             for layer in r.layers.clone() {
-                let offset = region.buffer_offset + region.buffer_slice_pitch as NSUInteger * (layer - r.layers.start) as NSUInteger;
+                let offset = src.offset + region.buffer_offset + region.buffer_slice_pitch as NSUInteger * (layer - r.layers.start) as NSUInteger;
                 unsafe {
                     msg_send![encoder.0,
-                        copyFromBuffer: (src.0).0
+                        copyFromBuffer: src.raw.0
                         sourceOffset: offset as NSUInteger
                         sourceBytesPerRow: region.buffer_row_pitch as NSUInteger
                         sourceBytesPerImage: region.buffer_slice_pitch as NSUInteger
@@ -768,19 +970,15 @@ impl core::RawCommandBuffer<Backend> for CommandBuffer {
         regions: &[BufferImageCopy],
     ) {
         let encoder = self.encode_blit();
-        let extent = MTLSize {
-            width: src.0.width(),
-            height: src.0.height(),
-            depth: src.0.depth(),
-        };
         // FIXME: layout
 
         for region in regions {
             let image_offset = &region.image_offset;
             let r = &region.image_layers;
+            let extent = map_copy_size(src.0, region);
 
             for layer in r.layers.clone() {
-                let offset = region.buffer_offset + region.buffer_slice_pitch as NSUInteger * (layer - r.layers.start) as NSUInteger;
+                let offset = dst.offset + region.buffer_offset + region.buffer_slice_pitch as NSUInteger * (layer - r.layers.start) as NSUInteger;
                 unsafe {
                     msg_send![encoder.0,
                         copyFromTexture: (src.0).0
@@ -788,7 +986,7 @@ impl core::RawCommandBuffer<Backend> for CommandBuffer {
                         sourceLevel: r.level as NSUInteger
                         sourceOrigin: MTLOrigin { x: image_offset.x as _, y: image_offset.y as _, z: image_offset.z as _ }
                         sourceSize: extent
-                        toBuffer: (dst.0).0
+                        toBuffer: dst.raw.0
                         destinationOffset: offset as NSUInteger
                         destinationBytesPerRow: region.buffer_row_pitch as NSUInteger
                         destinationBytesPerImage: region.buffer_slice_pitch as NSUInteger
@@ -844,4 +1042,8 @@ impl core::RawCommandBuffer<Backend> for CommandBuffer {
     ) {
         unimplemented!()
     }
+
+    fn draw_auto(&mut self) {
+        error!("Transform feedback is not supported");
+    }
 }
@@ -14,6 +14,65 @@ pub fn map_format(format: Format) -> Option<(MTLPixelFormat, bool)> {
         Format(R8_G8_B8_A8, Srgb) => Some((MTLPixelFormat::RGBA8Unorm_sRGB, false)),
         Format(B8_G8_R8_A8, Unorm) => Some((MTLPixelFormat::BGRA8Unorm, false)),
         Format(B8_G8_R8_A8, Srgb) => Some((MTLPixelFormat::BGRA8Unorm_sRGB, false)),
+        Format(D16, Unorm) => Some((MTLPixelFormat::Depth16Unorm, true)),
+        Format(D24_S8, Unorm) => Some((MTLPixelFormat::Depth24Unorm_Stencil8, true)),
+        Format(D24_S8, Uint) => Some((MTLPixelFormat::X24_Stencil8, true)),
+        Format(D32, Float) => Some((MTLPixelFormat::Depth32Float, true)),
+        Format(D32_S8, Float) => Some((MTLPixelFormat::Depth32Float_Stencil8, true)),
+        Format(D32_S8, Uint) => Some((MTLPixelFormat::X32_Stencil8, true)),
+        Format(S8, Uint) => Some((MTLPixelFormat::Stencil8, true)),
+        Format(BC1_R8_G8_B8, Unorm) | Format(BC1_R8_G8_B8_A8, Unorm) => Some((MTLPixelFormat::BC1_RGBA, false)),
+        Format(BC1_R8_G8_B8, Srgb) | Format(BC1_R8_G8_B8_A8, Srgb) => Some((MTLPixelFormat::BC1_RGBA_sRGB, false)),
+        Format(BC2_R8_G8_B8_A8, Unorm) => Some((MTLPixelFormat::BC2_RGBA, false)),
+        Format(BC2_R8_G8_B8_A8, Srgb) => Some((MTLPixelFormat::BC2_RGBA_sRGB, false)),
+        Format(BC3_R8_G8_B8_A8, Unorm) => Some((MTLPixelFormat::BC3_RGBA, false)),
+        Format(BC3_R8_G8_B8_A8, Srgb) => Some((MTLPixelFormat::BC3_RGBA_sRGB, false)),
+        Format(BC4_R8, Unorm) => Some((MTLPixelFormat::BC4_RUnorm, false)),
+        Format(BC4_R8, Inorm) => Some((MTLPixelFormat::BC4_RSnorm, false)),
+        Format(BC5_R8_G8, Unorm) => Some((MTLPixelFormat::BC5_RGUnorm, false)),
+        Format(BC5_R8_G8, Inorm) => Some((MTLPixelFormat::BC5_RGSnorm, false)),
+        Format(BC6_R16_G16_B16, Float) => Some((MTLPixelFormat::BC6H_RGBFloat, false)),
+        Format(BC6_R16_G16_B16, Ufloat) => Some((MTLPixelFormat::BC6H_RGBUfloat, false)),
+        Format(BC7_R8_G8_B8_A8, Unorm) => Some((MTLPixelFormat::BC7_RGBAUnorm, false)),
+        Format(BC7_R8_G8_B8_A8, Srgb) => Some((MTLPixelFormat::BC7_RGBAUnorm_sRGB, false)),
+        Format(ETC2_R8_G8_B8, Unorm) => Some((MTLPixelFormat::ETC2_RGB8, false)),
+        Format(ETC2_R8_G8_B8, Srgb) => Some((MTLPixelFormat::ETC2_RGB8_sRGB, false)),
+        Format(ETC2_R8_G8_B8_A1, Unorm) => Some((MTLPixelFormat::ETC2_RGB8A1, false)),
+        Format(ETC2_R8_G8_B8_A1, Srgb) => Some((MTLPixelFormat::ETC2_RGB8A1_sRGB, false)),
+        Format(ETC2_R8_G8_B8_A8, Unorm) => Some((MTLPixelFormat::EAC_RGBA8, false)),
+        Format(ETC2_R8_G8_B8_A8, Srgb) => Some((MTLPixelFormat::EAC_RGBA8_sRGB, false)),
+        Format(EAC_R11, Unorm) => Some((MTLPixelFormat::EAC_R11Unorm, false)),
+        Format(EAC_R11, Inorm) => Some((MTLPixelFormat::EAC_R11Snorm, false)),
+        Format(EAC_R11_G11, Unorm) => Some((MTLPixelFormat::EAC_RG11Unorm, false)),
+        Format(EAC_R11_G11, Inorm) => Some((MTLPixelFormat::EAC_RG11Snorm, false)),
+        Format(ASTC_4x4, Unorm) => Some((MTLPixelFormat::ASTC_4x4_LDR, false)),
+        Format(ASTC_4x4, Srgb) => Some((MTLPixelFormat::ASTC_4x4_sRGB, false)),
+        Format(ASTC_5x4, Unorm) => Some((MTLPixelFormat::ASTC_5x4_LDR, false)),
+        Format(ASTC_5x4, Srgb) => Some((MTLPixelFormat::ASTC_5x4_sRGB, false)),
+        Format(ASTC_5x5, Unorm) => Some((MTLPixelFormat::ASTC_5x5_LDR, false)),
+        Format(ASTC_5x5, Srgb) => Some((MTLPixelFormat::ASTC_5x5_sRGB, false)),
+        Format(ASTC_6x5, Unorm) => Some((MTLPixelFormat::ASTC_6x5_LDR, false)),
+        Format(ASTC_6x5, Srgb) => Some((MTLPixelFormat::ASTC_6x5_sRGB, false)),
+        Format(ASTC_6x6, Unorm) => Some((MTLPixelFormat::ASTC_6x6_LDR, false)),
+        Format(ASTC_6x6, Srgb) => Some((MTLPixelFormat::ASTC_6x6_sRGB, false)),
+        Format(ASTC_8x5, Unorm) => Some((MTLPixelFormat::ASTC_8x5_LDR, false)),
+        Format(ASTC_8x5, Srgb) => Some((MTLPixelFormat::ASTC_8x5_sRGB, false)),
+        Format(ASTC_8x6, Unorm) => Some((MTLPixelFormat::ASTC_8x6_LDR, false)),
+        Format(ASTC_8x6, Srgb) => Some((MTLPixelFormat::ASTC_8x6_sRGB, false)),
+        Format(ASTC_8x8, Unorm) => Some((MTLPixelFormat::ASTC_8x8_LDR, false)),
+        Format(ASTC_8x8, Srgb) => Some((MTLPixelFormat::ASTC_8x8_sRGB, false)),
+        Format(ASTC_10x5, Unorm) => Some((MTLPixelFormat::ASTC_10x5_LDR, false)),
+        Format(ASTC_10x5, Srgb) => Some((MTLPixelFormat::ASTC_10x5_sRGB, false)),
+        Format(ASTC_10x6, Unorm) => Some((MTLPixelFormat::ASTC_10x6_LDR, false)),
+        Format(ASTC_10x6, Srgb) => Some((MTLPixelFormat::ASTC_10x6_sRGB, false)),
+        Format(ASTC_10x8, Unorm) => Some((MTLPixelFormat::ASTC_10x8_LDR, false)),
+        Format(ASTC_10x8, Srgb) => Some((MTLPixelFormat::ASTC_10x8_sRGB, false)),
+        Format(ASTC_10x10, Unorm) => Some((MTLPixelFormat::ASTC_10x10_LDR, false)),
+        Format(ASTC_10x10, Srgb) => Some((MTLPixelFormat::ASTC_10x10_sRGB, false)),
+        Format(ASTC_12x10, Unorm) => Some((MTLPixelFormat::ASTC_12x10_LDR, false)),
+        Format(ASTC_12x10, Srgb) => Some((MTLPixelFormat::ASTC_12x10_sRGB, false)),
+        Format(ASTC_12x12, Unorm) => Some((MTLPixelFormat::ASTC_12x12_LDR, false)),
+        Format(ASTC_12x12, Srgb) => Some((MTLPixelFormat::ASTC_12x12_sRGB, false)),
         _ => None,
     }
 }
@@ -113,11 +172,46 @@ pub fn map_vertex_format(format: Format) -> Option<MTLVertexFormat> {
     use core::format::SurfaceType::*;
     use core::format::ChannelType::*;
 
-    // TODO: more formats
+    // Single component 8 and 16-bit formats need newer feature sets,
+    // scaled integers aren't supported at all.
     Some(match format {
-        Format(R32_G32_B32_A32, Float) => MTLVertexFormat::Float4,
-        Format(R32_G32_B32, Float) => MTLVertexFormat::Float3,
+        Format(R8_G8, Int) => MTLVertexFormat::Char2,
+        Format(R8_G8, Uint) => MTLVertexFormat::UChar2,
+        Format(R8_G8, Inorm) => MTLVertexFormat::Char2Normalized,
+        Format(R8_G8, Unorm) => MTLVertexFormat::UChar2Normalized,
+        Format(R8_G8_B8_A8, Int) => MTLVertexFormat::Char4,
+        Format(R8_G8_B8_A8, Uint) => MTLVertexFormat::UChar4,
+        Format(R8_G8_B8_A8, Inorm) => MTLVertexFormat::Char4Normalized,
+        Format(R8_G8_B8_A8, Unorm) => MTLVertexFormat::UChar4Normalized,
+        Format(R10_G10_B10_A2, Inorm) => MTLVertexFormat::Int1010102Normalized,
+        Format(R10_G10_B10_A2, Unorm) => MTLVertexFormat::UInt1010102Normalized,
+        Format(R16_G16, Int) => MTLVertexFormat::Short2,
+        Format(R16_G16, Uint) => MTLVertexFormat::UShort2,
+        Format(R16_G16, Inorm) => MTLVertexFormat::Short2Normalized,
+        Format(R16_G16, Unorm) => MTLVertexFormat::UShort2Normalized,
+        Format(R16_G16, Float) => MTLVertexFormat::Half2,
+        Format(R16_G16_B16, Int) => MTLVertexFormat::Short3,
+        Format(R16_G16_B16, Uint) => MTLVertexFormat::UShort3,
+        Format(R16_G16_B16, Inorm) => MTLVertexFormat::Short3Normalized,
+        Format(R16_G16_B16, Unorm) => MTLVertexFormat::UShort3Normalized,
+        Format(R16_G16_B16, Float) => MTLVertexFormat::Half3,
+        Format(R16_G16_B16_A16, Int) => MTLVertexFormat::Short4,
+        Format(R16_G16_B16_A16, Uint) => MTLVertexFormat::UShort4,
+        Format(R16_G16_B16_A16, Inorm) => MTLVertexFormat::Short4Normalized,
+        Format(R16_G16_B16_A16, Unorm) => MTLVertexFormat::UShort4Normalized,
+        Format(R16_G16_B16_A16, Float) => MTLVertexFormat::Half4,
+        Format(R32, Int) => MTLVertexFormat::Int,
+        Format(R32, Uint) => MTLVertexFormat::UInt,
+        Format(R32, Float) => MTLVertexFormat::Float,
+        Format(R32_G32, Int) => MTLVertexFormat::Int2,
+        Format(R32_G32, Uint) => MTLVertexFormat::UInt2,
         Format(R32_G32, Float) => MTLVertexFormat::Float2,
+        Format(R32_G32_B32, Int) => MTLVertexFormat::Int3,
+        Format(R32_G32_B32, Uint) => MTLVertexFormat::UInt3,
+        Format(R32_G32_B32, Float) => MTLVertexFormat::Float3,
+        Format(R32_G32_B32_A32, Int) => MTLVertexFormat::Int4,
+        Format(R32_G32_B32_A32, Uint) => MTLVertexFormat::UInt4,
+        Format(R32_G32_B32_A32, Float) => MTLVertexFormat::Float4,
         _ => return None,
     })
 }
@@ -178,3 +272,50 @@ pub fn map_texture_usage(usage: image::Usage) -> MTLTextureUsage {
     // TODO shader write
     texture_usage
 }
+
+pub fn map_filter(filter: image::Filter) -> MTLSamplerMinMagFilter {
+    match filter {
+        image::Filter::Nearest => MTLSamplerMinMagFilter::Nearest,
+        image::Filter::Linear => MTLSamplerMinMagFilter::Linear,
+    }
+}
+
+pub fn map_mip_filter(filter: Option<image::Filter>) -> MTLSamplerMipFilter {
+    match filter {
+        None => MTLSamplerMipFilter::NotMipmapped,
+        Some(image::Filter::Nearest) => MTLSamplerMipFilter::Nearest,
+        Some(image::Filter::Linear) => MTLSamplerMipFilter::Linear,
+    }
+}
+
+pub fn map_wrap_mode(wrap: image::WrapMode) -> MTLSamplerAddressMode {
+    match wrap {
+        image::WrapMode::Tile => MTLSamplerAddressMode::Repeat,
+        image::WrapMode::Mirror => MTLSamplerAddressMode::MirrorRepeat,
+        image::WrapMode::Clamp => MTLSamplerAddressMode::ClampToEdge,
+        image::WrapMode::Border => MTLSamplerAddressMode::ClampToBorderColor,
+    }
+}
+
+pub fn map_border_color(color: image::BorderColor) -> Option<MTLSamplerBorderColor> {
+    match color {
+        image::BorderColor::TransparentBlack => Some(MTLSamplerBorderColor::TransparentBlack),
+        image::BorderColor::OpaqueBlack => Some(MTLSamplerBorderColor::OpaqueBlack),
+        image::BorderColor::OpaqueWhite => Some(MTLSamplerBorderColor::OpaqueWhite),
+        image::BorderColor::Custom(_) => None,
+    }
+}
+
+pub fn map_compare_function(fun: core::state::Comparison) -> MTLCompareFunction {
+    use core::state::Comparison::*;
+    match fun {
+        Never => MTLCompareFunction::Never,
+        Less => MTLCompareFunction::Less,
+        LessEqual => MTLCompareFunction::LessEqual,
+        Equal => MTLCompareFunction::Equal,
+        GreaterEqual => MTLCompareFunction::GreaterEqual,
+        Greater => MTLCompareFunction::Greater,
+        NotEqual => MTLCompareFunction::NotEqual,
+        Always => MTLCompareFunction::Always,
+    }
+}
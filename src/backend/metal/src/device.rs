@@ -7,6 +7,7 @@ use std::ops::Range;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::{cmp, mem, ptr, slice};
+use std::os::raw::c_void;
 
 use core::{self,
         image, pass, format, mapping, memory, buffer, pso};
@@ -14,9 +15,10 @@ use core::device::{WaitFor, BindError, OutOfMemory, FramebufferError, ShaderErro
 use core::pso::{DescriptorSetWrite, DescriptorType, DescriptorSetLayoutBinding, AttributeDesc};
 use core::pass::{Subpass};
 
-use cocoa::foundation::{NSRange, NSUInteger};
+use cocoa::base::{nil, NO, YES};
+use cocoa::foundation::{NSRange, NSString, NSUInteger};
 use metal::*;
-use objc::runtime::Object as ObjcObject;
+use objc::runtime::{Class, Object as ObjcObject};
 use spirv_cross::{msl, spirv, ErrorCode as SpirvErrorCode};
 
 
@@ -41,6 +43,7 @@ fn gen_parse_error(err: SpirvErrorCode) -> ShaderError {
     };
     ShaderError::CompilationFailed(msg)
 }
+
 /// Emit error during shader module creation. Used if we execute an query command.
 fn gen_query_error(err: SpirvErrorCode) -> ShaderError {
     let msg = match err {
@@ -50,6 +53,65 @@ fn gen_query_error(err: SpirvErrorCode) -> ShaderError {
     ShaderError::CompilationFailed(msg)
 }
 
+/// Get a function of the library, setting the values of its function constants.
+fn get_function(
+    lib: MTLLibrary, entry: &str, specialization: &[pso::Specialization],
+) -> Result<MTLFunction, ShaderError> {
+    if specialization.is_empty() {
+        let function = lib.get_function(entry); // Returns retained
+        return if function.is_null() {
+            Err(ShaderError::CompilationFailed(format!("Entry point {} not found", entry)))
+        } else {
+            Ok(function)
+        };
+    }
+
+    unsafe {
+        let values: *mut ObjcObject = msg_send![Class::get("MTLFunctionConstantValues").unwrap(), new]; // Returns retained
+        for spec in specialization {
+            // Metal reads constants with the size of their declared type.
+            let bits = spec.value.to_bits();
+            let (bool_value, word_value) = (bits as u8, bits as u32);
+            let (ty, data) = match spec.value {
+                pso::Constant::Bool(_) => (MTLDataType::Bool, &bool_value as *const u8 as *const c_void),
+                pso::Constant::U32(_) => (MTLDataType::UInt, &word_value as *const u32 as *const c_void),
+                pso::Constant::I32(_) => (MTLDataType::Int, &word_value as *const u32 as *const c_void),
+                pso::Constant::F32(_) => (MTLDataType::Float, &word_value as *const u32 as *const c_void),
+                pso::Constant::U64(_) |
+                pso::Constant::I64(_) |
+                pso::Constant::F64(_) => {
+                    msg_send![values, release];
+                    return Err(ShaderError::CompilationFailed(
+                        format!("Function constant {} has an unsupported 64-bit type", spec.id)));
+                }
+            };
+            msg_send![values,
+                setConstantValue: data
+                type: ty as NSUInteger
+                atIndex: spec.id as NSUInteger
+            ];
+        }
+
+        let name = NSString::alloc(nil).init_str(entry);
+        let mut err_ptr: *mut ObjcObject = ptr::null_mut();
+        let function: MTLFunction = msg_send![lib.0,
+            newFunctionWithName: name
+            constantValues: values
+            error: &mut err_ptr
+        ]; // Returns retained
+        msg_send![name, release];
+        msg_send![values, release];
+
+        if function.is_null() {
+            let message = n::objc_err_description(err_ptr);
+            msg_send![err_ptr, release];
+            Err(ShaderError::CompilationFailed(message))
+        } else {
+            Ok(function)
+        }
+    }
+}
+
 pub struct Adapter {
     pub(crate) device: MTLDevice,
     pub(crate) adapter_info: core::AdapterInfo,
@@ -70,6 +132,7 @@ impl Drop for Adapter {
 
 #[derive(Clone, Copy)]
 struct PrivateCapabilities {
+    is_mac: bool,
     resource_heaps: bool,
     argument_buffers: bool,
     max_buffers_per_stage: usize,
@@ -80,6 +143,7 @@ struct PrivateCapabilities {
 pub struct Device {
     device: MTLDevice,
     private_caps: PrivateCapabilities,
+    features: core::Features,
     limits: core::Limits,
 }
 unsafe impl Send for Device {}
@@ -95,7 +159,12 @@ impl Drop for Device {
 impl Clone for Device {
     fn clone(&self) -> Device {
         unsafe { self.device.retain(); }
-        Device { device: self.device, private_caps: self.private_caps, limits: self.limits }
+        Device {
+            device: self.device,
+            private_caps: self.private_caps,
+            features: self.features,
+            limits: self.limits,
+        }
     }
 }
 
@@ -124,6 +193,7 @@ impl core::Adapter<Backend> for Adapter {
         let is_mac = self.device.supports_feature_set(MTLFeatureSet::macOS_GPUFamily1_v1);
 
         let private_caps = PrivateCapabilities {
+            is_mac,
             resource_heaps: self.supports_any(RESOURCE_HEAP_SUPPORT),
             argument_buffers: self.supports_any(ARGUMENT_BUFFER_SUPPORT) && false, //TODO
             max_buffers_per_stage: 31,
@@ -135,6 +205,42 @@ impl core::Adapter<Backend> for Adapter {
         let device = Device {
             device: self.device,
             private_caps,
+            features: core::Features { // TODO: feature sets
+                indirect_execution: false,
+                draw_instanced: true,
+                draw_instanced_base: is_mac,
+                draw_indexed_base: is_mac,
+                draw_indexed_instanced: true,
+                draw_indexed_instanced_base_vertex: is_mac,
+                draw_indexed_instanced_base: is_mac,
+                instance_rate: true,
+                vertex_base: is_mac,
+                srgb_color: true,
+                constant_buffer: true,
+                unordered_access_view: false,
+                separate_blending_slots: true,
+                copy_buffer: true,
+                sampler_objects: true,
+                sampler_lod_bias: false,
+                sampler_anisotropy: true,
+                sampler_border_color: is_mac,
+                sampler_reduction: false,
+                sampler_unnormalized: true,
+                persistent_mapping: true,
+                sparse_binding: false,
+                sparse_residency_buffer: false,
+                sparse_residency_image: false,
+                format_bc: is_mac,
+                format_etc2: !is_mac,
+                format_astc_ldr: !is_mac,
+                depth_bounds: false,
+                wide_lines: false,
+                logic_op: false,
+                stream_output: false,
+                draw_auto: false,
+                events: false,
+                blit_1d_3d: false,
+            },
             limits: core::Limits {
                 max_texture_size: 4096, // TODO: feature set
                 max_patch_size: 0, // No tesselation
@@ -143,9 +249,15 @@ impl core::Adapter<Backend> for Adapter {
                 min_buffer_copy_offset_alignment: if is_mac {256} else {64},
                 min_buffer_copy_pitch_alignment: 4, // TODO: made this up
                 min_uniform_buffer_offset_alignment: 1, // TODO
+                non_coherent_atom_size: 1,
 
                 max_compute_group_count: [0; 3], // TODO
                 max_compute_group_size: [0; 3], // TODO
+
+                max_vertex_input_attributes: 31,
+                max_vertex_input_bindings: 31,
+                max_vertex_input_attribute_offset: 2047, // TODO: feature set
+                max_vertex_input_binding_stride: 2048, // TODO: feature set
             },
         };
 
@@ -233,6 +345,7 @@ impl Device {
         &mut self,
         raw_data: &[u8],
         overrides: &HashMap<msl::ResourceBindingLocation, msl::ResourceBinding>,
+        specialization: &[pso::Specialization],
     ) -> Result<MTLLibrary, ShaderError> {
         // spec requires "codeSize must be a multiple of 4"
         assert_eq!(raw_data.len() & 3, 0);
@@ -248,6 +361,13 @@ impl Device {
         let mut ast = spirv::Ast::<msl::Target>::parse(&module)
             .map_err(gen_parse_error)?;
 
+        for constant in ast.get_specialization_constants().map_err(gen_parse_error)? {
+            if let Some(spec) = specialization.iter().find(|spec| spec.id == constant.constant_id) {
+                ast.set_scalar_constant(constant.id, spec.value.to_bits())
+                    .map_err(gen_parse_error)?;
+            }
+        }
+
         // compile with options
         let mut compiler_options = msl::CompilerOptions::default();
         compiler_options.vertex.invert_y = true;
@@ -308,6 +428,83 @@ impl Device {
         &(ref shader_set, pipeline_layout, ref pass_descriptor, pipeline_desc):
         &(pso::GraphicsShaderSet<'a, Backend>, &n::PipelineLayout, Subpass<'a, Backend>, &pso::GraphicsPipelineDesc),
     ) -> Result<n::GraphicsPipeline, pso::CreationError> {
+        pipeline_desc.validate_vertex_input(&self.features, &self.limits)?;
+        pipeline_desc.validate_baked_states(&self.features, &self.limits)?;
+        pipeline_desc.validate_blender(&self.features)?;
+        pipeline_desc.validate_tessellation(shader_set, &self.limits)?;
+        pipeline_desc.validate_stream_output(&self.features)?;
+        let main_pass = pass_descriptor.main_pass;
+        let color_formats = main_pass.attachments[.. main_pass.num_colors]
+            .iter()
+            .map(|attachment| attachment.format)
+            .collect::<Vec<_>>();
+        pipeline_desc.validate_shader_interface(self, shader_set, &pipeline_layout.set_bindings, &color_formats)?;
+        // Metal reads vertex data in units of 4 bytes.
+        for (i, vertex_buffer) in pipeline_desc.vertex_buffers.iter().enumerate() {
+            if vertex_buffer.stride % 4 != 0 {
+                return Err(pso::InputError::UnalignedStride(i as pso::BufferIndex).into());
+            }
+        }
+
+        if shader_set.hull.is_some() {
+            error!("Metal tesselation shaders are not supported");
+            return Err(pso::CreationError::Other);
+        }
+        if shader_set.domain.is_some() {
+            error!("Metal tesselation shaders are not supported");
+            return Err(pso::CreationError::Other);
+        }
+        if shader_set.geometry.is_some() {
+            error!("Metal geometry shaders are not supported");
+            return Err(pso::CreationError::Other);
+        }
+
+        // Shaders
+        // Specialization of SPIR-V modules happens when translating them,
+        // while compiled libraries get their function constants set.
+        let (vs_lib, vs_specialization) = match shader_set.vertex.module {
+            &n::ShaderModule::Compiled(lib) => (lib, shader_set.vertex.specialization),
+            &n::ShaderModule::Raw(ref data) => {
+                //TODO: cache them all somewhere!
+                let lib = self.compile_shader_library(
+                    data, &pipeline_layout.res_overrides, shader_set.vertex.specialization)?;
+                (lib, &[][..])
+            }
+        };
+        let mtl_vertex_function = get_function(vs_lib, shader_set.vertex.entry, vs_specialization)
+            .map_err(|err| {
+                error!("invalid vertex shader entry point: {}", err);
+                err
+            })?;
+        let (fs_lib, mtl_fragment_function) = if let Some(fragment_entry) = shader_set.fragment {
+            let (fs_lib, fs_specialization) = match fragment_entry.module {
+                &n::ShaderModule::Compiled(lib) => (lib, fragment_entry.specialization),
+                &n::ShaderModule::Raw(ref data) => {
+                    match self.compile_shader_library(
+                        data, &pipeline_layout.res_overrides, fragment_entry.specialization)
+                    {
+                        Ok(lib) => (lib, &[][..]),
+                        Err(err) => {
+                            unsafe { mtl_vertex_function.release() };
+                            return Err(err.into());
+                        }
+                    }
+                }
+            };
+            let mtl_fragment_function = match get_function(fs_lib, fragment_entry.entry, fs_specialization) {
+                Ok(function) => function,
+                Err(err) => {
+                    error!("invalid pixel shader entry point: {}", err);
+                    unsafe { mtl_vertex_function.release() };
+                    return Err(err.into());
+                }
+            };
+            (Some(fs_lib), Some(mtl_fragment_function))
+        } else {
+            (None, None)
+        };
+
+        // Allocated after the shader functions, so that their errors don't leak it.
         let pipeline =  MTLRenderPipelineDescriptor::alloc().init(); // Returns retained
 
         // FIXME: lots missing
@@ -322,52 +519,11 @@ impl Device {
         };
         pipeline.set_input_primitive_topology(primitive_class);
 
-        // Shaders
-        let vs_lib = match shader_set.vertex.module {
-            &n::ShaderModule::Compiled(lib) => lib,
-            &n::ShaderModule::Raw(ref data) => {
-                //TODO: cache them all somewhere!
-                self.compile_shader_library(data, &pipeline_layout.res_overrides).unwrap()
-            }
-        };
-        let mtl_vertex_function = vs_lib
-            .get_function(shader_set.vertex.entry); // Returns retained
-        if mtl_vertex_function.is_null() {
-            error!("invalid vertex shader entry point");
-            return Err(pso::CreationError::Other);
-        }
         pipeline.set_vertex_function(mtl_vertex_function);
         unsafe { mtl_vertex_function.release() };
-        let fs_lib = if let Some(fragment_entry) = shader_set.fragment {
-            let fs_lib = match fragment_entry.module {
-                &n::ShaderModule::Compiled(lib) => lib,
-                &n::ShaderModule::Raw(ref data) => {
-                    self.compile_shader_library(data, &pipeline_layout.res_overrides).unwrap()
-                }
-            };
-            let mtl_fragment_function = fs_lib
-                .get_function(fragment_entry.entry); // Returns retained
-            if mtl_fragment_function.is_null() {
-                error!("invalid pixel shader entry point");
-                return Err(pso::CreationError::Other);
-            }
+        if let Some(mtl_fragment_function) = mtl_fragment_function {
             pipeline.set_fragment_function(mtl_fragment_function);
             unsafe { mtl_fragment_function.release() };
-            Some(fs_lib)
-        } else {
-            None
-        };
-        if shader_set.hull.is_some() {
-            error!("Metal tesselation shaders are not supported");
-            return Err(pso::CreationError::Other);
-        }
-        if shader_set.domain.is_some() {
-            error!("Metal tesselation shaders are not supported");
-            return Err(pso::CreationError::Other);
-        }
-        if shader_set.geometry.is_some() {
-            error!("Metal geometry shaders are not supported");
-            return Err(pso::CreationError::Other);
         }
 
         // Copy color target info from Subpass
@@ -376,6 +532,13 @@ impl Device {
 
             let (mtl_format, is_depth) = map_format(attachment.format).expect("unsupported color format for Metal");
             if is_depth {
+                let aspects = attachment.format.0.aspects();
+                if aspects.contains(image::ASPECT_DEPTH) {
+                    pipeline.set_depth_attachment_pixel_format(mtl_format);
+                }
+                if aspects.contains(image::ASPECT_STENCIL) {
+                    pipeline.set_stencil_attachment_pixel_format(mtl_format);
+                }
                 continue;
             }
 
@@ -401,6 +564,10 @@ impl Device {
                 descriptor.set_alpha_blend_operation(map_blend_op(blend.equation));
             }
         }
+        unsafe {
+            let enabled = if pipeline_desc.blender.alpha_coverage { YES } else { NO };
+            msg_send![pipeline.0, setAlphaToCoverageEnabled: enabled];
+        }
 
         // Vertex buffers
         let vertex_descriptor = MTLVertexDescriptor::new();
@@ -409,11 +576,6 @@ impl Device {
             mtl_buffer_desc.set_stride(vertex_buffer.stride as u64);
             match vertex_buffer.rate {
                 0 => {
-                    // FIXME: should this use MTLVertexStepFunction::Constant?
-                    mtl_buffer_desc.set_step_function(MTLVertexStepFunction::PerVertex);
-                },
-                1 => {
-                    // FIXME: how to determine instancing in this case?
                     mtl_buffer_desc.set_step_function(MTLVertexStepFunction::PerVertex);
                 },
                 c => {
@@ -422,11 +584,20 @@ impl Device {
                 }
             }
         }
-        for (i, &AttributeDesc { binding, element, ..}) in pipeline_desc.attributes.iter().enumerate() {
-            let mtl_vertex_format = map_vertex_format(element.format).expect("unsupported vertex format for Metal");
+        for &AttributeDesc { location, binding, element } in pipeline_desc.attributes.iter() {
+            let mtl_vertex_format = match map_vertex_format(element.format) {
+                Some(format) => format,
+                None => {
+                    unsafe {
+                        pipeline.release();
+                        vertex_descriptor.release();
+                    }
+                    return Err(pso::InputError::UnsupportedFormat(location, element.format).into());
+                }
+            };
 
-            let mtl_attribute_desc = vertex_descriptor.attributes().object_at(i);
-            mtl_attribute_desc.set_buffer_index(binding as NSUInteger); // TODO: Might be binding, not location?
+            let mtl_attribute_desc = vertex_descriptor.attributes().object_at(location as usize);
+            mtl_attribute_desc.set_buffer_index(binding as NSUInteger);
             mtl_attribute_desc.set_offset(element.offset as NSUInteger);
             mtl_attribute_desc.set_format(mtl_vertex_format);
         }
@@ -452,6 +623,7 @@ impl Device {
                 fs_lib,
                 raw: pso,
                 primitive_type,
+                baked_states: pipeline_desc.baked_states.clone(),
             })
         }
     }
@@ -459,13 +631,107 @@ impl Device {
 
 impl core::Device<Backend> for Device {
     fn get_features(&self) -> &core::Features {
-        unimplemented!()
+        &self.features
     }
 
     fn get_limits(&self) -> &core::Limits {
         &self.limits
     }
 
+    fn format_properties(&self, format: format::Format) -> format::Properties {
+        use core::format::SurfaceType::*;
+        use core::format::ChannelType::*;
+
+        let mut properties = format::Properties::empty();
+        if map_vertex_format(format).is_some() {
+            properties.buffer_features |= format::VERTEX;
+        }
+        if map_format(format).is_none() {
+            return properties;
+        }
+
+        if format.0.is_compressed() {
+            // BC formats are exclusive to macOS, ETC2/EAC and ASTC to iOS.
+            let is_bc = match format.0 {
+                BC1_R8_G8_B8 | BC1_R8_G8_B8_A8 | BC2_R8_G8_B8_A8 | BC3_R8_G8_B8_A8 |
+                BC4_R8 | BC5_R8_G8 | BC6_R16_G16_B16 | BC7_R8_G8_B8_A8 => true,
+                _ => false,
+            };
+            if is_bc == self.private_caps.is_mac {
+                properties.optimal_tiling = format::SAMPLED | format::SAMPLED_LINEAR;
+            }
+            return properties;
+        }
+
+        let is_integer = match format.1 {
+            Int | Uint => true,
+            _ => false,
+        };
+        // Blits are limited to unscaled copies between images of the same format,
+        // so `BLIT_SRC` and `BLIT_DST` aren't reported.
+        let mut features = format::SAMPLED | format::STORAGE;
+        if format.0.is_depth() || format.0.is_stencil() {
+            features |= format::DEPTH_STENCIL_ATTACHMENT;
+        } else {
+            features |= format::COLOR_ATTACHMENT;
+            if !is_integer {
+                features |= format::COLOR_ATTACHMENT_BLEND;
+            }
+        }
+        if !is_integer {
+            features |= format::SAMPLED_LINEAR;
+        }
+
+        // Linear images are rejected by `create_image`.
+        properties.optimal_tiling = features;
+        properties
+    }
+
+    fn image_format_properties(
+        &self, kind: image::Kind, format: format::Format, tiling: image::Tiling, usage: image::Usage,
+    ) -> Option<image::FormatProperties> {
+        // TODO: linear images, backed by buffers
+        if tiling == image::Tiling::Linear {
+            return None;
+        }
+        let features = self.format_properties(format).optimal_tiling;
+        let required = [
+            (image::SAMPLED, format::SAMPLED),
+            (image::STORAGE, format::STORAGE),
+            (image::COLOR_ATTACHMENT, format::COLOR_ATTACHMENT),
+            (image::DEPTH_STENCIL_ATTACHMENT, format::DEPTH_STENCIL_ATTACHMENT),
+        ];
+        if features.is_empty() || required.iter().any(|&(u, f)| usage.contains(u) && !features.contains(f)) {
+            return None;
+        }
+
+        let max_size = self.limits.max_texture_size as u32;
+        let max_volume_size = 2048;
+        let (max_extent, max_layers) = match kind {
+            image::Kind::D1(..) | image::Kind::D1Array(..) =>
+                (Extent { width: max_size, height: 1, depth: 1 }, 2048),
+            image::Kind::D2(..) | image::Kind::D2Array(..) |
+            image::Kind::Cube(..) | image::Kind::CubeArray(..) =>
+                (Extent { width: max_size, height: max_size, depth: 1 }, 2048),
+            image::Kind::D3(..) =>
+                (Extent { width: max_volume_size, height: max_volume_size, depth: max_volume_size }, 1),
+        };
+        let max_levels = 32 - cmp::max(max_extent.width, max_extent.depth).leading_zeros();
+        let sample_count_mask = match kind {
+            image::Kind::D2(..) | image::Kind::D2Array(..) if self.private_caps.is_mac => 0b1111,
+            image::Kind::D2(..) | image::Kind::D2Array(..) => 0b0101,
+            _ => 0b0001,
+        };
+
+        Some(image::FormatProperties {
+            max_extent,
+            max_levels: max_levels as _,
+            max_layers,
+            sample_count_mask,
+            max_resource_size: 1 << 31,
+        })
+    }
+
     fn create_render_pass(
         &mut self,
         attachments: &[pass::Attachment],
@@ -489,7 +755,17 @@ impl core::Device<Backend> for Device {
 
                     mtl_attachment = mem::transmute(color_attachment);
                 } else {
-                    unimplemented!()
+                    let aspects = attachment.format.0.aspects();
+                    if aspects.contains(image::ASPECT_STENCIL) {
+                        let stencil_attachment = pass.stencil_attachment();
+                        stencil_attachment.set_load_action(map_load_operation(attachment.stencil_ops.load));
+                        stencil_attachment.set_store_action(map_store_operation(attachment.stencil_ops.store));
+                    }
+                    if !aspects.contains(image::ASPECT_DEPTH) {
+                        continue;
+                    }
+
+                    mtl_attachment = mem::transmute(pass.depth_attachment());
                 }
 
                 mtl_attachment.set_load_action(map_load_operation(attachment.ops.load));
@@ -549,7 +825,7 @@ impl core::Device<Backend> for Device {
                         }
                     }
                 }
-                &&n::DescriptorSetLayout::ArgumentBuffer(_, stage_flags) => {
+                &&n::DescriptorSetLayout::ArgumentBuffer(_, stage_flags, _) => {
                     for &mut(stage_bit, stage, ref mut counters) in stage_infos.iter_mut() {
                         if !stage_flags.contains(stage_bit) {
                             continue
@@ -577,7 +853,15 @@ impl core::Device<Backend> for Device {
             assert!(counters.samplers <= self.private_caps.max_samplers_per_stage);
         }
 
-        n::PipelineLayout { res_overrides }
+        let set_bindings = set_layouts
+            .iter()
+            .map(|set_layout| match **set_layout {
+                n::DescriptorSetLayout::Emulated(ref bindings) |
+                n::DescriptorSetLayout::ArgumentBuffer(_, _, ref bindings) => bindings.clone(),
+            })
+            .collect();
+
+        n::PipelineLayout { res_overrides, set_bindings }
     }
 
     fn create_graphics_pipelines<'a>(
@@ -616,9 +900,17 @@ impl core::Device<Backend> for Device {
                 "Metal does not support multiple depth attachments");
 
             if let Some(attachment) = attachments.get(renderpass.num_colors) {
-                let mtl_attachment = desc.depth_attachment();
-                mtl_attachment.set_texture(attachment.0);
-                // TODO: stencil
+                let aspects = renderpass.attachments
+                    .iter()
+                    .map(|at| at.format.0.aspects())
+                    .find(|aspects| !aspects.contains(image::ASPECT_COLOR))
+                    .unwrap_or(image::ASPECT_DEPTH);
+                if aspects.contains(image::ASPECT_DEPTH) {
+                    desc.depth_attachment().set_texture(attachment.0);
+                }
+                if aspects.contains(image::ASPECT_STENCIL) {
+                    desc.stencil_attachment().set_texture(attachment.0);
+                }
             }
 
             desc
@@ -633,37 +925,55 @@ impl core::Device<Backend> for Device {
         if depends_on_pipeline_layout {
             Ok(n::ShaderModule::Raw(raw_data.to_vec()))
         } else {
-            self.compile_shader_library(raw_data, &HashMap::new())
+            self.compile_shader_library(raw_data, &HashMap::new(), &[])
                 .map(n::ShaderModule::Compiled)
         }
     }
 
-    fn create_sampler(&mut self, info: image::SamplerInfo) -> n::Sampler {
+    fn reflect_shader_module(&self, module: &n::ShaderModule) -> Result<pso::ShaderReflection, pso::ReflectError> {
+        match *module {
+            n::ShaderModule::Compiled(_) => Err(pso::ReflectError::NotSpirv),
+            n::ShaderModule::Raw(ref spirv) => pso::ShaderReflection::from_spirv(spirv),
+        }
+    }
+
+    fn create_sampler(&mut self, info: image::SamplerInfo) -> Result<n::Sampler, image::SamplerError> {
+        info.validate(&self.features)?;
+
         unsafe {
             let descriptor = MTLSamplerDescriptor::new(); // Returns retained
             defer! { descriptor.release() };
 
-            use self::image::FilterMethod::*;
-            let (min_mag, mipmap) = match info.filter {
-                Scale => (MTLSamplerMinMagFilter::Nearest, MTLSamplerMipFilter::NotMipmapped),
-                Mipmap => (MTLSamplerMinMagFilter::Nearest, MTLSamplerMipFilter::Nearest),
-                Bilinear => {
-                    (MTLSamplerMinMagFilter::Linear, MTLSamplerMipFilter::NotMipmapped)
-                }
-                Trilinear => (MTLSamplerMinMagFilter::Linear, MTLSamplerMipFilter::Linear),
-                Anisotropic(max) => {
-                    descriptor.set_max_anisotropy(max as u64);
-                    (MTLSamplerMinMagFilter::Linear, MTLSamplerMipFilter::NotMipmapped)
-                }
-            };
+            descriptor.set_min_filter(map_filter(info.min_filter));
+            descriptor.set_mag_filter(map_filter(info.mag_filter));
+            descriptor.set_mip_filter(map_mip_filter(info.mip_filter));
+
+            if let image::Anisotropic::On(max) = info.anisotropic {
+                descriptor.set_max_anisotropy(max as u64);
+            }
 
-            descriptor.set_min_filter(min_mag);
-            descriptor.set_mag_filter(min_mag);
-            descriptor.set_mip_filter(mipmap);
+            let (s, t, r) = info.wrap_mode;
+            descriptor.set_address_mode_s(map_wrap_mode(s));
+            descriptor.set_address_mode_t(map_wrap_mode(t));
+            descriptor.set_address_mode_r(map_wrap_mode(r));
 
-            // FIXME: more state
+            descriptor.set_lod_min_clamp(info.lod_range.start.into());
+            descriptor.set_lod_max_clamp(info.lod_range.end.into());
+            descriptor.set_normalized_coordinates(info.normalized);
+
+            if let Some(fun) = info.comparison {
+                descriptor.set_compare_function(map_compare_function(fun));
+            }
+            match map_border_color(info.border) {
+                Some(color) => descriptor.set_border_color(color),
+                None => error!("Unsupported border color {:?}", info.border),
+            }
+
+            if info.lod_bias != image::Lod::from(0.0) {
+                warn!("Sampler LOD bias is not supported");
+            }
 
-            n::Sampler(self.device.new_sampler(descriptor))
+            Ok(n::Sampler(self.device.new_sampler(descriptor)))
         }
     }
 
@@ -671,33 +981,80 @@ impl core::Device<Backend> for Device {
         unsafe { sampler.0.release(); }
     }
 
+    fn map_memory(
+        &mut self, memory: &n::Memory, range: Range<u64>
+    ) -> Result<*mut u8, mapping::Error> {
+        let raw = match *memory {
+            n::Memory::Shared { raw, .. } => raw,
+            _ => return Err(mapping::Error::InvalidAccess),
+        };
+
+        if range.start > range.end || range.end > raw.length() {
+            return Err(mapping::Error::OutOfBounds);
+        }
+
+        let base_ptr = raw.contents() as *mut u8;
+        Ok(unsafe { base_ptr.offset(range.start as _) })
+    }
+
+    fn flush_mapped_memory_ranges(
+        &mut self, ranges: &[(&n::Memory, Range<u64>)]
+    ) -> Result<(), d::OutOfMemory> {
+        for &(memory, ref range) in ranges {
+            if let n::Memory::Shared { raw, .. } = *memory {
+                if raw.storage_mode() != MTLStorageMode::Shared {
+                    raw.did_modify_range(NSRange {
+                        location: range.start as NSUInteger,
+                        length: (range.end - range.start) as NSUInteger,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn invalidate_mapped_memory_ranges(
+        &mut self, _ranges: &[(&n::Memory, Range<u64>)]
+    ) -> Result<(), d::OutOfMemory> {
+        // Managed resources are synchronized on the GPU timeline,
+        // with `synchronizeResource` of a blit encoder.
+        Ok(())
+    }
+
+    fn unmap_memory(&mut self, _memory: &n::Memory) {
+        // Buffer contents are always accessible
+    }
+
     fn acquire_mapping_raw(
         &mut self, buf: &n::Buffer, read: Option<Range<u64>>
     ) -> Result<*mut u8, mapping::Error> {
-        let base_ptr = buf.0.contents() as *mut u8;
+        let base_ptr = buf.raw.contents() as *mut u8;
 
         if base_ptr.is_null() {
             return Err(mapping::Error::InvalidAccess);
         }
 
         if let Some(range) = read {
-            if range.end > buf.0.length() {
+            if buf.offset + range.end > buf.raw.length() {
                 return Err(mapping::Error::OutOfBounds);
             }
         }
 
-        Ok(base_ptr)
+        Ok(unsafe { base_ptr.offset(buf.offset as _) })
     }
 
-    fn release_mapping_raw(&mut self, buffer: &n::Buffer, wrote: Option<Range<u64>>) {
+    fn release_mapping_raw(
+        &mut self, buffer: &n::Buffer, wrote: Option<Range<u64>>
+    ) -> Result<(), mapping::Error> {
         if let Some(range) = wrote {
-            if buffer.0.storage_mode() != MTLStorageMode::Shared {
-                buffer.0.did_modify_range(NSRange {
-                    location: range.start as NSUInteger,
+            if buffer.raw.storage_mode() != MTLStorageMode::Shared {
+                buffer.raw.did_modify_range(NSRange {
+                    location: (buffer.offset + range.start) as NSUInteger,
                     length: (range.end - range.start) as NSUInteger,
                 });
             }
         }
+        Ok(())
     }
 
     fn create_semaphore(&mut self) -> n::Semaphore {
@@ -754,7 +1111,7 @@ impl core::Device<Backend> for Device {
         let arg_array = NSArray::array_with_objects(&arguments);
         let encoder = self.device.new_argument_encoder(arg_array);
 
-        n::DescriptorSetLayout::ArgumentBuffer(encoder, stage_flags)
+        n::DescriptorSetLayout::ArgumentBuffer(encoder, stage_flags, bindings.to_vec())
     }
 
     fn update_descriptor_sets(&mut self, writes: &[DescriptorSetWrite<Backend>]) {
@@ -873,6 +1230,9 @@ impl core::Device<Backend> for Device {
         unsafe { n::dispatch_release(semaphore.0) }
     }
 
+    fn destroy_event(&mut self, _event: n::Event) {
+    }
+
     fn allocate_memory(&mut self, memory_type: &core::MemoryType, size: u64) -> Result<n::Memory, OutOfMemory> {
         let (storage, cache) = map_memory_properties_to_storage_and_cache(memory_type.properties);
 
@@ -884,6 +1244,13 @@ impl core::Device<Backend> for Device {
             descriptor.set_cpu_cache_mode(cache);
             descriptor.set_size(size);
             Ok(n::Memory::Native(self.device.new_heap(descriptor)))
+        } else if memory_type.properties.contains(memory::CPU_VISIBLE) {
+            let options = map_memory_properties_to_options(memory_type.properties);
+            let raw = self.device.new_buffer(size, options); // Returns retained
+            if raw.is_null() {
+                return Err(OutOfMemory);
+            }
+            Ok(n::Memory::Shared { memory_type: *memory_type, raw })
         } else {
             Ok(n::Memory::Emulated { memory_type: *memory_type, size })
         }
@@ -892,12 +1259,13 @@ impl core::Device<Backend> for Device {
     fn free_memory(&mut self, memory: n::Memory) {
         match memory {
             n::Memory::Emulated { .. } => {},
+            n::Memory::Shared { raw, .. } => unsafe { raw.release(); },
             n::Memory::Native(heap) => unsafe { heap.release(); },
         }
     }
 
     fn create_buffer(
-        &mut self, size: u64, _stride: u64, _usage: buffer::Usage
+        &mut self, size: u64, _stride: u64, _usage: buffer::Usage, _sharing: memory::SharingMode,
     ) -> Result<n::UnboundBuffer, buffer::CreationError> {
         Ok(n::UnboundBuffer {
             size
@@ -932,28 +1300,35 @@ impl core::Device<Backend> for Device {
     fn bind_buffer_memory(
         &mut self, memory: &n::Memory, offset: u64, buffer: n::UnboundBuffer
     ) -> Result<n::Buffer, BindError> {
-        let bound_buffer = match *memory {
+        let (bound_buffer, offset) = match *memory {
             n::Memory::Native(ref heap) => {
                 let resource_options = resource_options_from_storage_and_cache(
                     heap.storage_mode(),
                     heap.cpu_cache_mode());
-                heap.new_buffer(buffer.size, resource_options)
+                (heap.new_buffer(buffer.size, resource_options), 0)
+            }
+            n::Memory::Shared { raw, .. } => {
+                if offset + buffer.size > raw.length() {
+                    return Err(BindError::OutOfBounds);
+                }
+                unsafe { raw.retain(); }
+                (raw, offset)
             }
             n::Memory::Emulated { ref memory_type, size: _ } => {
                 // TODO: disable hazard tracking?
                 let resource_options = map_memory_properties_to_options(memory_type.properties);
-                self.device.new_buffer(buffer.size, resource_options)
+                (self.device.new_buffer(buffer.size, resource_options), 0)
             }
         };
         if !bound_buffer.is_null() {
-            Ok(n::Buffer(bound_buffer))
+            Ok(n::Buffer { raw: bound_buffer, offset })
         } else {
             Err(BindError::OutOfBounds)
         }
     }
 
     fn destroy_buffer(&mut self, buffer: n::Buffer) {
-        unsafe { buffer.0.release(); }
+        unsafe { buffer.raw.release(); }
     }
 
     fn create_buffer_view(
@@ -967,9 +1342,14 @@ impl core::Device<Backend> for Device {
     }
 
     fn create_image(
-        &mut self, kind: image::Kind, mip_levels: image::Level, format: format::Format, usage: image::Usage)
+        &mut self, kind: image::Kind, mip_levels: image::Level, format: format::Format, tiling: image::Tiling,
+        usage: image::Usage, _sharing: memory::SharingMode)
          -> Result<n::UnboundImage, image::CreationError>
     {
+        // TODO: linear images, backed by buffers
+        if tiling == image::Tiling::Linear {
+            return Err(image::CreationError::Tiling(tiling));
+        }
         let (mtl_format, _) = map_format(format).ok_or(image::CreationError::Format(format.0, Some(format.1)))?;
 
         unsafe {
@@ -1022,6 +1402,12 @@ impl core::Device<Backend> for Device {
         }
     }
 
+    fn get_image_subresource_footprint(
+        &mut self, _image: &n::Image, _aspect: image::AspectFlags, _subresource: image::Subresource,
+    ) -> image::SubresourceFootprint {
+        unreachable!("Linear images are rejected by `create_image`")
+    }
+
     fn bind_image_memory(
         &mut self, memory: &n::Memory, offset: u64, image: n::UnboundImage
     ) -> Result<n::Image, BindError> {
@@ -1033,7 +1419,8 @@ impl core::Device<Backend> for Device {
                 image.0.set_resource_options(resource_options);
                 heap.new_texture(image.0)
             },
-            n::Memory::Emulated { ref memory_type, size: _ } => {
+            n::Memory::Emulated { ref memory_type, .. } |
+            n::Memory::Shared { ref memory_type, .. } => {
                 // TODO: disable hazard tracking?
                 let resource_options = map_memory_properties_to_options(memory_type.properties);
                 image.0.set_resource_options(resource_options);
@@ -1048,6 +1435,29 @@ impl core::Device<Backend> for Device {
         }
     }
 
+    fn create_sparse_buffer(
+        &mut self, _size: u64, _stride: u64, _usage: buffer::Usage, _sharing: memory::SharingMode,
+    ) -> Result<n::Buffer, buffer::CreationError> {
+        // `Features::sparse_binding` isn't supported.
+        Err(buffer::CreationError::Sparse)
+    }
+
+    fn get_sparse_buffer_requirements(&mut self, _buffer: &n::Buffer) -> memory::Requirements {
+        unreachable!("Sparse buffers are rejected by `create_sparse_buffer`")
+    }
+
+    fn create_sparse_image(
+        &mut self, _kind: image::Kind, _mip_levels: image::Level, _format: format::Format, _usage: image::Usage,
+        _sharing: memory::SharingMode,
+    ) -> Result<n::Image, image::CreationError> {
+        // `Features::sparse_binding` isn't supported.
+        Err(image::CreationError::Sparse)
+    }
+
+    fn get_sparse_image_requirements(&mut self, _image: &n::Image) -> (memory::Requirements, Extent) {
+        unreachable!("Sparse images are rejected by `create_sparse_image`")
+    }
+
     fn destroy_image(&mut self, image: n::Image) {
         unsafe { image.0.release(); }
     }
@@ -1055,12 +1465,11 @@ impl core::Device<Backend> for Device {
     fn create_image_view(
         &mut self,
         image: &n::Image,
+        kind: image::ViewKind,
         format: format::Format,
         _swizzle: format::Swizzle,
-        _range: image::SubresourceRange,
+        range: image::SubresourceRange,
     ) -> Result<n::ImageView, image::ViewError> {
-        // TODO: subresource range
-
         let (mtl_format, _) = match map_format(format) {
             Some(f) => f,
             None => {
@@ -1068,9 +1477,32 @@ impl core::Device<Backend> for Device {
                 return Err(image::ViewError::BadFormat);
             },
         };
+        let mtl_type = match kind {
+            image::ViewKind::D1 => MTLTextureType::D1,
+            image::ViewKind::D1Array => MTLTextureType::D1Array,
+            image::ViewKind::D2 => MTLTextureType::D2,
+            image::ViewKind::D2Array => MTLTextureType::D2Array,
+            image::ViewKind::D3 => MTLTextureType::D3,
+            image::ViewKind::Cube => MTLTextureType::Cube,
+            image::ViewKind::CubeArray => MTLTextureType::CubeArray,
+        };
+        let levels = NSRange {
+            location: range.levels.start as _,
+            length: (range.levels.end - range.levels.start) as _,
+        };
+        let slices = NSRange {
+            location: range.layers.start as _,
+            length: (range.layers.end - range.layers.start) as _,
+        };
 
         unsafe {
-            Ok(n::ImageView(image.0.new_texture_view(mtl_format))) // Returns retained
+            let view: MTLTexture = msg_send![(image.0).0,
+                newTextureViewWithPixelFormat: mtl_format as NSUInteger
+                textureType: mtl_type as NSUInteger
+                levels: levels
+                slices: slices
+            ]; // Returns retained
+            Ok(n::ImageView(view))
         }
     }
 
@@ -1078,6 +1510,24 @@ impl core::Device<Backend> for Device {
         unsafe { view.0.release(); }
     }
 
+    fn create_event(&mut self) -> n::Event {
+        error!("Events are not supported");
+        n::Event
+    }
+
+    fn set_event(&mut self, _event: &n::Event) {
+        error!("Events are not supported");
+    }
+
+    fn reset_event(&mut self, _event: &n::Event) {
+        error!("Events are not supported");
+    }
+
+    fn get_event_status(&mut self, _event: &n::Event) -> bool {
+        error!("Events are not supported");
+        false
+    }
+
     // Emulated fence implementations
     #[cfg(not(feature = "native_fence"))]
     fn create_fence(&mut self, signaled: bool) -> n::Fence {
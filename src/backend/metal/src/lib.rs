@@ -133,5 +133,6 @@ impl core::Backend for Backend {
 
     type Fence = native::Fence;
     type Semaphore = native::Semaphore;
+    type Event = native::Event;
 }
 
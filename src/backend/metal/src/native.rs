@@ -45,6 +45,8 @@ unsafe impl Sync for FrameBuffer {}
 #[derive(Debug)]
 pub struct PipelineLayout {
     pub(crate) res_overrides: HashMap<msl::ResourceBindingLocation, msl::ResourceBinding>,
+    /// Bindings of each descriptor set layout.
+    pub(crate) set_bindings: Vec<Vec<pso::DescriptorSetLayoutBinding>>,
 }
 
 #[derive(Debug)]
@@ -55,6 +57,7 @@ pub struct GraphicsPipeline {
     pub(crate) fs_lib: Option<MTLLibrary>,
     pub(crate) raw: MTLRenderPipelineState,
     pub(crate) primitive_type: MTLPrimitiveType,
+    pub(crate) baked_states: pso::BakedStates,
 }
 
 unsafe impl Send for GraphicsPipeline {}
@@ -91,7 +94,14 @@ unsafe impl Send for Semaphore {}
 unsafe impl Sync for Semaphore {}
 
 #[derive(Debug)]
-pub struct Buffer(pub(crate) MTLBuffer);
+pub struct Event;
+
+#[derive(Debug)]
+pub struct Buffer {
+    pub(crate) raw: MTLBuffer,
+    // Offset into `raw`, non-zero if the buffer is sub-allocated from `Memory::Shared`.
+    pub(crate) offset: u64,
+}
 
 unsafe impl Send for Buffer {}
 unsafe impl Sync for Buffer {}
@@ -146,7 +156,7 @@ impl core::DescriptorPool<Backend> for DescriptorPool {
             DescriptorPool::ArgumentBuffer { buffer, total_size, ref mut offset } => {
                 layouts.iter().map(|layout| {
                     let (encoder, stage_flags) = match layout {
-                        &&DescriptorSetLayout::ArgumentBuffer(ref encoder, stages) => (encoder, stages),
+                        &&DescriptorSetLayout::ArgumentBuffer(ref encoder, stages, _) => (encoder, stages),
                         _ => panic!("Incompatible descriptor set layout type"),
                     };
 
@@ -173,7 +183,7 @@ impl core::DescriptorPool<Backend> for DescriptorPool {
 #[derive(Debug)]
 pub enum DescriptorSetLayout {
     Emulated(Vec<pso::DescriptorSetLayoutBinding>),
-    ArgumentBuffer(MTLArgumentEncoder, pso::ShaderStageFlags),
+    ArgumentBuffer(MTLArgumentEncoder, pso::ShaderStageFlags, Vec<pso::DescriptorSetLayoutBinding>),
 }
 unsafe impl Send for DescriptorSetLayout {}
 unsafe impl Sync for DescriptorSetLayout {}
@@ -240,9 +250,15 @@ impl Drop for DescriptorSetBinding {
 #[derive(Debug)]
 pub enum Memory {
     Emulated { memory_type: core::MemoryType, size: u64 },
+    /// CPU visible memory, backed by a single buffer which is shared
+    /// by all the buffers bound to it.
+    Shared { memory_type: core::MemoryType, raw: MTLBuffer },
     Native(MTLHeap),
 }
 
+unsafe impl Send for Memory {}
+unsafe impl Sync for Memory {}
+
 #[derive(Debug)]
 pub struct UnboundBuffer {
     pub(crate) size: u64,
@@ -264,6 +280,9 @@ impl core::QueueFamily for QueueFamily {
     fn num_queues(&self) -> u32 {
         1 // TODO: don't think there is a queue limit
     }
+    fn id(&self) -> core::QueueFamilyId {
+        core::QueueFamilyId(0)
+    }
 }
 
 pub unsafe fn objc_err_description(object: *mut objc::runtime::Object) -> String {
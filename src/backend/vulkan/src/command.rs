@@ -9,7 +9,7 @@ use core::{command as com, memory, pso, target};
 use core::{IndexCount, InstanceCount, VertexCount, VertexOffset, Viewport};
 use core::buffer::IndexBufferView;
 use core::image::{
-    ImageLayout, SubresourceRange,
+    Filter, ImageLayout, SubresourceRange,
     ASPECT_COLOR, ASPECT_DEPTH, ASPECT_STENCIL,
 };
 use {conv, native as n};
@@ -28,6 +28,68 @@ fn map_subpass_contents(contents: com::SubpassContents) -> vk::SubpassContents {
     }
 }
 
+fn map_barriers(
+    barriers: &[memory::Barrier<Backend>],
+) -> (
+    SmallVec<[vk::MemoryBarrier; 1]>,
+    SmallVec<[vk::BufferMemoryBarrier; 4]>,
+    SmallVec<[vk::ImageMemoryBarrier; 4]>,
+) {
+    let mut memory_bars: SmallVec<[vk::MemoryBarrier; 1]> = SmallVec::new();
+    let mut buffer_bars: SmallVec<[vk::BufferMemoryBarrier; 4]> = SmallVec::new();
+    let mut image_bars: SmallVec<[vk::ImageMemoryBarrier; 4]> = SmallVec::new();
+
+    for barrier in barriers {
+        match *barrier {
+            memory::Barrier::Buffer { ref states, target, ref families } => {
+                let (src_family, dst_family) = conv::map_queue_families(families);
+                buffer_bars.push(vk::BufferMemoryBarrier {
+                    s_type: vk::StructureType::BufferMemoryBarrier,
+                    p_next: ptr::null(),
+                    src_access_mask: conv::map_buffer_access(states.start),
+                    dst_access_mask: conv::map_buffer_access(states.end),
+                    src_queue_family_index: src_family,
+                    dst_queue_family_index: dst_family,
+                    buffer: target.raw,
+                    offset: 0,
+                    size: vk::VK_WHOLE_SIZE,
+                });
+            }
+            memory::Barrier::Image { ref states, target, ref range, ref families } => {
+                let (src_family, dst_family) = conv::map_queue_families(families);
+                let subresource_range = conv::map_subresource_range(range);
+                image_bars.push(vk::ImageMemoryBarrier {
+                    s_type: vk::StructureType::ImageMemoryBarrier,
+                    p_next: ptr::null(),
+                    src_access_mask: conv::map_image_access(states.start.0),
+                    dst_access_mask: conv::map_image_access(states.end.0),
+                    old_layout: conv::map_image_layout(states.start.1),
+                    new_layout: conv::map_image_layout(states.end.1),
+                    src_queue_family_index: src_family,
+                    dst_queue_family_index: dst_family,
+                    image: target.raw,
+                    subresource_range,
+                });
+            }
+            memory::Barrier::Aliasing { .. } => {
+                // Vulkan has no dedicated aliasing barrier, use a global memory one.
+                // The layout of the aliasing image is expected to be transitioned
+                // from `Undefined` separately.
+                if memory_bars.is_empty() {
+                    memory_bars.push(vk::MemoryBarrier {
+                        s_type: vk::StructureType::MemoryBarrier,
+                        p_next: ptr::null(),
+                        src_access_mask: vk::ACCESS_MEMORY_WRITE_BIT,
+                        dst_access_mask: vk::ACCESS_MEMORY_READ_BIT | vk::ACCESS_MEMORY_WRITE_BIT,
+                    });
+                }
+            }
+        }
+    }
+
+    (memory_bars, buffer_bars, image_bars)
+}
+
 fn map_buffer_image_regions(
     image: &n::Image,
     regions: &[com::BufferImageCopy],
@@ -41,19 +103,22 @@ fn map_buffer_image_regions(
         .map(|region| {
             let r = &region.image_layers;
             let aspect_mask = conv::map_image_aspects(r.aspects);
-            let row_length = div(region.buffer_row_pitch, image.bytes_per_texel as u32);
+            // Vulkan expects the buffer layout in texels, which are
+            // multiples of the block dimensions for compressed formats.
+            let row_blocks = div(region.buffer_row_pitch, image.block.bytes as u32);
+            let slice_rows = div(region.buffer_slice_pitch, region.buffer_row_pitch);
             let image_subresource = conv::map_subresource_layers(aspect_mask, r.level, &r.layers);
             vk::BufferImageCopy {
                 buffer_offset: region.buffer_offset,
-                buffer_row_length: row_length,
-                buffer_image_height: div(region.buffer_slice_pitch, row_length),
+                buffer_row_length: row_blocks * image.block.width as u32,
+                buffer_image_height: slice_rows * image.block.height as u32,
                 image_subresource,
                 image_offset: vk::Offset3D {
                     x: region.image_offset.x,
                     y: region.image_offset.y,
                     z: region.image_offset.z,
                 },
-                image_extent: image.extent.clone(),
+                image_extent: conv::map_extent(region.image_extent),
             }
         })
         .collect()
@@ -175,42 +240,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         stages: Range<pso::PipelineStage>,
         barriers: &[memory::Barrier<Backend>],
     ) {
-        let mut buffer_bars: SmallVec<[vk::BufferMemoryBarrier; 4]> = SmallVec::new();
-        let mut image_bars: SmallVec<[vk::ImageMemoryBarrier; 4]> = SmallVec::new();
-
-        for barrier in barriers {
-            match *barrier {
-                memory::Barrier::Buffer { ref states, target} => {
-                    buffer_bars.push(vk::BufferMemoryBarrier {
-                        s_type: vk::StructureType::BufferMemoryBarrier,
-                        p_next: ptr::null(),
-                        src_access_mask: conv::map_buffer_access(states.start),
-                        dst_access_mask: conv::map_buffer_access(states.end),
-                        src_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED, // TODO
-                        dst_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED, // TODO
-                        buffer: target.raw,
-                        offset: 0,
-                        size: vk::VK_WHOLE_SIZE,
-                    });
-                }
-                memory::Barrier::Image { ref states, target, ref range } => {
-                    assert_eq!(range.aspects, ASPECT_COLOR);
-                    let subresource_range = conv::map_subresource_range(range);
-                    image_bars.push(vk::ImageMemoryBarrier {
-                        s_type: vk::StructureType::ImageMemoryBarrier,
-                        p_next: ptr::null(),
-                        src_access_mask: conv::map_image_access(states.start.0),
-                        dst_access_mask: conv::map_image_access(states.end.0),
-                        old_layout: conv::map_image_layout(states.start.1),
-                        new_layout: conv::map_image_layout(states.end.1),
-                        src_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED, // TODO
-                        dst_queue_family_index: vk::VK_QUEUE_FAMILY_IGNORED, // TODO
-                        image: target.raw,
-                        subresource_range,
-                    });
-                }
-            }
-        }
+        let (memory_bars, buffer_bars, image_bars) = map_barriers(barriers);
 
         unsafe {
             self.device.0.cmd_pipeline_barrier(
@@ -218,13 +248,59 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
                 conv::map_pipeline_stage(stages.start),
                 conv::map_pipeline_stage(stages.end),
                 vk::DependencyFlags::empty(), // dependencyFlags // TODO
-                &[],
+                &memory_bars,
                 &buffer_bars,
                 &image_bars,
             );
         }
     }
 
+    fn set_event(&mut self, event: &n::Event, stage: pso::PipelineStage) {
+        unsafe {
+            self.device.0.fp_v1_0().cmd_set_event(
+                self.raw,
+                event.0,
+                conv::map_pipeline_stage(stage),
+            );
+        }
+    }
+
+    fn reset_event(&mut self, event: &n::Event, stage: pso::PipelineStage) {
+        unsafe {
+            self.device.0.fp_v1_0().cmd_reset_event(
+                self.raw,
+                event.0,
+                conv::map_pipeline_stage(stage),
+            );
+        }
+    }
+
+    fn wait_events(
+        &mut self,
+        events: &[&n::Event],
+        stages: Range<pso::PipelineStage>,
+        barriers: &[memory::Barrier<Backend>],
+    ) {
+        let events = events.iter().map(|event| event.0).collect::<SmallVec<[_; 4]>>();
+        let (memory_bars, buffer_bars, image_bars) = map_barriers(barriers);
+
+        unsafe {
+            self.device.0.fp_v1_0().cmd_wait_events(
+                self.raw,
+                events.len() as u32,
+                events.as_ptr(),
+                conv::map_pipeline_stage(stages.start),
+                conv::map_pipeline_stage(stages.end),
+                memory_bars.len() as u32,
+                memory_bars.as_ptr(),
+                buffer_bars.len() as u32,
+                buffer_bars.as_ptr(),
+                image_bars.len() as u32,
+                image_bars.as_ptr(),
+            );
+        }
+    }
+
     fn fill_buffer(
         &mut self,
         buffer: &n::Buffer,
@@ -420,6 +496,53 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         }
     }
 
+    fn blit_image(
+        &mut self,
+        src: &n::Image,
+        src_layout: ImageLayout,
+        dst: &n::Image,
+        dst_layout: ImageLayout,
+        filter: Filter,
+        regions: &[com::ImageBlit],
+    ) {
+        let regions = regions
+            .iter()
+            .map(|region| {
+                vk::ImageBlit {
+                    src_subresource: conv::map_subresource_layers(
+                        conv::map_image_aspects(region.src_subresource.aspects),
+                        region.src_subresource.level,
+                        &region.src_subresource.layers,
+                    ),
+                    src_offsets: [
+                        conv::map_offset(region.src_bounds.start),
+                        conv::map_offset(region.src_bounds.end),
+                    ],
+                    dst_subresource: conv::map_subresource_layers(
+                        conv::map_image_aspects(region.dst_subresource.aspects),
+                        region.dst_subresource.level,
+                        &region.dst_subresource.layers,
+                    ),
+                    dst_offsets: [
+                        conv::map_offset(region.dst_bounds.start),
+                        conv::map_offset(region.dst_bounds.end),
+                    ],
+                }
+            })
+            .collect::<SmallVec<[_; 16]>>();
+        unsafe {
+            self.device.0.cmd_blit_image(
+                self.raw,
+                src.raw,
+                conv::map_image_layout(src_layout),
+                dst.raw,
+                conv::map_image_layout(dst_layout),
+                &regions,
+                conv::map_filter(filter),
+            );
+        }
+    }
+
     fn bind_index_buffer(&mut self, ibv: IndexBufferView<Backend>) {
         unsafe {
             self.device.0.cmd_bind_index_buffer(
@@ -447,19 +570,22 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         }
     }
 
+    fn bind_transform_feedback_buffers(&mut self, _: u32, _: &[com::TransformFeedbackBuffer<Backend>]) {
+        error!("Transform feedback is not supported");
+    }
+
+    fn begin_transform_feedback(&mut self) {
+        error!("Transform feedback is not supported");
+    }
+
+    fn end_transform_feedback(&mut self) {
+        error!("Transform feedback is not supported");
+    }
+
     fn set_viewports(&mut self, viewports: &[Viewport]) {
         let viewports: SmallVec<[vk::Viewport; 16]> = viewports
             .iter()
-            .map(|viewport| {
-                vk::Viewport {
-                    x: viewport.x as f32,
-                    y: viewport.y as f32,
-                    width: viewport.w as f32,
-                    height: viewport.h as f32,
-                    min_depth: viewport.near,
-                    max_depth: viewport.far,
-                }
-            })
+            .map(conv::map_viewport)
             .collect();
 
         unsafe {
@@ -470,18 +596,7 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
     fn set_scissors(&mut self, scissors: &[target::Rect]) {
         let scissors: SmallVec<[vk::Rect2D; 16]> = scissors
             .iter()
-            .map(|scissor| {
-                vk::Rect2D {
-                    offset: vk::Offset2D {
-                        x: scissor.x as i32,
-                        y: scissor.y as i32,
-                    },
-                    extent: vk::Extent2D {
-                        width: scissor.w as u32,
-                        height: scissor.h as u32,
-                    },
-                }
-            })
+            .map(conv::map_rect)
             .collect();
 
         unsafe {
@@ -520,6 +635,18 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
         }
     }
 
+    fn set_depth_bounds(&mut self, bounds: Range<f32>) {
+        unsafe {
+            self.device.0.cmd_set_depth_bounds(self.raw, bounds.start, bounds.end);
+        }
+    }
+
+    fn set_line_width(&mut self, width: f32) {
+        unsafe {
+            self.device.0.cmd_set_line_width(self.raw, width);
+        }
+    }
+
     fn bind_graphics_pipeline(&mut self, pipeline: &n::GraphicsPipeline) {
         unsafe {
             self.device.0.cmd_bind_pipeline(
@@ -750,6 +877,10 @@ impl com::RawCommandBuffer<Backend> for CommandBuffer {
             )
         }
     }
+
+    fn draw_auto(&mut self) {
+        error!("Transform feedback is not supported");
+    }
 }
 
 pub struct SubpassCommandBuffer(pub CommandBuffer);
@@ -1,8 +1,9 @@
 use ash::vk;
-use core::{buffer, format, image, pass, pso, state};
+use core::{buffer, format, image, pass, pso, state, target};
 use core::command::{ClearColor, ClearDepthStencil, ClearValue, Offset};
 use core::device::Extent;
-use core::{IndexType, Primitive};
+use core::{IndexType, Primitive, QueueFamilyId, Viewport};
+use std::mem;
 use std::ops::Range;
 
 
@@ -31,6 +32,8 @@ pub fn map_format(surface: format::SurfaceType, chan: format::ChannelType) -> Op
             Uint  => vk::Format::R8Uint,
             Inorm => vk::Format::R8Snorm,
             Unorm => vk::Format::R8Unorm,
+            Iscaled => vk::Format::R8Sscaled,
+            Uscaled => vk::Format::R8Uscaled,
             Srgb  => vk::Format::R8Srgb,
             _ => return None,
         },
@@ -39,6 +42,8 @@ pub fn map_format(surface: format::SurfaceType, chan: format::ChannelType) -> Op
             Uint  => vk::Format::R8g8Uint,
             Inorm => vk::Format::R8g8Snorm,
             Unorm => vk::Format::R8g8Unorm,
+            Iscaled => vk::Format::R8g8Sscaled,
+            Uscaled => vk::Format::R8g8Uscaled,
             Srgb  => vk::Format::R8g8Srgb,
             _ => return None,
         },
@@ -47,14 +52,18 @@ pub fn map_format(surface: format::SurfaceType, chan: format::ChannelType) -> Op
             Uint  => vk::Format::R8g8b8a8Uint,
             Inorm => vk::Format::R8g8b8a8Snorm,
             Unorm => vk::Format::R8g8b8a8Unorm,
+            Iscaled => vk::Format::R8g8b8a8Sscaled,
+            Uscaled => vk::Format::R8g8b8a8Uscaled,
             Srgb  => vk::Format::R8g8b8a8Srgb,
             _ => return None,
         },
         R10_G10_B10_A2 => match chan {
-            Int   => vk::Format::A2r10g10b10SintPack32,
-            Uint  => vk::Format::A2r10g10b10UintPack32,
-            Inorm => vk::Format::A2r10g10b10SnormPack32,
-            Unorm => vk::Format::A2r10g10b10UnormPack32,
+            Int   => vk::Format::A2b10g10r10SintPack32,
+            Uint  => vk::Format::A2b10g10r10UintPack32,
+            Inorm => vk::Format::A2b10g10r10SnormPack32,
+            Unorm => vk::Format::A2b10g10r10UnormPack32,
+            Iscaled => vk::Format::A2b10g10r10SscaledPack32,
+            Uscaled => vk::Format::A2b10g10r10UscaledPack32,
             _ => return None,
         },
         R11_G11_B10 => match chan {
@@ -66,6 +75,8 @@ pub fn map_format(surface: format::SurfaceType, chan: format::ChannelType) -> Op
             Uint  => vk::Format::R16Uint,
             Inorm => vk::Format::R16Snorm,
             Unorm => vk::Format::R16Unorm,
+            Iscaled => vk::Format::R16Sscaled,
+            Uscaled => vk::Format::R16Uscaled,
             Float => vk::Format::R16Sfloat,
             _ => return None,
         },
@@ -74,6 +85,8 @@ pub fn map_format(surface: format::SurfaceType, chan: format::ChannelType) -> Op
             Uint  => vk::Format::R16g16Uint,
             Inorm => vk::Format::R16g16Snorm,
             Unorm => vk::Format::R16g16Unorm,
+            Iscaled => vk::Format::R16g16Sscaled,
+            Uscaled => vk::Format::R16g16Uscaled,
             Float => vk::Format::R16g16Sfloat,
             _ => return None,
         },
@@ -82,6 +95,8 @@ pub fn map_format(surface: format::SurfaceType, chan: format::ChannelType) -> Op
             Uint  => vk::Format::R16g16b16Uint,
             Inorm => vk::Format::R16g16b16Snorm,
             Unorm => vk::Format::R16g16b16Unorm,
+            Iscaled => vk::Format::R16g16b16Sscaled,
+            Uscaled => vk::Format::R16g16b16Uscaled,
             Float => vk::Format::R16g16b16Sfloat,
             _ => return None,
         },
@@ -90,6 +105,8 @@ pub fn map_format(surface: format::SurfaceType, chan: format::ChannelType) -> Op
             Uint  => vk::Format::R16g16b16a16Uint,
             Inorm => vk::Format::R16g16b16a16Snorm,
             Unorm => vk::Format::R16g16b16a16Unorm,
+            Iscaled => vk::Format::R16g16b16a16Sscaled,
+            Uscaled => vk::Format::R16g16b16a16Uscaled,
             Float => vk::Format::R16g16b16a16Sfloat,
             _ => return None,
         },
@@ -135,6 +152,7 @@ pub fn map_format(surface: format::SurfaceType, chan: format::ChannelType) -> Op
         },
         D24_S8 => match chan {
             Unorm => vk::Format::D24UnormS8Uint,
+            Uint  => vk::Format::D24UnormS8Uint,
             _ => return None,
         },
         D32 => match chan {
@@ -142,7 +160,148 @@ pub fn map_format(surface: format::SurfaceType, chan: format::ChannelType) -> Op
             _ => return None,
         },
         D32_S8 => match chan {
+            Unorm => vk::Format::D32SfloatS8Uint,
             Float => vk::Format::D32SfloatS8Uint,
+            Uint  => vk::Format::D32SfloatS8Uint,
+            _ => return None,
+        },
+        S8 => match chan {
+            Uint => vk::Format::S8Uint,
+            _ => return None,
+        },
+        BC1_R8_G8_B8 => match chan {
+            Unorm => vk::Format::Bc1RgbUnormBlock,
+            Srgb  => vk::Format::Bc1RgbSrgbBlock,
+            _ => return None,
+        },
+        BC1_R8_G8_B8_A8 => match chan {
+            Unorm => vk::Format::Bc1RgbaUnormBlock,
+            Srgb  => vk::Format::Bc1RgbaSrgbBlock,
+            _ => return None,
+        },
+        BC2_R8_G8_B8_A8 => match chan {
+            Unorm => vk::Format::Bc2UnormBlock,
+            Srgb  => vk::Format::Bc2SrgbBlock,
+            _ => return None,
+        },
+        BC3_R8_G8_B8_A8 => match chan {
+            Unorm => vk::Format::Bc3UnormBlock,
+            Srgb  => vk::Format::Bc3SrgbBlock,
+            _ => return None,
+        },
+        BC4_R8 => match chan {
+            Inorm => vk::Format::Bc4SnormBlock,
+            Unorm => vk::Format::Bc4UnormBlock,
+            _ => return None,
+        },
+        BC5_R8_G8 => match chan {
+            Inorm => vk::Format::Bc5SnormBlock,
+            Unorm => vk::Format::Bc5UnormBlock,
+            _ => return None,
+        },
+        BC6_R16_G16_B16 => match chan {
+            Float  => vk::Format::Bc6hSfloatBlock,
+            Ufloat => vk::Format::Bc6hUfloatBlock,
+            _ => return None,
+        },
+        BC7_R8_G8_B8_A8 => match chan {
+            Unorm => vk::Format::Bc7UnormBlock,
+            Srgb  => vk::Format::Bc7SrgbBlock,
+            _ => return None,
+        },
+        ETC2_R8_G8_B8 => match chan {
+            Unorm => vk::Format::Etc2R8g8b8UnormBlock,
+            Srgb  => vk::Format::Etc2R8g8b8SrgbBlock,
+            _ => return None,
+        },
+        ETC2_R8_G8_B8_A1 => match chan {
+            Unorm => vk::Format::Etc2R8g8b8a1UnormBlock,
+            Srgb  => vk::Format::Etc2R8g8b8a1SrgbBlock,
+            _ => return None,
+        },
+        ETC2_R8_G8_B8_A8 => match chan {
+            Unorm => vk::Format::Etc2R8g8b8a8UnormBlock,
+            Srgb  => vk::Format::Etc2R8g8b8a8SrgbBlock,
+            _ => return None,
+        },
+        EAC_R11 => match chan {
+            Inorm => vk::Format::EacR11SnormBlock,
+            Unorm => vk::Format::EacR11UnormBlock,
+            _ => return None,
+        },
+        EAC_R11_G11 => match chan {
+            Inorm => vk::Format::EacR11g11SnormBlock,
+            Unorm => vk::Format::EacR11g11UnormBlock,
+            _ => return None,
+        },
+        ASTC_4x4 => match chan {
+            Unorm => vk::Format::Astc4x4UnormBlock,
+            Srgb  => vk::Format::Astc4x4SrgbBlock,
+            _ => return None,
+        },
+        ASTC_5x4 => match chan {
+            Unorm => vk::Format::Astc5x4UnormBlock,
+            Srgb  => vk::Format::Astc5x4SrgbBlock,
+            _ => return None,
+        },
+        ASTC_5x5 => match chan {
+            Unorm => vk::Format::Astc5x5UnormBlock,
+            Srgb  => vk::Format::Astc5x5SrgbBlock,
+            _ => return None,
+        },
+        ASTC_6x5 => match chan {
+            Unorm => vk::Format::Astc6x5UnormBlock,
+            Srgb  => vk::Format::Astc6x5SrgbBlock,
+            _ => return None,
+        },
+        ASTC_6x6 => match chan {
+            Unorm => vk::Format::Astc6x6UnormBlock,
+            Srgb  => vk::Format::Astc6x6SrgbBlock,
+            _ => return None,
+        },
+        ASTC_8x5 => match chan {
+            Unorm => vk::Format::Astc8x5UnormBlock,
+            Srgb  => vk::Format::Astc8x5SrgbBlock,
+            _ => return None,
+        },
+        ASTC_8x6 => match chan {
+            Unorm => vk::Format::Astc8x6UnormBlock,
+            Srgb  => vk::Format::Astc8x6SrgbBlock,
+            _ => return None,
+        },
+        ASTC_8x8 => match chan {
+            Unorm => vk::Format::Astc8x8UnormBlock,
+            Srgb  => vk::Format::Astc8x8SrgbBlock,
+            _ => return None,
+        },
+        ASTC_10x5 => match chan {
+            Unorm => vk::Format::Astc10x5UnormBlock,
+            Srgb  => vk::Format::Astc10x5SrgbBlock,
+            _ => return None,
+        },
+        ASTC_10x6 => match chan {
+            Unorm => vk::Format::Astc10x6UnormBlock,
+            Srgb  => vk::Format::Astc10x6SrgbBlock,
+            _ => return None,
+        },
+        ASTC_10x8 => match chan {
+            Unorm => vk::Format::Astc10x8UnormBlock,
+            Srgb  => vk::Format::Astc10x8SrgbBlock,
+            _ => return None,
+        },
+        ASTC_10x10 => match chan {
+            Unorm => vk::Format::Astc10x10UnormBlock,
+            Srgb  => vk::Format::Astc10x10SrgbBlock,
+            _ => return None,
+        },
+        ASTC_12x10 => match chan {
+            Unorm => vk::Format::Astc12x10UnormBlock,
+            Srgb  => vk::Format::Astc12x10SrgbBlock,
+            _ => return None,
+        },
+        ASTC_12x12 => match chan {
+            Unorm => vk::Format::Astc12x12UnormBlock,
+            Srgb  => vk::Format::Astc12x12SrgbBlock,
             _ => return None,
         },
     })
@@ -192,6 +351,18 @@ pub fn map_image_layout(layout: image::ImageLayout) -> vk::ImageLayout {
     }
 }
 
+pub fn map_view_kind(kind: image::ViewKind) -> vk::ImageViewType {
+    match kind {
+        image::ViewKind::D1 => vk::ImageViewType::Type1d,
+        image::ViewKind::D1Array => vk::ImageViewType::Type1dArray,
+        image::ViewKind::D2 => vk::ImageViewType::Type2d,
+        image::ViewKind::D2Array => vk::ImageViewType::Type2dArray,
+        image::ViewKind::D3 => vk::ImageViewType::Type3d,
+        image::ViewKind::Cube => vk::ImageViewType::Cube,
+        image::ViewKind::CubeArray => vk::ImageViewType::CubeArray,
+    }
+}
+
 pub fn map_image_aspects(aspects: image::AspectFlags) -> vk::ImageAspectFlags {
     let mut flags = vk::ImageAspectFlags::empty();
     if aspects.contains(image::ASPECT_COLOR) {
@@ -234,6 +405,30 @@ pub fn map_clear_value(value: &ClearValue) -> vk::ClearValue {
     }
 }
 
+pub fn map_viewport(viewport: &Viewport) -> vk::Viewport {
+    vk::Viewport {
+        x: viewport.x as f32,
+        y: viewport.y as f32,
+        width: viewport.w as f32,
+        height: viewport.h as f32,
+        min_depth: viewport.near,
+        max_depth: viewport.far,
+    }
+}
+
+pub fn map_rect(rect: &target::Rect) -> vk::Rect2D {
+    vk::Rect2D {
+        offset: vk::Offset2D {
+            x: rect.x as i32,
+            y: rect.y as i32,
+        },
+        extent: vk::Extent2D {
+            width: rect.w as u32,
+            height: rect.h as u32,
+        },
+    }
+}
+
 pub fn map_offset(offset: Offset) -> vk::Offset3D {
     vk::Offset3D {
         x: offset.x,
@@ -475,6 +670,13 @@ pub fn map_buffer_usage(usage: buffer::Usage) -> vk::BufferUsageFlags {
     flags
 }
 
+pub fn map_tiling(tiling: image::Tiling) -> vk::ImageTiling {
+    match tiling {
+        image::Tiling::Optimal => vk::ImageTiling::Optimal,
+        image::Tiling::Linear => vk::ImageTiling::Linear,
+    }
+}
+
 pub fn map_image_usage(usage: image::Usage) -> vk::ImageUsageFlags {
     let mut flags = vk::ImageUsageFlags::empty();
 
@@ -500,6 +702,72 @@ pub fn map_image_usage(usage: image::Usage) -> vk::ImageUsageFlags {
     flags
 }
 
+pub fn map_image_create_flags(kind: image::Kind, usage: image::Usage) -> vk::ImageCreateFlags {
+    let mut flags = vk::ImageCreateFlags::empty();
+
+    if usage.contains(image::MUTABLE_FORMAT) {
+        flags |= vk::IMAGE_CREATE_MUTABLE_FORMAT_BIT;
+    }
+    if kind.is_cube() {
+        flags |= vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT;
+    }
+
+    flags
+}
+
+pub fn map_image_features(features: vk::FormatFeatureFlags) -> format::ImageFeature {
+    let mut flags = format::ImageFeature::empty();
+
+    if features.intersects(vk::FORMAT_FEATURE_SAMPLED_IMAGE_BIT) {
+        flags |= format::SAMPLED;
+    }
+    if features.intersects(vk::FORMAT_FEATURE_SAMPLED_IMAGE_FILTER_LINEAR_BIT) {
+        flags |= format::SAMPLED_LINEAR;
+    }
+    if features.intersects(vk::FORMAT_FEATURE_STORAGE_IMAGE_BIT) {
+        flags |= format::STORAGE;
+    }
+    if features.intersects(vk::FORMAT_FEATURE_STORAGE_IMAGE_ATOMIC_BIT) {
+        flags |= format::STORAGE_ATOMIC;
+    }
+    if features.intersects(vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BIT) {
+        flags |= format::COLOR_ATTACHMENT;
+    }
+    if features.intersects(vk::FORMAT_FEATURE_COLOR_ATTACHMENT_BLEND_BIT) {
+        flags |= format::COLOR_ATTACHMENT_BLEND;
+    }
+    if features.intersects(vk::FORMAT_FEATURE_DEPTH_STENCIL_ATTACHMENT_BIT) {
+        flags |= format::DEPTH_STENCIL_ATTACHMENT;
+    }
+    if features.intersects(vk::FORMAT_FEATURE_BLIT_SRC_BIT) {
+        flags |= format::BLIT_SRC;
+    }
+    if features.intersects(vk::FORMAT_FEATURE_BLIT_DST_BIT) {
+        flags |= format::BLIT_DST;
+    }
+
+    flags
+}
+
+pub fn map_buffer_features(features: vk::FormatFeatureFlags) -> format::BufferFeature {
+    let mut flags = format::BufferFeature::empty();
+
+    if features.intersects(vk::FORMAT_FEATURE_UNIFORM_TEXEL_BUFFER_BIT) {
+        flags |= format::UNIFORM_TEXEL;
+    }
+    if features.intersects(vk::FORMAT_FEATURE_STORAGE_TEXEL_BUFFER_BIT) {
+        flags |= format::STORAGE_TEXEL;
+    }
+    if features.intersects(vk::FORMAT_FEATURE_STORAGE_TEXEL_BUFFER_ATOMIC_BIT) {
+        flags |= format::STORAGE_TEXEL_ATOMIC;
+    }
+    if features.intersects(vk::FORMAT_FEATURE_VERTEX_BUFFER_BIT) {
+        flags |= format::VERTEX;
+    }
+
+    flags
+}
+
 pub fn map_descriptor_type(ty: pso::DescriptorType) -> vk::DescriptorType {
     use core::pso::DescriptorType as Dt;
     match ty {
@@ -545,14 +813,27 @@ pub fn map_stage_flags(stages: pso::ShaderStageFlags) -> vk::ShaderStageFlags {
 }
 
 
-pub fn map_filter(filter: image::FilterMethod) -> (vk::Filter, vk::Filter, vk::SamplerMipmapMode, f32) {
-    use core::image::FilterMethod as Fm;
+pub fn map_filter(filter: image::Filter) -> vk::Filter {
     match filter {
-        Fm::Scale          => (vk::Filter::Nearest, vk::Filter::Nearest, vk::SamplerMipmapMode::Nearest, 1.0),
-        Fm::Mipmap         => (vk::Filter::Nearest, vk::Filter::Nearest, vk::SamplerMipmapMode::Linear,  1.0),
-        Fm::Bilinear       => (vk::Filter::Linear,  vk::Filter::Linear,  vk::SamplerMipmapMode::Nearest, 1.0),
-        Fm::Trilinear      => (vk::Filter::Linear,  vk::Filter::Linear,  vk::SamplerMipmapMode::Linear,  1.0),
-        Fm::Anisotropic(a) => (vk::Filter::Linear,  vk::Filter::Linear,  vk::SamplerMipmapMode::Linear,  a as f32),
+        image::Filter::Nearest => vk::Filter::Nearest,
+        image::Filter::Linear  => vk::Filter::Linear,
+    }
+}
+
+/// Maps to a `VkSamplerReductionModeEXT` of `VK_EXT_sampler_filter_minmax`.
+pub fn map_reduction_mode(mode: image::ReductionMode) -> u32 {
+    match mode {
+        image::ReductionMode::WeightedAverage => 0,
+        image::ReductionMode::Minimum => 1,
+        image::ReductionMode::Maximum => 2,
+    }
+}
+
+pub fn map_mip_filter(filter: Option<image::Filter>) -> vk::SamplerMipmapMode {
+    match filter {
+        None |
+        Some(image::Filter::Nearest) => vk::SamplerMipmapMode::Nearest,
+        Some(image::Filter::Linear)  => vk::SamplerMipmapMode::Linear,
     }
 }
 
@@ -566,12 +847,12 @@ pub fn map_wrap(wrap: image::WrapMode) -> vk::SamplerAddressMode {
     }
 }
 
-pub fn map_border_color(col: image::PackedColor) -> Option<vk::BorderColor> {
-    match col.0 {
-        0x00000000 => Some(vk::BorderColor::FloatTransparentBlack),
-        0xFF000000 => Some(vk::BorderColor::FloatOpaqueBlack),
-        0xFFFFFFFF => Some(vk::BorderColor::FloatOpaqueWhite),
-        _ => None
+pub fn map_border_color(col: image::BorderColor) -> Option<vk::BorderColor> {
+    match col {
+        image::BorderColor::TransparentBlack => Some(vk::BorderColor::FloatTransparentBlack),
+        image::BorderColor::OpaqueBlack => Some(vk::BorderColor::FloatOpaqueBlack),
+        image::BorderColor::OpaqueWhite => Some(vk::BorderColor::FloatOpaqueWhite),
+        image::BorderColor::Custom(_) => None,
     }
 }
 
@@ -590,11 +871,11 @@ pub fn map_topology(prim: Primitive) -> vk::PrimitiveTopology {
     }
 }
 
-pub fn map_polygon_mode(rm: state::RasterMethod) -> (vk::PolygonMode, f32) {
+pub fn map_polygon_mode(rm: state::RasterMethod) -> vk::PolygonMode {
     match rm {
-        state::RasterMethod::Point   => (vk::PolygonMode::Point, 1.0),
-        state::RasterMethod::Line(w) => (vk::PolygonMode::Line, w as f32),
-        state::RasterMethod::Fill    => (vk::PolygonMode::Fill, 1.0),
+        state::RasterMethod::Point   => vk::PolygonMode::Point,
+        state::RasterMethod::Line(_) => vk::PolygonMode::Line,
+        state::RasterMethod::Fill    => vk::PolygonMode::Fill,
     }
 }
 
@@ -641,7 +922,7 @@ pub fn map_stencil_op(op: state::StencilOp) -> vk::StencilOp {
     }
 }
 
-pub fn map_stencil_side(side: &state::StencilSide) -> vk::StencilOpState {
+pub fn map_stencil_side(side: &state::StencilSide, reference: target::Stencil) -> vk::StencilOpState {
     vk::StencilOpState {
         fail_op: map_stencil_op(side.op_fail),
         pass_op: map_stencil_op(side.op_pass),
@@ -649,7 +930,7 @@ pub fn map_stencil_side(side: &state::StencilSide) -> vk::StencilOpState {
         compare_op: map_comparison(side.fun),
         compare_mask: side.mask_read as u32,
         write_mask: side.mask_write as u32,
-        reference: 0,
+        reference: reference as u32,
     }
 }
 
@@ -690,3 +971,54 @@ pub fn map_blend_op(equation: state::Equation) -> vk::BlendOp {
         Max => vk::BlendOp::Max,
     }
 }
+
+pub fn map_logic_op(op: pso::LogicOp) -> vk::LogicOp {
+    use core::pso::LogicOp::*;
+    match op {
+        Clear => vk::LogicOp::Clear,
+        And => vk::LogicOp::And,
+        AndReverse => vk::LogicOp::AndReverse,
+        AndInverted => vk::LogicOp::AndInverted,
+        Copy => vk::LogicOp::Copy,
+        CopyInverted => vk::LogicOp::CopyInverted,
+        NoOp => vk::LogicOp::NoOp,
+        Xor => vk::LogicOp::Xor,
+        Nor => vk::LogicOp::Nor,
+        Or => vk::LogicOp::Or,
+        OrReverse => vk::LogicOp::OrReverse,
+        OrInverted => vk::LogicOp::OrInverted,
+        Equivalent => vk::LogicOp::Equivalent,
+        Invert => vk::LogicOp::Invert,
+        Nand => vk::LogicOp::Nand,
+        Set => vk::LogicOp::Set,
+    }
+}
+
+pub fn map_queue_families(families: &Option<Range<QueueFamilyId>>) -> (u32, u32) {
+    match *families {
+        Some(ref families) => (families.start.0 as u32, families.end.0 as u32),
+        None => (vk::VK_QUEUE_FAMILY_IGNORED, vk::VK_QUEUE_FAMILY_IGNORED),
+    }
+}
+
+pub fn map_specialization(
+    specialization: &[pso::Specialization],
+) -> (Vec<vk::SpecializationMapEntry>, Vec<u8>) {
+    let mut entries = Vec::with_capacity(specialization.len());
+    let mut data = Vec::new();
+    for spec in specialization {
+        let bits = spec.value.to_bits();
+        let size = spec.value.size();
+        entries.push(vk::SpecializationMapEntry {
+            constant_id: spec.id,
+            offset: data.len() as _,
+            size,
+        });
+        match size {
+            4 => data.extend_from_slice(&unsafe { mem::transmute::<_, [u8; 4]>(bits as u32) }),
+            8 => data.extend_from_slice(&unsafe { mem::transmute::<_, [u8; 8]>(bits) }),
+            _ => unreachable!(),
+        }
+    }
+    (entries, data)
+}
@@ -1,16 +1,17 @@
 use ash::vk;
-use ash::version::DeviceV1_0;
+use ash::version::{DeviceV1_0, InstanceV1_0};
 use core::{buffer, device as d, format, image, mapping, pass, pso};
-use core::{Features, Limits, MemoryType};
+use core::{Features, Limits, MemoryType, Primitive};
 use core::memory::Requirements;
 use native as n;
 use smallvec::SmallVec;
-use std::{mem, ptr};
+use std::{cmp, mem, ptr};
 use std::ffi::CString;
 use std::ops::Range;
 
 use {Backend as B, Device};
 use {conv, memory};
+use core::memory::SharingMode;
 
 
 #[derive(Debug)]
@@ -19,6 +20,43 @@ pub struct UnboundBuffer(n::Buffer);
 #[derive(Debug)]
 pub struct UnboundImage(n::Image);
 
+/// `VkSamplerReductionModeCreateInfoEXT` of `VK_EXT_sampler_filter_minmax`, not exposed by ash.
+#[repr(C)]
+struct SamplerReductionModeCreateInfo {
+    s_type: u32,
+    p_next: *const vk::types::c_void,
+    reduction_mode: u32,
+}
+
+const STRUCTURE_TYPE_SAMPLER_REDUCTION_MODE_CREATE_INFO: u32 = 1000130001;
+
+/// Vulkan specialization info along with the storage it points to.
+struct Specialization {
+    info: Box<vk::SpecializationInfo>,
+    _entries: Vec<vk::SpecializationMapEntry>,
+    _data: Vec<u8>,
+}
+
+impl Specialization {
+    fn new(specialization: &[pso::Specialization]) -> Option<Self> {
+        if specialization.is_empty() {
+            return None;
+        }
+        let (entries, data) = conv::map_specialization(specialization);
+        let info = Box::new(vk::SpecializationInfo {
+            map_entry_count: entries.len() as _,
+            p_map_entries: entries.as_ptr(),
+            data_size: data.len(),
+            p_data: data.as_ptr() as *const _,
+        });
+        Some(Specialization { info, _entries: entries, _data: data })
+    }
+
+    fn as_ptr(&self) -> *const vk::SpecializationInfo {
+        &*self.info
+    }
+}
+
 impl Device {
     #[cfg(feature = "glsl-to-spirv")]
     pub fn create_shader_module_from_glsl(
@@ -48,12 +86,239 @@ impl Device {
             Err(string) => Err(d::ShaderError::CompilationFailed(string)),
         }
     }
+
+    /// Native sharing mode and the queue family indices it applies to.
+    fn map_sharing_mode(&self, sharing: SharingMode) -> (vk::SharingMode, &[u32]) {
+        match sharing {
+            // Concurrent sharing requires at least two distinct families.
+            SharingMode::Concurrent if self.queue_families.len() > 1 =>
+                (vk::SharingMode::Concurrent, &self.queue_families[..]),
+            _ => (vk::SharingMode::Exclusive, &[]),
+        }
+    }
+
+    fn create_buffer_raw(
+        &self, size: u64, usage: buffer::Usage, sharing: SharingMode, flags: vk::BufferCreateFlags
+    ) -> n::Buffer {
+        let (sharing_mode, families) = self.map_sharing_mode(sharing);
+        let info = vk::BufferCreateInfo {
+            s_type: vk::StructureType::BufferCreateInfo,
+            p_next: ptr::null(),
+            flags,
+            size,
+            usage: conv::map_buffer_usage(usage),
+            sharing_mode,
+            queue_family_index_count: families.len() as u32,
+            p_queue_family_indices: families.as_ptr(),
+        };
+
+        let buffer = unsafe {
+            self.raw.0.create_buffer(&info, None)
+                .expect("Error on buffer creation") // TODO: error handling
+        };
+
+        n::Buffer {
+            raw: buffer,
+            memory: vk::DeviceMemory::null(),
+            memory_size: 0,
+            offset: 0,
+            ptr: ptr::null_mut(),
+        }
+    }
+
+    fn create_image_raw(
+        &self,
+        kind: image::Kind,
+        mip_levels: image::Level,
+        format: format::Format,
+        tiling: image::Tiling,
+        usage: image::Usage,
+        sharing: SharingMode,
+        mut flags: vk::ImageCreateFlags,
+    ) -> Result<n::Image, image::CreationError> {
+        use core::image::Kind::*;
+
+        flags |= conv::map_image_create_flags(kind, usage);
+
+        let (image_type, extent, array_layers, aa_mode) = match kind {
+            D1(width) => (
+                vk::ImageType::Type1d,
+                vk::Extent3D { width: width as u32, height: 1, depth: 1 },
+                1,
+                image::AaMode::Single,
+            ),
+            D1Array(width, layers) => (
+                vk::ImageType::Type1d,
+                vk::Extent3D { width: width as u32, height: 1, depth: 1 },
+                layers,
+                image::AaMode::Single,
+            ),
+            D2(width, height, aa_mode) => (
+                vk::ImageType::Type2d,
+                vk::Extent3D { width: width as u32, height: height as u32, depth: 1 },
+                1,
+                aa_mode,
+            ),
+            D2Array(width, height, layers, aa_mode) => (
+                vk::ImageType::Type2d,
+                vk::Extent3D { width: width as u32, height: height as u32, depth: 1 },
+                layers,
+                aa_mode,
+            ),
+            D3(width, height, depth) => (
+                vk::ImageType::Type3d,
+                vk::Extent3D { width: width as u32, height: height as u32, depth: depth as u32 },
+                1,
+                image::AaMode::Single,
+            ),
+            Cube(size) => (
+                vk::ImageType::Type2d,
+                vk::Extent3D { width: size as u32, height: size as u32, depth: 1 },
+                6,
+                image::AaMode::Single,
+            ),
+            CubeArray(size, layers) => (
+                vk::ImageType::Type2d,
+                vk::Extent3D { width: size as u32, height: size as u32, depth: 1 },
+                6 * layers,
+                image::AaMode::Single,
+            ),
+        };
+
+        let block = format.0.describe_block();
+        let samples = match aa_mode {
+            image::AaMode::Single => vk::SAMPLE_COUNT_1_BIT,
+            _ => unimplemented!(),
+        };
+
+        let (sharing_mode, families) = self.map_sharing_mode(sharing);
+        let info = vk::ImageCreateInfo {
+            s_type: vk::StructureType::ImageCreateInfo,
+            p_next: ptr::null(),
+            flags,
+            image_type,
+            format: conv::map_format(format.0, format.1).unwrap(), // TODO
+            extent: extent.clone(),
+            mip_levels: mip_levels as u32,
+            array_layers: array_layers as u32,
+            samples,
+            tiling: conv::map_tiling(tiling),
+            usage: conv::map_image_usage(usage),
+            sharing_mode,
+            queue_family_index_count: families.len() as u32,
+            p_queue_family_indices: families.as_ptr(),
+            // Keep the contents written by the host before the first transition.
+            initial_layout: match tiling {
+                image::Tiling::Optimal => vk::ImageLayout::Undefined,
+                image::Tiling::Linear => vk::ImageLayout::Preinitialized,
+            },
+        };
+
+        let raw = unsafe {
+            self.raw.0.create_image(&info, None)
+                .expect("Error on image creation") // TODO: error handling
+        };
+
+        Ok(n::Image { raw, kind, format, usage, block, extent })
+    }
+
+    /// Convert memory ranges into native mapped ranges, extending them
+    /// to the non-coherent atom size.
+    fn map_memory_ranges(
+        &self, ranges: &[(&n::Memory, Range<u64>)]
+    ) -> SmallVec<[vk::MappedMemoryRange; 4]> {
+        ranges
+            .iter()
+            .map(|&(memory, ref range)| self.map_memory_range(memory.inner, memory.size, range))
+            .collect()
+    }
+
+    fn map_memory_range(
+        &self, memory: vk::DeviceMemory, memory_size: u64, range: &Range<u64>
+    ) -> vk::MappedMemoryRange {
+        let atom = self.limits.non_coherent_atom_size as u64;
+        let start = range.start - range.start % atom;
+        let end = cmp::min(memory_size, (range.end + atom - 1) / atom * atom);
+        vk::MappedMemoryRange {
+            s_type: vk::StructureType::MappedMemoryRange,
+            p_next: ptr::null(),
+            memory,
+            offset: start,
+            size: end - start,
+        }
+    }
 }
 
 impl d::Device<B> for Device {
     fn get_features(&self) -> &Features { &self.features }
     fn get_limits(&self) -> &Limits { &self.limits }
 
+    fn format_properties(&self, format: format::Format) -> format::Properties {
+        let vk_format = match conv::map_format(format.0, format.1) {
+            Some(vk_format) => vk_format,
+            None => return format::Properties::empty(),
+        };
+        let properties = unsafe {
+            let mut properties = mem::zeroed();
+            self.instance.0.fp_v1_0().get_physical_device_format_properties(
+                self.physical_device,
+                vk_format,
+                &mut properties,
+            );
+            properties
+        };
+
+        format::Properties {
+            linear_tiling: conv::map_image_features(properties.linear_tiling_features),
+            optimal_tiling: conv::map_image_features(properties.optimal_tiling_features),
+            buffer_features: conv::map_buffer_features(properties.buffer_features),
+        }
+    }
+
+    fn image_format_properties(
+        &self, kind: image::Kind, format: format::Format, tiling: image::Tiling, usage: image::Usage,
+    ) -> Option<image::FormatProperties> {
+        use core::image::Kind::*;
+
+        let vk_format = conv::map_format(format.0, format.1)?;
+        let image_type = match kind {
+            D1(..) | D1Array(..) => vk::ImageType::Type1d,
+            D2(..) | D2Array(..) | Cube(..) | CubeArray(..) => vk::ImageType::Type2d,
+            D3(..) => vk::ImageType::Type3d,
+        };
+        // Matches the flags used by `create_image_raw`.
+        let flags = conv::map_image_create_flags(kind, usage);
+
+        let mut properties: vk::ImageFormatProperties = unsafe { mem::zeroed() };
+        let result = unsafe {
+            self.instance.0.fp_v1_0().get_physical_device_image_format_properties(
+                self.physical_device,
+                vk_format,
+                image_type,
+                conv::map_tiling(tiling),
+                conv::map_image_usage(usage),
+                flags,
+                &mut properties,
+            )
+        };
+
+        match result {
+            vk::Result::Success => Some(image::FormatProperties {
+                max_extent: d::Extent {
+                    width: properties.max_extent.width,
+                    height: properties.max_extent.height,
+                    depth: properties.max_extent.depth,
+                },
+                max_levels: cmp::min(properties.max_mip_levels, image::Level::max_value() as u32) as _,
+                max_layers: cmp::min(properties.max_array_layers, image::Layer::max_value() as u32) as _,
+                sample_count_mask: properties.sample_counts.flags() as _,
+                max_resource_size: properties.max_resource_size,
+            }),
+            vk::Result::ErrorFormatNotSupported => None,
+            _ => panic!("Unexpected error querying image format properties: {:?}", result),
+        }
+    }
+
     fn allocate_memory(&mut self, memory_type: &MemoryType, size: u64) -> Result<n::Memory, d::OutOfMemory> {
         let info = vk::MemoryAllocateInfo {
             s_type: vk::StructureType::MemoryAllocateInfo,
@@ -79,7 +344,7 @@ impl d::Device<B> for Device {
             ptr::null_mut()
         } as *mut _;
 
-        Ok(n::Memory { inner: memory, ptr })
+        Ok(n::Memory { inner: memory, ptr, size })
     }
 
     fn create_render_pass(&mut self, attachments: &[pass::Attachment],
@@ -92,9 +357,16 @@ impl d::Device<B> for Device {
             }
         };
 
+        let color_formats = subpasses.iter().map(|subpass| {
+            subpass.colors.iter()
+                .map(|&(id, _)| attachments[id].format)
+                .collect()
+        }).collect();
+
         let attachments = attachments.iter().map(|attachment| {
             vk::AttachmentDescription {
-                flags: vk::AttachmentDescriptionFlags::empty(), // TODO: may even alias!
+                // Attachments of a render pass are not allowed to alias each other
+                flags: vk::AttachmentDescriptionFlags::empty(),
                 format: conv::map_format(attachment.format.0, attachment.format.1).unwrap(), // TODO: error handling
                 samples: vk::SAMPLE_COUNT_1_BIT, // TODO: multisampling
                 load_op: conv::map_attachment_load_op(attachment.ops.load),
@@ -181,7 +453,7 @@ impl d::Device<B> for Device {
                 .expect("Error on render pass creation") // TODO: handle this better
         };
 
-        n::RenderPass { raw: renderpass }
+        n::RenderPass { raw: renderpass, color_formats }
     }
 
     fn create_pipeline_layout(&mut self, sets: &[&n::DescriptorSetLayout]) -> n::PipelineLayout {
@@ -206,7 +478,10 @@ impl d::Device<B> for Device {
                 .expect("Error on pipeline signature creation") // TODO: handle this better
         };
 
-        n::PipelineLayout { raw }
+        n::PipelineLayout {
+            raw,
+            set_bindings: sets.iter().map(|set| set.bindings.clone()).collect(),
+        }
     }
 
     fn create_graphics_pipelines<'a>(
@@ -229,13 +504,24 @@ impl d::Device<B> for Device {
         let mut info_color_blend_states    = Vec::with_capacity(descs.len());
         let mut info_dynamic_states        = Vec::with_capacity(descs.len());
         let mut color_attachments          = Vec::with_capacity(descs.len());
+        let mut viewports                  = Vec::with_capacity(descs.len());
+        let mut scissors                   = Vec::with_capacity(descs.len());
+        let mut dynamic_states             = Vec::with_capacity(descs.len());
 
-        let dynamic_states = [vk::DynamicState::Viewport, vk::DynamicState::Scissor];
         let mut c_strings = Vec::new(); // hold the C strings temporarily
+        let mut specializations = Vec::new(); // hold the specialization data temporarily
         let mut make_stage = |stage, source: pso::EntryPoint<'a, B>| {
             let string = CString::new(source.entry).unwrap();
             let p_name = string.as_ptr();
             c_strings.push(string);
+            let p_specialization_info = match Specialization::new(source.specialization) {
+                Some(specialization) => {
+                    let ptr = specialization.as_ptr();
+                    specializations.push(specialization);
+                    ptr
+                }
+                None => ptr::null(),
+            };
             vk::PipelineShaderStageCreateInfo {
                 s_type: vk::StructureType::PipelineShaderStageCreateInfo,
                 p_next: ptr::null(),
@@ -243,11 +529,22 @@ impl d::Device<B> for Device {
                 stage,
                 module: source.module.raw,
                 p_name,
-                p_specialization_info: ptr::null(),
+                p_specialization_info,
             }
         };
 
         let infos = descs.iter().map(|&(shaders, layout, subpass, desc)| {
+            desc.validate_vertex_input(&self.features, &self.limits)?;
+            desc.validate_baked_states(&self.features, &self.limits)?;
+            desc.validate_blender(&self.features)?;
+            desc.validate_tessellation(&shaders, &self.limits)?;
+            desc.validate_stream_output(&self.features)?;
+            let color_formats = subpass.main_pass.color_formats
+                .get(subpass.index)
+                .ok_or(pso::CreationError::InvalidSubpass(subpass.index))?;
+            desc.validate_shader_interface(self, &shaders, &layout.set_bindings, color_formats)?;
+            let baked_states = &desc.baked_states;
+
             let mut stages = Vec::new();
             // Vertex stage
             if true { //vertex shader is required
@@ -270,7 +567,7 @@ impl d::Device<B> for Device {
                 stages.push(make_stage(vk::SHADER_STAGE_TESSELLATION_CONTROL_BIT, entry));
             }
 
-            let (polygon_mode, line_width) = conv::map_polygon_mode(desc.rasterizer.polgyon_mode);
+            let polygon_mode = conv::map_polygon_mode(desc.rasterizer.polgyon_mode);
             info_stages.push(stages);
 
             {
@@ -279,6 +576,7 @@ impl d::Device<B> for Device {
                     vertex_bindings.push(vk::VertexInputBindingDescription {
                         binding: i as u32,
                         stride: vbuf.stride as u32,
+                        // Divisors above one are rejected by the validation.
                         input_rate: if vbuf.rate == 0 {
                             vk::VertexInputRate::Vertex
                         } else {
@@ -293,7 +591,8 @@ impl d::Device<B> for Device {
                         binding: attr.binding as u32,
                         format: match conv::map_format(attr.element.format.0, attr.element.format.1) {
                             Some(fm) => fm,
-                            None => return Err(pso::CreationError::Other),
+                            None => return Err(pso::InputError::UnsupportedFormat(
+                                attr.location, attr.element.format).into()),
                         },
                         offset: attr.element.offset as u32,
                     });
@@ -335,27 +634,43 @@ impl d::Device<B> for Device {
                 depth_bias_constant_factor: desc.rasterizer.depth_bias.map_or(0.0, |off| off.const_factor),
                 depth_bias_clamp: desc.rasterizer.depth_bias.map_or(0.0, |off| off.clamp),
                 depth_bias_slope_factor: desc.rasterizer.depth_bias.map_or(0.0, |off| off.slope_factor),
-                line_width: line_width,
+                line_width: desc.rasterizer.line_width.static_value().cloned().unwrap_or(1.0),
             });
 
             let is_tessellated = shaders.hull.is_some() && shaders.domain.is_some();
-            if is_tessellated {
+            if let Primitive::PatchList(patch_size) = desc.input_assembler.primitive {
                 info_tessellation_states.push(vk::PipelineTessellationStateCreateInfo {
                     s_type: vk::StructureType::PipelineTessellationStateCreateInfo,
                     p_next: ptr::null(),
                     flags: vk::PipelineTessellationStateCreateFlags::empty(),
-                    patch_control_points: 1 // TODO: 0 < control_points <= VkPhysicalDeviceLimits::maxTessellationPatchSize
+                    patch_control_points: patch_size as _,
                 });
             }
 
+            viewports.push(match baked_states.viewports {
+                pso::State::Static(ref vps) => vps.iter().map(conv::map_viewport).collect(),
+                pso::State::Dynamic => Vec::new(),
+            });
+            scissors.push(match baked_states.scissors {
+                pso::State::Static(ref rects) => rects.iter().map(conv::map_rect).collect(),
+                pso::State::Dynamic => Vec::new(),
+            });
             info_viewport_states.push(vk::PipelineViewportStateCreateInfo {
                 s_type: vk::StructureType::PipelineViewportStateCreateInfo,
                 p_next: ptr::null(),
                 flags: vk::PipelineViewportStateCreateFlags::empty(),
-                scissor_count: 1, // TODO:
-                p_scissors: ptr::null(), // dynamic
-                viewport_count: 1, // TODO:
-                p_viewports: ptr::null(), // dynamic
+                scissor_count: baked_states.viewport_count as u32,
+                p_scissors: if baked_states.scissors.is_dynamic() {
+                    ptr::null()
+                } else {
+                    scissors.last().unwrap().as_ptr()
+                },
+                viewport_count: baked_states.viewport_count as u32,
+                p_viewports: if baked_states.viewports.is_dynamic() {
+                    ptr::null()
+                } else {
+                    viewports.last().unwrap().as_ptr()
+                },
             });
 
             info_multisample_states.push(vk::PipelineMultisampleStateCreateInfo {
@@ -366,10 +681,11 @@ impl d::Device<B> for Device {
                 sample_shading_enable: vk::VK_FALSE, // TODO
                 min_sample_shading: 0.0,  // TODO
                 p_sample_mask: ptr::null(), // TODO
-                alpha_to_coverage_enable: vk::VK_FALSE, // TODO
+                alpha_to_coverage_enable: if desc.blender.alpha_coverage { vk::VK_TRUE } else { vk::VK_FALSE },
                 alpha_to_one_enable: vk::VK_FALSE, // TODO
             });
 
+            let stencil_reference = baked_states.stencil_reference.static_value().cloned().unwrap_or((0, 0));
             info_depth_stencil_states.push(vk::PipelineDepthStencilStateCreateInfo {
                 s_type: vk::StructureType::PipelineDepthStencilStateCreateInfo,
                 p_next: ptr::null(),
@@ -380,22 +696,25 @@ impl d::Device<B> for Device {
                     desc.depth_stencil { vk::VK_TRUE } else { vk::VK_FALSE },
                 depth_compare_op: if let Some((_, pso::DepthStencilInfo { depth: Some(s::Depth { fun, .. }), ..})) =
                     desc.depth_stencil { conv::map_comparison(fun) } else { vk::CompareOp::Never },
-                depth_bounds_test_enable: vk::VK_FALSE, // TODO
+                depth_bounds_test_enable: match desc.depth_stencil {
+                    Some((_, pso::DepthStencilInfo { depth_bounds: true, .. })) => vk::VK_TRUE,
+                    _ => vk::VK_FALSE,
+                },
                 stencil_test_enable: match desc.depth_stencil {
                     Some((_, pso::DepthStencilInfo { front: Some(_), .. })) |
                     Some((_, pso::DepthStencilInfo { back: Some(_), .. })) => vk::VK_TRUE,
                     _ => vk::VK_FALSE,
                 },
                 front: match desc.depth_stencil {
-                    Some((_, pso::DepthStencilInfo { front: Some(ref s), .. })) => conv::map_stencil_side(s),
+                    Some((_, pso::DepthStencilInfo { front: Some(ref s), .. })) => conv::map_stencil_side(s, stencil_reference.0),
                     _ => unsafe { mem::zeroed() }, // TODO
                 },
                 back: match desc.depth_stencil {
-                    Some((_, pso::DepthStencilInfo { back: Some(ref s), .. })) => conv::map_stencil_side(s),
+                    Some((_, pso::DepthStencilInfo { back: Some(ref s), .. })) => conv::map_stencil_side(s, stencil_reference.1),
                     _ => unsafe { mem::zeroed() }, // TODO
                 },
-                min_depth_bounds: 0.0,
-                max_depth_bounds: 1.0,
+                min_depth_bounds: baked_states.depth_bounds.static_value().map_or(0.0, |range| range.start),
+                max_depth_bounds: baked_states.depth_bounds.static_value().map_or(1.0, |range| range.end),
             });
 
             // Build blend states for color attachments
@@ -435,19 +754,43 @@ impl d::Device<B> for Device {
                 s_type: vk::StructureType::PipelineColorBlendStateCreateInfo,
                 p_next: ptr::null(),
                 flags: vk::PipelineColorBlendStateCreateFlags::empty(),
-                logic_op_enable: vk::VK_FALSE, // TODO
-                logic_op: vk::LogicOp::Clear,
+                logic_op_enable: if desc.blender.logic_op.is_some() { vk::VK_TRUE } else { vk::VK_FALSE },
+                logic_op: desc.blender.logic_op.map_or(vk::LogicOp::Clear, conv::map_logic_op),
                 attachment_count: color_attachments.last().unwrap().len() as u32,
-                p_attachments: color_attachments.last().unwrap().as_ptr(), // TODO:
-                blend_constants: [0.0; 4], // TODO:
+                p_attachments: color_attachments.last().unwrap().as_ptr(),
+                blend_constants: baked_states.blend_constants.static_value().cloned().unwrap_or([0.0; 4]),
             });
 
+            {
+                let mut states = Vec::new();
+                if baked_states.viewports.is_dynamic() {
+                    states.push(vk::DynamicState::Viewport);
+                }
+                if baked_states.scissors.is_dynamic() {
+                    states.push(vk::DynamicState::Scissor);
+                }
+                if baked_states.blend_constants.is_dynamic() {
+                    states.push(vk::DynamicState::BlendConstants);
+                }
+                if baked_states.stencil_reference.is_dynamic() {
+                    states.push(vk::DynamicState::StencilReference);
+                }
+                if baked_states.depth_bounds.is_dynamic() {
+                    states.push(vk::DynamicState::DepthBounds);
+                }
+                if desc.rasterizer.line_width.is_dynamic() {
+                    states.push(vk::DynamicState::LineWidth);
+                }
+                dynamic_states.push(states);
+            }
+            let states = dynamic_states.last().unwrap();
+
             info_dynamic_states.push(vk::PipelineDynamicStateCreateInfo {
                 s_type: vk::StructureType::PipelineDynamicStateCreateInfo,
                 p_next: ptr::null(),
                 flags: vk::PipelineDynamicStateCreateFlags::empty(),
-                dynamic_state_count: dynamic_states.len() as u32,
-                p_dynamic_states: dynamic_states.as_ptr(),
+                dynamic_state_count: states.len() as u32,
+                p_dynamic_states: states.as_ptr(),
             });
 
             Ok(vk::GraphicsPipelineCreateInfo {
@@ -510,10 +853,19 @@ impl d::Device<B> for Device {
         descs: &[(pso::EntryPoint<'a, B>, &n::PipelineLayout)],
     ) -> Vec<Result<n::ComputePipeline, pso::CreationError>> {
         let mut c_strings = Vec::new(); // hold the C strings temporarily
+        let mut specializations = Vec::new(); // hold the specialization data temporarily
         let infos = descs.iter().map(|&(entry_point, layout)| {
             let string = CString::new(entry_point.entry).unwrap();
             let p_name = string.as_ptr();
             c_strings.push(string);
+            let p_specialization_info = match Specialization::new(entry_point.specialization) {
+                Some(specialization) => {
+                    let ptr = specialization.as_ptr();
+                    specializations.push(specialization);
+                    ptr
+                }
+                None => ptr::null(),
+            };
 
             let stage = vk::PipelineShaderStageCreateInfo {
                 s_type: vk::StructureType::PipelineShaderStageCreateInfo,
@@ -522,7 +874,7 @@ impl d::Device<B> for Device {
                 stage: vk::SHADER_STAGE_COMPUTE_BIT,
                 module: entry_point.module.raw,
                 p_name,
-                p_specialization_info: ptr::null(),
+                p_specialization_info,
             };
 
             Ok(vk::ComputePipelineCreateInfo {
@@ -615,43 +967,78 @@ impl d::Device<B> for Device {
         };
 
         match module {
-            Ok(raw) => Ok(n::ShaderModule { raw }),
+            Ok(raw) => Ok(n::ShaderModule { raw, spirv: spirv_data.to_vec() }),
             Err(e) => {
                 error!("Shader module error {:?}", e);
-                Err(d::ShaderError::CompilationFailed(String::new())) // TODO
+                // Vulkan doesn't report a log for invalid modules.
+                Err(d::ShaderError::CompilationFailed(format!("{:?}", e)))
             }
         }
     }
 
-    fn create_sampler(&mut self, sampler_info: image::SamplerInfo) -> n::Sampler {
+    fn reflect_shader_module(&self, module: &n::ShaderModule) -> Result<pso::ShaderReflection, pso::ReflectError> {
+        pso::ShaderReflection::from_spirv(&module.spirv)
+    }
+
+    fn create_sampler(
+        &mut self, sampler_info: image::SamplerInfo
+    ) -> Result<n::Sampler, image::SamplerError> {
         use core::state::Comparison;
 
-        let (min_filter, mag_filter, mipmap_mode, aniso) = conv::map_filter(sampler_info.filter);
+        sampler_info.validate(&self.features)?;
+
+        let (min_lod, max_lod) = if !sampler_info.normalized {
+            // Unnormalized coordinates require both LOD clamps to be zero.
+            (0.0, 0.0)
+        } else {
+            let min_lod: f32 = sampler_info.lod_range.start.into();
+            match sampler_info.mip_filter {
+                Some(_) => (min_lod, sampler_info.lod_range.end.into()),
+                // Clamp to the base level, as suggested by the spec for emulating non-mipmapped filtering.
+                None => (min_lod, min_lod + 0.25),
+            }
+        };
+        let max_anisotropy = match sampler_info.anisotropic {
+            image::Anisotropic::On(max) if max > 1 => Some(max as f32),
+            _ => None,
+        };
+
+        // Only chained for non-default modes, which require the extension.
+        let reduction_info = SamplerReductionModeCreateInfo {
+            s_type: STRUCTURE_TYPE_SAMPLER_REDUCTION_MODE_CREATE_INFO,
+            p_next: ptr::null(),
+            reduction_mode: conv::map_reduction_mode(sampler_info.reduction),
+        };
+
         let info = vk::SamplerCreateInfo {
             s_type: vk::StructureType::SamplerCreateInfo,
-            p_next: ptr::null(),
+            p_next: if sampler_info.reduction != image::ReductionMode::WeightedAverage {
+                &reduction_info as *const _ as *const _
+            } else {
+                ptr::null()
+            },
             flags: vk::SamplerCreateFlags::empty(),
-            mag_filter,
-            min_filter,
-            mipmap_mode,
+            mag_filter: conv::map_filter(sampler_info.mag_filter),
+            min_filter: conv::map_filter(sampler_info.min_filter),
+            mipmap_mode: conv::map_mip_filter(sampler_info.mip_filter),
             address_mode_u: conv::map_wrap(sampler_info.wrap_mode.0),
             address_mode_v: conv::map_wrap(sampler_info.wrap_mode.1),
             address_mode_w: conv::map_wrap(sampler_info.wrap_mode.2),
             mip_lod_bias: sampler_info.lod_bias.into(),
-            anisotropy_enable: if aniso > 1.0 { vk::VK_TRUE } else { vk::VK_FALSE },
-            max_anisotropy: aniso,
+            anisotropy_enable: if max_anisotropy.is_some() { vk::VK_TRUE } else { vk::VK_FALSE },
+            max_anisotropy: max_anisotropy.unwrap_or(1.0),
             compare_enable: if sampler_info.comparison.is_some() { vk::VK_TRUE } else { vk::VK_FALSE },
             compare_op: conv::map_comparison(sampler_info.comparison.unwrap_or(Comparison::Never)),
-            min_lod: sampler_info.lod_range.start.into(),
-            max_lod: sampler_info.lod_range.end.into(),
+            min_lod,
+            max_lod,
             border_color: match conv::map_border_color(sampler_info.border) {
                 Some(bc) => bc,
                 None => {
-                    error!("Unsupported border color {:x}", sampler_info.border.0);
+                    error!("Unsupported border color {:?}", sampler_info.border);
                     vk::BorderColor::FloatTransparentBlack
                 }
             },
-            unnormalized_coordinates: vk::VK_FALSE,
+            unnormalized_coordinates: if sampler_info.normalized { vk::VK_FALSE } else { vk::VK_TRUE },
         };
 
         let sampler = unsafe {
@@ -659,33 +1046,14 @@ impl d::Device<B> for Device {
                         .expect("error on sampler creation")
         };
 
-        n::Sampler(sampler)
+        Ok(n::Sampler(sampler))
     }
 
     ///
-    fn create_buffer(&mut self, size: u64, _stride: u64, usage: buffer::Usage) -> Result<UnboundBuffer, buffer::CreationError> {
-        let info = vk::BufferCreateInfo {
-            s_type: vk::StructureType::BufferCreateInfo,
-            p_next: ptr::null(),
-            flags: vk::BufferCreateFlags::empty(), // TODO:
-            size,
-            usage: conv::map_buffer_usage(usage),
-            sharing_mode: vk::SharingMode::Exclusive, // TODO:
-            queue_family_index_count: 0,
-            p_queue_family_indices: ptr::null(),
-        };
-
-        let buffer = unsafe {
-            self.raw.0.create_buffer(&info, None)
-                .expect("Error on buffer creation") // TODO: error handling
-        };
-
-        Ok(UnboundBuffer(n::Buffer {
-            raw: buffer,
-            memory: vk::DeviceMemory::null(),
-            offset: 0,
-            ptr: ptr::null_mut(),
-        }))
+    fn create_buffer(
+        &mut self, size: u64, _stride: u64, usage: buffer::Usage, sharing: SharingMode,
+    ) -> Result<UnboundBuffer, buffer::CreationError> {
+        Ok(UnboundBuffer(self.create_buffer_raw(size, usage, sharing, vk::BufferCreateFlags::empty())))
     }
 
     fn get_buffer_requirements(&mut self, buffer: &UnboundBuffer) -> Requirements {
@@ -706,6 +1074,7 @@ impl d::Device<B> for Device {
         let buffer = n::Buffer {
             raw: buffer.0.raw,
             memory: memory.inner,
+            memory_size: memory.size,
             offset,
             ptr: unsafe { memory.ptr.offset(offset as _) }
         };
@@ -733,92 +1102,84 @@ impl d::Device<B> for Device {
         Ok(n::BufferView { raw: view })
     }
 
-    fn create_image(&mut self, kind: image::Kind, mip_levels: image::Level, format: format::Format, usage: image::Usage)
-         -> Result<UnboundImage, image::CreationError>
-    {
-        use core::image::Kind::*;
+    fn create_image(
+        &mut self, kind: image::Kind, mip_levels: image::Level, format: format::Format, tiling: image::Tiling,
+        usage: image::Usage, sharing: SharingMode,
+    ) -> Result<UnboundImage, image::CreationError> {
+        self.create_image_raw(kind, mip_levels, format, tiling, usage, sharing, vk::ImageCreateFlags::empty())
+            .map(UnboundImage)
+    }
 
-        let flags = match kind {
-            Cube(_) => vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT,
-            CubeArray(_, _) => vk::IMAGE_CREATE_CUBE_COMPATIBLE_BIT,
-            _ => vk::ImageCreateFlags::empty(),
-        };
+    fn create_sparse_buffer(
+        &mut self, size: u64, _stride: u64, usage: buffer::Usage, sharing: SharingMode,
+    ) -> Result<n::Buffer, buffer::CreationError> {
+        if !self.features.sparse_binding {
+            return Err(buffer::CreationError::Sparse);
+        }
+        let mut flags = vk::BUFFER_CREATE_SPARSE_BINDING_BIT;
+        if self.features.sparse_residency_buffer {
+            flags |= vk::BUFFER_CREATE_SPARSE_RESIDENCY_BIT;
+        }
+        Ok(self.create_buffer_raw(size, usage, sharing, flags))
+    }
 
-        let (image_type, extent, array_layers, aa_mode) = match kind {
-            D1(width) => (
-                vk::ImageType::Type1d,
-                vk::Extent3D { width: width as u32, height: 1, depth: 1 },
-                1,
-                image::AaMode::Single,
-            ),
-            D1Array(width, layers) => (
-                vk::ImageType::Type1d,
-                vk::Extent3D { width: width as u32, height: 1, depth: 1 },
-                layers,
-                image::AaMode::Single,
-            ),
-            D2(width, height, aa_mode) => (
-                vk::ImageType::Type2d,
-                vk::Extent3D { width: width as u32, height: height as u32, depth: 1 },
-                1,
-                aa_mode,
-            ),
-            D2Array(width, height, layers, aa_mode) => (
-                vk::ImageType::Type2d,
-                vk::Extent3D { width: width as u32, height: height as u32, depth: 1 },
-                layers,
-                aa_mode,
-            ),
-            D3(width, height, depth) => (
-                vk::ImageType::Type3d,
-                vk::Extent3D { width: width as u32, height: height as u32, depth: depth as u32 },
-                1,
-                image::AaMode::Single,
-            ),
-            Cube(size) => (
-                vk::ImageType::Type2d,
-                vk::Extent3D { width: size as u32, height: size as u32, depth: 1 },
-                6,
-                image::AaMode::Single,
-            ),
-            CubeArray(size, layers) => (
-                vk::ImageType::Type2d,
-                vk::Extent3D { width: size as u32, height: size as u32, depth: 1 },
-                6 * layers,
-                image::AaMode::Single,
-            ),
-        };
+    fn get_sparse_buffer_requirements(&mut self, buffer: &n::Buffer) -> Requirements {
+        let req = self.raw.0.get_buffer_memory_requirements(buffer.raw);
 
-        let bytes_per_texel = format.0.describe_bits().total / 8;
-        let samples = match aa_mode {
-            image::AaMode::Single => vk::SAMPLE_COUNT_1_BIT,
-            _ => unimplemented!(),
-        };
+        Requirements {
+            size: req.size,
+            alignment: req.alignment,
+            type_mask: req.memory_type_bits as _,
+        }
+    }
 
-        let info = vk::ImageCreateInfo {
-            s_type: vk::StructureType::ImageCreateInfo,
-            p_next: ptr::null(),
-            flags,
-            image_type,
-            format: conv::map_format(format.0, format.1).unwrap(), // TODO
-            extent: extent.clone(),
-            mip_levels: mip_levels as u32,
-            array_layers: array_layers as u32,
-            samples,
-            tiling: vk::ImageTiling::Optimal, // TODO: read back?
-            usage: conv::map_image_usage(usage),
-            sharing_mode: vk::SharingMode::Exclusive, // TODO:
-            queue_family_index_count: 0,
-            p_queue_family_indices: ptr::null(),
-            initial_layout: vk::ImageLayout::Undefined,
+    fn create_sparse_image(
+        &mut self, kind: image::Kind, mip_levels: image::Level, format: format::Format, usage: image::Usage, sharing: SharingMode,
+    ) -> Result<n::Image, image::CreationError> {
+        if !self.features.sparse_binding {
+            return Err(image::CreationError::Sparse);
+        }
+        let mut flags = vk::IMAGE_CREATE_SPARSE_BINDING_BIT;
+        if self.features.sparse_residency_image {
+            if let image::Kind::D2(..) = kind {
+                flags |= vk::IMAGE_CREATE_SPARSE_RESIDENCY_BIT;
+            }
+        }
+        self.create_image_raw(kind, mip_levels, format, image::Tiling::Optimal, usage, sharing, flags)
+    }
+
+    fn get_sparse_image_requirements(&mut self, image: &n::Image) -> (Requirements, d::Extent) {
+        let req = self.raw.0.get_image_memory_requirements(image.raw);
+        let requirements = Requirements {
+            size: req.size,
+            alignment: req.alignment,
+            type_mask: req.memory_type_bits as _,
         };
 
-        let raw = unsafe {
-            self.raw.0.create_image(&info, None)
-                .expect("Error on image creation") // TODO: error handling
+        let mut count = 0;
+        let mut sparse_reqs = Vec::new();
+        unsafe {
+            let fp = self.raw.0.fp_v1_0();
+            fp.get_image_sparse_memory_requirements(self.raw.0.handle(), image.raw, &mut count, ptr::null_mut());
+            sparse_reqs.reserve(count as usize);
+            fp.get_image_sparse_memory_requirements(self.raw.0.handle(), image.raw, &mut count, sparse_reqs.as_mut_ptr());
+            sparse_reqs.set_len(count as usize);
+        }
+
+        // Only non-resident images have no sparse requirements,
+        // these can only be bound as a whole in the opaque layout.
+        let granularity = sparse_reqs
+            .first()
+            .map(|req| req.format_properties.image_granularity.clone())
+            .unwrap_or(image.extent.clone());
+
+        let granularity = d::Extent {
+            width: granularity.width,
+            height: granularity.height,
+            depth: granularity.depth,
         };
 
-        Ok(UnboundImage(n::Image{ raw, bytes_per_texel, extent }))
+        (requirements, granularity)
     }
 
     fn get_image_requirements(&mut self, image: &UnboundImage) -> Requirements {
@@ -831,6 +1192,34 @@ impl d::Device<B> for Device {
         }
     }
 
+    fn get_image_subresource_footprint(
+        &mut self, image: &n::Image, aspect: image::AspectFlags, (level, layer): image::Subresource,
+    ) -> image::SubresourceFootprint {
+        debug_assert_eq!(aspect.bits().count_ones(), 1);
+        let subresource = vk::ImageSubresource {
+            aspect_mask: conv::map_image_aspects(aspect),
+            mip_level: level as _,
+            array_layer: layer as _,
+        };
+
+        let mut layout: vk::SubresourceLayout = unsafe { mem::zeroed() };
+        unsafe {
+            self.raw.0.fp_v1_0().get_image_subresource_layout(
+                self.raw.0.handle(),
+                image.raw,
+                &subresource,
+                &mut layout,
+            );
+        }
+
+        image::SubresourceFootprint {
+            slice: layout.offset .. layout.offset + layout.size,
+            row_pitch: layout.row_pitch,
+            array_pitch: layout.array_pitch,
+            depth_pitch: layout.depth_pitch,
+        }
+    }
+
     fn bind_image_memory(&mut self, memory: &n::Memory, offset: u64, image: UnboundImage) -> Result<n::Image, d::BindError> {
         // TODO: error handling
         // TODO: check required type
@@ -844,12 +1233,18 @@ impl d::Device<B> for Device {
     fn create_image_view(
         &mut self,
         image: &n::Image,
+        kind: image::ViewKind,
         format: format::Format,
         swizzle: format::Swizzle,
         range: image::SubresourceRange,
     ) -> Result<n::ImageView, image::ViewError> {
+        image.kind.check_view(image.format, kind, format, &range)?;
+        if format != image.format && !image.usage.contains(image::MUTABLE_FORMAT) {
+            return Err(image::ViewError::BadFormat);
+        }
+
         let subresource_range = vk::ImageSubresourceRange {
-            aspect_mask: vk::IMAGE_ASPECT_COLOR_BIT, //TODO
+            aspect_mask: conv::map_image_aspects(range.aspects),
             base_mip_level: range.levels.start as _,
             level_count: (range.levels.end - range.levels.start) as _,
             base_array_layer: range.layers.start as _,
@@ -859,10 +1254,10 @@ impl d::Device<B> for Device {
         let info = vk::ImageViewCreateInfo {
             s_type: vk::StructureType::ImageViewCreateInfo,
             p_next: ptr::null(),
-            flags: vk::ImageViewCreateFlags::empty(), // TODO
+            flags: vk::ImageViewCreateFlags::empty(),
             image: image.raw,
-            view_type: vk::ImageViewType::Type2d, // TODO
-            format: conv::map_format(format.0, format.1).unwrap(), // TODO
+            view_type: conv::map_view_kind(kind),
+            format: conv::map_format(format.0, format.1).ok_or(image::ViewError::BadFormat)?,
             components: conv::map_swizzle(swizzle),
             subresource_range,
         };
@@ -913,7 +1308,7 @@ impl d::Device<B> for Device {
     fn create_descriptor_set_layout(&mut self, bindings: &[pso::DescriptorSetLayoutBinding])-> n::DescriptorSetLayout {
         debug!("create_descriptor_set_layout {:?}", bindings);
 
-        let bindings = bindings.iter().map(|binding| {
+        let raw_bindings = bindings.iter().map(|binding| {
             vk::DescriptorSetLayoutBinding {
                 binding: binding.binding as u32,
                 descriptor_type: conv::map_descriptor_type(binding.ty),
@@ -927,8 +1322,8 @@ impl d::Device<B> for Device {
             s_type: vk::StructureType::DescriptorSetLayoutCreateInfo,
             p_next: ptr::null(),
             flags: vk::DescriptorSetLayoutCreateFlags::empty(),
-            binding_count: bindings.len() as u32,
-            p_bindings: bindings.as_ptr(),
+            binding_count: raw_bindings.len() as u32,
+            p_bindings: raw_bindings.as_ptr(),
         };
 
         let layout = unsafe {
@@ -937,6 +1332,7 @@ impl d::Device<B> for Device {
 
         n::DescriptorSetLayout {
             raw: layout,
+            bindings: bindings.to_vec(),
         }
     }
 
@@ -1049,39 +1445,65 @@ impl d::Device<B> for Device {
         }
     }
 
+    fn map_memory(&mut self, memory: &n::Memory, range: Range<u64>)
+        -> Result<*mut u8, mapping::Error>
+    {
+        // CPU visible memory is persistently mapped on allocation
+        if memory.ptr.is_null() {
+            return Err(mapping::Error::InvalidAccess);
+        }
+        if range.start > range.end || range.end > memory.size {
+            return Err(mapping::Error::OutOfBounds);
+        }
+        Ok(unsafe { memory.ptr.offset(range.start as _) })
+    }
+
+    fn flush_mapped_memory_ranges(
+        &mut self, ranges: &[(&n::Memory, Range<u64>)]
+    ) -> Result<(), d::OutOfMemory> {
+        let ranges = self.map_memory_ranges(ranges);
+        unsafe {
+            self.raw.0.flush_mapped_memory_ranges(&ranges)
+        }.map_err(|_| d::OutOfMemory)
+    }
+
+    fn invalidate_mapped_memory_ranges(
+        &mut self, ranges: &[(&n::Memory, Range<u64>)]
+    ) -> Result<(), d::OutOfMemory> {
+        let ranges = self.map_memory_ranges(ranges);
+        unsafe {
+            self.raw.0.invalidate_mapped_memory_ranges(&ranges)
+        }.map_err(|_| d::OutOfMemory)
+    }
+
+    fn unmap_memory(&mut self, _memory: &n::Memory) {
+        // Memory stays mapped until it's freed
+    }
+
     fn acquire_mapping_raw(&mut self, buf: &n::Buffer, read: Option<Range<u64>>)
         -> Result<*mut u8, mapping::Error>
     {
         if let Some(read) = read {
-            let range = vk::MappedMemoryRange {
-                s_type: vk::StructureType::MappedMemoryRange,
-                p_next: ptr::null(),
-                memory: buf.memory,
-                offset: buf.offset + read.start,
-                size: read.end - read.start,
-            };
+            let range = self.map_memory_range(
+                buf.memory, buf.memory_size, &(buf.offset + read.start .. buf.offset + read.end));
             unsafe {
                 self.raw.0.invalidate_mapped_memory_ranges(&[range])
-                    .expect("memory invalidation failed"); // TODO
-            }
+            }.map_err(|_| mapping::Error::OutOfMemory)?;
         }
         Ok(buf.ptr)
     }
 
-    fn release_mapping_raw(&mut self, buf: &n::Buffer, wrote: Option<Range<u64>>) {
+    fn release_mapping_raw(&mut self, buf: &n::Buffer, wrote: Option<Range<u64>>)
+        -> Result<(), mapping::Error>
+    {
         if let Some(wrote) = wrote {
-            let range = vk::MappedMemoryRange {
-                s_type: vk::StructureType::MappedMemoryRange,
-                p_next: ptr::null(),
-                memory: buf.memory,
-                offset: buf.offset + wrote.start,
-                size: wrote.end - wrote.start,
-            };
+            let range = self.map_memory_range(
+                buf.memory, buf.memory_size, &(buf.offset + wrote.start .. buf.offset + wrote.end));
             unsafe {
                 self.raw.0.flush_mapped_memory_ranges(&[range])
-                    .expect("memory flush failed"); // TODO
-            }
+            }.map_err(|_| mapping::Error::OutOfMemory)?;
         }
+        Ok(())
     }
 
     fn create_semaphore(&mut self) -> n::Semaphore {
@@ -1141,6 +1563,45 @@ impl d::Device<B> for Device {
         }
     }
 
+    fn create_event(&mut self) -> n::Event {
+        let info = vk::EventCreateInfo {
+            s_type: vk::StructureType::EventCreateInfo,
+            p_next: ptr::null(),
+            flags: vk::EventCreateFlags::empty(),
+        };
+
+        let mut event = vk::Event::null();
+        let result = unsafe {
+            self.raw.0.fp_v1_0().create_event(self.raw.0.handle(), &info, ptr::null(), &mut event)
+        };
+        assert_eq!(vk::Result::Success, result, "error on event creation");
+
+        n::Event(event)
+    }
+
+    fn set_event(&mut self, event: &n::Event) {
+        assert_eq!(vk::Result::Success, unsafe {
+            self.raw.0.fp_v1_0().set_event(self.raw.0.handle(), event.0)
+        });
+    }
+
+    fn reset_event(&mut self, event: &n::Event) {
+        assert_eq!(vk::Result::Success, unsafe {
+            self.raw.0.fp_v1_0().reset_event(self.raw.0.handle(), event.0)
+        });
+    }
+
+    fn get_event_status(&mut self, event: &n::Event) -> bool {
+        let result = unsafe {
+            self.raw.0.fp_v1_0().get_event_status(self.raw.0.handle(), event.0)
+        };
+        match result {
+            vk::Result::EventSet => true,
+            vk::Result::EventReset => false,
+            _ => panic!("Unexpected event status {:?}", result),
+        }
+    }
+
     fn free_memory(&mut self, memory: n::Memory) {
         if !memory.ptr.is_null() {
             unsafe { self.raw.0.unmap_memory(memory.inner) }
@@ -1207,4 +1668,10 @@ impl d::Device<B> for Device {
     fn destroy_semaphore(&mut self, semaphore: n::Semaphore) {
         unsafe { self.raw.0.destroy_semaphore(semaphore.0, None); }
     }
+
+    fn destroy_event(&mut self, event: n::Event) {
+        unsafe {
+            self.raw.0.fp_v1_0().destroy_event(self.raw.0.handle(), event.0, ptr::null());
+        }
+    }
 }
@@ -46,6 +46,8 @@ const EXTENSIONS: &'static [&'static str] = &[
 const DEVICE_EXTENSIONS: &'static [&'static str] = &[
     vk::VK_KHR_SWAPCHAIN_EXTENSION_NAME,
 ];
+// Optional device extension for the minimum and maximum sampler reduction modes.
+const SAMPLER_FILTER_MINMAX_EXTENSION: &'static str = "VK_EXT_sampler_filter_minmax";
 const SURFACE_EXTENSIONS: &'static [&'static str] = &[
     vk::VK_KHR_SURFACE_EXTENSION_NAME,
 
@@ -318,6 +320,9 @@ impl core::QueueFamily for QueueFamily {
     fn num_queues(&self) -> u32 {
         self.queue_count
     }
+    fn id(&self) -> core::QueueFamilyId {
+        core::QueueFamilyId(self.family_index as _)
+    }
 }
 
 /// Create associated command queues for a specific queue type
@@ -380,11 +385,22 @@ impl core::Adapter<Backend> for Adapter {
                 }
             }).collect::<Vec<_>>();
 
+        let physical_features = self.instance.0.get_physical_device_features(self.handle);
+        let sampler_filter_minmax = self.instance.0
+            .enumerate_device_extension_properties(self.handle)
+            .map(|extensions| extensions.iter().any(|ext| unsafe {
+                CStr::from_ptr(ext.extension_name.as_ptr()).to_bytes() ==
+                    SAMPLER_FILTER_MINMAX_EXTENSION.as_bytes()
+            }))
+            .unwrap_or(false);
+
         // Create device
         let device_raw = {
             let cstrings = DEVICE_EXTENSIONS
                 .iter()
-                .map(|&s| CString::new(s).unwrap())
+                .cloned()
+                .chain(if sampler_filter_minmax { Some(SAMPLER_FILTER_MINMAX_EXTENSION) } else { None })
+                .map(|s| CString::new(s).unwrap())
                 .collect::<Vec<_>>();
 
             let str_pointers = cstrings
@@ -392,7 +408,19 @@ impl core::Adapter<Backend> for Adapter {
                 .map(|s| s.as_ptr())
                 .collect::<Vec<_>>();
 
-            let features = unsafe { mem::zeroed() };
+            // Only enable the features exposed by the HAL
+            let mut features: vk::PhysicalDeviceFeatures = unsafe { mem::zeroed() };
+            features.sparse_binding = physical_features.sparse_binding;
+            features.sparse_residency_buffer = physical_features.sparse_residency_buffer;
+            features.sparse_residency_image2_d = physical_features.sparse_residency_image2_d;
+            features.texture_compression_bc = physical_features.texture_compression_bc;
+            features.texture_compression_etc2 = physical_features.texture_compression_etc2;
+            features.texture_compression_astc_ldr = physical_features.texture_compression_astc_ldr;
+            features.depth_bounds = physical_features.depth_bounds;
+            features.wide_lines = physical_features.wide_lines;
+            features.independent_blend = physical_features.independent_blend;
+            features.logic_op = physical_features.logic_op;
+            features.sampler_anisotropy = physical_features.sampler_anisotropy;
             let info = vk::DeviceCreateInfo {
                 s_type: vk::StructureType::DeviceCreateInfo,
                 p_next: ptr::null(),
@@ -411,12 +439,22 @@ impl core::Adapter<Backend> for Adapter {
                     .expect("Error on device creation")
             }
         };
+        let mut queue_families = queue_descs
+            .iter()
+            .map(|&(family, _, _)| family.family_index)
+            .collect::<Vec<_>>();
+        queue_families.sort();
+        queue_families.dedup();
+
         let limits = &self.properties.limits;
         let max_group_count = limits.max_compute_work_group_count;
         let max_group_size = limits.max_compute_work_group_size;
 
         let device = Device {
             raw: Arc::new(RawDevice(device_raw)),
+            instance: self.instance.clone(),
+            physical_device: self.handle,
+            queue_families,
             features: Features { //TODO
                 indirect_execution: limits.max_draw_indirect_count != 0,
                 draw_instanced: false,
@@ -430,10 +468,26 @@ impl core::Adapter<Backend> for Adapter {
                 srgb_color: false,
                 constant_buffer: false,
                 unordered_access_view: false,
-                separate_blending_slots: false,
+                separate_blending_slots: physical_features.independent_blend != 0,
                 copy_buffer: false,
-                sampler_anisotropy: false,
+                sampler_anisotropy: physical_features.sampler_anisotropy != 0,
                 sampler_border_color: false,
+                sampler_reduction: sampler_filter_minmax,
+                sampler_unnormalized: true,
+                persistent_mapping: true,
+                sparse_binding: physical_features.sparse_binding != 0,
+                sparse_residency_buffer: physical_features.sparse_residency_buffer != 0,
+                sparse_residency_image: physical_features.sparse_residency_image2_d != 0,
+                format_bc: physical_features.texture_compression_bc != 0,
+                format_etc2: physical_features.texture_compression_etc2 != 0,
+                format_astc_ldr: physical_features.texture_compression_astc_ldr != 0,
+                depth_bounds: physical_features.depth_bounds != 0,
+                wide_lines: physical_features.wide_lines != 0,
+                logic_op: physical_features.logic_op != 0,
+                stream_output: false,
+                draw_auto: false,
+                events: true,
+                blit_1d_3d: true,
                 sampler_lod_bias: false,
                 sampler_objects: false,
             },
@@ -443,9 +497,14 @@ impl core::Adapter<Backend> for Adapter {
                 max_viewports: limits.max_viewports as _,
                 max_compute_group_count: [max_group_count[0] as _, max_group_count[1] as _, max_group_count[2] as _],
                 max_compute_group_size: [max_group_size[0] as _, max_group_size[1] as _, max_group_size[2] as _],
+                max_vertex_input_attributes: limits.max_vertex_input_attributes as _,
+                max_vertex_input_bindings: limits.max_vertex_input_bindings as _,
+                max_vertex_input_attribute_offset: limits.max_vertex_input_attribute_offset as _,
+                max_vertex_input_binding_stride: limits.max_vertex_input_binding_stride as _,
                 min_buffer_copy_offset_alignment: limits.optimal_buffer_copy_offset_alignment as _,
                 min_buffer_copy_pitch_alignment: limits.optimal_buffer_copy_row_pitch_alignment as _,
                 min_uniform_buffer_offset_alignment: limits.min_uniform_buffer_offset_alignment as _,
+                non_coherent_atom_size: limits.non_coherent_atom_size as _,
             },
         };
 
@@ -581,11 +640,122 @@ impl core::RawCommandQueue<Backend> for CommandQueue {
         let result = self.device.0.queue_submit(*self.raw, &[info], fence_raw);
         assert_eq!(Ok(()), result);
     }
+
+    unsafe fn bind_sparse(&mut self,
+        submission: core::queue::RawSparseSubmission<Backend>,
+        fence: Option<&native::Fence>,
+    ) -> core::SubmissionResult<()> {
+        let map_bind = |bind: &core::queue::SparseBind<Backend>| vk::SparseMemoryBind {
+            resource_offset: bind.range.start,
+            size: bind.range.end - bind.range.start,
+            memory: bind.memory.map_or(vk::DeviceMemory::null(), |(memory, _)| memory.inner),
+            memory_offset: bind.memory.map_or(0, |(_, offset)| offset),
+            flags: vk::SparseMemoryBindFlags::empty(),
+        };
+
+        let buffer_binds = submission.buffer_binds
+            .iter()
+            .map(|&(_, binds)| binds.iter().map(&map_bind).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let buffer_infos = submission.buffer_binds
+            .iter()
+            .zip(buffer_binds.iter())
+            .map(|(&(buffer, _), binds)| vk::SparseBufferMemoryBindInfo {
+                buffer: buffer.raw,
+                bind_count: binds.len() as u32,
+                p_binds: binds.as_ptr(),
+            })
+            .collect::<Vec<_>>();
+
+        let image_opaque_binds = submission.image_opaque_binds
+            .iter()
+            .map(|&(_, binds)| binds.iter().map(&map_bind).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let image_opaque_infos = submission.image_opaque_binds
+            .iter()
+            .zip(image_opaque_binds.iter())
+            .map(|(&(image, _), binds)| vk::SparseImageOpaqueMemoryBindInfo {
+                image: image.raw,
+                bind_count: binds.len() as u32,
+                p_binds: binds.as_ptr(),
+            })
+            .collect::<Vec<_>>();
+
+        let image_binds = submission.image_binds
+            .iter()
+            .map(|&(_, binds)| {
+                binds
+                    .iter()
+                    .map(|bind| vk::SparseImageMemoryBind {
+                        subresource: vk::ImageSubresource {
+                            aspect_mask: conv::map_image_aspects(bind.aspects),
+                            mip_level: bind.level as _,
+                            array_layer: bind.layer as _,
+                        },
+                        offset: conv::map_offset(bind.offset),
+                        extent: conv::map_extent(bind.extent),
+                        memory: bind.memory.map_or(vk::DeviceMemory::null(), |(memory, _)| memory.inner),
+                        memory_offset: bind.memory.map_or(0, |(_, offset)| offset),
+                        flags: vk::SparseMemoryBindFlags::empty(),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let image_infos = submission.image_binds
+            .iter()
+            .zip(image_binds.iter())
+            .map(|(&(image, _), binds)| vk::SparseImageMemoryBindInfo {
+                image: image.raw,
+                bind_count: binds.len() as u32,
+                p_binds: binds.as_ptr(),
+            })
+            .collect::<Vec<_>>();
+
+        let waits = submission.wait_semaphores
+            .iter()
+            .map(|semaphore| semaphore.0)
+            .collect::<Vec<_>>();
+        let signals = submission.signal_semaphores
+            .iter()
+            .map(|semaphore| semaphore.0)
+            .collect::<Vec<_>>();
+
+        let info = vk::BindSparseInfo {
+            s_type: vk::StructureType::BindSparseInfo,
+            p_next: ptr::null(),
+            wait_semaphore_count: waits.len() as u32,
+            p_wait_semaphores: waits.as_ptr(),
+            buffer_bind_count: buffer_infos.len() as u32,
+            p_buffer_binds: buffer_infos.as_ptr(),
+            image_opaque_bind_count: image_opaque_infos.len() as u32,
+            p_image_opaque_binds: image_opaque_infos.as_ptr(),
+            image_bind_count: image_infos.len() as u32,
+            p_image_binds: image_infos.as_ptr(),
+            signal_semaphore_count: signals.len() as u32,
+            p_signal_semaphores: signals.as_ptr(),
+        };
+
+        let fence_raw = fence
+            .map(|fence| fence.0)
+            .unwrap_or(vk::Fence::null());
+
+        match self.device.0.fp_v1_0().queue_bind_sparse(*self.raw, 1, &info, fence_raw) {
+            vk::Result::Success => Ok(()),
+            vk::Result::ErrorOutOfHostMemory |
+            vk::Result::ErrorOutOfDeviceMemory => Err(core::SubmissionError::OutOfMemory),
+            vk::Result::ErrorDeviceLost => Err(core::SubmissionError::DeviceLost),
+            _ => unreachable!(),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct Device {
     raw: Arc<RawDevice>,
+    instance: Arc<RawInstance>,
+    physical_device: vk::PhysicalDevice,
+    /// Indices of the queue families the device was opened with.
+    queue_families: Vec<u32>,
     features: Features,
     limits: Limits,
 }
@@ -629,4 +799,5 @@ impl core::Backend for Backend {
 
     type Fence = native::Fence;
     type Semaphore = native::Semaphore;
+    type Event = native::Event;
 }
@@ -11,6 +11,9 @@ pub struct Semaphore(pub vk::Semaphore);
 #[derive(Debug, Hash, PartialEq, Eq)]
 pub struct Fence(pub vk::Fence);
 
+#[derive(Debug, Hash, PartialEq, Eq)]
+pub struct Event(pub vk::Event);
+
 #[derive(Debug, Hash)]
 pub struct GraphicsPipeline(pub vk::Pipeline);
 
@@ -21,12 +24,14 @@ pub struct ComputePipeline(pub vk::Pipeline);
 pub struct Memory {
     pub(crate) inner: vk::DeviceMemory,
     pub(crate) ptr: *mut u8,
+    pub(crate) size: u64,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Buffer {
     pub(crate) raw: vk::Buffer,
     pub(crate) memory: vk::DeviceMemory,
+    pub(crate) memory_size: u64,
     pub(crate) offset: u64,
     pub(crate) ptr: *mut u8,
 }
@@ -42,7 +47,10 @@ pub struct BufferView {
 #[derive(Debug, Hash, PartialEq, Eq)]
 pub struct Image {
     pub(crate) raw: vk::Image,
-    pub(crate) bytes_per_texel: u8,
+    pub(crate) kind: core::image::Kind,
+    pub(crate) format: core::format::Format,
+    pub(crate) usage: core::image::Usage,
+    pub(crate) block: core::format::FormatBlock,
     pub(crate) extent: vk::Extent3D,
 }
 
@@ -59,6 +67,8 @@ pub struct Sampler(pub vk::Sampler);
 #[derive(Debug, Hash)]
 pub struct RenderPass {
     pub raw: vk::RenderPass,
+    /// Color attachment formats of each subpass.
+    pub(crate)  color_formats: Vec<Vec<core::format::Format>>,
 }
 
 #[derive(Debug, Hash)]
@@ -69,6 +79,7 @@ pub struct FrameBuffer {
 #[derive(Debug)]
 pub struct DescriptorSetLayout {
     pub(crate)  raw: vk::DescriptorSetLayout,
+    pub(crate)  bindings: Vec<core::pso::DescriptorSetLayoutBinding>,
 }
 
 #[derive(Debug)]
@@ -79,11 +90,15 @@ pub struct DescriptorSet {
 #[derive(Debug, Hash)]
 pub struct PipelineLayout {
     pub(crate)  raw: vk::PipelineLayout,
+    /// Bindings of each descriptor set layout.
+    pub(crate)  set_bindings: Vec<Vec<core::pso::DescriptorSetLayoutBinding>>,
 }
 
 #[derive(Debug, Eq, Hash, PartialEq)]
 pub struct ShaderModule {
     pub(crate)  raw: vk::ShaderModule,
+    /// Source SPIR-V, kept around for reflection.
+    pub(crate)  spirv: Vec<u8>,
 }
 
 #[derive(Debug)]
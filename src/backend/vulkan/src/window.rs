@@ -310,7 +310,10 @@ impl core::Surface<Backend> for Surface {
             .map(|image| {
                 native::Image {
                     raw: image,
-                    bytes_per_texel: 4,
+                    kind: core::image::Kind::D2(self.width as _, self.height as _, core::image::AaMode::Single),
+                    format,
+                    usage: core::image::COLOR_ATTACHMENT,
+                    block: format.0.describe_block(),
                     extent: vk::Extent3D {
                         width: self.width,
                         height: self.height,
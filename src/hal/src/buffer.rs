@@ -11,6 +11,8 @@ use {IndexType, Backend};
 pub enum CreationError {
     /// Required `Usage` is not supported.
     Usage(Usage),
+    /// Sparse buffers require `Features::sparse_binding`.
+    Sparse,
     /// Some other problem.
     Other,
 }
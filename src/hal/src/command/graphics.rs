@@ -1,9 +1,10 @@
+use std::ops::Range;
 use {pso, target};
 use {Backend, Viewport};
 use buffer::IndexBufferView;
-use image::{ImageLayout, SubresourceRange};
+use image::{Filter, ImageLayout, SubresourceLayers, SubresourceRange};
 use queue::capability::{Graphics, Supports};
-use super::{CommandBuffer, RawCommandBuffer, RenderPassInlineEncoder};
+use super::{CommandBuffer, Offset, RawCommandBuffer, RenderPassInlineEncoder};
 
 
 /// A universal clear color supporting integet formats
@@ -95,6 +96,37 @@ pub enum AttachmentClear {
     DepthStencil(ClearDepthStencil),
 }
 
+/// Buffer capturing the stream output of the bound graphics pipeline.
+#[derive(Debug)]
+pub struct TransformFeedbackBuffer<'a, B: Backend> {
+    /// Buffer receiving the captured vertices.
+    pub buffer: &'a B::Buffer,
+    /// Byte range of the buffer written to.
+    pub range: Range<u64>,
+    /// Buffer and offset of a `u64` counter storing the number of written bytes.
+    /// The counter needs to be zeroed before capturing starts.
+    /// Ignored by backends tracking the count internally.
+    pub counter: (&'a B::Buffer, u64),
+}
+
+/// Region of two images for blitting.
+///
+/// The source and destination bounds may differ in size, in which case the
+/// texels are scaled using the filter given to `blit_image`. Swapping the
+/// start and end of a bound mirrors the image along that axis.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ImageBlit {
+    /// Source image layers.
+    pub src_subresource: SubresourceLayers,
+    /// Source region corners.
+    pub src_bounds: Range<Offset>,
+    /// Destination image layers. Must have the same number of layers as the source.
+    pub dst_subresource: SubresourceLayers,
+    /// Destination region corners.
+    pub dst_bounds: Range<Offset>,
+}
+
 impl<'a, B: Backend, C: Supports<Graphics>> CommandBuffer<'a, B, C> {
     ///
     pub fn begin_renderpass_inline(
@@ -130,6 +162,24 @@ impl<'a, B: Backend, C: Supports<Graphics>> CommandBuffer<'a, B, C> {
         self.raw.clear_depth_stencil_image(image, layout, range, value)
     }
 
+    /// Copy regions of an image to another one, scaling and converting
+    /// the format if needed.
+    ///
+    /// The source and destination formats need to support the `BLIT_SRC`
+    /// and `BLIT_DST` features, and both images have to be single-sampled.
+    /// Blitting 1D and 3D images requires the `blit_1d_3d` feature.
+    pub fn blit_image(
+        &mut self,
+        src: &B::Image,
+        src_layout: ImageLayout,
+        dst: &B::Image,
+        dst_layout: ImageLayout,
+        filter: Filter,
+        regions: &[ImageBlit],
+    ) {
+        self.raw.blit_image(src, src_layout, dst, dst_layout, filter, regions)
+    }
+
     /// Bind index buffer view.
     pub fn bind_index_buffer(&mut self, ibv: IndexBufferView<B>) {
         self.raw.bind_index_buffer(ibv)
@@ -140,6 +190,11 @@ impl<'a, B: Backend, C: Supports<Graphics>> CommandBuffer<'a, B, C> {
         self.raw.bind_vertex_buffers(vbs)
     }
 
+    /// Bind buffers capturing the stream output, starting at `first_binding`.
+    pub fn bind_transform_feedback_buffers(&mut self, first_binding: u32, buffers: &[TransformFeedbackBuffer<B>]) {
+        self.raw.bind_transform_feedback_buffers(first_binding, buffers)
+    }
+
     /// Bind a graphics pipeline.
     ///
     /// There is only *one* pipeline slot for compute and graphics.
@@ -177,4 +232,14 @@ impl<'a, B: Backend, C: Supports<Graphics>> CommandBuffer<'a, B, C> {
     pub fn set_blend_constants(&mut self, cv: target::ColorValue) {
         self.raw.set_blend_constants(cv)
     }
+
+    ///
+    pub fn set_depth_bounds(&mut self, bounds: Range<f32>) {
+        self.raw.set_depth_bounds(bounds)
+    }
+
+    ///
+    pub fn set_line_width(&mut self, width: f32) {
+        self.raw.set_line_width(width)
+    }
 }
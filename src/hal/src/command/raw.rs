@@ -3,12 +3,13 @@ use std::ops::Range;
 use {pso, target};
 use {Backend, IndexCount, InstanceCount, VertexCount, VertexOffset, Viewport};
 use buffer::IndexBufferView;
-use image::{ImageLayout, SubresourceRange};
+use image::{Filter, ImageLayout, SubresourceRange};
 use memory::Barrier;
 use super::{
     AttachmentClear, BufferCopy, BufferImageCopy,
     ClearColor, ClearDepthStencil, ClearValue,
-    ImageCopy, ImageResolve, SubpassContents,
+    ImageBlit, ImageCopy, ImageResolve, SubpassContents,
+    TransformFeedbackBuffer,
 };
 
 ///
@@ -29,6 +30,25 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Send {
         barriers: &[Barrier<B>],
     );
 
+    /// Signal an event once all the previous commands
+    /// have completed the given stage.
+    fn set_event(&mut self, event: &B::Event, stage: pso::PipelineStage);
+
+    /// Unsignal an event once all the previous commands
+    /// have completed the given stage.
+    fn reset_event(&mut self, event: &B::Event, stage: pso::PipelineStage);
+
+    /// Wait for the events to be signaled, then apply the barriers.
+    ///
+    /// The barriers and `stages.start` refer to the work recorded before
+    /// the events were set, allowing independent work in between to overlap.
+    fn wait_events(
+        &mut self,
+        events: &[&B::Event],
+        stages: Range<pso::PipelineStage>,
+        barriers: &[Barrier<B>],
+    );
+
     ///
     fn fill_buffer(
         &mut self,
@@ -76,12 +96,39 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Send {
         regions: &[ImageResolve],
     );
 
+    /// Copy regions of an image to another one, scaling and converting
+    /// the format of the texels if needed. Both images need to be
+    /// in the given layouts.
+    fn blit_image(
+        &mut self,
+        src: &B::Image,
+        src_layout: ImageLayout,
+        dst: &B::Image,
+        dst_layout: ImageLayout,
+        filter: Filter,
+        regions: &[ImageBlit],
+    );
+
     /// Bind index buffer view.
     fn bind_index_buffer(&mut self, IndexBufferView<B>);
 
     /// Bind vertex buffers.
     fn bind_vertex_buffers(&mut self, pso::VertexBufferSet<B>);
 
+    /// Bind buffers capturing the stream output, starting at `first_binding`.
+    ///
+    /// Requires `Features::stream_output`.
+    fn bind_transform_feedback_buffers(&mut self, first_binding: u32, buffers: &[TransformFeedbackBuffer<B>]);
+
+    /// Start capturing the stream output of the bound pipeline
+    /// into the bound transform feedback buffers.
+    ///
+    /// Must be called inside a renderpass.
+    fn begin_transform_feedback(&mut self);
+
+    /// Stop capturing the stream output.
+    fn end_transform_feedback(&mut self);
+
     /// Set the viewport parameters for the rasterizer.
     ///
     /// Every other viewport, which is not specified in this call,
@@ -98,6 +145,7 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Send {
     /// - Command buffer must be in recording state.
     /// - Number of viewports must be between 1 and `max_viewports`.
     /// - Only queues with graphics capability support this function.
+    /// - Viewports baked into the bound pipeline are not overridden.
     fn set_viewports(&mut self, &[Viewport]);
 
     /// Set the scissor rectangles for the rasterizer.
@@ -118,14 +166,31 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Send {
     /// - Command buffer must be in recording state.
     /// - Number of scissors must be between 1 and `max_viewports`.
     /// - Only queues with graphics capability support this function.
+    /// - Scissors baked into the bound pipeline are not overridden.
     fn set_scissors(&mut self, &[target::Rect]);
 
+    /// Set the stencil reference values of the front and back faces.
     ///
+    /// Only has an effect if the bound pipeline has a dynamic stencil reference.
     fn set_stencil_reference(&mut self, front: target::Stencil, back: target::Stencil);
 
+    /// Set the blend constant color.
     ///
+    /// Only has an effect if the bound pipeline has dynamic blend constants.
     fn set_blend_constants(&mut self, target::ColorValue);
 
+    /// Set the depth range of the depth bounds test.
+    ///
+    /// Only has an effect if the bound pipeline has dynamic depth bounds.
+    /// Requires `Features::depth_bounds`.
+    fn set_depth_bounds(&mut self, Range<f32>);
+
+    /// Set the width of rasterized lines.
+    ///
+    /// Only has an effect if the bound pipeline has a dynamic line width.
+    /// Widths other than one require `Features::wide_lines`.
+    fn set_line_width(&mut self, f32);
+
     ///
     fn begin_renderpass(
         &mut self,
@@ -272,4 +337,10 @@ pub trait RawCommandBuffer<B: Backend>: Clone + Send {
         draw_count: u32,
         stride: u32,
     );
+
+    /// Draw the vertices captured by the last transform feedback,
+    /// without reading the count back to the CPU.
+    ///
+    /// Requires `Features::draw_auto`.
+    fn draw_auto(&mut self);
 }
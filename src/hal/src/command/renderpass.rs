@@ -2,7 +2,7 @@ use std::ops::Range;
 use {pso, target, Backend, IndexCount, InstanceCount, VertexCount, VertexOffset, Viewport};
 use buffer::IndexBufferView;
 use queue::{Supports, Graphics};
-use super::{AttachmentClear, ClearValue, CommandBuffer, RawCommandBuffer};
+use super::{AttachmentClear, ClearValue, CommandBuffer, RawCommandBuffer, TransformFeedbackBuffer};
 
 
 /// Specifies how commands for the following renderpasses will be recorded.
@@ -65,6 +65,10 @@ impl<'a, B: Backend> RenderPassInlineEncoder<'a, B> {
     pub fn draw_indexed_indirect(&mut self, buffer: &B::Buffer, offset: u64, draw_count: u32, stride: u32) {
         self.0.draw_indexed_indirect(buffer, offset, draw_count, stride)
     }
+    /// Draw the vertices captured by the last transform feedback.
+    pub fn draw_auto(&mut self) {
+        self.0.draw_auto()
+    }
 
     /// Bind index buffer view.
     pub fn bind_index_buffer(&mut self, ibv: IndexBufferView<B>) {
@@ -76,6 +80,21 @@ impl<'a, B: Backend> RenderPassInlineEncoder<'a, B> {
         self.0.bind_vertex_buffers(vbs);
     }
 
+    /// Bind buffers capturing the stream output, starting at `first_binding`.
+    pub fn bind_transform_feedback_buffers(&mut self, first_binding: u32, buffers: &[TransformFeedbackBuffer<B>]) {
+        self.0.bind_transform_feedback_buffers(first_binding, buffers);
+    }
+
+    /// Start capturing the stream output of the bound pipeline.
+    pub fn begin_transform_feedback(&mut self) {
+        self.0.begin_transform_feedback()
+    }
+
+    /// Stop capturing the stream output.
+    pub fn end_transform_feedback(&mut self) {
+        self.0.end_transform_feedback()
+    }
+
     /// Bind a graphics pipeline.
     ///
     /// There is only *one* pipeline slot for compute and graphics.
@@ -114,8 +133,16 @@ impl<'a, B: Backend> RenderPassInlineEncoder<'a, B> {
         self.0.set_blend_constants(cv)
     }
 
-    // TODO: set_line_width
-    // TODO: set_depth_bounds
+    ///
+    pub fn set_depth_bounds(&mut self, bounds: Range<f32>) {
+        self.0.set_depth_bounds(bounds)
+    }
+
+    ///
+    pub fn set_line_width(&mut self, width: f32) {
+        self.0.set_line_width(width)
+    }
+
     // TODO: set_depth_bias
     // TODO: set_stencil_compare_mask
     // TODO: set_stencil_write_mask
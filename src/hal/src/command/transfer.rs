@@ -70,9 +70,11 @@ pub struct ImageCopy {
 pub struct BufferImageCopy {
     ///
     pub buffer_offset: u64,
-    ///
+    /// Size of a row in the buffer, in bytes.
+    /// Rows of compressed formats are rows of blocks, see `FormatBlock::row_pitch`.
     pub buffer_row_pitch: u32,
-    ///
+    /// Size of a depth slice in the buffer, in bytes.
+    /// Needs to be a multiple of the row pitch.
     pub buffer_slice_pitch: u32,
     ///
     pub image_layers: image::SubresourceLayers,
@@ -93,6 +95,26 @@ impl<'a, B: Backend, C: Supports<Transfer>> CommandBuffer<'a, B, C> {
         self.raw.pipeline_barrier(stages, barriers)
     }
 
+    ///
+    pub fn set_event(&mut self, event: &B::Event, stage: PipelineStage) {
+        self.raw.set_event(event, stage)
+    }
+
+    ///
+    pub fn reset_event(&mut self, event: &B::Event, stage: PipelineStage) {
+        self.raw.reset_event(event, stage)
+    }
+
+    ///
+    pub fn wait_events(
+        &mut self,
+        events: &[&B::Event],
+        stages: Range<PipelineStage>,
+        barriers: &[Barrier<B>],
+    ) {
+        self.raw.wait_events(events, stages, barriers)
+    }
+
 
     ///
     pub fn fill_buffer(
@@ -6,7 +6,7 @@
 use std::{fmt, mem, slice};
 use std::error::Error;
 use std::ops::Range;
-use {buffer, format, image, mapping, pass, pso};
+use {buffer, format, image, mapping, memory, pass, pso};
 use {Backend, Features, Limits, MemoryType};
 use memory::Requirements;
 
@@ -63,11 +63,86 @@ pub struct Extent {
     pub depth: u32,
 }
 
+/// A single message of a shader compiler or linker log.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ShaderDiagnostic {
+    /// Source line the message refers to, if reported.
+    pub line: Option<u32>,
+    /// Message text, as printed in the log.
+    pub message: String,
+}
+
 /// An error from creating a shader module.
 #[derive(Clone, Debug, PartialEq)]
 pub enum ShaderError {
-    /// The shader failed to compile.
+    /// The shader failed to compile, with the full compiler log.
     CompilationFailed(String),
+    /// The shader stages of a pipeline failed to link due to
+    /// mismatching interfaces, with the full linker log.
+    InterfaceMismatch(String),
+}
+
+impl ShaderError {
+    /// Return the full compiler or linker log.
+    pub fn log(&self) -> &str {
+        match *self {
+            ShaderError::CompilationFailed(ref log) |
+            ShaderError::InterfaceMismatch(ref log) => log,
+        }
+    }
+
+    /// Split the log into a diagnostic per line.
+    pub fn diagnostics(&self) -> Vec<ShaderDiagnostic> {
+        self.log()
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| ShaderDiagnostic {
+                line: parse_source_line(line),
+                message: line.to_owned(),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:\n{}", self.description(), self.log())
+    }
+}
+
+impl Error for ShaderError {
+    fn description(&self) -> &str {
+        match *self {
+            ShaderError::CompilationFailed(_) => "Shader compilation failed",
+            ShaderError::InterfaceMismatch(_) => "Shader stage interfaces mismatch",
+        }
+    }
+}
+
+/// Extract the source line of a log message, supporting the location formats of
+/// GLSL compilers (`0:12(5):`, `0(12) :`, `ERROR: 0:12:`), HLSL (`file(12,5):`)
+/// and Metal (`program_source:12:5:`).
+fn parse_source_line(message: &str) -> Option<u32> {
+    let leading_number = |text: &str| {
+        let end = text.find(|c: char| !c.is_digit(10)).unwrap_or(text.len());
+        text[.. end].parse::<u32>().ok()
+    };
+
+    let message = ["ERROR: ", "WARNING: "]
+        .iter()
+        .find(|prefix| message.starts_with(*prefix))
+        .map_or(message, |prefix| &message[prefix.len() ..]);
+    let location = message.split(": ").next().unwrap_or("").trim();
+
+    // `string:line` and `file:line:column`
+    if let Some(line) = location.split(':').nth(1).and_then(&leading_number) {
+        return Some(line);
+    }
+    // `string(line)` and `file(line,column)`
+    location
+        .rfind('(')
+        .and_then(|start| leading_number(&location[start + 1 ..]))
 }
 
 /// An error from creating a framebuffer.
@@ -118,6 +193,18 @@ pub trait Device<B: Backend>: Clone {
     /// Returns the limits of this `Device`.
     fn get_limits(&self) -> &Limits;
 
+    /// Returns the features supported by a format.
+    fn format_properties(&self, format: format::Format) -> format::Properties;
+
+    /// Returns the limits of images of the given kind, format and usage,
+    /// or `None` if such images can't be created at all.
+    ///
+    /// Not all backends support `Tiling::Linear` images, D3D12 and GL always
+    /// return `None` for them and report no `linear_tiling` format features.
+    fn image_format_properties(
+        &self, kind: image::Kind, format: format::Format, tiling: image::Tiling, usage: image::Usage,
+    ) -> Option<image::FormatProperties>;
+
     /// Allocate a memory segment of a specified type.
     ///
     /// There is only a limited amount of allocations allowed depending on the implementation!
@@ -162,11 +249,18 @@ pub trait Device<B: Backend>: Clone {
         &mut self, spirv_data: &[u8]
     ) -> Result<B::ShaderModule, ShaderError>;
 
+    /// Reflect the entry points, interfaces and resources of a shader module.
+    ///
+    /// Only modules created from SPIR-V can be reflected.
+    fn reflect_shader_module(
+        &self, &B::ShaderModule
+    ) -> Result<pso::ShaderReflection, pso::ReflectError>;
+
     /// Create a new buffer (unbound).
     ///
     /// The created buffer won't have associated memory until `bind_buffer_memory` is called.
     fn create_buffer(
-        &mut self, size: u64, stride: u64, buffer::Usage,
+        &mut self, size: u64, stride: u64, buffer::Usage, memory::SharingMode,
     ) -> Result<B::UnboundBuffer, buffer::CreationError>;
 
     ///
@@ -177,6 +271,11 @@ pub trait Device<B: Backend>: Clone {
     /// The unbound buffer will be consumed because the binding is *immutable*.
     /// Be sure to check that there is enough memory available for the buffer.
     /// Use `get_buffer_requirements` to acquire the memory requirements.
+    ///
+    /// Several buffers and images may be bound to overlapping memory ranges.
+    /// Only one of them can be used at a time, switching between them requires
+    /// a `memory::Barrier::Aliasing`. Aliasing images can't be attachments of
+    /// the same render pass.
     fn bind_buffer_memory(
         &mut self, &B::Memory, offset: u64, B::UnboundBuffer
     ) -> Result<B::Buffer, BindError>;
@@ -188,28 +287,66 @@ pub trait Device<B: Backend>: Clone {
 
     ///
     fn create_image(
-        &mut self, image::Kind, image::Level, format::Format, image::Usage,
+        &mut self, image::Kind, image::Level, format::Format, image::Tiling, image::Usage, memory::SharingMode,
     ) -> Result<B::UnboundImage, image::CreationError>;
 
     ///
     fn get_image_requirements(&mut self, &B::UnboundImage) -> Requirements;
 
+    /// Get the memory layout of a subresource of an image created with
+    /// `Tiling::Linear`, which is only possible if `image_format_properties`
+    /// supports it for the image. The aspect selects a single plane of the image:
+    /// color, depth or stencil.
+    fn get_image_subresource_footprint(
+        &mut self, &B::Image, image::AspectFlags, image::Subresource,
+    ) -> image::SubresourceFootprint;
+
+    /// Bind memory to an image.
     ///
+    /// Memory may be aliased, see `bind_buffer_memory`.
     fn bind_image_memory(
         &mut self, &B::Memory, offset: u64, B::UnboundImage
     ) -> Result<B::Image, BindError>;
 
+    /// Create a new sparse buffer.
+    ///
+    /// The memory of sparse buffers is bound by page-sized regions
+    /// with `RawCommandQueue::bind_sparse`, instead of `bind_buffer_memory`.
+    /// Requires `Features::sparse_binding`.
+    fn create_sparse_buffer(
+        &mut self, size: u64, stride: u64, buffer::Usage, memory::SharingMode,
+    ) -> Result<B::Buffer, buffer::CreationError>;
+
+    /// Get the memory requirements of a sparse buffer.
     ///
+    /// The alignment of the requirements is the page size.
+    fn get_sparse_buffer_requirements(&mut self, &B::Buffer) -> Requirements;
+
+    /// Create a new sparse image.
+    ///
+    /// See `create_sparse_buffer` for more information.
+    fn create_sparse_image(
+        &mut self, image::Kind, image::Level, format::Format, image::Usage, memory::SharingMode,
+    ) -> Result<B::Image, image::CreationError>;
+
+    /// Get the memory requirements of a sparse image, with the alignment being the page size,
+    /// together with the extent of a page used for binding image regions.
+    fn get_sparse_image_requirements(&mut self, &B::Image) -> (Requirements, Extent);
+
+    /// Create a view of the given kind into a subresource range of an image.
+    /// The aspects of the view are taken from the range.
     fn create_image_view(
         &mut self,
         &B::Image,
+        image::ViewKind,
         format::Format,
         format::Swizzle,
         image::SubresourceRange,
     ) -> Result<B::ImageView, image::ViewError>;
 
-    ///
-    fn create_sampler(&mut self, image::SamplerInfo) -> B::Sampler;
+    /// Create a sampler, failing if the description is invalid or uses
+    /// features not supported by the device.
+    fn create_sampler(&mut self, image::SamplerInfo) -> Result<B::Sampler, image::SamplerError>;
 
     /// Create a descriptor pool.
     ///
@@ -231,10 +368,34 @@ pub trait Device<B: Backend>: Clone {
     // TODO: copies
     fn update_descriptor_sets(&mut self, &[pso::DescriptorSetWrite<B>]);
 
-    // TODO: mapping requires further looking into.
-    // vulkan requires non-coherent mapping to round the range delimiters
-    // Nested mapping is not allowed in vulkan.
-    // How to handle it properly for backends? Explicit synchronization?
+    /// Map a range of a memory object into the host address space.
+    ///
+    /// The memory has to be allocated from a `CPU_VISIBLE` memory type.
+    /// The returned pointer corresponds to the start of the range.
+    /// Only one range of a memory object can be mapped at a time.
+    ///
+    /// If `Features::persistent_mapping` is supported, the mapping is persistent:
+    /// the pointer stays valid until `unmap_memory` and the device is allowed to
+    /// access the memory in the meantime, e.g. for streaming data every frame.
+    /// Host writes have to be made visible with `flush_mapped_memory_ranges` then.
+    /// Otherwise the memory has to be unmapped before the device accesses it.
+    fn map_memory(&mut self, &B::Memory, Range<u64>) -> Result<*mut u8, mapping::Error>;
+
+    /// Make host writes to the given mapped memory ranges visible to the device.
+    ///
+    /// Only required for memory types without `COHERENT` property.
+    /// Range delimiters have to be multiples of `Limits::non_coherent_atom_size`,
+    /// except for the end of the memory object.
+    fn flush_mapped_memory_ranges(&mut self, &[(&B::Memory, Range<u64>)]) -> Result<(), OutOfMemory>;
+
+    /// Make device writes to the given mapped memory ranges visible to the host.
+    ///
+    /// Only required for memory types without `COHERENT` property.
+    /// Range delimiters follow the same rules as `flush_mapped_memory_ranges`.
+    fn invalidate_mapped_memory_ranges(&mut self, &[(&B::Memory, Range<u64>)]) -> Result<(), OutOfMemory>;
+
+    /// Unmap a memory object previously mapped with `map_memory`.
+    fn unmap_memory(&mut self, &B::Memory);
 
     /// Acquire access to the buffer mapping.
     ///
@@ -242,13 +403,15 @@ pub trait Device<B: Backend>: Clone {
     ///
     /// While holding this access, you hold CPU-side exclusive access.
     /// You must ensure that there is no GPU access to the buffer in the meantime.
+    /// Data updated every frame is better written through a persistent `map_memory`.
     fn acquire_mapping_raw(&mut self, buf: &B::Buffer, read: Option<Range<u64>>)
         -> Result<*mut u8, mapping::Error>;
 
     /// Release access to the buffer mapping.
     ///
     /// If you wrote, you have to specify in which range.
-    fn release_mapping_raw(&mut self, buf: &B::Buffer, wrote: Option<Range<u64>>);
+    fn release_mapping_raw(&mut self, buf: &B::Buffer, wrote: Option<Range<u64>>)
+        -> Result<(), mapping::Error>;
 
     /// Acquire a mapping Reader
     ///
@@ -274,9 +437,11 @@ pub trait Device<B: Backend>: Clone {
     /// Release a mapping Reader
     ///
     /// See `acquire_mapping_raw` for more information.
-    fn release_mapping_reader<'a, T>(&mut self, mut reader: mapping::Reader<'a, B, T>) {
+    fn release_mapping_reader<'a, T>(&mut self, mut reader: mapping::Reader<'a, B, T>)
+        -> Result<(), mapping::Error>
+    {
         reader.released = true;
-        self.release_mapping_raw(reader.buffer, None);
+        self.release_mapping_raw(reader.buffer, None)
     }
 
     /// Acquire a mapping Writer
@@ -301,9 +466,11 @@ pub trait Device<B: Backend>: Clone {
             })
     }
 
-    fn release_mapping_writer<'a, T>(&mut self, mut writer: mapping::Writer<'a, B, T>) {
+    fn release_mapping_writer<'a, T>(&mut self, mut writer: mapping::Writer<'a, B, T>)
+        -> Result<(), mapping::Error>
+    {
         writer.released = true;
-        self.release_mapping_raw(writer.buffer, Some(writer.range.clone()));
+        self.release_mapping_raw(writer.buffer, Some(writer.range.clone()))
     }
 
     ///
@@ -319,6 +486,20 @@ pub trait Device<B: Backend>: Clone {
     /// Returns true if fences were signaled before the timeout.
     fn wait_for_fences(&mut self, &[&B::Fence], WaitFor, timeout_ms: u32) -> bool;
 
+    /// Create a new event in the unsignaled state.
+    ///
+    /// Requires `Features::events`.
+    fn create_event(&mut self) -> B::Event;
+
+    /// Signal an event from the host.
+    fn set_event(&mut self, &B::Event);
+
+    /// Unsignal an event from the host.
+    fn reset_event(&mut self, &B::Event);
+
+    /// Returns true if the event is signaled.
+    fn get_event_status(&mut self, &B::Event) -> bool;
+
     ///
     fn free_memory(&mut self, B::Memory);
 
@@ -382,4 +563,31 @@ pub trait Device<B: Backend>: Clone {
 
     ///
     fn destroy_semaphore(&mut self, B::Semaphore);
+
+    /// Destroys an event.
+    ///
+    /// The event shouldn't be destroyed before any submitted command buffer,
+    /// which references the event, has finished execution.
+    fn destroy_event(&mut self, B::Event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shader_diagnostics() {
+        let log = "0:12(5): error: `foo' undeclared\n\
+                   0(7) : error C1008: undefined variable \"bar\"\n\
+                   ERROR: 0:3: 'baz' : no such field\n\
+                   C:\\shaders\\blit.hlsl(21,9): error X3004: undeclared identifier\n\
+                   program_source:40:13: warning: unused variable\n\
+                   error: linking failed";
+        let lines = ShaderError::CompilationFailed(log.into())
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![Some(12), Some(7), Some(3), Some(21), Some(40), None]);
+    }
 }
@@ -1,18 +1,21 @@
 //! Universal format specification.
 //! Applicable to textures, views, and vertex buffers.
+//!
+//! Block-compressed formats (BC, ETC2/EAC, ASTC) store texels in blocks,
+//! see `FormatBlock` for the pitch calculations of their data.
 
-//TODO:
-//  DXT 1-5, BC7
-//  ETC2_RGB, // Use the EXT2 algorithm on 3 components.
-//  ETC2_SRGB, // Use the EXT2 algorithm on 4 components (RGBA) in the sRGB color space.
-//  ETC2_EAC_RGBA8, // Use the EXT2 EAC algorithm on 4 components.
+use image;
 use memory::Pod;
 
+mod texel;
+
+pub use self::texel::{Texel, TexelError};
+
 
 /// Description of the bits distribution of a format.
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct FormatBits {
-    /// Total number of bits
+    /// Total number of bits, per block for compressed formats
     pub total: u8,
     /// Number of color bits (summed for R/G/B)
     pub color: u8,
@@ -33,6 +36,134 @@ pub const BITS_ZERO: FormatBits = FormatBits {
     stencil: 0,
 };
 
+/// Description of the texel blocks of a format.
+/// Uncompressed formats have blocks of a single texel.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FormatBlock {
+    /// Width of a block in texels.
+    pub width: u8,
+    /// Height of a block in texels.
+    pub height: u8,
+    /// Size of a block in bytes.
+    pub bytes: u8,
+}
+
+impl FormatBlock {
+    /// Return true if the blocks consist of more than a single texel.
+    pub fn is_compressed(&self) -> bool {
+        self.width != 1 || self.height != 1
+    }
+
+    /// Number of blocks covering the given texel extent, partial blocks included.
+    pub fn count(&self, width: u32, height: u32) -> (u32, u32) {
+        let (bw, bh) = (self.width as u32, self.height as u32);
+        ((width + bw - 1) / bw, (height + bh - 1) / bh)
+    }
+
+    /// Tightly packed size of a row of blocks covering `width` texels, in bytes.
+    pub fn row_pitch(&self, width: u32) -> u32 {
+        self.count(width, 1).0 * self.bytes as u32
+    }
+
+    /// Tightly packed size of a slice of blocks covering
+    /// `width` x `height` texels, in bytes.
+    pub fn slice_pitch(&self, width: u32, height: u32) -> u32 {
+        let (w, h) = self.count(width, height);
+        w * h * self.bytes as u32
+    }
+}
+
+bitflags!(
+    /// Image feature flags of a format.
+    #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+    pub struct ImageFeature: u16 {
+        /// Image view can be sampled.
+        const SAMPLED = 0x1;
+        /// Image view can be sampled with a linear filter.
+        const SAMPLED_LINEAR = 0x2;
+        /// Image view can be used as storage image.
+        const STORAGE = 0x4;
+        /// Image view can be used as storage image with atomic operations.
+        const STORAGE_ATOMIC = 0x8;
+        /// Image view can be used as color attachment.
+        const COLOR_ATTACHMENT = 0x10;
+        /// Image view can be used as color attachment with blending.
+        const COLOR_ATTACHMENT_BLEND = 0x20;
+        /// Image view can be used as depth-stencil attachment.
+        const DEPTH_STENCIL_ATTACHMENT = 0x40;
+        /// Image can be used as source of blit commands.
+        const BLIT_SRC = 0x80;
+        /// Image can be used as destination of blit commands.
+        const BLIT_DST = 0x100;
+    }
+);
+
+/// Image view can be sampled.
+pub const SAMPLED: ImageFeature = ImageFeature::SAMPLED;
+/// Image view can be sampled with a linear filter.
+pub const SAMPLED_LINEAR: ImageFeature = ImageFeature::SAMPLED_LINEAR;
+/// Image view can be used as storage image.
+pub const STORAGE: ImageFeature = ImageFeature::STORAGE;
+/// Image view can be used as storage image with atomic operations.
+pub const STORAGE_ATOMIC: ImageFeature = ImageFeature::STORAGE_ATOMIC;
+/// Image view can be used as color attachment.
+pub const COLOR_ATTACHMENT: ImageFeature = ImageFeature::COLOR_ATTACHMENT;
+/// Image view can be used as color attachment with blending.
+pub const COLOR_ATTACHMENT_BLEND: ImageFeature = ImageFeature::COLOR_ATTACHMENT_BLEND;
+/// Image view can be used as depth-stencil attachment.
+pub const DEPTH_STENCIL_ATTACHMENT: ImageFeature = ImageFeature::DEPTH_STENCIL_ATTACHMENT;
+/// Image can be used as source of blit commands.
+pub const BLIT_SRC: ImageFeature = ImageFeature::BLIT_SRC;
+/// Image can be used as destination of blit commands.
+pub const BLIT_DST: ImageFeature = ImageFeature::BLIT_DST;
+
+bitflags!(
+    /// Buffer feature flags of a format.
+    #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+    pub struct BufferFeature: u8 {
+        /// Buffer view can be used as uniform texel buffer.
+        const UNIFORM_TEXEL = 0x1;
+        /// Buffer view can be used as storage texel buffer.
+        const STORAGE_TEXEL = 0x2;
+        /// Buffer view can be used as storage texel buffer with atomic operations.
+        const STORAGE_TEXEL_ATOMIC = 0x4;
+        /// Buffer can be used as vertex buffer with this format.
+        const VERTEX = 0x8;
+    }
+);
+
+/// Buffer view can be used as uniform texel buffer.
+pub const UNIFORM_TEXEL: BufferFeature = BufferFeature::UNIFORM_TEXEL;
+/// Buffer view can be used as storage texel buffer.
+pub const STORAGE_TEXEL: BufferFeature = BufferFeature::STORAGE_TEXEL;
+/// Buffer view can be used as storage texel buffer with atomic operations.
+pub const STORAGE_TEXEL_ATOMIC: BufferFeature = BufferFeature::STORAGE_TEXEL_ATOMIC;
+/// Buffer can be used as vertex buffer with this format.
+pub const VERTEX: BufferFeature = BufferFeature::VERTEX;
+
+/// Features supported by a format, as reported by the device.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Properties {
+    /// Features of images with linear tiling.
+    pub linear_tiling: ImageFeature,
+    /// Features of images with optimal tiling.
+    pub optimal_tiling: ImageFeature,
+    /// Features of buffers.
+    pub buffer_features: BufferFeature,
+}
+
+impl Properties {
+    /// Properties of a format that isn't supported at all.
+    pub fn empty() -> Self {
+        Properties {
+            linear_tiling: ImageFeature::empty(),
+            optimal_tiling: ImageFeature::empty(),
+            buffer_features: BufferFeature::empty(),
+        }
+    }
+}
+
 macro_rules! impl_channel_type {
     { $($name:ident = $shader_type:ident [ $($imp_trait:ident),* ] ,)* } => {
         /// Type of a surface channel. This is how we interpret the
@@ -67,6 +198,10 @@ impl_channel_type! {
     Unorm   = f32 [TextureChannel, RenderChannel, BlendChannel],
     Float   = f32 [TextureChannel, RenderChannel, BlendChannel],
     Srgb    = f32 [TextureChannel, RenderChannel, BlendChannel],
+    Ufloat  = f32 [TextureChannel],
+    // Integers converted to floats without normalization, for vertex attributes.
+    Iscaled = f32 [],
+    Uscaled = f32 [],
 }
 
 macro_rules! impl_formats {
@@ -135,6 +270,28 @@ macro_rules! impl_formats {
                     assert_eq!(size_of::<$data_type>() * 8, $total);
                 )*
             }
+
+            #[test]
+            fn test_block_pitch() {
+                use super::SurfaceType;
+                let block = SurfaceType::BC1_R8_G8_B8.describe_block();
+                assert_eq!(block.row_pitch(13), 4 * 8);
+                assert_eq!(block.slice_pitch(13, 2), 4 * 8);
+                let block = SurfaceType::ASTC_10x5.describe_block();
+                assert_eq!(block.count(21, 5), (3, 1));
+                let block = SurfaceType::R8_G8_B8_A8.describe_block();
+                assert_eq!(block.slice_pitch(13, 2), 13 * 2 * 4);
+            }
+
+            #[test]
+            fn test_aspects() {
+                use super::SurfaceType;
+                use image::{ASPECT_COLOR, ASPECT_DEPTH, ASPECT_STENCIL};
+                assert_eq!(SurfaceType::R5_G6_B5.aspects(), ASPECT_COLOR);
+                assert_eq!(SurfaceType::D16.aspects(), ASPECT_DEPTH);
+                assert_eq!(SurfaceType::D24_S8.aspects(), ASPECT_DEPTH | ASPECT_STENCIL);
+                assert!(SurfaceType::S8.is_stencil() && !SurfaceType::S8.is_depth());
+            }
         }
     }
 }
@@ -158,19 +315,19 @@ impl_formats! {
         { 16, color: 16 }
         [TextureSurface, RenderSurface],
     R8:
-        Vec1<Int, Uint, Inorm, Unorm> = u8
+        Vec1<Int, Uint, Inorm, Unorm, Iscaled, Uscaled> = u8
         { 8, color: 8 }
         [BufferSurface, TextureSurface, RenderSurface],
     R8_G8:
-        Vec2<Int, Uint, Inorm, Unorm> = [u8; 2]
+        Vec2<Int, Uint, Inorm, Unorm, Iscaled, Uscaled> = [u8; 2]
         { 16, color: 16 }
         [BufferSurface, TextureSurface, RenderSurface],
     R8_G8_B8_A8:
-        Vec4<Int, Uint, Inorm, Unorm, Srgb> = [u8; 4]
+        Vec4<Int, Uint, Inorm, Unorm, Srgb, Iscaled, Uscaled> = [u8; 4]
         { 32, color: 24, alpha: 8 }
         [BufferSurface, TextureSurface, RenderSurface],
     R10_G10_B10_A2:
-        Vec4<Uint, Unorm> = u32
+        Vec4<Int, Uint, Inorm, Unorm, Iscaled, Uscaled> = u32
         { 32, color: 30, alpha: 2 }
         [BufferSurface, TextureSurface, RenderSurface],
     R11_G11_B10:
@@ -178,19 +335,19 @@ impl_formats! {
         { 32, color: 32 }
         [BufferSurface, TextureSurface, RenderSurface],
     R16:
-        Vec1<Int, Uint, Inorm, Unorm, Float> = u16
+        Vec1<Int, Uint, Inorm, Unorm, Float, Iscaled, Uscaled> = u16
         { 16, color: 16 }
         [BufferSurface, TextureSurface, RenderSurface],
     R16_G16:
-        Vec2<Int, Uint, Inorm, Unorm, Float> = [u16; 2]
+        Vec2<Int, Uint, Inorm, Unorm, Float, Iscaled, Uscaled> = [u16; 2]
         { 32, color: 32 }
         [BufferSurface, TextureSurface, RenderSurface],
     R16_G16_B16:
-        Vec3<Int, Uint, Inorm, Unorm, Float> = [u16; 3]
+        Vec3<Int, Uint, Inorm, Unorm, Float, Iscaled, Uscaled> = [u16; 3]
         { 48, color: 48 }
         [BufferSurface, TextureSurface, RenderSurface],
     R16_G16_B16_A16:
-        Vec4<Int, Uint, Inorm, Unorm, Float> = [u16; 4]
+        Vec4<Int, Uint, Inorm, Unorm, Float, Iscaled, Uscaled> = [u16; 4]
         { 64, color: 48, alpha: 16 }
         [BufferSurface, TextureSurface, RenderSurface],
     R32:
@@ -232,6 +389,118 @@ impl_formats! {
         Vec1<Unorm, Float, Uint> = (f32, u32)
         { 64, depth: 32, stencil: 8 } //TODO: verify
         [TextureSurface, DepthSurface, StencilSurface],
+    S8:
+        Vec1<Uint> = u8
+        { 8, stencil: 8 }
+        [TextureSurface, StencilSurface],
+    BC1_R8_G8_B8:
+        Vec3<Unorm, Srgb> = [u8; 8]
+        { 64, color: 24 }
+        [TextureSurface],
+    BC1_R8_G8_B8_A8:
+        Vec4<Unorm, Srgb> = [u8; 8]
+        { 64, color: 24, alpha: 8 }
+        [TextureSurface],
+    BC2_R8_G8_B8_A8:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    BC3_R8_G8_B8_A8:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    BC4_R8:
+        Vec1<Inorm, Unorm> = [u8; 8]
+        { 64, color: 8 }
+        [TextureSurface],
+    BC5_R8_G8:
+        Vec2<Inorm, Unorm> = [u8; 16]
+        { 128, color: 16 }
+        [TextureSurface],
+    BC6_R16_G16_B16:
+        Vec3<Float, Ufloat> = [u8; 16]
+        { 128, color: 48 }
+        [TextureSurface],
+    BC7_R8_G8_B8_A8:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ETC2_R8_G8_B8:
+        Vec3<Unorm, Srgb> = [u8; 8]
+        { 64, color: 24 }
+        [TextureSurface],
+    ETC2_R8_G8_B8_A1:
+        Vec4<Unorm, Srgb> = [u8; 8]
+        { 64, color: 24, alpha: 1 }
+        [TextureSurface],
+    ETC2_R8_G8_B8_A8:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    EAC_R11:
+        Vec1<Inorm, Unorm> = [u8; 8]
+        { 64, color: 11 }
+        [TextureSurface],
+    EAC_R11_G11:
+        Vec2<Inorm, Unorm> = [u8; 16]
+        { 128, color: 22 }
+        [TextureSurface],
+    ASTC_4x4:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ASTC_5x4:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ASTC_5x5:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ASTC_6x5:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ASTC_6x6:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ASTC_8x5:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ASTC_8x6:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ASTC_8x8:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ASTC_10x5:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ASTC_10x6:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ASTC_10x8:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ASTC_10x10:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ASTC_12x10:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
+    ASTC_12x12:
+        Vec4<Unorm, Srgb> = [u8; 16]
+        { 128, color: 24, alpha: 8 }
+        [TextureSurface],
 }
 
 impl SurfaceType {
@@ -246,6 +515,68 @@ impl SurfaceType {
             _ => false,
         }
     }
+
+    /// Return the texel block description of this surface type.
+    pub fn describe_block(self) -> FormatBlock {
+        use self::SurfaceType::*;
+        let (width, height, bytes) = match self {
+            BC1_R8_G8_B8 |
+            BC1_R8_G8_B8_A8 |
+            BC4_R8 |
+            ETC2_R8_G8_B8 |
+            ETC2_R8_G8_B8_A1 |
+            EAC_R11 => (4, 4, 8),
+            BC2_R8_G8_B8_A8 |
+            BC3_R8_G8_B8_A8 |
+            BC5_R8_G8 |
+            BC6_R16_G16_B16 |
+            BC7_R8_G8_B8_A8 |
+            ETC2_R8_G8_B8_A8 |
+            EAC_R11_G11 => (4, 4, 16),
+            ASTC_4x4 => (4, 4, 16),
+            ASTC_5x4 => (5, 4, 16),
+            ASTC_5x5 => (5, 5, 16),
+            ASTC_6x5 => (6, 5, 16),
+            ASTC_6x6 => (6, 6, 16),
+            ASTC_8x5 => (8, 5, 16),
+            ASTC_8x6 => (8, 6, 16),
+            ASTC_8x8 => (8, 8, 16),
+            ASTC_10x5 => (10, 5, 16),
+            ASTC_10x6 => (10, 6, 16),
+            ASTC_10x8 => (10, 8, 16),
+            ASTC_10x10 => (10, 10, 16),
+            ASTC_12x10 => (12, 10, 16),
+            ASTC_12x12 => (12, 12, 16),
+            _ => (1, 1, self.describe_bits().total / 8),
+        };
+        FormatBlock { width, height, bytes }
+    }
+
+    /// Return true if it's a block-compressed surface type.
+    pub fn is_compressed(self) -> bool {
+        self.describe_block().is_compressed()
+    }
+
+    /// Return true if the surface has a stencil component.
+    pub fn is_stencil(self) -> bool {
+        self.describe_bits().stencil != 0
+    }
+
+    /// Return the image aspects covered by the surface.
+    pub fn aspects(self) -> image::AspectFlags {
+        let bits = self.describe_bits();
+        let mut aspects = image::AspectFlags::empty();
+        if bits.color + bits.alpha != 0 {
+            aspects |= image::ASPECT_COLOR;
+        }
+        if bits.depth != 0 {
+            aspects |= image::ASPECT_DEPTH;
+        }
+        if bits.stencil != 0 {
+            aspects |= image::ASPECT_STENCIL;
+        }
+        aspects
+    }
 }
 
 
@@ -456,6 +787,12 @@ pub type Depth = (D24, Unorm);
 pub type DepthStencil = (D24_S8, Unorm);
 /// Standard 32-bit floating-point depth format.
 pub type Depth32F = (D32, Float);
+/// Standard 16-bit depth format.
+pub type Depth16 = (D16, Unorm);
+/// Standard 32-bit floating-point depth format with 8-bit stencil.
+pub type Depth32FStencil8 = (D32_S8, Float);
+/// Standard 8-bit stencil format.
+pub type Stencil8 = (S8, Uint);
 
 macro_rules! impl_simple_formats {
     { $( $container:ident< $ty:ty > = $channel:ident $surface:ident, )* } => {
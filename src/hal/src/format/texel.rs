@@ -0,0 +1,431 @@
+//! CPU-side encoding and decoding of single texels.
+//!
+//! Texel data is expected in the little-endian layout used by the backends,
+//! with packed formats stored as a single little-endian word.
+
+use std::error::Error;
+use std::fmt;
+
+use super::{ChannelType, Component, F16, Format, SurfaceType, Swizzle};
+
+
+/// A single texel in the shader-visible representation of its channel type.
+///
+/// Components missing from the format are filled in as `(0, 0, 0, 1)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Texel {
+    /// Components of normalized, sRGB and floating-point channels.
+    Float([f32; 4]),
+    /// Components of unsigned integer channels.
+    Uint([u32; 4]),
+    /// Components of signed integer channels.
+    Int([i32; 4]),
+}
+
+impl Texel {
+    /// Rearrange the components according to a swizzle.
+    pub fn swizzle(self, swizzle: Swizzle) -> Texel {
+        fn apply<T: Copy>(v: [T; 4], swizzle: Swizzle, zero: T, one: T) -> [T; 4] {
+            let pick = |component| match component {
+                Component::Zero => zero,
+                Component::One => one,
+                Component::R => v[0],
+                Component::G => v[1],
+                Component::B => v[2],
+                Component::A => v[3],
+            };
+            [pick(swizzle.0), pick(swizzle.1), pick(swizzle.2), pick(swizzle.3)]
+        }
+
+        match self {
+            Texel::Float(v) => Texel::Float(apply(v, swizzle, 0.0, 1.0)),
+            Texel::Uint(v) => Texel::Uint(apply(v, swizzle, 0, 1)),
+            Texel::Int(v) => Texel::Int(apply(v, swizzle, 0, 1)),
+        }
+    }
+}
+
+/// Error encoding or decoding a texel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TexelError {
+    /// Block-compressed formats can't be accessed per texel.
+    Compressed,
+    /// The channel type isn't supported by the surface type,
+    /// or doesn't match the texel representation.
+    Channel(ChannelType),
+    /// The data is too short to hold a texel of the given size in bytes.
+    Size(usize),
+}
+
+impl fmt::Display for TexelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TexelError::Channel(channel) => write!(f, "{}: {:?}", self.description(), channel),
+            TexelError::Size(size) => write!(f, "{}: {}", self.description(), size),
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for TexelError {
+    fn description(&self) -> &str {
+        match *self {
+            TexelError::Compressed => "Block-compressed formats can't be accessed per texel",
+            TexelError::Channel(_) => "The channel type doesn't match the surface type or texel",
+            TexelError::Size(_) => "The data is too short to hold a texel",
+        }
+    }
+}
+
+/// Location of a component within the texel data.
+struct Field {
+    /// Component index, in RGBA order.
+    component: usize,
+    /// Offset of the containing word in bytes.
+    offset: usize,
+    /// Offset of the lowest bit within the word.
+    shift: u32,
+    /// Number of bits.
+    bits: u32,
+}
+
+macro_rules! fields {
+    ( $( $component:expr => $offset:expr, $shift:expr, $bits:expr; )* ) => {
+        &[ $( Field { component: $component, offset: $offset, shift: $shift, bits: $bits }, )* ]
+    }
+}
+
+fn layout(format: Format) -> Option<&'static [Field]> {
+    use super::SurfaceType::*;
+
+    let stencil = format.1 == ChannelType::Uint;
+    let fields: &'static [Field] = match format.0 {
+        R4_G4 => fields!{ 0 => 0, 4, 4; 1 => 0, 0, 4; },
+        R4_G4_B4_A4 => fields!{ 0 => 0, 12, 4; 1 => 0, 8, 4; 2 => 0, 4, 4; 3 => 0, 0, 4; },
+        R5_G5_B5_A1 => fields!{ 0 => 0, 11, 5; 1 => 0, 6, 5; 2 => 0, 1, 5; 3 => 0, 0, 1; },
+        R5_G6_B5 => fields!{ 0 => 0, 11, 5; 1 => 0, 5, 6; 2 => 0, 0, 5; },
+        R8 => fields!{ 0 => 0, 0, 8; },
+        R8_G8 => fields!{ 0 => 0, 0, 8; 1 => 1, 0, 8; },
+        R8_G8_B8_A8 => fields!{ 0 => 0, 0, 8; 1 => 1, 0, 8; 2 => 2, 0, 8; 3 => 3, 0, 8; },
+        B8_G8_R8_A8 => fields!{ 2 => 0, 0, 8; 1 => 1, 0, 8; 0 => 2, 0, 8; 3 => 3, 0, 8; },
+        R10_G10_B10_A2 => fields!{ 0 => 0, 0, 10; 1 => 0, 10, 10; 2 => 0, 20, 10; 3 => 0, 30, 2; },
+        R11_G11_B10 => fields!{ 0 => 0, 0, 11; 1 => 0, 11, 11; 2 => 0, 22, 10; },
+        R16 => fields!{ 0 => 0, 0, 16; },
+        R16_G16 => fields!{ 0 => 0, 0, 16; 1 => 2, 0, 16; },
+        R16_G16_B16 => fields!{ 0 => 0, 0, 16; 1 => 2, 0, 16; 2 => 4, 0, 16; },
+        R16_G16_B16_A16 => fields!{ 0 => 0, 0, 16; 1 => 2, 0, 16; 2 => 4, 0, 16; 3 => 6, 0, 16; },
+        R32 => fields!{ 0 => 0, 0, 32; },
+        R32_G32 => fields!{ 0 => 0, 0, 32; 1 => 4, 0, 32; },
+        R32_G32_B32 => fields!{ 0 => 0, 0, 32; 1 => 4, 0, 32; 2 => 8, 0, 32; },
+        R32_G32_B32_A32 => fields!{ 0 => 0, 0, 32; 1 => 4, 0, 32; 2 => 8, 0, 32; 3 => 12, 0, 32; },
+        D16 => fields!{ 0 => 0, 0, 16; },
+        D24 => fields!{ 0 => 0, 0, 24; },
+        // Integer views of the combined formats access the stencil.
+        D24_S8 if stencil => fields!{ 0 => 0, 24, 8; },
+        D24_S8 => fields!{ 0 => 0, 0, 24; },
+        D32 => fields!{ 0 => 0, 0, 32; },
+        D32_S8 if stencil => fields!{ 0 => 4, 0, 8; },
+        D32_S8 => fields!{ 0 => 0, 0, 32; },
+        S8 => fields!{ 0 => 0, 0, 8; },
+        _ => return None,
+    };
+    Some(fields)
+}
+
+/// Channel type describing how the bits of a non-integer field are stored.
+/// The depth of 32-bit depth formats is always stored as a float.
+fn storage_channel(format: Format) -> ChannelType {
+    match format.0 {
+        SurfaceType::D32 | SurfaceType::D32_S8 => ChannelType::Float,
+        _ => format.1,
+    }
+}
+
+fn mask(bits: u32) -> u64 {
+    (1u64 << bits) - 1
+}
+
+fn read_field(data: &[u8], field: &Field) -> u64 {
+    let bytes = ((field.shift + field.bits + 7) / 8) as usize;
+    let word = data[field.offset .. field.offset + bytes]
+        .iter()
+        .rev()
+        .fold(0u64, |word, &byte| word << 8 | byte as u64);
+    (word >> field.shift) & mask(field.bits)
+}
+
+fn write_field(data: &mut [u8], field: &Field, value: u64) {
+    let bytes = ((field.shift + field.bits + 7) / 8) as usize;
+    let field_mask = mask(field.bits) << field.shift;
+    for (i, byte) in data[field.offset .. field.offset + bytes].iter_mut().enumerate() {
+        let byte_mask = (field_mask >> (i * 8)) as u8;
+        let bits = ((value << field.shift) >> (i * 8)) as u8;
+        *byte = (*byte & !byte_mask) | (bits & byte_mask);
+    }
+}
+
+fn sign_extend(value: u64, bits: u32) -> i64 {
+    let shift = 64 - bits;
+    ((value << shift) as i64) >> shift
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Decode an unsigned float with a 5-bit exponent, as used by `R11_G11_B10`.
+fn ufloat_to_f32(value: u64, mantissa_bits: u32) -> f32 {
+    let exponent = (value >> mantissa_bits) as i32;
+    let mantissa = (value & mask(mantissa_bits)) as f32 / (1u32 << mantissa_bits) as f32;
+    match exponent {
+        0 => mantissa * 2f32.powi(-14),
+        0x1F if mantissa == 0.0 => ::std::f32::INFINITY,
+        0x1F => ::std::f32::NAN,
+        _ => (1.0 + mantissa) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// Encode an unsigned float with a 5-bit exponent, as used by `R11_G11_B10`.
+/// Negative values and NaN are flushed to zero.
+fn f32_to_ufloat(value: f32, mantissa_bits: u32) -> u64 {
+    let half = F16::from_f32(value.max(0.0)).0 as u64;
+    let shift = 10 - mantissa_bits;
+    (half + (1 << (shift - 1))) >> shift
+}
+
+impl F16 {
+    /// Convert to a single precision float.
+    pub fn to_f32(self) -> f32 {
+        let sign = (self.0 as u32 & 0x8000) << 16;
+        let exponent = (self.0 as u32 >> 10) & 0x1F;
+        let mantissa = self.0 as u32 & 0x3FF;
+        let bits = match exponent {
+            0 => {
+                // zero or subnormal
+                let value = mantissa as f32 * 2f32.powi(-24);
+                return if sign != 0 { -value } else { value };
+            }
+            0x1F => sign | 0x7F80_0000 | (mantissa << 13),
+            _ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+        };
+        f32::from_bits(bits)
+    }
+
+    /// Convert from a single precision float, rounding to the nearest even value.
+    pub fn from_f32(value: f32) -> F16 {
+        let bits = value.to_bits();
+        let sign = ((bits >> 16) & 0x8000) as u16;
+        let exponent = ((bits >> 23) & 0xFF) as i32;
+        let mantissa = bits & 0x7F_FFFF;
+
+        if exponent == 0xFF {
+            let nan = if mantissa != 0 { 0x200 } else { 0 };
+            return F16(sign | 0x7C00 | nan);
+        }
+        let exponent = exponent - 127 + 15;
+        if exponent >= 0x1F {
+            return F16(sign | 0x7C00);
+        }
+
+        let (half, remainder, halfway) = if exponent <= 0 {
+            if exponent < -10 {
+                return F16(sign);
+            }
+            let mantissa = mantissa | 0x80_0000;
+            let shift = (14 - exponent) as u32;
+            (mantissa >> shift, mantissa & ((1 << shift) - 1), 1 << (shift - 1))
+        } else {
+            ((exponent as u32) << 10 | mantissa >> 13, mantissa & 0x1FFF, 0x1000)
+        };
+        let round_up = remainder > halfway || (remainder == halfway && half & 1 != 0);
+        // A carry out of the mantissa correctly bumps the exponent.
+        F16(sign | (half + round_up as u32) as u16)
+    }
+}
+
+impl Format {
+    /// Decode a single texel from the start of `data`.
+    pub fn decode_texel(&self, data: &[u8]) -> Result<Texel, TexelError> {
+        let fields = self.texel_layout(data.len())?;
+        let channel = storage_channel(*self);
+
+        match self.1 {
+            ChannelType::Uint => {
+                let mut texel = [0, 0, 0, 1];
+                for field in fields {
+                    texel[field.component] = read_field(data, field) as u32;
+                }
+                Ok(Texel::Uint(texel))
+            }
+            ChannelType::Int => {
+                let mut texel = [0, 0, 0, 1];
+                for field in fields {
+                    texel[field.component] = sign_extend(read_field(data, field), field.bits) as i32;
+                }
+                Ok(Texel::Int(texel))
+            }
+            _ => {
+                let mut texel = [0.0, 0.0, 0.0, 1.0];
+                for field in fields {
+                    let raw = read_field(data, field);
+                    let max = mask(field.bits) as f32;
+                    texel[field.component] = match (channel, field.bits) {
+                        (ChannelType::Unorm, _) => raw as f32 / max,
+                        (ChannelType::Srgb, _) if field.component < 3 => srgb_to_linear(raw as f32 / max),
+                        (ChannelType::Srgb, _) => raw as f32 / max,
+                        (ChannelType::Inorm, _) => {
+                            let max = mask(field.bits - 1) as f32;
+                            (sign_extend(raw, field.bits) as f32 / max).max(-1.0)
+                        }
+                        (ChannelType::Uscaled, _) => raw as f32,
+                        (ChannelType::Iscaled, _) => sign_extend(raw, field.bits) as f32,
+                        (ChannelType::Float, 32) => f32::from_bits(raw as u32),
+                        (ChannelType::Float, 16) => F16(raw as u16).to_f32(),
+                        (ChannelType::Float, 11) => ufloat_to_f32(raw, 6),
+                        (ChannelType::Float, 10) => ufloat_to_f32(raw, 5),
+                        _ => return Err(TexelError::Channel(self.1)),
+                    };
+                }
+                Ok(Texel::Float(texel))
+            }
+        }
+    }
+
+    /// Encode a single texel into the start of `data`.
+    ///
+    /// Values are clamped to the range of the channel type,
+    /// components missing from the format are ignored.
+    pub fn encode_texel(&self, texel: Texel, data: &mut [u8]) -> Result<(), TexelError> {
+        let fields = self.texel_layout(data.len())?;
+        let channel = storage_channel(*self);
+
+        for field in fields {
+            let c = field.component;
+            let value = match (texel, self.1) {
+                (Texel::Uint(v), ChannelType::Uint) => (v[c] as u64).min(mask(field.bits)),
+                (Texel::Int(v), ChannelType::Int) => {
+                    let max = mask(field.bits - 1) as i64;
+                    (v[c] as i64).max(-max - 1).min(max) as u64 & mask(field.bits)
+                }
+                (Texel::Float(v), ChannelType::Unorm) |
+                (Texel::Float(v), ChannelType::Inorm) |
+                (Texel::Float(v), ChannelType::Srgb) |
+                (Texel::Float(v), ChannelType::Float) |
+                (Texel::Float(v), ChannelType::Uscaled) |
+                (Texel::Float(v), ChannelType::Iscaled) => {
+                    let max = mask(field.bits) as f32;
+                    match (channel, field.bits) {
+                        (ChannelType::Unorm, _) => (v[c].max(0.0).min(1.0) * max).round() as u64,
+                        (ChannelType::Srgb, _) if c < 3 => {
+                            (linear_to_srgb(v[c].max(0.0).min(1.0)) * max).round() as u64
+                        }
+                        (ChannelType::Srgb, _) => (v[c].max(0.0).min(1.0) * max).round() as u64,
+                        (ChannelType::Inorm, _) => {
+                            let max = mask(field.bits - 1) as f32;
+                            let value = (v[c].max(-1.0).min(1.0) * max).round() as i64;
+                            value as u64 & mask(field.bits)
+                        }
+                        (ChannelType::Uscaled, _) => v[c].max(0.0).min(max).round() as u64,
+                        (ChannelType::Iscaled, _) => {
+                            let max = mask(field.bits - 1) as f32;
+                            let value = v[c].max(-max - 1.0).min(max).round() as i64;
+                            value as u64 & mask(field.bits)
+                        }
+                        (ChannelType::Float, 32) => v[c].to_bits() as u64,
+                        (ChannelType::Float, 16) => F16::from_f32(v[c]).0 as u64,
+                        (ChannelType::Float, 11) => f32_to_ufloat(v[c], 6),
+                        (ChannelType::Float, 10) => f32_to_ufloat(v[c], 5),
+                        _ => return Err(TexelError::Channel(self.1)),
+                    }
+                }
+                _ => return Err(TexelError::Channel(self.1)),
+            };
+            write_field(data, field, value);
+        }
+
+        Ok(())
+    }
+
+    fn texel_layout(&self, size: usize) -> Result<&'static [Field], TexelError> {
+        if self.0.is_compressed() {
+            return Err(TexelError::Compressed);
+        }
+        let fields = layout(*self).ok_or(TexelError::Channel(self.1))?;
+        let texel_size = self.0.describe_bits().total as usize / 8;
+        if size < texel_size {
+            return Err(TexelError::Size(texel_size));
+        }
+        Ok(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use format::ChannelType::*;
+    use format::SurfaceType::*;
+
+    #[test]
+    fn test_half_float() {
+        for &value in &[0.0, 1.0, -2.5, 0.333, 65504.0] {
+            let half = F16::from_f32(value);
+            assert!((half.to_f32() - value).abs() <= value.abs() / 1024.0);
+        }
+        assert_eq!(F16::from_f32(1.0).0, 0x3C00);
+        assert_eq!(F16::from_f32(1.0e6).0, 0x7C00);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let formats = [
+            (Format(R8_G8_B8_A8, Unorm), Texel::Float([0.0, 1.0, 0.2, 1.0])),
+            (Format(B8_G8_R8_A8, Srgb), Texel::Float([0.5, 0.25, 1.0, 0.2])),
+            (Format(R10_G10_B10_A2, Unorm), Texel::Float([0.1, 0.5, 0.9, 1.0 / 3.0])),
+            (Format(R11_G11_B10, Float), Texel::Float([1.5, 0.25, 3.0, 1.0])),
+            (Format(R16_G16, Inorm), Texel::Float([-1.0, 0.5, 0.0, 1.0])),
+            (Format(R16_G16_B16_A16, Float), Texel::Float([0.5, -8.0, 1000.0, 1.0])),
+            (Format(R32_G32, Int), Texel::Int([-5, 7, 0, 1])),
+            (Format(R5_G6_B5, Unorm), Texel::Float([1.0, 0.0, 1.0, 1.0])),
+            (Format(R16_G16, Iscaled), Texel::Float([-300.0, 12.0, 0.0, 1.0])),
+            (Format(R8_G8_B8_A8, Uscaled), Texel::Float([0.0, 255.0, 7.0, 1.0])),
+        ];
+        for &(format, texel) in &formats {
+            let mut data = [0u8; 16];
+            format.encode_texel(texel, &mut data).unwrap();
+            match (format.decode_texel(&data).unwrap(), texel) {
+                (Texel::Float(a), Texel::Float(b)) => {
+                    for (a, b) in a.iter().zip(b.iter()) {
+                        assert!((a - b).abs() < 0.01, "{:?}: {} != {}", format, a, b);
+                    }
+                }
+                (decoded, _) => assert_eq!(decoded, texel),
+            }
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        let format = Format(R10_G10_B10_A2, Uint);
+        let data = [0xFF, 0x03, 0, 0xC0];
+        assert_eq!(format.decode_texel(&data), Ok(Texel::Uint([0x3FF, 0, 0, 3])));
+        let format = Format(D24_S8, Uint);
+        assert_eq!(format.decode_texel(&data), Ok(Texel::Uint([0xC0, 0, 0, 1])));
+        let swizzle = Swizzle(Component::B, Component::G, Component::R, Component::One);
+        let texel = Format(R8_G8, Unorm).decode_texel(&[0xFF, 0]).unwrap();
+        assert_eq!(texel.swizzle(swizzle), Texel::Float([0.0, 0.0, 1.0, 1.0]));
+        assert_eq!(Format(BC1_R8_G8_B8, Unorm).decode_texel(&data), Err(TexelError::Compressed));
+        assert_eq!(Format(R32, Float).decode_texel(&data[.. 2]), Err(TexelError::Size(4)));
+    }
+}
@@ -9,9 +9,10 @@
 
 use std::error::Error;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::Range;
 
-use {format, state, target};
+use {device, format, state, target, Features};
 pub use target::{Layer, Level};
 
 
@@ -33,6 +34,10 @@ pub enum CreationError {
     Data(usize),
     /// The mentioned usage mode is not supported
     Usage(Usage),
+    /// The requested tiling is not supported.
+    Tiling(Tiling),
+    /// Sparse images require `Features::sparse_binding`.
+    Sparse,
 }
 
 impl fmt::Display for CreationError {
@@ -44,6 +49,7 @@ impl fmt::Display for CreationError {
             CreationError::Size(size) => write!(f, "{}: {}", self.description(), size),
             CreationError::Data(data) => write!(f, "{}: {}", self.description(), data),
             CreationError::Usage(usage) => write!(f, "{}: {:?}", self.description(), usage),
+            CreationError::Tiling(tiling) => write!(f, "{}: {:?}", self.description(), tiling),
             _ => write!(f, "{}", self.description()),
         }
     }
@@ -58,6 +64,8 @@ impl Error for CreationError {
             CreationError::Size(_) => "Unsupported size in one of the dimensions",
             CreationError::Data(_) => "The given data has a different size than the target texture slice",
             CreationError::Usage(_) => "The expected texture usage mode is not supported by a graphic API",
+            CreationError::Tiling(_) => "The requested tiling is not supported",
+            CreationError::Sparse => "Sparse images are not supported",
         }
     }
 }
@@ -73,6 +81,8 @@ pub enum ViewError {
     Layer(LayerError),
     /// An incompatible format was requested for the view.
     BadFormat,
+    /// The view kind is not compatible with the image kind.
+    BadKind(ViewKind),
     /// The backend refused for some reason.
     Unsupported,
 }
@@ -84,11 +94,61 @@ impl fmt::Display for ViewError {
             ViewError::Usage(usage) => write!(f, "{}: {:?}", description, usage),
             ViewError::Level(level) => write!(f, "{}: {}", description, level),
             ViewError::Layer(ref layer) => write!(f, "{}: {}", description, layer),
+            ViewError::BadKind(kind) => write!(f, "{}: {:?}", description, kind),
             _ => write!(f, "{}", description)
         }
     }
 }
 
+/// Error in a sampler description.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SamplerError {
+    /// Anisotropy is outside of the `[1, 16]` range.
+    Anisotropy(u8),
+    /// Anisotropic filtering is not supported by the device.
+    UnsupportedAnisotropy,
+    /// The reduction mode is not supported by the device.
+    UnsupportedReduction(ReductionMode),
+    /// A reduction mode other than `WeightedAverage` is used with comparison.
+    ComparisonReduction(ReductionMode),
+    /// Unnormalized coordinates are not supported by the device.
+    UnsupportedUnnormalized,
+    /// Unnormalized coordinates are used with different minification and
+    /// magnification filters, mipmapping, anisotropy or comparison.
+    Unnormalized,
+}
+
+impl fmt::Display for SamplerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = self.description();
+        match *self {
+            SamplerError::Anisotropy(max) => write!(f, "{}: {}", description, max),
+            SamplerError::UnsupportedReduction(mode) |
+            SamplerError::ComparisonReduction(mode) => write!(f, "{}: {:?}", description, mode),
+            _ => write!(f, "{}", description)
+        }
+    }
+}
+
+impl Error for SamplerError {
+    fn description(&self) -> &str {
+        match *self {
+            SamplerError::Anisotropy(_) =>
+                "Anisotropy is out of range",
+            SamplerError::UnsupportedAnisotropy =>
+                "Anisotropic filtering is not supported",
+            SamplerError::UnsupportedReduction(_) =>
+                "Reduction mode is not supported",
+            SamplerError::ComparisonReduction(_) =>
+                "Reduction mode can't be used with comparison",
+            SamplerError::UnsupportedUnnormalized =>
+                "Unnormalized coordinates are not supported",
+            SamplerError::Unnormalized =>
+                "Unnormalized coordinates are used with an unsupported filter or comparison",
+        }
+    }
+}
+
 impl Error for ViewError {
     fn description(&self) -> &str {
         match *self {
@@ -100,6 +160,8 @@ impl Error for ViewError {
                 "Selected array layer doesn't exist",
             ViewError::BadFormat =>
                 "An incompatible format was requested for the view",
+            ViewError::BadKind(_) =>
+                "The view kind is not compatible with the image kind",
             ViewError::Unsupported =>
                 "The backend refused for some reason",
         }
@@ -218,6 +280,67 @@ pub enum FilterMethod {
     Anisotropic(u8)
 }
 
+/// Filter used for a single sampling stage: minification, magnification or
+/// between mipmap levels.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Filter {
+    /// Use the nearest texel.
+    Nearest,
+    /// Linearly interpolate between the nearest texels.
+    Linear,
+}
+
+/// Anisotropic filtering description for the sampler.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Anisotropic {
+    /// Disable anisotropic filtering.
+    Off,
+    /// Enable anisotropic filtering with a given maximum, which must be
+    /// between 1 and 16, inclusive. Requires `Features::sampler_anisotropy`.
+    On(u8),
+}
+
+/// How the filtered texels are combined into the sampled value.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ReductionMode {
+    /// Weighted average of the texels, as defined by the filters.
+    WeightedAverage,
+    /// Component-wise minimum of the texels with non-zero weight.
+    Minimum,
+    /// Component-wise maximum of the texels with non-zero weight.
+    Maximum,
+}
+
+/// Arrangement of the texels of an image in memory.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Tiling {
+    /// Implementation-defined layout, optimal for device access.
+    Optimal,
+    /// Texels are laid out in rows, which allows reading and writing them
+    /// directly from the host. The supported kinds, formats and usages are
+    /// very limited, see `Device::image_format_properties`.
+    Linear,
+}
+
+/// Limits of images of a particular kind, format and usage, as reported by the device.
+#[derive(Clone, Copy, Debug)]
+pub struct FormatProperties {
+    /// Maximum extent of the image.
+    pub max_extent: device::Extent,
+    /// Maximum number of mipmap levels.
+    pub max_levels: Level,
+    /// Maximum number of array layers.
+    pub max_layers: Layer,
+    /// Supported sample counts, where bit `i` stands for `1 << i` samples.
+    pub sample_count_mask: NumSamples,
+    /// Maximum total size of the image resource in bytes.
+    pub max_resource_size: u64,
+}
+
 /// The face of a cube texture to do an operation on.
 #[allow(missing_docs)]
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -288,6 +411,19 @@ impl Kind {
         };
         (map(w), map(h), dm, AaMode::Single)
     }
+    /// Get the dimensionality of a particular mipmap level,
+    /// rounded up to whole blocks of the given format block.
+    ///
+    /// Buffer data of compressed images covers these dimensions, while
+    /// copy extents may stop at the texel edges of the level.
+    pub fn get_level_dimensions_aligned(&self, level: Level, block: format::FormatBlock) -> Dimensions {
+        let align = |val: Size, size: u8| {
+            let size = size as Size;
+            (val + size - 1) / size * size
+        };
+        let (w, h, d, aa) = self.get_level_dimensions(level);
+        (align(w, block.width), align(h, block.height), d, aa)
+    }
     /// Count the number of mipmap levels.
     pub fn get_num_levels(&self) -> Level {
         use std::cmp::max;
@@ -327,6 +463,81 @@ impl Kind {
             _ => false,
         }
     }
+    /// Check if a view of the given kind can be created for an image of this kind.
+    pub fn is_view_compatible(&self, view_kind: ViewKind) -> bool {
+        match (*self, view_kind) {
+            (Kind::D1(..), ViewKind::D1) |
+            (Kind::D1(..), ViewKind::D1Array) |
+            (Kind::D1Array(..), ViewKind::D1) |
+            (Kind::D1Array(..), ViewKind::D1Array) |
+            (Kind::D2(..), ViewKind::D2) |
+            (Kind::D2(..), ViewKind::D2Array) |
+            (Kind::D2Array(..), ViewKind::D2) |
+            (Kind::D2Array(..), ViewKind::D2Array) |
+            (Kind::D3(..), ViewKind::D3) => true,
+            (Kind::Cube(..), _) |
+            (Kind::CubeArray(..), _) => match view_kind {
+                ViewKind::D2 | ViewKind::D2Array | ViewKind::Cube | ViewKind::CubeArray => true,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+    /// Validate a view of the given kind and format, covering `range`, of an
+    /// image with this kind and `format`.
+    pub fn check_view(
+        &self,
+        format: format::Format,
+        view_kind: ViewKind,
+        view_format: format::Format,
+        range: &SubresourceRange,
+    ) -> Result<(), ViewError> {
+        if !self.is_view_compatible(view_kind) {
+            return Err(ViewError::BadKind(view_kind));
+        }
+        if format.0 != view_format.0 || range.aspects.is_empty() ||
+            !format.0.aspects().contains(range.aspects)
+        {
+            return Err(ViewError::BadFormat);
+        }
+        if range.levels.start >= range.levels.end {
+            return Err(ViewError::Level(range.levels.start));
+        }
+
+        let num_layers = range.layers.end.saturating_sub(range.layers.start);
+        let layers_fit = match view_kind {
+            ViewKind::D1 | ViewKind::D2 | ViewKind::D3 => num_layers == 1,
+            ViewKind::D1Array | ViewKind::D2Array => num_layers != 0,
+            ViewKind::Cube => num_layers == 6,
+            ViewKind::CubeArray => num_layers != 0 && num_layers % 6 == 0,
+        };
+        if !layers_fit || range.layers.end > self.get_num_layers() {
+            return Err(ViewError::Layer(LayerError::OutOfBounds(range.layers.clone())));
+        }
+
+        Ok(())
+    }
+}
+
+/// Specifies the kind of an image view, which may differ from the kind of
+/// the viewed image, e.g. a 2D array view of a cube map.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ViewKind {
+    /// A single row of texels.
+    D1,
+    /// An array of rows of texels.
+    D1Array,
+    /// A 2D view.
+    D2,
+    /// An array of 2D views.
+    D2Array,
+    /// A volume view.
+    D3,
+    /// A cube view of 6 consecutive layers.
+    Cube,
+    /// An array of cube views, the number of layers is a multiple of 6.
+    CubeArray,
 }
 
 bitflags!(
@@ -345,6 +556,9 @@ bitflags!(
         const STORAGE = 0x10;
         ///
         const SAMPLED = 0x20;
+        /// Views of the image may use a different channel type
+        /// than the image format.
+        const MUTABLE_FORMAT = 0x40;
         // TODO
     }
 );
@@ -361,6 +575,8 @@ pub const DEPTH_STENCIL_ATTACHMENT: Usage = Usage::DEPTH_STENCIL_ATTACHMENT;
 pub const STORAGE: Usage = Usage::STORAGE;
 ///
 pub const SAMPLED: Usage = Usage::SAMPLED;
+///
+pub const MUTABLE_FORMAT: Usage = Usage::MUTABLE_FORMAT;
 
 impl Usage {
     /// Can this image be used in transfer operations ?
@@ -390,19 +606,39 @@ pub enum WrapMode {
 }
 
 /// A wrapper for the LOD level of a texture.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd)]
+///
+/// The value is never NaN and zero is always positive, so that equal levels
+/// also compare and hash equal.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
-pub struct Lod(i16);
+pub struct Lod(f32);
+
+impl Lod {
+    /// Create a new LOD level. Panics if `value` is NaN.
+    pub fn new(value: f32) -> Lod {
+        assert!(!value.is_nan(), "LOD level can't be NaN");
+        // `-0.0 + 0.0` is `0.0`, every other value is unchanged.
+        Lod(value + 0.0)
+    }
+}
+
+impl Eq for Lod {}
+
+impl Hash for Lod {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state)
+    }
+}
 
 impl From<f32> for Lod {
     fn from(v: f32) -> Lod {
-        Lod((v * 8.0) as i16)
+        Lod::new(v)
     }
 }
 
 impl Into<f32> for Lod {
     fn into(self) -> f32 {
-        self.0 as f32 / 8.0
+        self.0
     }
 }
 
@@ -430,13 +666,53 @@ impl Into<[f32; 4]> for PackedColor {
     }
 }
 
+/// Color used for texels sampled outside of the image with `WrapMode::Border`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum BorderColor {
+    /// All components are zero.
+    TransparentBlack,
+    /// Color components are zero, alpha is one.
+    OpaqueBlack,
+    /// All components are one.
+    OpaqueWhite,
+    /// Arbitrary color. Requires `Features::sampler_border_color`.
+    Custom(PackedColor),
+}
+
+impl From<PackedColor> for BorderColor {
+    fn from(color: PackedColor) -> BorderColor {
+        match color.0 {
+            0x00000000 => BorderColor::TransparentBlack,
+            0xFF000000 => BorderColor::OpaqueBlack,
+            0xFFFFFFFF => BorderColor::OpaqueWhite,
+            _ => BorderColor::Custom(color),
+        }
+    }
+}
+
+impl Into<[f32; 4]> for BorderColor {
+    fn into(self) -> [f32; 4] {
+        match self {
+            BorderColor::TransparentBlack => [0.0, 0.0, 0.0, 0.0],
+            BorderColor::OpaqueBlack => [0.0, 0.0, 0.0, 1.0],
+            BorderColor::OpaqueWhite => [1.0, 1.0, 1.0, 1.0],
+            BorderColor::Custom(color) => color.into(),
+        }
+    }
+}
+
 /// Specifies how to sample from a texture.
 // TODO: document the details of sampling.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct SamplerInfo {
-    /// Filter method to use.
-    pub filter: FilterMethod,
+    /// Minification filter.
+    pub min_filter: Filter,
+    /// Magnification filter.
+    pub mag_filter: Filter,
+    /// Filter between mipmap levels, `None` disables mipmapping.
+    pub mip_filter: Option<Filter>,
     /// Wrapping mode for each of the U, V, and W axis (S, T, and R in OpenGL
     /// speak).
     pub wrap_mode: (WrapMode, WrapMode, WrapMode),
@@ -449,22 +725,73 @@ pub struct SamplerInfo {
     /// Comparison mode, used primary for a shadow map.
     pub comparison: Option<state::Comparison>,
     /// Border color is used when one of the wrap modes is set to border.
-    pub border: PackedColor,
+    pub border: BorderColor,
+    /// Anisotropic filtering.
+    pub anisotropic: Anisotropic,
+    /// Whether the texture coordinates are normalized to `[0, 1]`. Unnormalized
+    /// coordinates are given in texels and require equal minification and
+    /// magnification filters, without mipmapping, anisotropy or comparison.
+    pub normalized: bool,
+    /// How the filtered texels are combined. Modes other than
+    /// `WeightedAverage` require `Features::sampler_reduction` and
+    /// can't be used with comparison.
+    pub reduction: ReductionMode,
 }
 
 impl SamplerInfo {
     /// Create a new sampler description with a given filter method and wrapping mode, using no LOD
     /// modifications.
     pub fn new(filter: FilterMethod, wrap: WrapMode) -> SamplerInfo {
+        let (min_mag, mip_filter, anisotropic) = match filter {
+            FilterMethod::Scale => (Filter::Nearest, None, Anisotropic::Off),
+            FilterMethod::Mipmap => (Filter::Nearest, Some(Filter::Nearest), Anisotropic::Off),
+            FilterMethod::Bilinear => (Filter::Linear, None, Anisotropic::Off),
+            FilterMethod::Trilinear => (Filter::Linear, Some(Filter::Linear), Anisotropic::Off),
+            FilterMethod::Anisotropic(max) => (Filter::Linear, Some(Filter::Linear), Anisotropic::On(max)),
+        };
         SamplerInfo {
-            filter: filter,
+            min_filter: min_mag,
+            mag_filter: min_mag,
+            mip_filter,
             wrap_mode: (wrap, wrap, wrap),
-            lod_bias: Lod(0),
-            lod_range: Lod(-8000)..Lod(8000),
+            lod_bias: Lod(0.0),
+            lod_range: Lod(-1000.0)..Lod(1000.0),
             comparison: None,
-            border: PackedColor(0),
+            border: BorderColor::TransparentBlack,
+            anisotropic,
+            normalized: true,
+            reduction: ReductionMode::WeightedAverage,
         }
     }
+
+    /// Check that the sampler can be created on a device with the given features.
+    pub fn validate(&self, features: &Features) -> Result<(), SamplerError> {
+        let anisotropic = match self.anisotropic {
+            Anisotropic::On(max) if max == 0 || max > 16 => return Err(SamplerError::Anisotropy(max)),
+            Anisotropic::On(max) => max > 1,
+            Anisotropic::Off => false,
+        };
+        if anisotropic && !features.sampler_anisotropy {
+            return Err(SamplerError::UnsupportedAnisotropy);
+        }
+        if self.reduction != ReductionMode::WeightedAverage {
+            if !features.sampler_reduction {
+                return Err(SamplerError::UnsupportedReduction(self.reduction));
+            }
+            if self.comparison.is_some() {
+                return Err(SamplerError::ComparisonReduction(self.reduction));
+            }
+        }
+        if !self.normalized && !features.sampler_unnormalized {
+            return Err(SamplerError::UnsupportedUnnormalized);
+        }
+        if !self.normalized && (self.min_filter != self.mag_filter || self.mip_filter.is_some() ||
+            anisotropic || self.comparison.is_some())
+        {
+            return Err(SamplerError::Unnormalized);
+        }
+        Ok(())
+    }
 }
 
 /// Texture resource view descriptor.
@@ -665,3 +992,76 @@ pub struct SubresourceRange {
     /// Included array levels
     pub layers: Range<Layer>,
 }
+
+/// Layout of a single subresource of a linear image in memory, relative to
+/// the start of the image.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SubresourceFootprint {
+    /// Byte range occupied by the subresource.
+    pub slice: Range<u64>,
+    /// Byte distance between consecutive rows.
+    pub row_pitch: u64,
+    /// Byte distance between consecutive array layers.
+    pub array_pitch: u64,
+    /// Byte distance between consecutive depth slices.
+    pub depth_pitch: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use pso::tests::features;
+    use super::*;
+
+    fn hash(lod: Lod) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        lod.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_lod() {
+        assert_eq!(Lod::from(-0.0), Lod::from(0.0));
+        assert_eq!(hash(Lod::from(-0.0)), hash(Lod::from(0.0)));
+        assert_eq!(Into::<f32>::into(Lod::from(1.5)), 1.5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_lod_nan() {
+        let _ = Lod::from(::std::f32::NAN);
+    }
+
+    #[test]
+    fn test_sampler() {
+        let mut features = features();
+        let info = SamplerInfo::new(FilterMethod::Bilinear, WrapMode::Clamp);
+        assert_eq!(info.validate(&features), Ok(()));
+
+        let anisotropic = SamplerInfo::new(FilterMethod::Anisotropic(16), WrapMode::Clamp);
+        assert_eq!(anisotropic.validate(&features), Err(SamplerError::UnsupportedAnisotropy));
+        features.sampler_anisotropy = true;
+        assert_eq!(anisotropic.validate(&features), Ok(()));
+        let anisotropic = SamplerInfo::new(FilterMethod::Anisotropic(17), WrapMode::Clamp);
+        assert_eq!(anisotropic.validate(&features), Err(SamplerError::Anisotropy(17)));
+
+        let minimum = SamplerInfo { reduction: ReductionMode::Minimum, .. info.clone() };
+        assert_eq!(minimum.validate(&features), Err(SamplerError::UnsupportedReduction(ReductionMode::Minimum)));
+        features.sampler_reduction = true;
+        assert_eq!(minimum.validate(&features), Ok(()));
+        let comparison = SamplerInfo { comparison: Some(state::Comparison::Less), .. minimum };
+        assert_eq!(comparison.validate(&features), Err(SamplerError::ComparisonReduction(ReductionMode::Minimum)));
+
+        let unnormalized = SamplerInfo { normalized: false, .. info.clone() };
+        assert_eq!(unnormalized.validate(&features), Err(SamplerError::UnsupportedUnnormalized));
+        features.sampler_unnormalized = true;
+        assert_eq!(unnormalized.validate(&features), Ok(()));
+        let mipmapped = SamplerInfo { mip_filter: Some(Filter::Nearest), .. unnormalized.clone() };
+        assert_eq!(mipmapped.validate(&features), Err(SamplerError::Unnormalized));
+        let mixed = SamplerInfo { min_filter: Filter::Nearest, .. unnormalized.clone() };
+        assert_eq!(mixed.validate(&features), Err(SamplerError::Unnormalized));
+        let comparison = SamplerInfo { comparison: Some(state::Comparison::Less), .. unnormalized };
+        assert_eq!(comparison.validate(&features), Err(SamplerError::Unnormalized));
+    }
+}
@@ -28,7 +28,7 @@ pub use self::device::Device;
 pub use self::pool::{CommandPool, RawCommandPool, SubpassCommandPool};
 pub use self::pso::{DescriptorPool};
 pub use self::queue::{
-    CommandQueue, QueueFamily, QueueType, RawCommandQueue, RawSubmission, Submission,
+    CommandQueue, QueueFamily, QueueFamilyId, QueueType, RawCommandQueue, RawSubmission, Submission,
     General, Graphics, Compute, Transfer,
 };
 pub use self::window::{
@@ -142,6 +142,38 @@ pub struct Features {
     pub sampler_anisotropy: bool,
     /// Support setting border texel colors.
     pub sampler_border_color: bool,
+    /// Support the minimum and maximum sampler reduction modes.
+    pub sampler_reduction: bool,
+    /// Support unnormalized texel coordinates in samplers.
+    pub sampler_unnormalized: bool,
+    /// Support keeping memory mapped while the device accesses it.
+    pub persistent_mapping: bool,
+    /// Support sparse buffers and images, bound through the queue.
+    pub sparse_binding: bool,
+    /// Support partially resident sparse buffers.
+    pub sparse_residency_buffer: bool,
+    /// Support partially resident sparse 2D images, bound by image regions.
+    pub sparse_residency_image: bool,
+    /// Support BC1-BC7 compressed formats.
+    pub format_bc: bool,
+    /// Support ETC2 and EAC compressed formats.
+    pub format_etc2: bool,
+    /// Support ASTC LDR compressed formats.
+    pub format_astc_ldr: bool,
+    /// Support the depth bounds test.
+    pub depth_bounds: bool,
+    /// Support lines wider than one pixel.
+    pub wide_lines: bool,
+    /// Support logical operations on color targets.
+    pub logic_op: bool,
+    /// Support capturing the vertex output of a pipeline into buffers.
+    pub stream_output: bool,
+    /// Support drawing the vertices captured by stream output.
+    pub draw_auto: bool,
+    /// Support events, signaled and waited on by the host and command buffers.
+    pub events: bool,
+    /// Support blitting 1D and 3D images, otherwise blits are limited to 2D and cube images.
+    pub blit_1d_3d: bool,
 }
 
 /// Limits of the device.
@@ -158,6 +190,14 @@ pub struct Limits {
     pub max_compute_group_count: [usize; 3],
     ///
     pub max_compute_group_size: [usize; 3],
+    /// Maximum number of vertex input attributes.
+    pub max_vertex_input_attributes: usize,
+    /// Maximum number of vertex input buffers.
+    pub max_vertex_input_bindings: usize,
+    /// Maximum offset of a vertex input attribute, in bytes.
+    pub max_vertex_input_attribute_offset: usize,
+    /// Maximum stride of a vertex input buffer, in bytes.
+    pub max_vertex_input_binding_stride: usize,
 
     /// The alignment of the start of the buffer used as a GPU copy source, in bytes, non-zero.
     pub min_buffer_copy_offset_alignment: usize,
@@ -166,6 +206,9 @@ pub struct Limits {
     pub min_buffer_copy_pitch_alignment: usize,
     /// The alignment of the start of buffer used for uniform buffer updates, in bytes, non-zero.
     pub min_uniform_buffer_offset_alignment: usize,
+    /// The granularity of flushed and invalidated ranges of non-coherent
+    /// mapped memory, in bytes, non-zero.
+    pub non_coherent_atom_size: usize,
 }
 
 /// Describes what geometric primitives are created from vertex data.
@@ -283,12 +326,18 @@ pub trait Backend: 'static + Sized + Eq + Clone + Hash + Debug + Any {
 
     type Fence:               Debug + Any + Send + Sync;
     type Semaphore:           Debug + Any + Send + Sync;
+    type Event:               Debug + Any + Send + Sync;
 }
 
-#[allow(missing_docs)]
+/// Error submitting work to a queue.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
-pub enum SubmissionError {}
+pub enum SubmissionError {
+    /// Out of host or device memory.
+    OutOfMemory,
+    /// The logical device was lost.
+    DeviceLost,
+}
 
 impl fmt::Display for SubmissionError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -298,7 +347,10 @@ impl fmt::Display for SubmissionError {
 
 impl Error for SubmissionError {
     fn description(&self) -> &str {
-        "Submission error"
+        match *self {
+            SubmissionError::OutOfMemory => "Out of memory",
+            SubmissionError::DeviceLost => "Device lost",
+        }
     }
 }
 
@@ -3,6 +3,7 @@
 use std::mem;
 use std::ops::Range;
 use {buffer, image};
+use queue::QueueFamilyId;
 use Backend;
 
 /// A trait for plain-old-data types.
@@ -73,17 +74,59 @@ pub const CPU_CACHED: Properties = Properties::CPU_CACHED;
 ///
 pub const LAZILY_ALLOCATED: Properties = Properties::LAZILY_ALLOCATED;
 
+/// A resource, which memory may be aliased by other resources.
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub enum Resource<'a, B: Backend> {
+    Buffer(&'a B::Buffer),
+    Image(&'a B::Image),
+}
+
+impl<'a, B: Backend> Clone for Resource<'a, B> {
+    fn clone(&self) -> Self {
+        match *self {
+            Resource::Buffer(buffer) => Resource::Buffer(buffer),
+            Resource::Image(image) => Resource::Image(image),
+        }
+    }
+}
+
+/// How resources are shared between queue families.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum SharingMode {
+    /// Owned by a single queue family at a time.
+    /// Transferring the ownership requires barriers with `families` set,
+    /// releasing on the source queue and acquiring on the destination one.
+    Exclusive,
+    /// Accessible by all the queue families of the device without transfers.
+    /// May be slower than `Exclusive`.
+    Concurrent,
+}
+
 #[allow(missing_docs)] //TODO
 #[derive(Clone, Debug)]
 pub enum Barrier<'a, B: Backend> {
+    /// `families` transfers the ownership of an `Exclusive` resource
+    /// from the start queue family to the end one.
     Buffer {
         states: Range<buffer::State>,
         target: &'a B::Buffer,
+        families: Option<Range<QueueFamilyId>>,
     },
+    /// See `Buffer` for `families`.
     Image {
         states: Range<image::State>,
         target: &'a B::Image,
         range: image::SubresourceRange,
+        families: Option<Range<QueueFamilyId>>,
+    },
+    /// Switch between resources sharing the same memory.
+    /// The contents of the `after` resource are undefined after the barrier.
+    /// `None` stands for any resource aliasing the other one.
+    Aliasing {
+        before: Option<Resource<'a, B>>,
+        after: Option<Resource<'a, B>>,
     },
 }
 
@@ -1,17 +1,18 @@
 //! Graphics pipeline descriptor.
 
-use {state as s, Backend, Primitive};
-use super::EntryPoint;
-use super::input_assembler::{AttributeDesc, InputAssemblerDesc, VertexBufferDesc};
+use {state as s, target, Backend, Features, Limits, PatchSize, Primitive, Viewport};
+use device::Device;
+use format::{ChannelType, Format};
+use super::{CreationError, DescriptorSetLayoutBinding, EntryPoint, ShaderStageFlags, Stage};
+use super::reflect::{EntryPointReflection, ReflectError, ScalarType};
+use super::input_assembler::{self, AttributeDesc, InputAssemblerDesc, InputError, Location, VertexBufferDesc};
 use super::output_merger::{ColorInfo, DepthStencilDesc};
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
 
-// Vulkan:
-//  - SpecializationInfo not provided per shader
-//
 // D3D12:
 //  - rootSignature specified outside
-//  - logicOp can be set for each RTV
-//  - streamOutput not included
 //  - IA: semantic name and index extracted from shader reflection
 
 /// A complete set of shaders to build a graphics pipeline.
@@ -47,6 +48,10 @@ pub struct GraphicsPipelineDesc {
     pub blender: BlendDesc,
     /// Depth stencil (DSV)
     pub depth_stencil: Option<DepthStencilDesc>,
+    /// States baked into the pipeline or set by the command buffer.
+    pub baked_states: BakedStates,
+    /// Optional capture of the vertex output.
+    pub stream_output: Option<StreamOutputDesc>,
 }
 
 impl GraphicsPipelineDesc {
@@ -59,6 +64,412 @@ impl GraphicsPipelineDesc {
             input_assembler: InputAssemblerDesc::new(primitive),
             blender: BlendDesc::new(),
             depth_stencil: None,
+            baked_states: BakedStates::default(),
+            stream_output: None,
+        }
+    }
+
+    /// Validate the vertex buffers and attributes against the device features and limits.
+    pub fn validate_vertex_input(&self, features: &Features, limits: &Limits) -> Result<(), InputError> {
+        input_assembler::validate_vertex_input(&self.vertex_buffers, &self.attributes, features, limits)
+    }
+
+    /// Validate the patch size of the input assembler against the device limits
+    /// and the tessellation stages of the shader set.
+    pub fn validate_tessellation<B: Backend>(
+        &self, shaders: &GraphicsShaderSet<B>, limits: &Limits
+    ) -> Result<(), TessellationError> {
+        let has_stages = shaders.hull.is_some() && shaders.domain.is_some();
+        match self.input_assembler.primitive {
+            Primitive::PatchList(size) => {
+                if size == 0 || size > limits.max_patch_size {
+                    return Err(TessellationError::PatchSize(size));
+                }
+                if !has_stages {
+                    return Err(TessellationError::MissingStages);
+                }
+            }
+            _ => {
+                if shaders.hull.is_some() || shaders.domain.is_some() {
+                    return Err(TessellationError::UnexpectedStages);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate the stream output description against the device features.
+    pub fn validate_stream_output(&self, features: &Features) -> Result<(), StreamOutputError> {
+        let desc = match self.stream_output {
+            Some(ref desc) => desc,
+            None => return Ok(()),
+        };
+        if !features.stream_output {
+            return Err(StreamOutputError::Unsupported);
+        }
+        for element in &desc.elements {
+            if element.buffer as usize >= desc.strides.len() {
+                return Err(StreamOutputError::Buffer(element.buffer));
+            }
+            if element.component_count == 0 || element.start_component + element.component_count > 4 {
+                return Err(StreamOutputError::Components(element.location));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate the shader interfaces against the descriptor set layouts of the pipeline layout,
+    /// the vertex attributes and the color attachment formats of the subpass.
+    ///
+    /// Only the descriptors statically used by each entry point are checked.
+    /// Shader modules which weren't created from SPIR-V are skipped.
+    pub fn validate_shader_interface<B: Backend>(
+        &self,
+        device: &B::Device,
+        shaders: &GraphicsShaderSet<B>,
+        set_layouts: &[Vec<DescriptorSetLayoutBinding>],
+        color_formats: &[Format],
+    ) -> Result<(), CreationError> {
+        let stages = [
+            (Stage::Vertex, Some(&shaders.vertex)),
+            (Stage::Hull, shaders.hull.as_ref()),
+            (Stage::Domain, shaders.domain.as_ref()),
+            (Stage::Geometry, shaders.geometry.as_ref()),
+            (Stage::Fragment, shaders.fragment.as_ref()),
+        ];
+
+        for &(stage, entry) in &stages {
+            let entry = match entry {
+                Some(entry) => entry,
+                None => continue,
+            };
+            let reflection = match device.reflect_shader_module(entry.module) {
+                Ok(reflection) => reflection,
+                Err(ReflectError::NotSpirv) => continue,
+                Err(error) => return Err(CreationError::Reflect(error)),
+            };
+            if let Some(entry_point) = reflection.entry_point(entry.entry) {
+                self.validate_entry_point(stage, entry_point, set_layouts, color_formats)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_entry_point(
+        &self,
+        stage: Stage,
+        entry_point: &EntryPointReflection,
+        set_layouts: &[Vec<DescriptorSetLayoutBinding>],
+        color_formats: &[Format],
+    ) -> Result<(), CreationError> {
+        for desc in &entry_point.descriptors {
+            let (set, binding) = (desc.set as usize, desc.binding as usize);
+            let layout_binding = set_layouts
+                .get(set)
+                .and_then(|bindings| bindings.iter().find(|b| b.binding == binding))
+                .ok_or(CreationError::MissingDescriptor(set, binding))?;
+            if layout_binding.ty != desc.ty {
+                return Err(CreationError::DescriptorType(set, binding, layout_binding.ty));
+            }
+            if desc.count > layout_binding.count {
+                return Err(CreationError::DescriptorCount(set, binding));
+            }
+            if !layout_binding.stage_flags.contains(ShaderStageFlags::from(stage)) {
+                return Err(CreationError::DescriptorStage(set, binding, stage));
+            }
+        }
+
+        match stage {
+            Stage::Vertex => for input in &entry_point.inputs {
+                let attribute = self.attributes
+                    .iter()
+                    .find(|at| at.location == input.location)
+                    .ok_or(CreationError::MissingAttribute(input.location))?;
+                if !is_compatible_channel(input.ty, attribute.element.format.1) {
+                    return Err(CreationError::AttributeFormat(input.location, attribute.element.format));
+                }
+            },
+            Stage::Fragment => for output in &entry_point.outputs {
+                let format = *color_formats
+                    .get(output.location as usize)
+                    .ok_or(CreationError::MissingColorAttachment(output.location))?;
+                if !is_compatible_channel(output.ty, format.1) {
+                    return Err(CreationError::ColorFormat(output.location, format));
+                }
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Validate the blend description against the device features.
+    pub fn validate_blender(&self, features: &Features) -> Result<(), BlendError> {
+        self.blender.validate(features)
+    }
+
+    /// Validate the baked states against the device features and limits.
+    pub fn validate_baked_states(&self, features: &Features, limits: &Limits) -> Result<(), StateError> {
+        let states = &self.baked_states;
+        if states.viewport_count == 0 || states.viewport_count > limits.max_viewports {
+            return Err(StateError::ViewportCount(states.viewport_count));
+        }
+        if let State::Static(ref viewports) = states.viewports {
+            if viewports.len() != states.viewport_count {
+                return Err(StateError::ViewportMismatch(viewports.len()));
+            }
+        }
+        if let State::Static(ref scissors) = states.scissors {
+            if scissors.len() != states.viewport_count {
+                return Err(StateError::ScissorMismatch(scissors.len()));
+            }
+        }
+        if let State::Static(ref bounds) = states.depth_bounds {
+            if !(0.0 <= bounds.start && bounds.start <= bounds.end && bounds.end <= 1.0) {
+                return Err(StateError::DepthBounds);
+            }
+        }
+        if let Some((_, ref info)) = self.depth_stencil {
+            if info.depth_bounds && !features.depth_bounds {
+                return Err(StateError::UnsupportedDepthBounds);
+            }
+        }
+        if let State::Static(width) = self.rasterizer.line_width {
+            if !(width > 0.0) {
+                return Err(StateError::LineWidth);
+            }
+            if width != 1.0 && !features.wide_lines {
+                return Err(StateError::UnsupportedLineWidth);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check if a shader variable type can be read from or written to a channel type.
+fn is_compatible_channel(ty: ScalarType, channel: ChannelType) -> bool {
+    match (ty, channel) {
+        (ScalarType::Int(_), ChannelType::Int) |
+        (ScalarType::Uint(_), ChannelType::Uint) => true,
+        (ScalarType::Float(_), ChannelType::Int) |
+        (ScalarType::Float(_), ChannelType::Uint) => false,
+        (ScalarType::Float(_), _) => true,
+        _ => false,
+    }
+}
+
+/// Pipeline state, either baked into the pipeline or set by the command buffer.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature="serialize", derive(Serialize, Deserialize))]
+pub enum State<T> {
+    /// Value baked into the pipeline.
+    Static(T),
+    /// Value set with the corresponding command, e.g. `set_viewports`.
+    Dynamic,
+}
+
+impl<T> State<T> {
+    /// Check if the state is set by the command buffer.
+    pub fn is_dynamic(&self) -> bool {
+        match *self {
+            State::Static(_) => false,
+            State::Dynamic => true,
+        }
+    }
+
+    /// Return the baked value, if any.
+    pub fn static_value(&self) -> Option<&T> {
+        match *self {
+            State::Static(ref value) => Some(value),
+            State::Dynamic => None,
+        }
+    }
+}
+
+/// Pipeline states which can be baked into the pipeline or left dynamic.
+///
+/// Commands setting a state only have an effect if the bound pipeline
+/// leaves it dynamic, binding a pipeline overrides all of its baked states.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature="serialize", derive(Serialize, Deserialize))]
+pub struct BakedStates {
+    /// Number of viewports and scissor rectangles used by the pipeline.
+    pub viewport_count: usize,
+    /// Viewports, `viewport_count` of them if static.
+    pub viewports: State<Vec<Viewport>>,
+    /// Scissor rectangles, `viewport_count` of them if static.
+    pub scissors: State<Vec<target::Rect>>,
+    /// Blend constant color.
+    pub blend_constants: State<target::ColorValue>,
+    /// Stencil reference values of the front and back faces.
+    pub stencil_reference: State<(target::Stencil, target::Stencil)>,
+    /// Depth range for the depth bounds test.
+    pub depth_bounds: State<Range<f32>>,
+}
+
+impl Default for BakedStates {
+    fn default() -> Self {
+        BakedStates {
+            viewport_count: 1,
+            viewports: State::Dynamic,
+            scissors: State::Dynamic,
+            blend_constants: State::Dynamic,
+            stencil_reference: State::Dynamic,
+            depth_bounds: State::Static(0.0 .. 1.0),
+        }
+    }
+}
+
+/// Capture of the vertex output of the last vertex processing stage
+/// (vertex, domain or geometry shader) into buffers.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature="serialize", derive(Serialize, Deserialize))]
+pub struct StreamOutputDesc {
+    /// Captured shader outputs, written to each buffer in order.
+    pub elements: Vec<StreamOutputElement>,
+    /// Stride in bytes of each output buffer.
+    pub strides: Vec<u32>,
+    /// Stream passed on to the rasterizer, rasterization is disabled if `None`.
+    pub rasterized_stream: Option<u32>,
+}
+
+/// Shader output captured by stream output.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature="serialize", derive(Serialize, Deserialize))]
+pub struct StreamOutputElement {
+    /// Vertex stream, non-zero streams require a geometry shader.
+    pub stream: u32,
+    /// Location of the shader output.
+    pub location: Location,
+    /// First captured component.
+    pub start_component: u8,
+    /// Number of captured components.
+    pub component_count: u8,
+    /// Index of the output buffer.
+    pub buffer: u8,
+}
+
+/// Error validating the stream output of a pipeline.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamOutputError {
+    /// Stream output needs `Features::stream_output`.
+    Unsupported,
+    /// Output buffer has no stride.
+    Buffer(u8),
+    /// Captured components of the output at this location exceed a 4-component vector.
+    Components(Location),
+    /// The last vertex processing stage has no output at this location.
+    MissingOutput(Location),
+    /// The backend can't capture a part of the output at this location.
+    PartialOutput(Location),
+    /// Stride of the output buffer doesn't match the captured outputs.
+    Stride(u8),
+    /// The backend can't capture or rasterize this vertex stream.
+    Stream(u32),
+}
+
+impl fmt::Display for StreamOutputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StreamOutputError::Unsupported => write!(f, "{}", self.description()),
+            StreamOutputError::Buffer(buffer) |
+            StreamOutputError::Stride(buffer) => write!(f, "{} ({})", self.description(), buffer),
+            StreamOutputError::Stream(stream) => write!(f, "{} ({})", self.description(), stream),
+            StreamOutputError::Components(location) |
+            StreamOutputError::MissingOutput(location) |
+            StreamOutputError::PartialOutput(location) => write!(f, "{} (location {})", self.description(), location),
+        }
+    }
+}
+
+impl Error for StreamOutputError {
+    fn description(&self) -> &str {
+        match *self {
+            StreamOutputError::Unsupported => "Stream output isn't supported",
+            StreamOutputError::Buffer(_) => "Output buffer has no stride",
+            StreamOutputError::Components(_) => "Captured components are out of range",
+            StreamOutputError::MissingOutput(_) => "Captured shader output doesn't exist",
+            StreamOutputError::PartialOutput(_) => "Partial capture of a shader output isn't supported",
+            StreamOutputError::Stride(_) => "Output buffer stride doesn't match the captured outputs",
+            StreamOutputError::Stream(_) => "Vertex stream isn't supported",
+        }
+    }
+}
+
+/// Error validating the tessellation setup of a pipeline.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TessellationError {
+    /// Patch size is zero or exceeds `Limits::max_patch_size`.
+    PatchSize(PatchSize),
+    /// Patch lists need both a hull and a domain shader.
+    MissingStages,
+    /// Hull and domain shaders can only be used with patch lists.
+    UnexpectedStages,
+}
+
+impl fmt::Display for TessellationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TessellationError::PatchSize(size) => write!(f, "{} ({})", self.description(), size),
+            TessellationError::MissingStages |
+            TessellationError::UnexpectedStages => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for TessellationError {
+    fn description(&self) -> &str {
+        match *self {
+            TessellationError::PatchSize(_) => "Patch size is out of the device limits",
+            TessellationError::MissingStages => "Patch lists need hull and domain shaders",
+            TessellationError::UnexpectedStages => "Hull and domain shaders need a patch list primitive",
+        }
+    }
+}
+
+/// Error validating the baked states of a pipeline.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StateError {
+    /// Viewport count is zero or exceeds `Limits::max_viewports`.
+    ViewportCount(usize),
+    /// Number of static viewports doesn't match the viewport count.
+    ViewportMismatch(usize),
+    /// Number of static scissor rectangles doesn't match the viewport count.
+    ScissorMismatch(usize),
+    /// Depth bounds aren't an ordered range within `[0, 1]`.
+    DepthBounds,
+    /// Depth bounds test needs `Features::depth_bounds`.
+    UnsupportedDepthBounds,
+    /// Line width isn't positive.
+    LineWidth,
+    /// Line widths other than one need `Features::wide_lines`.
+    UnsupportedLineWidth,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StateError::ViewportCount(count) |
+            StateError::ViewportMismatch(count) |
+            StateError::ScissorMismatch(count) =>
+                write!(f, "{} ({})", self.description(), count),
+            StateError::DepthBounds |
+            StateError::UnsupportedDepthBounds |
+            StateError::LineWidth |
+            StateError::UnsupportedLineWidth => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for StateError {
+    fn description(&self) -> &str {
+        match *self {
+            StateError::ViewportCount(_) => "Viewport count is out of the device limits",
+            StateError::ViewportMismatch(_) => "Number of viewports doesn't match the viewport count",
+            StateError::ScissorMismatch(_) => "Number of scissors doesn't match the viewport count",
+            StateError::DepthBounds => "Depth bounds are out of range",
+            StateError::UnsupportedDepthBounds => "Depth bounds test isn't supported",
+            StateError::LineWidth => "Line width isn't positive",
+            StateError::UnsupportedLineWidth => "Wide lines aren't supported",
         }
     }
 }
@@ -91,6 +502,8 @@ pub struct Rasterizer {
     pub depth_bias: Option<DepthBias>,
     ///
     pub conservative: bool,
+    /// Width of rasterized lines, widths other than one need `Features::wide_lines`.
+    pub line_width: State<f32>,
 }
 
 impl Rasterizer {
@@ -103,6 +516,7 @@ impl Rasterizer {
             depth_clamping: true,
             depth_bias: None,
             conservative: false,
+            line_width: State::Static(1.0),
         }
     }
 }
@@ -128,10 +542,49 @@ impl BlendDesc {
             targets: Vec::new(),
         }
     }
+
+    /// Check that the blend description only uses supported features.
+    pub fn validate(&self, features: &Features) -> Result<(), BlendError> {
+        if self.logic_op.is_some() && !features.logic_op {
+            return Err(BlendError::UnsupportedLogicOp);
+        }
+        if !features.separate_blending_slots {
+            if let Some(first) = self.targets.first() {
+                if self.targets[1..].iter().any(|target| target != first) {
+                    return Err(BlendError::UnsupportedSeparateBlending);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error validating the blend description of a pipeline.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlendError {
+    /// Logic operations need `Features::logic_op`.
+    UnsupportedLogicOp,
+    /// Different blend states per color target need `Features::separate_blending_slots`.
+    UnsupportedSeparateBlending,
+}
+
+impl fmt::Display for BlendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+impl Error for BlendError {
+    fn description(&self) -> &str {
+        match *self {
+            BlendError::UnsupportedLogicOp => "Logic operations aren't supported",
+            BlendError::UnsupportedSeparateBlending => "Separate blend states per color target aren't supported",
+        }
+    }
 }
 
 ///
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(feature="serialize", derive(Serialize, Deserialize))]
 pub enum LogicOp {
     ///
@@ -167,3 +620,244 @@ pub enum LogicOp {
     ///
     Set,
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use format::{ChannelType, Format, SurfaceType};
+    use pso::{
+        AttributeDesc, CreationError, DescriptorSetLayoutBinding, DescriptorType, Element, ShaderReflection,
+        ShaderStageFlags, Stage,
+    };
+    use pso::reflect::tests::{fragment_module, to_bytes, vertex_module};
+    use pso::reflect::ScalarType;
+    use pso::DepthStencilInfo;
+    use Primitive;
+    use super::*;
+
+    fn binding(binding: usize, ty: DescriptorType, count: usize, stage_flags: ShaderStageFlags) -> DescriptorSetLayoutBinding {
+        DescriptorSetLayoutBinding { binding, ty, count, stage_flags }
+    }
+
+    /// Device without any optional feature.
+    pub(crate) fn features() -> Features {
+        Features {
+            indirect_execution: false,
+            draw_instanced: false,
+            draw_instanced_base: false,
+            draw_indexed_base: false,
+            draw_indexed_instanced: false,
+            draw_indexed_instanced_base_vertex: false,
+            draw_indexed_instanced_base: false,
+            instance_rate: false,
+            vertex_base: false,
+            srgb_color: false,
+            constant_buffer: false,
+            unordered_access_view: false,
+            separate_blending_slots: false,
+            copy_buffer: false,
+            sampler_objects: false,
+            sampler_lod_bias: false,
+            sampler_anisotropy: false,
+            sampler_border_color: false,
+            sampler_reduction: false,
+            sampler_unnormalized: false,
+            persistent_mapping: false,
+            sparse_binding: false,
+            sparse_residency_buffer: false,
+            sparse_residency_image: false,
+            format_bc: false,
+            format_etc2: false,
+            format_astc_ldr: false,
+            depth_bounds: false,
+            wide_lines: false,
+            logic_op: false,
+            stream_output: false,
+            draw_auto: false,
+            events: false,
+            blit_1d_3d: false,
+        }
+    }
+
+    /// Device with small, Vulkan-like minimum limits.
+    pub(crate) fn limits() -> Limits {
+        Limits {
+            max_texture_size: 4096,
+            max_patch_size: 32,
+            max_viewports: 16,
+            max_compute_group_count: [65535; 3],
+            max_compute_group_size: [128, 128, 64],
+            max_vertex_input_attributes: 16,
+            max_vertex_input_bindings: 16,
+            max_vertex_input_attribute_offset: 2047,
+            max_vertex_input_binding_stride: 2048,
+            min_buffer_copy_offset_alignment: 1,
+            min_buffer_copy_pitch_alignment: 1,
+            min_uniform_buffer_offset_alignment: 256,
+            non_coherent_atom_size: 256,
+        }
+    }
+
+    #[test]
+    fn test_baked_states() {
+        let viewport = Viewport { x: 0, y: 0, w: 64, h: 64, near: 0.0, far: 1.0 };
+        let scissor = target::Rect { x: 0, y: 0, w: 64, h: 64 };
+        let mut desc = GraphicsPipelineDesc::new(Primitive::TriangleList, Rasterizer::new_fill());
+        desc.baked_states.viewports = State::Static(vec![viewport]);
+        desc.baked_states.scissors = State::Static(vec![scissor]);
+        assert_eq!(desc.validate_baked_states(&features(), &limits()), Ok(()));
+
+        let mut count = desc.clone();
+        count.baked_states.viewport_count = 0;
+        assert_eq!(
+            count.validate_baked_states(&features(), &limits()),
+            Err(StateError::ViewportCount(0))
+        );
+        count.baked_states.viewport_count = 17;
+        assert_eq!(
+            count.validate_baked_states(&features(), &limits()),
+            Err(StateError::ViewportCount(17))
+        );
+
+        let mut mismatch = desc.clone();
+        mismatch.baked_states.viewports = State::Static(vec![viewport, viewport]);
+        assert_eq!(
+            mismatch.validate_baked_states(&features(), &limits()),
+            Err(StateError::ViewportMismatch(2))
+        );
+        mismatch.baked_states.viewports = State::Dynamic;
+        mismatch.baked_states.scissors = State::Static(Vec::new());
+        assert_eq!(
+            mismatch.validate_baked_states(&features(), &limits()),
+            Err(StateError::ScissorMismatch(0))
+        );
+
+        let mut bounds = desc.clone();
+        bounds.baked_states.depth_bounds = State::Static(0.5 .. 0.25);
+        assert_eq!(
+            bounds.validate_baked_states(&features(), &limits()),
+            Err(StateError::DepthBounds)
+        );
+        bounds.baked_states.depth_bounds = State::Static(0.0 .. 1.0);
+        bounds.depth_stencil = Some((
+            Format(SurfaceType::D32, ChannelType::Float),
+            DepthStencilInfo { depth: None, front: None, back: None, depth_bounds: true },
+        ));
+        assert_eq!(
+            bounds.validate_baked_states(&features(), &limits()),
+            Err(StateError::UnsupportedDepthBounds)
+        );
+        let depth_bounds = Features { depth_bounds: true, .. features() };
+        assert_eq!(bounds.validate_baked_states(&depth_bounds, &limits()), Ok(()));
+
+        let mut line = desc.clone();
+        line.rasterizer.line_width = State::Static(0.0);
+        assert_eq!(
+            line.validate_baked_states(&features(), &limits()),
+            Err(StateError::LineWidth)
+        );
+        line.rasterizer.line_width = State::Static(2.0);
+        assert_eq!(
+            line.validate_baked_states(&features(), &limits()),
+            Err(StateError::UnsupportedLineWidth)
+        );
+        let wide_lines = Features { wide_lines: true, .. features() };
+        assert_eq!(line.validate_baked_states(&wide_lines, &limits()), Ok(()));
+    }
+
+    #[test]
+    fn test_compatible_channel() {
+        assert!(is_compatible_channel(ScalarType::Float(32), ChannelType::Float));
+        assert!(is_compatible_channel(ScalarType::Float(32), ChannelType::Unorm));
+        assert!(is_compatible_channel(ScalarType::Float(32), ChannelType::Uscaled));
+        assert!(is_compatible_channel(ScalarType::Int(32), ChannelType::Int));
+        assert!(is_compatible_channel(ScalarType::Uint(32), ChannelType::Uint));
+        assert!(!is_compatible_channel(ScalarType::Float(32), ChannelType::Uint));
+        assert!(!is_compatible_channel(ScalarType::Int(32), ChannelType::Float));
+        assert!(!is_compatible_channel(ScalarType::Uint(32), ChannelType::Int));
+        assert!(!is_compatible_channel(ScalarType::Bool, ChannelType::Uint));
+    }
+
+    #[test]
+    fn test_shader_interface_vertex() {
+        let reflection = ShaderReflection::from_spirv(&to_bytes(&vertex_module())).unwrap();
+        let entry = reflection.entry_point("main").unwrap();
+        let position = Format(SurfaceType::R32_G32_B32, ChannelType::Float);
+        let mut desc = GraphicsPipelineDesc::new(Primitive::TriangleList, Rasterizer::new_fill());
+        desc.attributes.push(AttributeDesc {
+            location: 1,
+            binding: 0,
+            element: Element { format: position, offset: 0 },
+        });
+        // The unused uniform block at (set 0, binding 3) isn't required.
+        let sets = vec![
+            vec![binding(2, DescriptorType::UniformBuffer, 1, ShaderStageFlags::STAGE_VERTEX)],
+            vec![binding(0, DescriptorType::SampledImage, 4, ShaderStageFlags::STAGE_GRAPHICS)],
+        ];
+        assert_eq!(desc.validate_entry_point(Stage::Vertex, entry, &sets, &[]), Ok(()));
+
+        let mut missing = sets.clone();
+        missing[0].clear();
+        assert_eq!(
+            desc.validate_entry_point(Stage::Vertex, entry, &missing, &[]),
+            Err(CreationError::MissingDescriptor(0, 2))
+        );
+        assert_eq!(
+            desc.validate_entry_point(Stage::Vertex, entry, &sets[.. 1], &[]),
+            Err(CreationError::MissingDescriptor(1, 0))
+        );
+
+        let mut ty = sets.clone();
+        ty[0][0].ty = DescriptorType::StorageBuffer;
+        assert_eq!(
+            desc.validate_entry_point(Stage::Vertex, entry, &ty, &[]),
+            Err(CreationError::DescriptorType(0, 2, DescriptorType::StorageBuffer))
+        );
+
+        let mut count = sets.clone();
+        count[1][0].count = 2;
+        assert_eq!(
+            desc.validate_entry_point(Stage::Vertex, entry, &count, &[]),
+            Err(CreationError::DescriptorCount(1, 0))
+        );
+
+        let mut stage = sets.clone();
+        stage[0][0].stage_flags = ShaderStageFlags::STAGE_FRAGMENT;
+        assert_eq!(
+            desc.validate_entry_point(Stage::Vertex, entry, &stage, &[]),
+            Err(CreationError::DescriptorStage(0, 2, Stage::Vertex))
+        );
+
+        let integer = Format(SurfaceType::R32_G32_B32, ChannelType::Uint);
+        desc.attributes[0].element.format = integer;
+        assert_eq!(
+            desc.validate_entry_point(Stage::Vertex, entry, &sets, &[]),
+            Err(CreationError::AttributeFormat(1, integer))
+        );
+
+        desc.attributes[0].location = 0;
+        assert_eq!(
+            desc.validate_entry_point(Stage::Vertex, entry, &sets, &[]),
+            Err(CreationError::MissingAttribute(1))
+        );
+    }
+
+    #[test]
+    fn test_shader_interface_fragment() {
+        let reflection = ShaderReflection::from_spirv(&to_bytes(&fragment_module())).unwrap();
+        let entry = reflection.entry_point("main").unwrap();
+        let desc = GraphicsPipelineDesc::new(Primitive::TriangleList, Rasterizer::new_fill());
+
+        let color = Format(SurfaceType::R8_G8_B8_A8, ChannelType::Unorm);
+        assert_eq!(desc.validate_entry_point(Stage::Fragment, entry, &[], &[color]), Ok(()));
+        assert_eq!(
+            desc.validate_entry_point(Stage::Fragment, entry, &[], &[]),
+            Err(CreationError::MissingColorAttachment(0))
+        );
+
+        let integer = Format(SurfaceType::R8_G8_B8_A8, ChannelType::Uint);
+        assert_eq!(
+            desc.validate_entry_point(Stage::Fragment, entry, &[], &[integer]),
+            Err(CreationError::ColorFormat(0, integer))
+        );
+    }
+}
@@ -1,7 +1,10 @@
 //! Input Assembler(IA) stage description.
 
+use std::error::Error;
+use std::fmt;
+
 use format;
-use {Backend, Primitive};
+use {Backend, Features, Limits, Primitive};
 
 /// Shader binding location.
 pub type Location = u32;
@@ -11,7 +14,8 @@ pub type BufferIndex = u32;
 pub type ElemOffset = u32;
 /// Offset between attribute values, in bytes
 pub type ElemStride = u32;
-/// The number of instances between each subsequent attribute value
+/// The number of instances between each subsequent attribute value.
+/// Zero makes the buffer advance per vertex instead.
 pub type InstanceRate = u8;
 /// An offset inside a vertex buffer, in bytes.
 pub type BufferOffset = usize;
@@ -49,6 +53,134 @@ pub struct AttributeDesc {
     pub element: Element<format::Format>,
 }
 
+/// Error in the vertex input layout of a pipeline.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum InputError {
+    /// Attribute refers to a vertex buffer that isn't described.
+    MissingBuffer(Location, BufferIndex),
+    /// Attribute format can't be used for vertex input.
+    UnsupportedFormat(Location, format::Format),
+    /// Attribute offset isn't aligned to the size of its components.
+    UnalignedOffset(Location),
+    /// Attribute doesn't fit into the stride of its vertex buffer.
+    OutOfStride(Location),
+    /// Several attributes share the same location.
+    DuplicateLocation(Location),
+    /// Buffer stride isn't aligned to the size of its attribute components.
+    UnalignedStride(BufferIndex),
+    /// Instance rates above one need `Features::instance_rate`.
+    InstanceRate(BufferIndex),
+    /// Number of buffers or attributes, a stride or an offset exceeds the device limits.
+    Limit,
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InputError::MissingBuffer(location, binding) =>
+                write!(f, "{} (location {}, buffer {})", self.description(), location, binding),
+            InputError::UnsupportedFormat(location, format) =>
+                write!(f, "{} (location {}, {:?})", self.description(), location, format),
+            InputError::UnalignedOffset(location) |
+            InputError::OutOfStride(location) |
+            InputError::DuplicateLocation(location) =>
+                write!(f, "{} (location {})", self.description(), location),
+            InputError::UnalignedStride(binding) |
+            InputError::InstanceRate(binding) =>
+                write!(f, "{} (buffer {})", self.description(), binding),
+            InputError::Limit => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for InputError {
+    fn description(&self) -> &str {
+        match *self {
+            InputError::MissingBuffer(..) => "Attribute refers to an undescribed vertex buffer",
+            InputError::UnsupportedFormat(..) => "Attribute format isn't supported for vertex input",
+            InputError::UnalignedOffset(_) => "Attribute offset isn't aligned to its components",
+            InputError::OutOfStride(_) => "Attribute doesn't fit into the buffer stride",
+            InputError::DuplicateLocation(_) => "Attribute location is used more than once",
+            InputError::UnalignedStride(_) => "Buffer stride isn't aligned to its attribute components",
+            InputError::InstanceRate(_) => "Instance rate divisors aren't supported",
+            InputError::Limit => "Vertex input exceeds the device limits",
+        }
+    }
+}
+
+/// Return the required alignment of a vertex attribute of the given surface type, in bytes.
+///
+/// Attributes are aligned to the size of their components,
+/// packed formats to the size of the whole element.
+pub fn attribute_alignment(surface: format::SurfaceType) -> ElemOffset {
+    use format::SurfaceType::*;
+    match surface {
+        R8 | R8_G8 | R8_G8_B8_A8 | B8_G8_R8_A8 => 1,
+        R16 | R16_G16 | R16_G16_B16 | R16_G16_B16_A16 => 2,
+        _ => 4,
+    }
+}
+
+/// Validate a vertex input layout against the device features and limits.
+///
+/// The support of the attribute formats is up to the backend and isn't checked.
+pub fn validate_vertex_input(
+    buffers: &[VertexBufferDesc],
+    attributes: &[AttributeDesc],
+    features: &Features,
+    limits: &Limits,
+) -> Result<(), InputError> {
+    if buffers.len() > limits.max_vertex_input_bindings ||
+        attributes.len() > limits.max_vertex_input_attributes
+    {
+        return Err(InputError::Limit);
+    }
+
+    for (binding, buffer) in buffers.iter().enumerate() {
+        if buffer.stride as usize > limits.max_vertex_input_binding_stride {
+            return Err(InputError::Limit);
+        }
+        if buffer.rate > 1 && !features.instance_rate {
+            return Err(InputError::InstanceRate(binding as BufferIndex));
+        }
+    }
+
+    for (i, attribute) in attributes.iter().enumerate() {
+        let location = attribute.location;
+        if attributes[.. i].iter().any(|other| other.location == location) {
+            return Err(InputError::DuplicateLocation(location));
+        }
+        let buffer = buffers
+            .get(attribute.binding as usize)
+            .ok_or(InputError::MissingBuffer(location, attribute.binding))?;
+
+        let element = attribute.element;
+        if element.offset as usize > limits.max_vertex_input_attribute_offset {
+            return Err(InputError::Limit);
+        }
+        let surface = element.format.0;
+        if surface.is_depth() || surface.is_stencil() || surface.is_compressed() {
+            return Err(InputError::UnsupportedFormat(location, element.format));
+        }
+        let alignment = attribute_alignment(surface);
+        if element.offset % alignment != 0 {
+            return Err(InputError::UnalignedOffset(location));
+        }
+        // A zero stride reads the same element for all vertices.
+        if buffer.stride != 0 {
+            let size = surface.describe_bits().total as ElemStride / 8;
+            if element.offset + size > buffer.stride {
+                return Err(InputError::OutOfStride(location));
+            }
+            if buffer.stride % alignment != 0 {
+                return Err(InputError::UnalignedStride(attribute.binding));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 ///
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
@@ -94,3 +226,102 @@ impl<'a, B: Backend> VertexBufferSet<'a, B> {
         VertexBufferSet(Vec::new())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use format::{ChannelType, Format, SurfaceType};
+    use pso::graphics::tests::{features, limits};
+    use Features;
+    use super::*;
+
+    fn attribute(location: Location, binding: BufferIndex, surface: SurfaceType, offset: ElemOffset) -> AttributeDesc {
+        AttributeDesc {
+            location,
+            binding,
+            element: Element { format: Format(surface, ChannelType::Float), offset },
+        }
+    }
+
+    #[test]
+    fn test_attribute_alignment() {
+        assert_eq!(attribute_alignment(SurfaceType::R8_G8_B8_A8), 1);
+        assert_eq!(attribute_alignment(SurfaceType::R16_G16), 2);
+        assert_eq!(attribute_alignment(SurfaceType::R32_G32_B32), 4);
+        assert_eq!(attribute_alignment(SurfaceType::R10_G10_B10_A2), 4);
+    }
+
+    #[test]
+    fn test_vertex_input() {
+        let buffers = [VertexBufferDesc { stride: 16, rate: 0 }];
+        let attributes = [
+            attribute(0, 0, SurfaceType::R32_G32, 0),
+            attribute(1, 0, SurfaceType::R16_G16, 8),
+            attribute(2, 0, SurfaceType::R8_G8_B8_A8, 12),
+        ];
+        assert_eq!(validate_vertex_input(&buffers, &attributes, &features(), &limits()), Ok(()));
+
+        let unaligned = [attribute(0, 0, SurfaceType::R32, 2)];
+        assert_eq!(
+            validate_vertex_input(&buffers, &unaligned, &features(), &limits()),
+            Err(InputError::UnalignedOffset(0))
+        );
+        let out_of_stride = [attribute(0, 0, SurfaceType::R32_G32, 12)];
+        assert_eq!(
+            validate_vertex_input(&buffers, &out_of_stride, &features(), &limits()),
+            Err(InputError::OutOfStride(0))
+        );
+        let missing = [attribute(0, 1, SurfaceType::R32, 0)];
+        assert_eq!(
+            validate_vertex_input(&buffers, &missing, &features(), &limits()),
+            Err(InputError::MissingBuffer(0, 1))
+        );
+        let duplicate = [attribute(0, 0, SurfaceType::R32, 0), attribute(0, 0, SurfaceType::R32, 4)];
+        assert_eq!(
+            validate_vertex_input(&buffers, &duplicate, &features(), &limits()),
+            Err(InputError::DuplicateLocation(0))
+        );
+        let depth = [AttributeDesc {
+            location: 0,
+            binding: 0,
+            element: Element { format: Format(SurfaceType::D32, ChannelType::Float), offset: 0 },
+        }];
+        assert_eq!(
+            validate_vertex_input(&buffers, &depth, &features(), &limits()),
+            Err(InputError::UnsupportedFormat(0, depth[0].element.format))
+        );
+
+        // A zero stride reads the same element for every vertex.
+        let constant = [VertexBufferDesc { stride: 0, rate: 0 }];
+        assert_eq!(validate_vertex_input(&constant, &attributes, &features(), &limits()), Ok(()));
+    }
+
+    #[test]
+    fn test_vertex_input_stride() {
+        let attributes = [attribute(0, 0, SurfaceType::R32, 0)];
+        let unaligned = [VertexBufferDesc { stride: 6, rate: 0 }];
+        assert_eq!(
+            validate_vertex_input(&unaligned, &attributes, &features(), &limits()),
+            Err(InputError::UnalignedStride(0))
+        );
+        let large = [VertexBufferDesc { stride: 4096, rate: 0 }];
+        assert_eq!(
+            validate_vertex_input(&large, &attributes, &features(), &limits()),
+            Err(InputError::Limit)
+        );
+    }
+
+    #[test]
+    fn test_vertex_input_instance_rate() {
+        let attributes = [attribute(0, 0, SurfaceType::R32, 0)];
+        let per_instance = [VertexBufferDesc { stride: 4, rate: 1 }];
+        assert_eq!(validate_vertex_input(&per_instance, &attributes, &features(), &limits()), Ok(()));
+
+        let divisor = [VertexBufferDesc { stride: 4, rate: 2 }];
+        assert_eq!(
+            validate_vertex_input(&divisor, &attributes, &features(), &limits()),
+            Err(InputError::InstanceRate(0))
+        );
+        let instance_rate = Features { instance_rate: true, .. features() };
+        assert_eq!(validate_vertex_input(&divisor, &attributes, &instance_rate, &limits()), Ok(()));
+    }
+}
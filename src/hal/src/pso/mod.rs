@@ -4,6 +4,8 @@
 //! will want to use the typed and safe `PipelineState`. See the `pso` module inside the `gfx`
 //! crate.
 
+use device::ShaderError;
+use format::Format;
 use pass;
 use std::error::Error;
 use std::fmt;
@@ -12,11 +14,15 @@ mod descriptor;
 mod graphics;
 mod input_assembler;
 mod output_merger;
+mod reflect;
 
 pub use self::descriptor::*;
 pub use self::graphics::*;
 pub use self::input_assembler::*;
 pub use self::output_merger::*;
+pub use self::reflect::*;
+#[cfg(test)]
+pub(crate) use self::graphics::tests;
 
 use Backend;
 
@@ -27,12 +33,104 @@ pub enum CreationError {
     Other,
     /// Invalid subpass (not part of renderpass).
     InvalidSubpass(pass::SubpassId),
+    /// Invalid vertex input layout.
+    VertexInput(InputError),
+    /// Shader translation or compilation failed on pipeline creation.
+    Shader(ShaderError),
+    /// Shader module is malformed and couldn't be reflected.
+    Reflect(ReflectError),
+    /// Invalid baked pipeline state.
+    BakedState(StateError),
+    /// Unsupported blend state.
+    Blend(BlendError),
+    /// Invalid tessellation setup.
+    Tessellation(TessellationError),
+    /// Invalid or unsupported stream output.
+    StreamOutput(StreamOutputError),
+    /// Shader descriptor (set, binding) isn't part of the pipeline layout.
+    MissingDescriptor(usize, usize),
+    /// Shader descriptor (set, binding) has a different type in the pipeline layout.
+    DescriptorType(usize, usize, DescriptorType),
+    /// Shader descriptor array (set, binding) is larger than in the pipeline layout.
+    DescriptorCount(usize, usize),
+    /// Shader descriptor (set, binding) isn't visible to the stage using it.
+    DescriptorStage(usize, usize, Stage),
+    /// Vertex shader input has no vertex attribute.
+    MissingAttribute(Location),
+    /// Vertex attribute format doesn't match the type of the shader input.
+    AttributeFormat(Location, Format),
+    /// Fragment shader output has no color attachment in the subpass.
+    MissingColorAttachment(Location),
+    /// Color attachment format doesn't match the type of the fragment shader output.
+    ColorFormat(Location, Format),
+}
+
+impl From<StreamOutputError> for CreationError {
+    fn from(error: StreamOutputError) -> Self {
+        CreationError::StreamOutput(error)
+    }
+}
+
+impl From<TessellationError> for CreationError {
+    fn from(error: TessellationError) -> Self {
+        CreationError::Tessellation(error)
+    }
+}
+
+impl From<BlendError> for CreationError {
+    fn from(error: BlendError) -> Self {
+        CreationError::Blend(error)
+    }
+}
+
+impl From<StateError> for CreationError {
+    fn from(error: StateError) -> Self {
+        CreationError::BakedState(error)
+    }
+}
+
+impl From<InputError> for CreationError {
+    fn from(error: InputError) -> Self {
+        CreationError::VertexInput(error)
+    }
+}
+
+impl From<ShaderError> for CreationError {
+    fn from(error: ShaderError) -> Self {
+        CreationError::Shader(error)
+    }
+}
+
+impl From<ReflectError> for CreationError {
+    fn from(error: ReflectError) -> Self {
+        CreationError::Reflect(error)
+    }
 }
 
 impl fmt::Display for CreationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             CreationError::InvalidSubpass(id) => write!(f, "{}: {:?}", self.description(), id),
+            CreationError::VertexInput(ref error) => write!(f, "{}: {}", self.description(), error),
+            CreationError::Shader(ref error) => write!(f, "{}: {}", self.description(), error),
+            CreationError::BakedState(ref error) => write!(f, "{}: {}", self.description(), error),
+            CreationError::Blend(ref error) => write!(f, "{}: {}", self.description(), error),
+            CreationError::Tessellation(ref error) => write!(f, "{}: {}", self.description(), error),
+            CreationError::StreamOutput(ref error) => write!(f, "{}: {}", self.description(), error),
+            CreationError::Reflect(ref error) => write!(f, "{}: {}", self.description(), error),
+            CreationError::MissingDescriptor(set, binding) |
+            CreationError::DescriptorCount(set, binding) =>
+                write!(f, "{} (set {}, binding {})", self.description(), set, binding),
+            CreationError::DescriptorType(set, binding, ty) =>
+                write!(f, "{} (set {}, binding {}, {:?})", self.description(), set, binding, ty),
+            CreationError::DescriptorStage(set, binding, stage) =>
+                write!(f, "{} (set {}, binding {}, {:?})", self.description(), set, binding, stage),
+            CreationError::MissingAttribute(location) |
+            CreationError::MissingColorAttachment(location) =>
+                write!(f, "{} (location {})", self.description(), location),
+            CreationError::AttributeFormat(location, format) |
+            CreationError::ColorFormat(location, format) =>
+                write!(f, "{} (location {}, {:?})", self.description(), location, format),
             _ => write!(f, "{}", self.description()),
         }
     }
@@ -43,6 +141,21 @@ impl Error for CreationError {
         match *self {
             CreationError::Other => "Unknown other error.",
             CreationError::InvalidSubpass(_) => "Invalid subpass index.",
+            CreationError::VertexInput(_) => "Invalid vertex input layout.",
+            CreationError::Shader(_) => "Shader compilation failed.",
+            CreationError::Reflect(_) => "Shader reflection failed.",
+            CreationError::BakedState(_) => "Invalid baked pipeline state.",
+            CreationError::Blend(_) => "Unsupported blend state.",
+            CreationError::Tessellation(_) => "Invalid tessellation setup.",
+            CreationError::StreamOutput(_) => "Invalid stream output.",
+            CreationError::MissingDescriptor(..) => "Shader descriptor is missing from the pipeline layout.",
+            CreationError::DescriptorType(..) => "Shader descriptor type doesn't match the pipeline layout.",
+            CreationError::DescriptorCount(..) => "Shader descriptor count exceeds the pipeline layout.",
+            CreationError::DescriptorStage(..) => "Shader descriptor isn't visible to the shader stage.",
+            CreationError::MissingAttribute(_) => "Vertex shader input has no vertex attribute.",
+            CreationError::AttributeFormat(..) => "Vertex attribute format doesn't match the shader input.",
+            CreationError::MissingColorAttachment(_) => "Fragment shader output has no color attachment.",
+            CreationError::ColorFormat(..) => "Color attachment format doesn't match the shader output.",
         }
     }
 }
@@ -175,6 +288,74 @@ pub enum Stage {
     Compute
 }
 
+impl From<Stage> for ShaderStageFlags {
+    fn from(stage: Stage) -> Self {
+        match stage {
+            Stage::Vertex => STAGE_VERTEX,
+            Stage::Hull => STAGE_HULL,
+            Stage::Domain => STAGE_DOMAIN,
+            Stage::Geometry => STAGE_GEOMETRY,
+            Stage::Fragment => STAGE_FRAGMENT,
+            Stage::Compute => STAGE_COMPUTE,
+        }
+    }
+}
+
+/// Value of a specialization constant.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Constant {
+    Bool(bool),
+    U32(u32),
+    U64(u64),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl Constant {
+    /// Size of the value in bytes. Booleans take 4 bytes, like in SPIR-V.
+    pub fn size(&self) -> usize {
+        match *self {
+            Constant::Bool(_) |
+            Constant::U32(_) |
+            Constant::I32(_) |
+            Constant::F32(_) => 4,
+            Constant::U64(_) |
+            Constant::I64(_) |
+            Constant::F64(_) => 8,
+        }
+    }
+
+    /// Raw bits of the value, zero-extended to 64 bits.
+    pub fn to_bits(&self) -> u64 {
+        match *self {
+            Constant::Bool(v) => v as u64,
+            Constant::U32(v) => v as u64,
+            Constant::U64(v) => v,
+            Constant::I32(v) => v as u32 as u64,
+            Constant::I64(v) => v as u64,
+            Constant::F32(v) => v.to_bits() as u64,
+            Constant::F64(v) => v.to_bits(),
+        }
+    }
+}
+
+/// Specialization constant of a shader entry point.
+///
+/// Overrides the default value of the constant with the given `id`
+/// (`constant_id` in SPIR-V) when the pipeline is created.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Specialization {
+    /// Constant identifier in the shader.
+    pub id: u32,
+    /// Value of the constant.
+    pub value: Constant,
+}
+
 /// Shader entry point.
 #[derive(Debug)]
 pub struct EntryPoint<'a, B: Backend> {
@@ -182,6 +363,8 @@ pub struct EntryPoint<'a, B: Backend> {
     pub entry: &'a str,
     /// Shader module reference.
     pub module: &'a B::ShaderModule,
+    /// Specialization constants applied to the shader.
+    pub specialization: &'a [Specialization],
 }
 
 impl<'a, B: Backend> Clone for EntryPoint<'a, B> {
@@ -189,6 +372,7 @@ impl<'a, B: Backend> Clone for EntryPoint<'a, B> {
         EntryPoint {
             entry: self.entry,
             module: self.module,
+            specialization: self.specialization,
         }
     }
 }
@@ -196,7 +380,9 @@ impl<'a, B: Backend> Clone for EntryPoint<'a, B> {
 impl<'a, B: Backend> PartialEq for EntryPoint<'a, B> {
     fn eq(&self, other: &Self) -> bool {
         self.entry.as_ptr() == other.entry.as_ptr() &&
-        self.module as *const _ == other.module as *const _
+        self.module as *const _ == other.module as *const _ &&
+        self.specialization.as_ptr() == other.specialization.as_ptr() &&
+        self.specialization.len() == other.specialization.len()
     }
 }
 
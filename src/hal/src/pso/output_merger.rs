@@ -47,6 +47,9 @@ pub struct DepthStencilInfo {
     pub front: Option<s::StencilSide>,
     /// Optional stencil test on the back faces
     pub back: Option<s::StencilSide>,
+    /// Enable the depth bounds test, with the range given by `BakedStates::depth_bounds`.
+    /// Requires `Features::depth_bounds`.
+    pub depth_bounds: bool,
 }
 impl From<s::Depth> for DepthStencilInfo {
     fn from(depth: s::Depth) -> DepthStencilInfo {
@@ -54,6 +57,7 @@ impl From<s::Depth> for DepthStencilInfo {
             depth: Some(depth),
             front: None,
             back: None,
+            depth_bounds: false,
         }
     }
 }
@@ -63,6 +67,7 @@ impl From<s::Stencil> for DepthStencilInfo {
             depth: None,
             front: Some(stencil.front),
             back: Some(stencil.back),
+            depth_bounds: false,
         }
     }
 }
@@ -72,6 +77,7 @@ impl From<(s::Depth, s::Stencil)> for DepthStencilInfo {
             depth: Some(ds.0),
             front: Some(ds.1.front),
             back: Some(ds.1.back),
+            depth_bounds: false,
         }
     }
 }
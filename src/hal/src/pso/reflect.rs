@@ -0,0 +1,812 @@
+//! SPIR-V shader reflection.
+//!
+//! Extracts the entry points, stage interfaces and resources of a SPIR-V module,
+//! without translating it for any backend.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+
+use super::{DescriptorSetLayoutBinding, DescriptorType, Location, ShaderStageFlags, Stage};
+
+const MAGIC_NUMBER: u32 = 0x0723_0203;
+const HEADER_LENGTH: usize = 5;
+
+// Opcodes
+const OP_NAME: u16 = 5;
+const OP_ENTRY_POINT: u16 = 15;
+const OP_EXECUTION_MODE: u16 = 16;
+const OP_TYPE_BOOL: u16 = 20;
+const OP_TYPE_INT: u16 = 21;
+const OP_TYPE_FLOAT: u16 = 22;
+const OP_TYPE_VECTOR: u16 = 23;
+const OP_TYPE_MATRIX: u16 = 24;
+const OP_TYPE_IMAGE: u16 = 25;
+const OP_TYPE_SAMPLER: u16 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u16 = 27;
+const OP_TYPE_ARRAY: u16 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u16 = 29;
+const OP_TYPE_STRUCT: u16 = 30;
+const OP_TYPE_POINTER: u16 = 32;
+const OP_CONSTANT: u16 = 43;
+const OP_FUNCTION: u16 = 54;
+const OP_FUNCTION_END: u16 = 56;
+const OP_FUNCTION_CALL: u16 = 57;
+const OP_VARIABLE: u16 = 59;
+const OP_IMAGE_TEXEL_POINTER: u16 = 60;
+const OP_LOAD: u16 = 61;
+const OP_STORE: u16 = 62;
+const OP_COPY_MEMORY: u16 = 63;
+const OP_COPY_MEMORY_SIZED: u16 = 64;
+const OP_ACCESS_CHAIN: u16 = 65;
+const OP_IN_BOUNDS_ACCESS_CHAIN: u16 = 66;
+const OP_PTR_ACCESS_CHAIN: u16 = 67;
+const OP_ARRAY_LENGTH: u16 = 68;
+const OP_IN_BOUNDS_PTR_ACCESS_CHAIN: u16 = 70;
+const OP_DECORATE: u16 = 71;
+const OP_MEMBER_DECORATE: u16 = 72;
+const OP_COPY_OBJECT: u16 = 83;
+const OP_ATOMIC_LOAD: u16 = 227;
+const OP_ATOMIC_STORE: u16 = 228;
+const OP_ATOMIC_XOR: u16 = 242;
+
+// Execution modes
+const EXECUTION_MODE_LOCAL_SIZE: u32 = 17;
+
+// Storage classes
+const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_UNIFORM: u32 = 2;
+const STORAGE_OUTPUT: u32 = 3;
+const STORAGE_PUSH_CONSTANT: u32 = 9;
+const STORAGE_STORAGE_BUFFER: u32 = 12;
+
+// Decorations
+const DECORATION_BLOCK: u32 = 2;
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+// Image dimensions
+const DIM_BUFFER: u32 = 5;
+const DIM_SUBPASS_DATA: u32 = 6;
+
+/// Error reflecting a shader module.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReflectError {
+    /// The module wasn't created from SPIR-V.
+    NotSpirv,
+    /// The SPIR-V header is missing or has an invalid magic number.
+    InvalidHeader,
+    /// The instruction at this word offset is truncated.
+    InvalidInstruction(usize),
+}
+
+impl fmt::Display for ReflectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReflectError::InvalidInstruction(offset) => write!(f, "{} (word {})", self.description(), offset),
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl Error for ReflectError {
+    fn description(&self) -> &str {
+        match *self {
+            ReflectError::NotSpirv => "Shader module wasn't created from SPIR-V",
+            ReflectError::InvalidHeader => "Invalid SPIR-V header",
+            ReflectError::InvalidInstruction(_) => "Truncated SPIR-V instruction",
+        }
+    }
+}
+
+/// Scalar type of a shader interface variable.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum ScalarType {
+    /// Boolean.
+    Bool,
+    /// Signed integer of the given bit width.
+    Int(u32),
+    /// Unsigned integer of the given bit width.
+    Uint(u32),
+    /// Floating point number of the given bit width.
+    Float(u32),
+}
+
+/// Input or output variable of a shader stage.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct InterfaceVariable {
+    /// Variable name, empty if the module has no debug names.
+    pub name: String,
+    /// Interface location.
+    pub location: Location,
+    /// Scalar type of the components.
+    pub ty: ScalarType,
+    /// Number of vector components, or rows of a matrix.
+    pub components: u32,
+}
+
+/// Descriptor declared by a shader module.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct DescriptorReflection {
+    /// Variable name, empty if the module has no debug names.
+    pub name: String,
+    /// Descriptor set index.
+    pub set: u32,
+    /// Binding index inside the set.
+    pub binding: u32,
+    /// Type of the descriptor.
+    pub ty: DescriptorType,
+    /// Number of descriptors, zero for runtime sized arrays.
+    pub count: usize,
+}
+
+/// Push constant block declared by a shader module.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PushConstantReflection {
+    /// Variable name, empty if the module has no debug names.
+    pub name: String,
+    /// Size of the block in bytes.
+    pub size: u32,
+}
+
+/// Entry point of a shader module.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct EntryPointReflection {
+    /// Entry point name.
+    pub name: String,
+    /// Shader stage of the entry point.
+    pub stage: Stage,
+    /// Input variables with a location, built-ins are skipped.
+    pub inputs: Vec<InterfaceVariable>,
+    /// Output variables with a location, built-ins are skipped.
+    pub outputs: Vec<InterfaceVariable>,
+    /// Descriptors statically used by the entry point.
+    pub descriptors: Vec<DescriptorReflection>,
+    /// Workgroup size of compute shaders.
+    pub workgroup_size: Option<[u32; 3]>,
+}
+
+/// Reflection data of a SPIR-V shader module.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ShaderReflection {
+    /// Entry points of the module.
+    pub entry_points: Vec<EntryPointReflection>,
+    /// Descriptors declared by the module, including unused ones.
+    pub descriptors: Vec<DescriptorReflection>,
+    /// Push constant blocks declared by the module.
+    pub push_constants: Vec<PushConstantReflection>,
+}
+
+impl ShaderReflection {
+    /// Reflect a SPIR-V module.
+    pub fn from_spirv(data: &[u8]) -> Result<Self, ReflectError> {
+        if data.len() % 4 != 0 || data.len() < HEADER_LENGTH * 4 {
+            return Err(ReflectError::InvalidHeader);
+        }
+        let mut words = data
+            .chunks(4)
+            .map(|b| b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+            .collect::<Vec<_>>();
+        if words[0] == MAGIC_NUMBER.swap_bytes() {
+            for word in &mut words {
+                *word = word.swap_bytes();
+            }
+        } else if words[0] != MAGIC_NUMBER {
+            return Err(ReflectError::InvalidHeader);
+        }
+
+        let module = Module::parse(&words[HEADER_LENGTH ..])?;
+        Ok(module.reflect())
+    }
+
+    /// Find an entry point by name.
+    pub fn entry_point(&self, name: &str) -> Option<&EntryPointReflection> {
+        self.entry_points.iter().find(|entry| entry.name == name)
+    }
+
+    /// Find a descriptor by set and binding.
+    pub fn descriptor(&self, set: u32, binding: u32) -> Option<&DescriptorReflection> {
+        self.descriptors.iter().find(|desc| desc.set == set && desc.binding == binding)
+    }
+
+    /// Generate the layout bindings of a descriptor set, visible to the given stages.
+    pub fn descriptor_set_layout_bindings(
+        &self, set: u32, stage_flags: ShaderStageFlags
+    ) -> Vec<DescriptorSetLayoutBinding> {
+        let mut bindings = self.descriptors
+            .iter()
+            .filter(|desc| desc.set == set)
+            .map(|desc| DescriptorSetLayoutBinding {
+                binding: desc.binding as usize,
+                ty: desc.ty,
+                count: desc.count,
+                stage_flags,
+            })
+            .collect::<Vec<_>>();
+        bindings.sort_by_key(|binding| binding.binding);
+        bindings
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Type {
+    Bool,
+    Int { width: u32, signed: bool },
+    Float { width: u32 },
+    Vector { component: u32, count: u32 },
+    Matrix { column: u32, count: u32 },
+    Image { dim: u32, sampled: u32 },
+    Sampler,
+    SampledImage,
+    Array { element: u32, length: u32 },
+    RuntimeArray { element: u32 },
+    Struct { members: Vec<u32> },
+    Pointer { ty: u32 },
+}
+
+struct EntryPoint {
+    model: u32,
+    function: u32,
+    name: String,
+    interface: Vec<u32>,
+}
+
+struct Variable {
+    id: u32,
+    ty: u32,
+    storage: u32,
+}
+
+#[derive(Default)]
+struct Function {
+    calls: Vec<u32>,
+    // Operands which may be global variables.
+    pointers: Vec<u32>,
+}
+
+#[derive(Default)]
+struct Module {
+    names: HashMap<u32, String>,
+    types: HashMap<u32, Type>,
+    constants: HashMap<u32, u32>,
+    entry_points: Vec<EntryPoint>,
+    local_sizes: HashMap<u32, [u32; 3]>,
+    variables: Vec<Variable>,
+    functions: HashMap<u32, Function>,
+    current_function: Option<u32>,
+    decorations: HashMap<(u32, u32), u32>,
+    flags: HashSet<(u32, u32)>,
+    member_decorations: HashMap<(u32, u32, u32), u32>,
+}
+
+// Operands of a function instruction which may be pointers to global variables.
+fn pointer_operands(opcode: u16, ops: &[u32]) -> &[u32] {
+    let range = match opcode {
+        OP_FUNCTION_CALL => 3 .. ops.len(),
+        OP_STORE | OP_ATOMIC_STORE => 0 .. 1,
+        OP_COPY_MEMORY | OP_COPY_MEMORY_SIZED => 0 .. 2,
+        OP_IMAGE_TEXEL_POINTER | OP_LOAD | OP_ACCESS_CHAIN | OP_IN_BOUNDS_ACCESS_CHAIN |
+        OP_PTR_ACCESS_CHAIN | OP_ARRAY_LENGTH | OP_IN_BOUNDS_PTR_ACCESS_CHAIN | OP_COPY_OBJECT => 2 .. 3,
+        op if op >= OP_ATOMIC_LOAD && op <= OP_ATOMIC_XOR => 2 .. 3,
+        _ => return &[],
+    };
+    ops.get(range).unwrap_or(&[])
+}
+
+// Decode a nul-terminated literal string, returning the string
+// and the number of words it occupies.
+fn parse_string(words: &[u32]) -> (String, usize) {
+    let mut bytes = Vec::new();
+    for (i, &word) in words.iter().enumerate() {
+        for shift in 0 .. 4 {
+            let byte = (word >> (shift * 8)) as u8;
+            if byte == 0 {
+                return (String::from_utf8_lossy(&bytes).into_owned(), i + 1);
+            }
+            bytes.push(byte);
+        }
+    }
+    (String::from_utf8_lossy(&bytes).into_owned(), words.len())
+}
+
+impl Module {
+    fn parse(words: &[u32]) -> Result<Self, ReflectError> {
+        let mut module = Module::default();
+        let mut offset = 0;
+        while offset < words.len() {
+            let opcode = (words[offset] & 0xFFFF) as u16;
+            let count = (words[offset] >> 16) as usize;
+            if count == 0 || offset + count > words.len() {
+                return Err(ReflectError::InvalidInstruction(HEADER_LENGTH + offset));
+            }
+            let operands = &words[offset + 1 .. offset + count];
+            module.parse_instruction(opcode, operands)
+                .ok_or(ReflectError::InvalidInstruction(HEADER_LENGTH + offset))?;
+            offset += count;
+        }
+        Ok(module)
+    }
+
+    // Returns `None` if the operands are truncated.
+    fn parse_instruction(&mut self, opcode: u16, ops: &[u32]) -> Option<()> {
+        if let Some(id) = self.current_function {
+            let function = self.functions.entry(id).or_insert_with(Function::default);
+            if opcode == OP_FUNCTION_CALL {
+                function.calls.push(*ops.get(2)?);
+            }
+            function.pointers.extend_from_slice(pointer_operands(opcode, ops));
+        }
+        match opcode {
+            OP_NAME => {
+                let (name, _) = parse_string(ops.get(1 ..)?);
+                self.names.insert(*ops.get(0)?, name);
+            }
+            OP_ENTRY_POINT => {
+                let (name, length) = parse_string(ops.get(2 ..)?);
+                self.entry_points.push(EntryPoint {
+                    model: *ops.get(0)?,
+                    function: *ops.get(1)?,
+                    name,
+                    interface: ops.get(2 + length ..)?.to_vec(),
+                });
+            }
+            OP_EXECUTION_MODE => {
+                if *ops.get(1)? == EXECUTION_MODE_LOCAL_SIZE {
+                    self.local_sizes.insert(*ops.get(0)?, [*ops.get(2)?, *ops.get(3)?, *ops.get(4)?]);
+                }
+            }
+            OP_TYPE_BOOL => {
+                self.types.insert(*ops.get(0)?, Type::Bool);
+            }
+            OP_TYPE_INT => {
+                self.types.insert(*ops.get(0)?, Type::Int { width: *ops.get(1)?, signed: *ops.get(2)? != 0 });
+            }
+            OP_TYPE_FLOAT => {
+                self.types.insert(*ops.get(0)?, Type::Float { width: *ops.get(1)? });
+            }
+            OP_TYPE_VECTOR => {
+                self.types.insert(*ops.get(0)?, Type::Vector { component: *ops.get(1)?, count: *ops.get(2)? });
+            }
+            OP_TYPE_MATRIX => {
+                self.types.insert(*ops.get(0)?, Type::Matrix { column: *ops.get(1)?, count: *ops.get(2)? });
+            }
+            OP_TYPE_IMAGE => {
+                self.types.insert(*ops.get(0)?, Type::Image { dim: *ops.get(2)?, sampled: *ops.get(6)? });
+            }
+            OP_TYPE_SAMPLER => {
+                self.types.insert(*ops.get(0)?, Type::Sampler);
+            }
+            OP_TYPE_SAMPLED_IMAGE => {
+                self.types.insert(*ops.get(0)?, Type::SampledImage);
+            }
+            OP_TYPE_ARRAY => {
+                self.types.insert(*ops.get(0)?, Type::Array { element: *ops.get(1)?, length: *ops.get(2)? });
+            }
+            OP_TYPE_RUNTIME_ARRAY => {
+                self.types.insert(*ops.get(0)?, Type::RuntimeArray { element: *ops.get(1)? });
+            }
+            OP_TYPE_STRUCT => {
+                self.types.insert(*ops.get(0)?, Type::Struct { members: ops.get(1 ..)?.to_vec() });
+            }
+            OP_TYPE_POINTER => {
+                self.types.insert(*ops.get(0)?, Type::Pointer { ty: *ops.get(2)? });
+            }
+            OP_CONSTANT => {
+                // Only the low word is needed for array lengths.
+                self.constants.insert(*ops.get(1)?, *ops.get(2)?);
+            }
+            OP_FUNCTION => {
+                self.current_function = Some(*ops.get(1)?);
+            }
+            OP_FUNCTION_END => {
+                self.current_function = None;
+            }
+            OP_VARIABLE if self.current_function.is_none() => {
+                self.variables.push(Variable {
+                    ty: *ops.get(0)?,
+                    id: *ops.get(1)?,
+                    storage: *ops.get(2)?,
+                });
+            }
+            OP_DECORATE => {
+                let (target, decoration) = (*ops.get(0)?, *ops.get(1)?);
+                match ops.get(2) {
+                    Some(&value) => { self.decorations.insert((target, decoration), value); }
+                    None => { self.flags.insert((target, decoration)); }
+                }
+            }
+            OP_MEMBER_DECORATE => {
+                let (target, member, decoration) = (*ops.get(0)?, *ops.get(1)?, *ops.get(2)?);
+                if let Some(&value) = ops.get(3) {
+                    self.member_decorations.insert((target, member, decoration), value);
+                }
+            }
+            _ => {}
+        }
+        Some(())
+    }
+
+    fn name(&self, id: u32) -> String {
+        self.names.get(&id).cloned().unwrap_or_default()
+    }
+
+    fn pointee(&self, ty: u32) -> Option<u32> {
+        match self.types.get(&ty) {
+            Some(&Type::Pointer { ty }) => Some(ty),
+            _ => None,
+        }
+    }
+
+    // Strip arrays from a type, returning the element type and the element count.
+    fn strip_arrays(&self, mut ty: u32) -> (u32, usize) {
+        let mut count = 1;
+        loop {
+            match self.types.get(&ty) {
+                Some(&Type::Array { element, length }) => {
+                    count *= self.constants.get(&length).cloned().unwrap_or(1) as usize;
+                    ty = element;
+                }
+                Some(&Type::RuntimeArray { element }) => {
+                    count = 0;
+                    ty = element;
+                }
+                _ => return (ty, count),
+            }
+        }
+    }
+
+    fn scalar(&self, ty: u32) -> Option<(ScalarType, u32)> {
+        match *self.types.get(&ty)? {
+            Type::Bool => Some((ScalarType::Bool, 1)),
+            Type::Int { width, signed: true } => Some((ScalarType::Int(width), 1)),
+            Type::Int { width, signed: false } => Some((ScalarType::Uint(width), 1)),
+            Type::Float { width } => Some((ScalarType::Float(width), 1)),
+            Type::Vector { component, count } => self.scalar(component).map(|(ty, _)| (ty, count)),
+            Type::Matrix { column, .. } => self.scalar(column),
+            _ => None,
+        }
+    }
+
+    fn interface(&self, ids: &[u32], storage: u32, arrayed: bool) -> Vec<InterfaceVariable> {
+        let mut variables = self.variables
+            .iter()
+            .filter(|var| var.storage == storage && ids.contains(&var.id))
+            .filter_map(|var| {
+                let location = *self.decorations.get(&(var.id, DECORATION_LOCATION))?;
+                let mut ty = self.pointee(var.ty)?;
+                if arrayed {
+                    // Per-vertex interfaces of tessellation and geometry shaders are arrays.
+                    ty = self.strip_arrays(ty).0;
+                }
+                let (scalar, components) = self.scalar(ty)?;
+                Some(InterfaceVariable {
+                    name: self.name(var.id),
+                    location,
+                    ty: scalar,
+                    components,
+                })
+            })
+            .collect::<Vec<_>>();
+        variables.sort_by_key(|var| var.location);
+        variables
+    }
+
+    fn descriptor_type(&self, ty: u32, storage: u32) -> Option<DescriptorType> {
+        match *self.types.get(&ty)? {
+            Type::Sampler => Some(DescriptorType::Sampler),
+            // Combined image samplers are exposed as sampled images.
+            Type::SampledImage => Some(DescriptorType::SampledImage),
+            Type::Image { dim: DIM_SUBPASS_DATA, .. } => Some(DescriptorType::InputAttachment),
+            Type::Image { dim: DIM_BUFFER, sampled: 2 } => Some(DescriptorType::StorageTexelBuffer),
+            Type::Image { dim: DIM_BUFFER, .. } => Some(DescriptorType::UniformTexelBuffer),
+            Type::Image { sampled: 2, .. } => Some(DescriptorType::StorageImage),
+            Type::Image { .. } => Some(DescriptorType::SampledImage),
+            Type::Struct { .. } if storage == STORAGE_STORAGE_BUFFER => Some(DescriptorType::StorageBuffer),
+            Type::Struct { .. } if self.flags.contains(&(ty, DECORATION_BUFFER_BLOCK)) => Some(DescriptorType::StorageBuffer),
+            Type::Struct { .. } if self.flags.contains(&(ty, DECORATION_BLOCK)) => Some(DescriptorType::UniformBuffer),
+            _ => None,
+        }
+    }
+
+    // Size in bytes of a type with explicit layout.
+    fn size_of(&self, ty: u32) -> u32 {
+        match self.types.get(&ty) {
+            Some(&Type::Bool) => 4,
+            Some(&Type::Int { width, .. }) |
+            Some(&Type::Float { width }) => width / 8,
+            Some(&Type::Vector { component, count }) => self.size_of(component) * count,
+            Some(&Type::Matrix { column, count }) => self.size_of(column) * count,
+            Some(&Type::Array { element, length }) => {
+                let stride = self.decorations
+                    .get(&(ty, DECORATION_ARRAY_STRIDE))
+                    .cloned()
+                    .unwrap_or_else(|| self.size_of(element));
+                stride * self.constants.get(&length).cloned().unwrap_or(0)
+            }
+            Some(&Type::Struct { ref members }) => {
+                members
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &member)| {
+                        let offset = self.member_decorations
+                            .get(&(ty, i as u32, DECORATION_OFFSET))
+                            .cloned()
+                            .unwrap_or(0);
+                        let size = match (self.types.get(&member), self.member_decorations.get(&(ty, i as u32, DECORATION_MATRIX_STRIDE))) {
+                            (Some(&Type::Matrix { count, .. }), Some(&stride)) => stride * count,
+                            _ => self.size_of(member),
+                        };
+                        offset + size
+                    })
+                    .max()
+                    .unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+
+    // Collect the operands of a function and all the functions it calls,
+    // which may be global variables.
+    fn static_pointers(&self, function: u32) -> HashSet<u32> {
+        let mut pointers = HashSet::new();
+        let mut visited = HashSet::new();
+        let mut pending = vec![function];
+        while let Some(id) = pending.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+            if let Some(function) = self.functions.get(&id) {
+                pointers.extend(function.pointers.iter().cloned());
+                pending.extend(function.calls.iter().cloned());
+            }
+        }
+        pointers
+    }
+
+    fn reflect(&self) -> ShaderReflection {
+        let mut descriptors = Vec::new();
+        let mut push_constants = Vec::new();
+        for var in &self.variables {
+            let ty = match self.pointee(var.ty) {
+                Some(ty) => ty,
+                None => continue,
+            };
+            match var.storage {
+                STORAGE_UNIFORM_CONSTANT | STORAGE_UNIFORM | STORAGE_STORAGE_BUFFER => {
+                    let (element, count) = self.strip_arrays(ty);
+                    let set = self.decorations.get(&(var.id, DECORATION_DESCRIPTOR_SET));
+                    let binding = self.decorations.get(&(var.id, DECORATION_BINDING));
+                    if let (Some(ty), Some(&set), Some(&binding)) = (self.descriptor_type(element, var.storage), set, binding) {
+                        descriptors.push((var.id, DescriptorReflection {
+                            name: self.name(var.id),
+                            set,
+                            binding,
+                            ty,
+                            count,
+                        }));
+                    }
+                }
+                STORAGE_PUSH_CONSTANT => {
+                    push_constants.push(PushConstantReflection {
+                        name: self.name(var.id),
+                        size: self.size_of(ty),
+                    });
+                }
+                _ => {}
+            }
+        }
+        descriptors.sort_by_key(|&(_, ref desc)| (desc.set, desc.binding));
+
+        let entry_points = self.entry_points
+            .iter()
+            .filter_map(|entry| {
+                let stage = match entry.model {
+                    0 => Stage::Vertex,
+                    1 => Stage::Hull,
+                    2 => Stage::Domain,
+                    3 => Stage::Geometry,
+                    4 => Stage::Fragment,
+                    5 => Stage::Compute,
+                    _ => return None, // OpenCL kernels
+                };
+                let arrayed_inputs = stage == Stage::Hull || stage == Stage::Domain || stage == Stage::Geometry;
+                // Since SPIR-V 1.4 the interface lists all the global variables used.
+                let mut used = self.static_pointers(entry.function);
+                used.extend(entry.interface.iter().cloned());
+                Some(EntryPointReflection {
+                    name: entry.name.clone(),
+                    stage,
+                    inputs: self.interface(&entry.interface, STORAGE_INPUT, arrayed_inputs),
+                    outputs: self.interface(&entry.interface, STORAGE_OUTPUT, stage == Stage::Hull),
+                    descriptors: descriptors
+                        .iter()
+                        .filter(|&&(id, _)| used.contains(&id))
+                        .map(|&(_, ref desc)| desc.clone())
+                        .collect(),
+                    workgroup_size: if stage == Stage::Compute {
+                        self.local_sizes.get(&entry.function).cloned()
+                    } else {
+                        None
+                    },
+                })
+            })
+            .collect();
+
+        ShaderReflection {
+            entry_points,
+            descriptors: descriptors.into_iter().map(|(_, desc)| desc).collect(),
+            push_constants,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const OP_TYPE_VOID: u16 = 19;
+    const OP_TYPE_FUNCTION: u16 = 33;
+    const OP_LABEL: u16 = 248;
+    const OP_RETURN: u16 = 253;
+
+    fn op(opcode: u16, operands: &[u32]) -> Vec<u32> {
+        let mut words = vec![((operands.len() as u32 + 1) << 16) | opcode as u32];
+        words.extend_from_slice(operands);
+        words
+    }
+
+    fn string(text: &str) -> Vec<u32> {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.push(0);
+        while bytes.len() % 4 != 0 {
+            bytes.push(0);
+        }
+        bytes.chunks(4)
+            .map(|b| b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+            .collect()
+    }
+
+    pub(crate) fn to_bytes(words: &[u32]) -> Vec<u8> {
+        words.iter()
+            .flat_map(|&w| (0 .. 4).map(move |i| (w >> (i * 8)) as u8))
+            .collect()
+    }
+
+    // Vertex shader with `layout(location = 1) in vec3 pos`,
+    // a uniform block at (set 0, binding 2) and an array of 4 textures at (set 1, binding 0),
+    // used by a function called from `main`. The uniform block at (set 0, binding 3) is unused.
+    pub(crate) fn vertex_module() -> Vec<u32> {
+        let mut words = vec![MAGIC_NUMBER, 0x0001_0000, 0, 30, 0];
+        let mut entry = vec![0, 1];
+        entry.extend(string("main"));
+        entry.push(10);
+        words.extend(op(OP_ENTRY_POINT, &entry));
+        let mut name = vec![10];
+        name.extend(string("pos"));
+        words.extend(op(OP_NAME, &name));
+        words.extend(op(OP_DECORATE, &[10, DECORATION_LOCATION, 1]));
+        words.extend(op(OP_DECORATE, &[13, DECORATION_BLOCK]));
+        words.extend(op(OP_DECORATE, &[15, DECORATION_DESCRIPTOR_SET, 0]));
+        words.extend(op(OP_DECORATE, &[15, DECORATION_BINDING, 2]));
+        words.extend(op(OP_DECORATE, &[19, DECORATION_DESCRIPTOR_SET, 1]));
+        words.extend(op(OP_DECORATE, &[19, DECORATION_BINDING, 0]));
+        words.extend(op(OP_DECORATE, &[22, DECORATION_DESCRIPTOR_SET, 0]));
+        words.extend(op(OP_DECORATE, &[22, DECORATION_BINDING, 3]));
+        words.extend(op(OP_TYPE_FLOAT, &[2, 32]));
+        words.extend(op(OP_TYPE_VECTOR, &[3, 2, 3]));
+        words.extend(op(OP_TYPE_POINTER, &[4, STORAGE_INPUT, 3]));
+        words.extend(op(OP_VARIABLE, &[4, 10, STORAGE_INPUT]));
+        words.extend(op(OP_TYPE_STRUCT, &[13, 3]));
+        words.extend(op(OP_TYPE_POINTER, &[14, STORAGE_UNIFORM, 13]));
+        words.extend(op(OP_VARIABLE, &[14, 15, STORAGE_UNIFORM]));
+        words.extend(op(OP_TYPE_INT, &[5, 32, 0]));
+        words.extend(op(OP_CONSTANT, &[5, 6, 4]));
+        words.extend(op(OP_TYPE_IMAGE, &[16, 2, 1, 0, 0, 0, 1, 0]));
+        words.extend(op(OP_TYPE_ARRAY, &[17, 16, 6]));
+        words.extend(op(OP_TYPE_POINTER, &[18, STORAGE_UNIFORM_CONSTANT, 17]));
+        words.extend(op(OP_VARIABLE, &[18, 19, STORAGE_UNIFORM_CONSTANT]));
+        words.extend(op(OP_VARIABLE, &[14, 22, STORAGE_UNIFORM]));
+        words.extend(op(OP_TYPE_VOID, &[7]));
+        words.extend(op(OP_TYPE_FUNCTION, &[8, 7]));
+        // main
+        words.extend(op(OP_FUNCTION, &[7, 1, 0, 8]));
+        words.extend(op(OP_LABEL, &[9]));
+        words.extend(op(OP_LOAD, &[3, 11, 10]));
+        words.extend(op(OP_ACCESS_CHAIN, &[14, 12, 15, 6]));
+        words.extend(op(OP_FUNCTION_CALL, &[7, 24, 23]));
+        words.extend(op(OP_RETURN, &[]));
+        words.extend(op(OP_FUNCTION_END, &[]));
+        // helper
+        words.extend(op(OP_FUNCTION, &[7, 23, 0, 8]));
+        words.extend(op(OP_LABEL, &[25]));
+        words.extend(op(OP_LOAD, &[17, 26, 19]));
+        words.extend(op(OP_RETURN, &[]));
+        words.extend(op(OP_FUNCTION_END, &[]));
+        words
+    }
+
+    // Fragment shader with `layout(location = 0) out vec4 color`.
+    pub(crate) fn fragment_module() -> Vec<u32> {
+        let mut words = vec![MAGIC_NUMBER, 0x0001_0000, 0, 20, 0];
+        let mut entry = vec![4, 1];
+        entry.extend(string("main"));
+        entry.push(10);
+        words.extend(op(OP_ENTRY_POINT, &entry));
+        words.extend(op(OP_DECORATE, &[10, DECORATION_LOCATION, 0]));
+        words.extend(op(OP_TYPE_FLOAT, &[2, 32]));
+        words.extend(op(OP_TYPE_VECTOR, &[3, 2, 4]));
+        words.extend(op(OP_TYPE_POINTER, &[4, STORAGE_OUTPUT, 3]));
+        words.extend(op(OP_VARIABLE, &[4, 10, STORAGE_OUTPUT]));
+        words.extend(op(OP_TYPE_VOID, &[7]));
+        words.extend(op(OP_TYPE_FUNCTION, &[8, 7]));
+        words.extend(op(OP_FUNCTION, &[7, 1, 0, 8]));
+        words.extend(op(OP_LABEL, &[9]));
+        words.extend(op(OP_STORE, &[10, 11]));
+        words.extend(op(OP_RETURN, &[]));
+        words.extend(op(OP_FUNCTION_END, &[]));
+        words
+    }
+
+    #[test]
+    fn test_reflect_vertex_module() {
+        let reflection = ShaderReflection::from_spirv(&to_bytes(&vertex_module())).unwrap();
+
+        let entry = reflection.entry_point("main").unwrap();
+        assert_eq!(entry.stage, Stage::Vertex);
+        assert_eq!(entry.inputs, vec![InterfaceVariable {
+            name: "pos".into(),
+            location: 1,
+            ty: ScalarType::Float(32),
+            components: 3,
+        }]);
+        assert!(entry.outputs.is_empty());
+
+        assert_eq!(reflection.descriptors.len(), 3);
+        assert_eq!(reflection.descriptor(0, 2).unwrap().ty, DescriptorType::UniformBuffer);
+        let textures = reflection.descriptor(1, 0).unwrap();
+        assert_eq!((textures.ty, textures.count), (DescriptorType::SampledImage, 4));
+
+        let used = entry.descriptors
+            .iter()
+            .map(|desc| (desc.set, desc.binding))
+            .collect::<Vec<_>>();
+        assert_eq!(used, vec![(0, 2), (1, 0)]);
+    }
+
+    #[test]
+    fn test_reflect_fragment_module() {
+        let reflection = ShaderReflection::from_spirv(&to_bytes(&fragment_module())).unwrap();
+
+        let entry = reflection.entry_point("main").unwrap();
+        assert_eq!(entry.stage, Stage::Fragment);
+        assert!(entry.inputs.is_empty());
+        assert_eq!(entry.outputs.len(), 1);
+        assert_eq!((entry.outputs[0].location, entry.outputs[0].ty), (0, ScalarType::Float(32)));
+        assert!(entry.descriptors.is_empty());
+    }
+
+    #[test]
+    fn test_reflect_invalid() {
+        assert_eq!(ShaderReflection::from_spirv(&[0; 20]), Err(ReflectError::InvalidHeader));
+
+        let mut words = vertex_module();
+        words.push(op(OP_TYPE_FLOAT, &[20, 32])[0]);
+        let length = words.len();
+        assert_eq!(
+            ShaderReflection::from_spirv(&to_bytes(&words)),
+            Err(ReflectError::InvalidInstruction(length - 1))
+        );
+    }
+}
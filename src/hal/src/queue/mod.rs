@@ -10,12 +10,14 @@
 pub mod capability;
 pub mod submission;
 
-use Backend;
+use {Backend, SubmissionResult};
 use pool::{CommandPool, CommandPoolCreateFlags};
 use std::marker::PhantomData;
 
 pub use self::capability::{Compute, Graphics, General, Transfer, Supports};
-pub use self::submission::{RawSubmission, Submission};
+pub use self::submission::{
+    RawSparseSubmission, RawSubmission, SparseBind, SparseImageBind, Submission,
+};
 
 ///
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -49,6 +51,11 @@ impl QueueType {
     pub fn supports_transfer(&self) -> bool { true }
 }
 
+/// Identifier of a queue family, unique for an adapter.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct QueueFamilyId(pub usize);
+
 /// `QueueFamily` denotes a group of command queues provided by the backend
 /// with the same properties/type.
 ///
@@ -57,6 +64,8 @@ pub trait QueueFamily: 'static {
     /// Return the number of available queues of this family.
     // TODO: some backends like d3d12 support infinite software queues (verify)
     fn num_queues(&self) -> u32;
+    /// Return the identifier of this family.
+    fn id(&self) -> QueueFamilyId;
 }
 
 /// `RawCommandQueue` are abstractions to the internal GPU execution engines.
@@ -69,6 +78,13 @@ pub trait RawCommandQueue<B: Backend> {
     /// Trying to submit compute commands to a graphics queue will result in undefined behavior.
     /// Each queue implements safe wrappers according to their supported functionalities!
     unsafe fn submit_raw(&mut self, RawSubmission<B>, Option<&B::Fence>);
+
+    /// Bind memory to regions of sparse buffers and images.
+    /// `fence` will be signalled after binding and _must_ be unsignalled.
+    ///
+    /// Unsafe because it's not checked that the queue supports sparse binding,
+    /// and that the resources aren't used by the device during binding.
+    unsafe fn bind_sparse(&mut self, RawSparseSubmission<B>, Option<&B::Fence>) -> SubmissionResult<()>;
 }
 
 /// Stronger-typed and safer `CommandQueue` wraps around `RawCommandQueue`.
@@ -102,6 +118,14 @@ impl<B: Backend, C> CommandQueue<B, C> {
         }
     }
 
+    /// Bind memory to sparse resources, see `RawCommandQueue::bind_sparse`.
+    pub unsafe fn bind_sparse(&mut self,
+        submission: RawSparseSubmission<B>,
+        fence: Option<&B::Fence>,
+    ) -> SubmissionResult<()> {
+        self.0.bind_sparse(submission, fence)
+    }
+
     ///
     pub fn create_general_pool(
         &self,
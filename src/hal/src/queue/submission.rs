@@ -2,10 +2,12 @@
 //!
 //! // TODO
 
-use {pso, Backend};
-use command::{Submit};
+use {image, pso, Backend};
+use command::{Offset, Submit};
+use device::Extent;
 use super::capability::{Transfer, Supports, Upper};
 use std::marker::PhantomData;
+use std::ops::Range;
 use smallvec::SmallVec;
 
 /// Raw submission information for a command queue.
@@ -18,6 +20,44 @@ pub struct RawSubmission<'a, B: Backend + 'a> {
     pub signal_semaphores: &'a [&'a B::Semaphore],
 }
 
+/// Binding of memory to a range of a sparse resource.
+pub struct SparseBind<'a, B: Backend + 'a> {
+    /// Range of the resource in bytes, aligned to the page size.
+    pub range: Range<u64>,
+    /// Memory with an offset to bind to the range, `None` to unbind it.
+    pub memory: Option<(&'a B::Memory, u64)>,
+}
+
+/// Binding of memory to a region of a partially resident sparse image.
+pub struct SparseImageBind<'a, B: Backend + 'a> {
+    /// Aspects of the bound region.
+    pub aspects: image::AspectFlags,
+    /// Mipmap level of the bound region.
+    pub level: image::Level,
+    /// Array layer of the bound region.
+    pub layer: image::Layer,
+    /// Offset of the region in texels, aligned to the page extent.
+    pub offset: Offset,
+    /// Extent of the region in texels, aligned to the page extent.
+    pub extent: Extent,
+    /// Memory with an offset to bind to the region, `None` to unbind it.
+    pub memory: Option<(&'a B::Memory, u64)>,
+}
+
+/// Raw sparse binding information for a command queue.
+pub struct RawSparseSubmission<'a, B: Backend + 'a> {
+    /// Buffer ranges to bind.
+    pub buffer_binds: &'a [(&'a B::Buffer, &'a [SparseBind<'a, B>])],
+    /// Image ranges to bind, in the opaque memory layout of the images.
+    pub image_opaque_binds: &'a [(&'a B::Image, &'a [SparseBind<'a, B>])],
+    /// Image regions to bind, requires `Features::sparse_residency_image`.
+    pub image_binds: &'a [(&'a B::Image, &'a [SparseImageBind<'a, B>])],
+    /// Semaphores to wait being signalled before binding.
+    pub wait_semaphores: &'a [&'a B::Semaphore],
+    /// Semaphores which get signalled after binding.
+    pub signal_semaphores: &'a [&'a B::Semaphore],
+}
+
 /// Submission information for a command queue.
 pub struct Submission<'a, B: Backend, C> {
     cmd_buffers: SmallVec<[B::CommandBuffer; 16]>,
@@ -191,7 +191,7 @@ pub enum FrameSync<'a, B: Backend> {
 pub struct SwapchainConfig {
     /// Color format of the backbuffer images.
     pub color_format: format::Format,
-    /// Depth stencil format of the backbuffer images (optional).
+    /// Depth, stencil or depth stencil format of the backbuffer images (optional).
     pub depth_stencil_format: Option<format::Format>,
 }
 
@@ -236,7 +236,35 @@ impl SwapchainConfig {
         self
     }
 
-    // TODO: depth-only, stencil-only, swapchain size, present modes, etc.
+    /// Specify a depth-only format for the backbuffer images.
+    ///
+    /// The Swapchain will create additional depth images for each backbuffer.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    ///
+    /// ```
+    pub fn with_depth<Df: format::DepthFormat>(mut self) -> Self {
+        self.depth_stencil_format = Some(Df::SELF);
+        self
+    }
+
+    /// Specify a stencil-only format for the backbuffer images.
+    ///
+    /// The Swapchain will create additional stencil images for each backbuffer.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    ///
+    /// ```
+    pub fn with_stencil<Sf: format::StencilFormat>(mut self) -> Self {
+        self.depth_stencil_format = Some(Sf::SELF);
+        self
+    }
+
+    // TODO: swapchain size, present modes, etc.
 }
 
 /// Swapchain backbuffer type
@@ -1,7 +1,7 @@
 use std::mem;
 use std::ops::Range;
 use core::{Device as CoreDevice, MemoryType};
-use core::memory::{Properties,
+use core::memory::{Properties, SharingMode,
     DEVICE_LOCAL, CPU_VISIBLE, CPU_CACHED, COHERENT
 };
 
@@ -115,7 +115,7 @@ impl<B: Backend> Device<B> {
     ) -> Result<(handle::raw::Buffer<B>, InitToken<B>), buffer::CreationError>
         where A: Allocator<B>
     {
-        let buffer = self.raw.create_buffer(size, stride, usage)?;
+        let buffer = self.raw.create_buffer(size, stride, usage, SharingMode::Exclusive)?;
         let (buffer, memory) = allocator.allocate_buffer(self, usage, buffer);
         let info = buffer::Info::new(usage, memory, size, stride);
         let handle = handle::raw::Buffer::from(
@@ -193,9 +193,10 @@ impl<B: Backend> Device<B> {
     pub fn release_mapping_reader<'a, T>(
         &mut self,
         reader: mapping::Reader<'a, B, T>
-    ) {
-        self.raw.release_mapping_reader(reader.inner);
+    ) -> Result<(), mapping::Error> {
+        let result = self.raw.release_mapping_reader(reader.inner);
         reader.info.access.release_exclusive();
+        result.map_err(mapping::Error::Core)
     }
 
     /// Acquire a mapping Writer.
@@ -231,9 +232,10 @@ impl<B: Backend> Device<B> {
     pub fn release_mapping_writer<'a, T>(
         &mut self,
         writer: mapping::Writer<'a, B, T>
-    ) {
-        self.raw.release_mapping_writer(writer.inner);
+    ) -> Result<(), mapping::Error> {
+        let result = self.raw.release_mapping_writer(writer.inner);
         writer.info.access.release_exclusive();
+        result.map_err(mapping::Error::Core)
     }
 
     /// Sugar to acquire and release a mapping reader.
@@ -282,19 +284,8 @@ impl<B: Backend> Device<B> {
         };
         use core::image::ImageLayout;
 
-        let bits = format.0.describe_bits();
-        let mut aspects = core::image::AspectFlags::empty();
-        if bits.color + bits.alpha != 0 {
-            aspects |= core::image::ASPECT_COLOR;
-        }
-        if bits.depth != 0 {
-            aspects |= core::image::ASPECT_DEPTH;
-        }
-        if bits.stencil != 0 {
-            aspects |= core::image::ASPECT_STENCIL;
-        }
-
-        let image = self.raw.create_image(kind, mip_levels, format, usage)?;
+        let aspects = format.0.aspects();
+        let image = self.raw.create_image(kind, mip_levels, format, core::image::Tiling::Optimal, usage, SharingMode::Exclusive)?;
         let (image, memory) = allocator.allocate_image(self, usage, image);
         let origin = image::Origin::User(memory);
         let stable_access = core::image::Access::empty();
@@ -312,7 +303,8 @@ impl<B: Backend> Device<B> {
             _ => ImageLayout::General,
         };
         let stable_state = (stable_access, stable_layout);
-        let info = image::Info { aspects, usage, kind, mip_levels, format, origin, stable_state };
+        let features = self.raw.format_properties(format).optimal_tiling;
+        let info = image::Info { aspects, usage, kind, mip_levels, format, features, origin, stable_state };
         let handle = handle::raw::Image::from(
             Image::new(image, info, self.garbage.clone()));
         let token = InitToken { handle: handle.clone().into() };
@@ -341,10 +333,11 @@ impl<B: Backend> Device<B> {
     pub fn create_image_view_raw(
         &mut self,
         image: &handle::raw::Image<B>,
+        kind: image::ViewKind,
         format: format::Format,
         range: image::SubresourceRange,
     ) -> Result<handle::raw::ImageView<B>, image::ViewError> {
-        self.raw.create_image_view(image.resource(), format, format::Swizzle::NO, range)
+        self.raw.create_image_view(image.resource(), kind, format, format::Swizzle::NO, range)
             .map(|view| ImageView::new(
                 view,
                 image.clone(),
@@ -355,18 +348,20 @@ impl<B: Backend> Device<B> {
     pub fn create_image_view<F>(
         &mut self,
         image: &handle::Image<B, F>,
+        kind: image::ViewKind,
         range: image::SubresourceRange,
     ) -> Result<handle::ImageView<B, F>, image::ViewError>
         where F: format::RenderFormat
     {
-        self.create_image_view_raw(image.as_ref(), F::SELF, range)
+        self.create_image_view_raw(image.as_ref(), kind, F::SELF, range)
             .map(Typed::new)
     }
 
-    pub fn create_sampler(&mut self, info: image::SamplerInfo) -> handle::Sampler<B> {
-        handle::inner::Sampler::new(
-            self.raw.create_sampler(info.clone()), info, self.garbage.clone()
-        ).into()
+    pub fn create_sampler(
+        &mut self, info: image::SamplerInfo
+    ) -> Result<handle::Sampler<B>, image::SamplerError> {
+        let sampler = self.raw.create_sampler(info.clone())?;
+        Ok(handle::inner::Sampler::new(sampler, info, self.garbage.clone()).into())
     }
 
     // TODO: smarter allocation
@@ -1,6 +1,7 @@
 //! Commands encoder.
 
-use std::mem;
+use std::{fmt, mem};
+use std::error::Error as StdError;
 use std::ops::Range;
 use std::sync::mpsc;
 use std::collections::{HashMap, HashSet};
@@ -16,10 +17,35 @@ use {Backend, Supports, Transfer, Graphics};
 use {VertexCount};
 
 pub use core::command::{
-    BufferCopy, ImageCopy, BufferImageCopy,
-    ClearColor, ClearDepthStencil,
+    BufferCopy, ImageCopy, ImageBlit, BufferImageCopy,
+    ClearColor, ClearDepthStencil, Offset,
 };
 
+/// Error generating the mipmap chain of an image.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum MipmapError {
+    /// The format can't be blitted with a linear filter.
+    UnsupportedFormat(core::format::Format),
+}
+
+impl fmt::Display for MipmapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MipmapError::UnsupportedFormat(format) =>
+                write!(f, "{}: {:?}", self.description(), format),
+        }
+    }
+}
+
+impl StdError for MipmapError {
+    fn description(&self) -> &str {
+        match *self {
+            MipmapError::UnsupportedFormat(_) =>
+                "The image format does not support linear blits",
+        }
+    }
+}
+
 pub struct Pool<B: Backend, C>(Provider<PoolInner<B, C>>);
 
 #[derive(Clone)]
@@ -229,6 +255,7 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
                 levels: 0 .. num_levels,
                 layers: 0 .. num_layers,
             },
+            families: None,
         }
     }
 
@@ -277,6 +304,7 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
             Some(Barrier::Buffer {
                 states: state..next,
                 target: buffer.resource(),
+                families: None,
             })
         } else {
             None
@@ -300,6 +328,7 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
                     levels: level .. (level+1),
                     layers: layer .. (layer+1),
                 },
+                families: None,
             })
         } else {
             None
@@ -648,16 +677,120 @@ impl<'a, B: Backend, C> Encoder<'a, B, C>
         data.begin_renderpass(self, pipeline).draw(vertices, 0..1);
     }
 
-/*
-    /// Generate a mipmap chain for the given resource view.
-    pub fn generate_mipmap<T: format::BlendFormat>(&mut self, view: &handle::ShaderResourceView<B, T>) {
-        self.generate_mipmap_raw(view.raw())
+    /// Copy parts of an image to another, scaling them with `filter`
+    /// if the bounds differ in size.
+    pub fn blit_image<IA, IB>(
+        &mut self,
+        src: IA,
+        dst: IB,
+        filter: i::Filter,
+        regions: &[ImageBlit],
+    )
+        where IA: AsRef<handle::raw::Image<B>>,
+              IB: AsRef<handle::raw::Image<B>>
+    {
+        let src = src.as_ref();
+        let dst = dst.as_ref();
+        if regions.is_empty() { return };
+
+        debug_assert!(src.info().usage.contains(image::TRANSFER_SRC),
+            "missing TRANSFER_SRC usage flag");
+        debug_assert!(dst.info().usage.contains(image::TRANSFER_DST),
+            "missing TRANSFER_DST usage flag");
+
+        let src_state = (core::image::TRANSFER_READ, i::ImageLayout::TransferSrcOptimal);
+        let dst_state = (core::image::TRANSFER_WRITE, i::ImageLayout::TransferDstOptimal);
+        let mut image_states = Vec::new();
+        for region in regions {
+            let (s, d) = (&region.src_subresource, &region.dst_subresource);
+            for layer in s.layers.clone() {
+                image_states.push((src, (s.level, layer), src_state));
+            }
+            for layer in d.layers.clone() {
+                image_states.push((dst, (d.level, layer), dst_state));
+            }
+        }
+        self.require_state(
+            core::pso::TRANSFER,
+            &[],
+            &image_states[..]);
+
+        self.buffer.blit_image(
+            src.resource(), src_state.1,
+            dst.resource(), dst_state.1,
+            filter,
+            regions);
+    }
+
+    /// Generate the mipmap chain of `image` from its first level.
+    pub fn generate_mipmap<F>(&mut self, image: &handle::Image<B, F>) -> Result<(), MipmapError>
+        where F: format::BlendFormat
+    {
+        self.generate_mipmap_raw(image.as_ref())
     }
 
     /// Untyped version of mipmap generation.
-    pub fn generate_mipmap_raw(&mut self, view: &handle::RawShaderResourceView<B>) {
-        let srv = self.handles.ref_srv(view).clone();
-        self.command_buffer.generate_mipmap(srv);
+    ///
+    /// Each level is downsampled from the previous one with a linear blit,
+    /// which requires the `BLIT_SRC`, `BLIT_DST` and `SAMPLED_LINEAR` format features.
+    pub fn generate_mipmap_raw(&mut self, image: &handle::raw::Image<B>) -> Result<(), MipmapError> {
+        let info = image.info();
+        debug_assert!(info.usage.contains(image::TRANSFER_SRC | image::TRANSFER_DST),
+            "missing TRANSFER_SRC or TRANSFER_DST usage flag");
+        let required = core::format::BLIT_SRC | core::format::BLIT_DST | core::format::SAMPLED_LINEAR;
+        if !info.features.contains(required) {
+            return Err(MipmapError::UnsupportedFormat(info.format));
+        }
+
+        let layers = info.kind.get_num_layers();
+        let bounds = |level| {
+            let (width, height, depth, _) = info.kind.get_level_dimensions(level);
+            // Only 3D images have depth, the other kinds report their faces or layers there.
+            let depth = match info.kind {
+                i::Kind::D3(..) => depth,
+                _ => 1,
+            };
+            Offset { x: 0, y: 0, z: 0 } .. Offset {
+                x: width as i32,
+                y: height as i32,
+                z: depth as i32,
+            }
+        };
+        let src_state = (core::image::TRANSFER_READ, i::ImageLayout::TransferSrcOptimal);
+        let dst_state = (core::image::TRANSFER_WRITE, i::ImageLayout::TransferDstOptimal);
+
+        for level in 1 .. info.mip_levels {
+            let mut image_states = Vec::new();
+            for layer in 0 .. layers {
+                image_states.push((image, (level - 1, layer), src_state));
+                image_states.push((image, (level, layer), dst_state));
+            }
+            self.require_state(
+                core::pso::TRANSFER,
+                &[],
+                &image_states[..]);
+
+            let region = ImageBlit {
+                src_subresource: i::SubresourceLayers {
+                    aspects: info.aspects,
+                    level: level - 1,
+                    layers: 0 .. layers,
+                },
+                src_bounds: bounds(level - 1),
+                dst_subresource: i::SubresourceLayers {
+                    aspects: info.aspects,
+                    level,
+                    layers: 0 .. layers,
+                },
+                dst_bounds: bounds(level),
+            };
+            self.buffer.blit_image(
+                image.resource(), src_state.1,
+                image.resource(), dst_state.1,
+                i::Filter::Linear,
+                &[region]);
+        }
+        self.handles.add(image.clone());
+        Ok(())
     }
-    */
 }
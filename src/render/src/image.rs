@@ -3,7 +3,7 @@ use memory::Memory;
 
 pub use core::image::{
     CreationError, Kind, AaMode, Size, Level, Layer, Dimensions,
-    AspectFlags, SamplerInfo, ViewError, Usage,
+    AspectFlags, SamplerInfo, SamplerError, ViewError, ViewKind, Usage,
     Subresource, SubresourceLayers, SubresourceRange,
 };
 pub use core::image::{
@@ -20,6 +20,8 @@ pub struct Info {
     pub kind: Kind,
     pub mip_levels: Level,
     pub format: core::format::Format,
+    /// Features of the format with optimal tiling.
+    pub features: core::format::ImageFeature,
     pub origin: Origin,
     pub(crate) stable_state: core::image::State,
 }
@@ -323,6 +323,7 @@ impl<B: Backend, C> Context<B, C>
                     device.mut_raw().create_fence(true)),
             }).collect();
 
+        let features = device.ref_raw().format_properties(Cf::SELF).optimal_tiling;
         let backbuffers = backbuffer_images
             .into_iter()
             .map(|raw| {
@@ -336,6 +337,7 @@ impl<B: Backend, C> Context<B, C>
                         kind: surface.get_kind(),
                         mip_levels: 1,
                         format: Cf::SELF,
+                        features,
                         origin: image::Origin::Backbuffer,
                         stable_state: (stable_access, stable_layout),
                     },
@@ -80,7 +80,9 @@ impl<'a, B: Backend, T: 'a> ops::Deref for ReadScope<'a, B, T> {
 
 impl<'a, B: Backend, T: 'a> Drop for ReadScope<'a, B, T> {
     fn drop(&mut self) {
-        self.device.release_mapping_reader(self.reader.take().unwrap());
+        if let Err(err) = self.device.release_mapping_reader(self.reader.take().unwrap()) {
+            error!("Error releasing a mapping reader: {:?}", err);
+        }
     }
 }
 
@@ -100,6 +102,8 @@ impl<'a, B: Backend, T: 'a> ops::DerefMut for WriteScope<'a, B, T> {
 
 impl<'a, B: Backend, T: 'a> Drop for WriteScope<'a, B, T> {
     fn drop(&mut self) {
-        self.device.release_mapping_writer(self.writer.take().unwrap());
+        if let Err(err) = self.device.release_mapping_writer(self.writer.take().unwrap()) {
+            error!("Error releasing a mapping writer: {:?}", err);
+        }
     }
 }
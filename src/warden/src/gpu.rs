@@ -37,7 +37,7 @@ impl<'a, B: hal::Backend> Drop for FetchGuard<'a, B> {
     fn drop(&mut self) {
         let buffer = self.buffer.take().unwrap();
         let memory = self.memory.take().unwrap();
-        self.device.release_mapping_raw(&buffer, None);
+        self.device.release_mapping_raw(&buffer, None).unwrap();
         self.device.destroy_buffer(buffer);
         self.device.free_memory(memory);
     }
@@ -144,7 +144,7 @@ impl<B: hal::Backend> Scene<B> {
                     raw::Resource::Buffer => {
                     }
                     raw::Resource::Image { kind, num_levels, format, usage, ref data } => {
-                        let unbound = device.create_image(kind, num_levels, format, usage)
+                        let unbound = device.create_image(kind, num_levels, format, hal::image::Tiling::Optimal, usage, hal::memory::SharingMode::Exclusive)
                             .unwrap();
                         let requirements = device.get_image_requirements(&unbound);
                         let memory_type = memory_types
@@ -158,14 +158,14 @@ impl<B: hal::Backend> Scene<B> {
                             .unwrap();
                         let image = device.bind_image_memory(&memory, 0, unbound)
                             .unwrap();
-                        let bits = format.0.describe_bits();
+                        let aspects = format.0.aspects();
 
                         // process initial data for the image
                         let stable_state = if data.is_empty() {
-                            let (aspects, access, layout) = if bits.color != 0 {
-                                (i::ASPECT_COLOR, i::COLOR_ATTACHMENT_WRITE, i::ImageLayout::ColorAttachmentOptimal)
+                            let (access, layout) = if aspects.contains(i::ASPECT_COLOR) {
+                                (i::COLOR_ATTACHMENT_WRITE, i::ImageLayout::ColorAttachmentOptimal)
                             } else {
-                                (i::ASPECT_DEPTH | i::ASPECT_STENCIL, i::DEPTH_STENCIL_ATTACHMENT_WRITE, i::ImageLayout::DepthStencilAttachmentOptimal)
+                                (i::DEPTH_STENCIL_ATTACHMENT_WRITE, i::ImageLayout::DepthStencilAttachmentOptimal)
                             };
                             if false { //TODO
                                 let image_barrier = hal::memory::Barrier::Image {
@@ -175,6 +175,7 @@ impl<B: hal::Backend> Scene<B> {
                                         aspects,
                                         .. COLOR_RANGE.clone()
                                     },
+                                    families: None,
                                 };
                                 init_cmd.pipeline_barrier(hal::pso::TOP_OF_PIPE .. hal::pso::BOTTOM_OF_PIPE, &[image_barrier]);
                             }
@@ -183,11 +184,13 @@ impl<B: hal::Backend> Scene<B> {
                             // calculate required sizes
                             let (w, h, d, aa) = kind.get_dimensions();
                             assert_eq!(aa, i::AaMode::Single);
-                            let width_bytes = bits.total as usize * w as usize / 8;
+                            let block = format.0.describe_block();
+                            let (_, rows) = block.count(w as _, h as _);
+                            let width_bytes = block.row_pitch(w as _) as usize;
                             let row_pitch = align(width_bytes, limits.min_buffer_copy_pitch_alignment);
-                            let upload_size = row_pitch as u64 * h as u64 * d as u64;
+                            let upload_size = row_pitch as u64 * rows as u64 * d as u64;
                             // create upload buffer
-                            let unbound_buffer = device.create_buffer(upload_size, bits.total as _, hal::buffer::TRANSFER_SRC)
+                            let unbound_buffer = device.create_buffer(upload_size, block.bytes as _, hal::buffer::TRANSFER_SRC, hal::memory::SharingMode::Exclusive)
                                 .unwrap();
                             let upload_req = device.get_buffer_requirements(&unbound_buffer);
                             assert_ne!(upload_req.type_mask & (1<<upload_type.id), 0);
@@ -201,12 +204,12 @@ impl<B: hal::Backend> Scene<B> {
                                     .unwrap();
                                 let mut mapping = device.acquire_mapping_writer::<u8>(&upload_buffer, 0..upload_size)
                                     .unwrap();
-                                for y in 0 .. (h as usize * d as usize) {
+                                for y in 0 .. (rows as usize * d as usize) {
                                     let dest_range = y as usize * row_pitch .. y as usize * row_pitch + width_bytes;
                                     file.read_exact(&mut mapping[dest_range])
                                         .unwrap();
                                 }
-                                device.release_mapping_writer(mapping);
+                                device.release_mapping_writer(mapping).unwrap();
                             }
                             // add init commands
                             let final_state = (i::SHADER_READ, i::ImageLayout::ShaderReadOnlyOptimal);
@@ -215,6 +218,7 @@ impl<B: hal::Backend> Scene<B> {
                                         (i::TRANSFER_WRITE, i::ImageLayout::TransferDstOptimal),
                                 target: &image,
                                 range: COLOR_RANGE.clone(), //TODO
+                                families: None,
                             };
                             init_cmd.pipeline_barrier(hal::pso::TOP_OF_PIPE .. hal::pso::TRANSFER, &[image_barrier]);
                             init_cmd.copy_buffer_to_image(
@@ -224,7 +228,7 @@ impl<B: hal::Backend> Scene<B> {
                                 &[hal::command::BufferImageCopy {
                                     buffer_offset: 0,
                                     buffer_row_pitch: row_pitch as u32,
-                                    buffer_slice_pitch: row_pitch as u32 * h as u32,
+                                    buffer_slice_pitch: row_pitch as u32 * rows,
                                     image_layers: i::SubresourceLayers {
                                         aspects: i::ASPECT_COLOR,
                                         level: 0,
@@ -241,6 +245,7 @@ impl<B: hal::Backend> Scene<B> {
                                 states: (i::TRANSFER_WRITE, i::ImageLayout::TransferDstOptimal) .. final_state,
                                 target: &image,
                                 range: COLOR_RANGE.clone(), //TODO
+                                families: None,
                             };
                             init_cmd.pipeline_barrier(hal::pso::TRANSFER .. hal::pso::BOTTOM_OF_PIPE, &[image_barrier]);
                             // done
@@ -337,9 +342,9 @@ impl<B: hal::Backend> Scene<B> {
             // Pass[2]: image & buffer views, descriptor sets, pipeline layouts
             for (name, resource) in &raw.resources {
                 match *resource {
-                    raw::Resource::ImageView { ref image, format, swizzle, ref range } => {
+                    raw::Resource::ImageView { ref image, kind, format, swizzle, ref range } => {
                         let image = &resources.images[image].handle;
-                        let view = device.create_image_view(image, format, swizzle, range.clone())
+                        let view = device.create_image_view(image, kind, format, swizzle, range.clone())
                             .unwrap();
                         resources.image_views.insert(name.clone(), view);
                     }
@@ -501,12 +506,13 @@ impl<B: hal::Backend> Scene<B> {
 
         let (width, height, depth, aa) = image.kind.get_dimensions();
         assert_eq!(aa, i::AaMode::Single);
-        let bpp = image.format.0.describe_bits().total as usize;
-        let width_bytes = bpp * width as usize / 8;
+        let block = image.format.0.describe_block();
+        let (_, rows) = block.count(width as _, height as _);
+        let width_bytes = block.row_pitch(width as _) as usize;
         let row_pitch = align(width_bytes, limits.min_buffer_copy_pitch_alignment);
-        let down_size = row_pitch as u64 * height as u64 * depth as u64;
+        let down_size = row_pitch as u64 * rows as u64 * depth as u64;
 
-        let unbound_buffer = self.device.create_buffer(down_size, bpp as _, hal::buffer::TRANSFER_DST)
+        let unbound_buffer = self.device.create_buffer(down_size, block.bytes as _, hal::buffer::TRANSFER_DST, hal::memory::SharingMode::Exclusive)
             .unwrap();
         let down_req = self.device.get_buffer_requirements(&unbound_buffer);
         assert_ne!(down_req.type_mask & (1<<self.download_type.id), 0);
@@ -525,6 +531,7 @@ impl<B: hal::Backend> Scene<B> {
                 states: image.stable_state .. (i::TRANSFER_READ, i::ImageLayout::TransferSrcOptimal),
                 target: &image.handle,
                 range: COLOR_RANGE.clone(), //TODO
+                families: None,
             };
             cmd_buffer.pipeline_barrier(hal::pso::TOP_OF_PIPE .. hal::pso::TRANSFER, &[image_barrier]);
             cmd_buffer.copy_image_to_buffer(
@@ -534,7 +541,7 @@ impl<B: hal::Backend> Scene<B> {
                 &[hal::command::BufferImageCopy {
                     buffer_offset: 0,
                     buffer_row_pitch: row_pitch as u32,
-                    buffer_slice_pitch: row_pitch as u32 * height as u32,
+                    buffer_slice_pitch: row_pitch as u32 * rows,
                     image_layers: i::SubresourceLayers {
                         aspects: i::ASPECT_COLOR,
                         level: 0,
@@ -551,6 +558,7 @@ impl<B: hal::Backend> Scene<B> {
                 states: (i::TRANSFER_READ, i::ImageLayout::TransferSrcOptimal) .. image.stable_state,
                 target: &image.handle,
                 range: COLOR_RANGE.clone(), //TODO
+                families: None,
             };
             cmd_buffer.pipeline_barrier(hal::pso::TRANSFER .. hal::pso::BOTTOM_OF_PIPE, &[image_barrier]);
             cmd_buffer.finish()
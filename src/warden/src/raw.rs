@@ -38,6 +38,7 @@ pub enum Resource {
     },
     ImageView {
         image: String,
+        kind: hal::image::ViewKind,
         format: hal::format::Format,
         #[serde(default)]
         swizzle: hal::format::Swizzle,